@@ -0,0 +1,87 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use minecraft::block_registry::BLOCK_REGISTRY;
+use minecraft::block_texture_faces::BlockFaces;
+use minecraft::chunk::{BlockID, BlockIterator, ChunkColumn};
+use minecraft::chunk_manager::ChunkManager;
+use minecraft::light::light_column;
+use minecraft::meshing::MeshingMode;
+use minecraft::types::TexturePack;
+use std::sync::Arc;
+
+// One texture layer per block
+fn texture_pack() -> TexturePack {
+    BLOCK_REGISTRY
+        .iter()
+        .map(|block| (block.id, BlockFaces::All(block.id.id() as u32)))
+        .collect()
+}
+
+// Lit column where `block_at` decides every block of its first chunk
+fn meshed_column(block_at: impl Fn(u32, u32, u32) -> BlockID) -> Arc<ChunkColumn> {
+    let chunk_manager = ChunkManager::new();
+    let column = Arc::new(ChunkColumn::new());
+
+    for (x, y, z) in BlockIterator::new() {
+        column.set_block(x, y, z, block_at(x, y, z));
+    }
+
+    chunk_manager.add_chunk_column((0, 0), Arc::clone(&column));
+    light_column(&chunk_manager, 0, 0);
+    chunk_manager.update_blocks(0, 0, 0, BlockIterator::new());
+
+    column
+}
+
+fn meshing(c: &mut Criterion) {
+    let texture_pack = texture_pack();
+
+    let chunks = [
+        (
+            "flat",
+            meshed_column(|_, y, _| if y < 8 { BlockID::STONE } else { BlockID::AIR }),
+        ),
+        (
+            "hills",
+            meshed_column(|x, y, z| {
+                let height = 6.0 + 3.0 * (x as f32 / 3.0).sin() + 2.0 * (z as f32 / 4.0).cos();
+
+                match y as f32 {
+                    y if y < height - 3.0 => BlockID::STONE,
+                    y if y < height - 1.0 => BlockID::DIRT,
+                    y if y < height => BlockID::GRASS_BLOCK,
+                    _ => BlockID::AIR,
+                }
+            }),
+        ),
+        (
+            "checkerboard",
+            meshed_column(|x, y, z| {
+                if (x + y + z) % 2 == 0 {
+                    BlockID::STONE
+                } else {
+                    BlockID::AIR
+                }
+            }),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("meshing");
+
+    for (name, column) in chunks.iter() {
+        let chunk = column.get_chunk(0);
+
+        for meshing_mode in [MeshingMode::Naive, MeshingMode::Greedy] {
+            let vertices = chunk.build_mesh(&texture_pack, meshing_mode).vertex_count();
+            println!("{name} ({meshing_mode:?}): {vertices} vertices");
+
+            group.bench_function(format!("{name}/{meshing_mode:?}"), |b| {
+                b.iter(|| chunk.build_mesh(&texture_pack, meshing_mode))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, meshing);
+criterion_main!(benches);
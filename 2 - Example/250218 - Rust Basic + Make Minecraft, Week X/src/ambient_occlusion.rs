@@ -39,7 +39,7 @@ pub fn ao_index((x, y, z): (i32, i32, i32)) -> usize {
 }
 
 lazy_static! {
-    static ref AO_AFFECTED_VERTICES: [(u8, u8); 27 * 4] = {
+    pub(crate) static ref AO_AFFECTED_VERTICES: [(u8, u8); 27 * 4] = {
         let mut affected_vertices: [(u8, u8); 27 * 4] = [(0, 0); 27 * 4];
         let mut add = |idx, key, value| affected_vertices[ao_index(key) + idx] = value;
 
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+
+// Range of an arena given by `ArenaAllocator::allocate`, in the unit of the arena
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Allocation {
+    pub offset: u32,
+    pub size: u32,
+}
+
+impl Allocation {
+    pub fn end(&self) -> u32 {
+        self.offset + self.size
+    }
+}
+
+// Sub-allocates the ranges of a fixed size arena with a free list. The free blocks next to each
+// other are merged back together when a range is freed.
+pub struct ArenaAllocator {
+    capacity: u32,
+    // Size of every free block, by offset
+    free_blocks: BTreeMap<u32, u32>,
+}
+
+impl ArenaAllocator {
+    pub fn new(capacity: u32) -> Self {
+        let mut free_blocks = BTreeMap::new();
+
+        if capacity > 0 {
+            free_blocks.insert(0, capacity);
+        }
+
+        Self {
+            capacity,
+            free_blocks,
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn free_space(&self) -> u32 {
+        self.free_blocks.values().sum()
+    }
+
+    // The biggest allocation that can succeed
+    pub fn largest_free_block(&self) -> u32 {
+        self.free_blocks.values().copied().max().unwrap_or(0)
+    }
+
+    // Takes the start of the smallest free block that fits, to keep the big blocks for the big
+    // allocations. Returns `None` if no free block is big enough.
+    pub fn allocate(&mut self, size: u32) -> Option<Allocation> {
+        assert!(size > 0, "Cannot allocate an empty range");
+
+        let (&offset, &block_size) = self
+            .free_blocks
+            .iter()
+            .filter(|(_, &block_size)| block_size >= size)
+            .min_by_key(|(_, &block_size)| block_size)?;
+
+        self.free_blocks.remove(&offset);
+
+        if block_size > size {
+            self.free_blocks.insert(offset + size, block_size - size);
+        }
+
+        Some(Allocation { offset, size })
+    }
+
+    pub fn free(&mut self, allocation: Allocation) {
+        let mut offset = allocation.offset;
+        let mut size = allocation.size;
+
+        debug_assert!(allocation.end() <= self.capacity);
+
+        // Merge with the free block right before
+        if let Some((&previous_offset, &previous_size)) =
+            self.free_blocks.range(..offset).next_back()
+        {
+            debug_assert!(previous_offset + previous_size <= offset, "Double free");
+
+            if previous_offset + previous_size == offset {
+                self.free_blocks.remove(&previous_offset);
+                offset = previous_offset;
+                size += previous_size;
+            }
+        }
+
+        // And with the one right after
+        if let Some(next_size) = self.free_blocks.remove(&allocation.end()) {
+            size += next_size;
+        }
+
+        self.free_blocks.insert(offset, size);
+    }
+
+    // Start of the free space at the end of the arena
+    fn end_of_allocations(&self) -> u32 {
+        match self.free_blocks.last_key_value() {
+            Some((&offset, &size)) if offset + size == self.capacity => offset,
+            _ => self.capacity,
+        }
+    }
+
+    // Capacity reached by halving the arena while less than a quarter of it is used and its
+    // second half is free, without going below `min_capacity`. The allocations don't move, so a
+    // single one near the end keeps the arena from shrinking.
+    pub fn shrunk_capacity(&self, min_capacity: u32) -> u32 {
+        let used = self.capacity - self.free_space();
+        let end = self.end_of_allocations();
+        let mut capacity = self.capacity;
+
+        while capacity / 2 >= min_capacity && used < capacity / 4 && end <= capacity / 2 {
+            capacity /= 2;
+        }
+
+        capacity
+    }
+
+    // Removes free space from the end of the arena, the allocations don't move
+    pub fn shrink(&mut self, capacity: u32) {
+        let end = self.end_of_allocations();
+        assert!(
+            capacity >= end,
+            "An arena cannot shrink below its allocations"
+        );
+
+        if capacity < self.capacity {
+            self.free_blocks.remove(&end);
+
+            if capacity > end {
+                self.free_blocks.insert(end, capacity - end);
+            }
+
+            self.capacity = capacity;
+        }
+    }
+
+    // Adds free space at the end of the arena, the allocations don't move
+    pub fn grow(&mut self, capacity: u32) {
+        assert!(capacity >= self.capacity, "An arena cannot shrink");
+
+        if capacity > self.capacity {
+            let added = Allocation {
+                offset: self.capacity,
+                size: capacity - self.capacity,
+            };
+            self.capacity = capacity;
+            self.free(added);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freed_ranges_are_reused() {
+        let mut allocator = ArenaAllocator::new(100);
+
+        let a = allocator.allocate(30).unwrap();
+        let b = allocator.allocate(30).unwrap();
+        assert_eq!(
+            a,
+            Allocation {
+                offset: 0,
+                size: 30
+            }
+        );
+        assert_eq!(
+            b,
+            Allocation {
+                offset: 30,
+                size: 30
+            }
+        );
+        assert_eq!(allocator.free_space(), 40);
+
+        // Too big for what is left
+        assert_eq!(allocator.allocate(50), None);
+
+        allocator.free(a);
+        assert_eq!(allocator.allocate(20).unwrap().offset, 0);
+        assert_eq!(allocator.allocate(10).unwrap().offset, 20);
+        assert_eq!(allocator.free_space(), 40);
+    }
+
+    #[test]
+    fn the_smallest_block_that_fits_is_used() {
+        let mut allocator = ArenaAllocator::new(100);
+        let blocks: Vec<_> = [20, 5, 10, 5, 60]
+            .iter()
+            .map(|&size| allocator.allocate(size).unwrap())
+            .collect();
+
+        // A hole of 20 then a hole of 10
+        allocator.free(blocks[0]);
+        allocator.free(blocks[2]);
+
+        assert_eq!(allocator.allocate(8).unwrap().offset, blocks[2].offset);
+        assert_eq!(allocator.allocate(15).unwrap().offset, blocks[0].offset);
+        assert_eq!(allocator.largest_free_block(), 5);
+    }
+
+    #[test]
+    fn free_blocks_next_to_each_other_are_merged() {
+        let mut allocator = ArenaAllocator::new(90);
+        let blocks: Vec<_> = (0..9).map(|_| allocator.allocate(10).unwrap()).collect();
+        assert_eq!(allocator.free_space(), 0);
+
+        // Fragmented: half of the arena is free but only in blocks of 10
+        for block in blocks.iter().step_by(2) {
+            allocator.free(*block);
+        }
+
+        assert_eq!(allocator.free_space(), 50);
+        assert_eq!(allocator.largest_free_block(), 10);
+        assert_eq!(allocator.allocate(20), None);
+
+        // Freeing the blocks in between joins everything again
+        for block in blocks.iter().skip(1).step_by(2) {
+            allocator.free(*block);
+        }
+
+        assert_eq!(allocator.largest_free_block(), 90);
+        assert_eq!(
+            allocator.allocate(90),
+            Some(Allocation {
+                offset: 0,
+                size: 90
+            })
+        );
+    }
+
+    #[test]
+    fn growing_adds_space_after_the_allocations() {
+        let mut allocator = ArenaAllocator::new(50);
+        let a = allocator.allocate(40).unwrap();
+        assert_eq!(allocator.allocate(30), None);
+
+        // The free end of the arena is merged with the new space
+        allocator.grow(80);
+        assert_eq!(allocator.capacity(), 80);
+        assert_eq!(allocator.largest_free_block(), 40);
+        assert_eq!(allocator.allocate(30).unwrap().offset, a.end());
+
+        allocator.free(a);
+        assert_eq!(allocator.free_space(), 50);
+    }
+
+    #[test]
+    fn shrinking_removes_the_free_space_at_the_end() {
+        let mut allocator = ArenaAllocator::new(64);
+        let blocks: Vec<_> = (0..8).map(|_| allocator.allocate(8).unwrap()).collect();
+        assert_eq!(allocator.shrunk_capacity(8), 64);
+
+        // Mostly empty, but the last block holds the end of the arena
+        for block in &blocks[1..7] {
+            allocator.free(*block);
+        }
+
+        assert_eq!(allocator.shrunk_capacity(8), 64);
+
+        // Only the first block is left, the arena halves while it is used at less than a quarter
+        allocator.free(blocks[7]);
+        assert_eq!(allocator.shrunk_capacity(8), 32);
+        assert_eq!(allocator.shrunk_capacity(64), 64);
+
+        allocator.shrink(32);
+        assert_eq!(allocator.capacity(), 32);
+        assert_eq!(allocator.free_space(), 24);
+        assert_eq!(allocator.allocate(32), None);
+        assert_eq!(allocator.allocate(24).unwrap().offset, 8);
+
+        // A full arena can still be shrunk to its exact size, and grow again
+        allocator.shrink(32);
+        assert_eq!(allocator.free_space(), 0);
+        allocator.grow(64);
+        assert_eq!(allocator.largest_free_block(), 32);
+    }
+}
@@ -0,0 +1,124 @@
+extern crate pretty_env_logger;
+#[macro_use]
+extern crate log;
+
+use minecraft::chunk_manager::ChunkManager;
+use minecraft::constants::*;
+use minecraft::network::server::Server;
+use minecraft::network::server_world::ServerWorld;
+use minecraft::physics::Transform;
+use minecraft::player::PlayerState;
+use minecraft::storage::world_settings::{WorldArguments, WorldSettings};
+use minecraft::storage::WorldStorage;
+use minecraft::world_generation::world_generator_from_name;
+use nalgebra_glm::vec3;
+use specs::{Join, WorldExt};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Runs the simulation without a window nor an OpenGL context, clients connect on `SERVER_PORT`.
+// Usage: minecraft-server [number of ticks to run before saving the world and exiting]
+//                         [--seed <number or text>]
+//                         [--generator <see `world_generator_from_name`>]
+// The seed and the generator of an existing world can't be changed
+fn main() {
+    let mut log_builder = pretty_env_logger::formatted_builder();
+    log_builder.parse_filters(LOG_LEVEL.as_str()).init();
+
+    let arguments =
+        WorldArguments::parse(std::env::args().skip(1)).unwrap_or_else(|err| panic!("{err}"));
+    assert!(
+        arguments.connect.is_none(),
+        "--connect is an option of the client"
+    );
+    let max_ticks = arguments.positional.first().map(|ticks| {
+        ticks
+            .parse::<u64>()
+            .expect("The number of ticks must be a positive integer")
+    });
+    let world_settings = WorldSettings::open(
+        WORLD_DIRECTORY,
+        arguments.seed,
+        arguments.generator.as_deref(),
+    )
+    .expect("Failed to read the world settings");
+    info!(
+        "Seed: {}, world generator: {}",
+        world_settings.seed, world_settings.generator
+    );
+    let world_generator =
+        world_generator_from_name(&world_settings.generator).unwrap_or_else(|err| panic!("{err}"));
+
+    let chunk_manager = Arc::new(ChunkManager::new());
+    let spawn_position = vec3(8.0, 195.0, 8.0);
+
+    let mut server = Server::new(Arc::clone(&chunk_manager), spawn_position);
+
+    match server.listen(("0.0.0.0", SERVER_PORT)) {
+        Ok(address) => info!("Listening on {address}"),
+        Err(err) => {
+            error!("Failed to listen on port {SERVER_PORT}: {err}");
+            std::process::exit(1);
+        }
+    }
+
+    let mut server_world = ServerWorld::new(
+        chunk_manager,
+        world_generator,
+        world_settings.seed,
+        spawn_position,
+    );
+    server_world.world.insert(Arc::new(
+        WorldStorage::open(WORLD_DIRECTORY).expect("Failed to open the world directory"),
+    ));
+
+    let tick_duration = Duration::from_secs_f32(1.0 / PHYSICS_TICKRATE);
+    let mut ticks = 0;
+
+    while max_ticks.is_none_or(|max_ticks| ticks < max_ticks) {
+        let tick_start = Instant::now();
+
+        for event in server.update() {
+            server_world.handle_event(event);
+        }
+
+        server_world.tick();
+        ticks += 1;
+
+        // Log the position of the players every second
+        if ticks % PHYSICS_TICKRATE as u64 == 0 {
+            let world = &server_world.world;
+            let transform = world.read_storage::<Transform>();
+            let player_state = world.read_storage::<PlayerState>();
+
+            for (transform, _) in (&transform, &player_state).join() {
+                let position = transform.position;
+                info!(
+                    "Tick {ticks}: player at ({:.2}, {:.2}, {:.2}), {} chunk columns loaded",
+                    position.x,
+                    position.y,
+                    position.z,
+                    world
+                        .read_resource::<Arc<ChunkManager>>()
+                        .loaded_chunk_columns
+                        .read()
+                        .len()
+                );
+            }
+        }
+
+        if let Some(remaining) = tick_duration.checked_sub(tick_start.elapsed()) {
+            thread::sleep(remaining);
+        }
+    }
+
+    server.shutdown("Server closed");
+
+    let chunk_manager = server_world.world.read_resource::<Arc<ChunkManager>>();
+    let world_storage = server_world.world.read_resource::<Arc<WorldStorage>>();
+
+    if let Err(err) = world_storage.save_loaded_columns(&chunk_manager) {
+        error!("Failed to save the world: {err}");
+    }
+}
@@ -0,0 +1,187 @@
+use crate::chunk::BlockID;
+
+// Landscape of a (x, z) position, chosen by the world generator and stored in the chunk columns.
+// The numeric ids are saved with the world, never change them once they are used.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[repr(u8)]
+pub enum Biome {
+    #[default]
+    Plains = 0,
+    Forest = 1,
+    Desert = 2,
+    Mountains = 3,
+    Ocean = 4,
+    Taiga = 5,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TreeKind {
+    Oak,
+    Spruce,
+}
+
+pub struct BiomeParameters {
+    // The terrain goes up and down around `base_height` by at most `height_amplitude` blocks,
+    // both are blended with the biomes around
+    pub base_height: f64,
+    pub height_amplitude: f64,
+    // Top block of the terrain, and the blocks right below it
+    pub surface_block: BlockID,
+    pub filler_block: BlockID,
+    pub filler_depth: i32,
+    pub tree: Option<TreeKind>,
+    // From 0 (never) to 1 (on every spot that allows a tree)
+    pub tree_density: f64,
+}
+
+const PLAINS: BiomeParameters = BiomeParameters {
+    base_height: 100.0,
+    height_amplitude: 12.0,
+    surface_block: BlockID::GRASS_BLOCK,
+    filler_block: BlockID::DIRT,
+    filler_depth: 3,
+    tree: Some(TreeKind::Oak),
+    tree_density: 0.08,
+};
+
+const FOREST: BiomeParameters = BiomeParameters {
+    base_height: 104.0,
+    height_amplitude: 24.0,
+    surface_block: BlockID::GRASS_BLOCK,
+    filler_block: BlockID::DIRT,
+    filler_depth: 3,
+    tree: Some(TreeKind::Oak),
+    tree_density: 1.0,
+};
+
+const DESERT: BiomeParameters = BiomeParameters {
+    base_height: 98.0,
+    height_amplitude: 10.0,
+    surface_block: BlockID::SAND,
+    filler_block: BlockID::SAND,
+    filler_depth: 4,
+    tree: None,
+    tree_density: 0.0,
+};
+
+const MOUNTAINS: BiomeParameters = BiomeParameters {
+    base_height: 130.0,
+    height_amplitude: 64.0,
+    surface_block: BlockID::SNOW_BLOCK,
+    filler_block: BlockID::STONE,
+    filler_depth: 1,
+    tree: Some(TreeKind::Spruce),
+    tree_density: 0.05,
+};
+
+const OCEAN: BiomeParameters = BiomeParameters {
+    base_height: 70.0,
+    height_amplitude: 10.0,
+    surface_block: BlockID::GRAVEL,
+    filler_block: BlockID::SAND,
+    filler_depth: 3,
+    tree: None,
+    tree_density: 0.0,
+};
+
+const TAIGA: BiomeParameters = BiomeParameters {
+    base_height: 106.0,
+    height_amplitude: 20.0,
+    surface_block: BlockID::GRASS_BLOCK,
+    filler_block: BlockID::DIRT,
+    filler_depth: 3,
+    tree: Some(TreeKind::Spruce),
+    tree_density: 0.6,
+};
+
+impl Biome {
+    pub const ALL: [Biome; 6] = [
+        Biome::Plains,
+        Biome::Forest,
+        Biome::Desert,
+        Biome::Mountains,
+        Biome::Ocean,
+        Biome::Taiga,
+    ];
+
+    #[inline]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Biome> {
+        Biome::ALL.get(id as usize).copied()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Plains => "plains",
+            Biome::Forest => "forest",
+            Biome::Desert => "desert",
+            Biome::Mountains => "mountains",
+            Biome::Ocean => "ocean",
+            Biome::Taiga => "taiga",
+        }
+    }
+
+    // Temperature and humidity are between -1 (cold, dry) and 1 (hot, wet)
+    pub fn from_climate(temperature: f64, humidity: f64) -> Biome {
+        if humidity > 0.45 {
+            Biome::Ocean
+        } else if temperature < -0.25 {
+            if humidity < -0.15 {
+                Biome::Mountains
+            } else {
+                Biome::Taiga
+            }
+        } else if temperature > 0.25 && humidity < 0.1 {
+            Biome::Desert
+        } else if humidity > 0.1 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+
+    pub fn parameters(self) -> &'static BiomeParameters {
+        match self {
+            Biome::Plains => &PLAINS,
+            Biome::Forest => &FOREST,
+            Biome::Desert => &DESERT,
+            Biome::Mountains => &MOUNTAINS,
+            Biome::Ocean => &OCEAN,
+            Biome::Taiga => &TAIGA,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn biome_ids_round_trip() {
+        for biome in Biome::ALL {
+            assert_eq!(Biome::from_id(biome.id()), Some(biome));
+        }
+
+        assert_eq!(Biome::from_id(Biome::ALL.len() as u8), None);
+    }
+
+    #[test]
+    fn climates_give_every_biome() {
+        let mut biomes = Vec::new();
+
+        for temperature in -10..=10 {
+            for humidity in -10..=10 {
+                let biome = Biome::from_climate(temperature as f64 / 10.0, humidity as f64 / 10.0);
+
+                if !biomes.contains(&biome) {
+                    biomes.push(biome);
+                }
+            }
+        }
+
+        assert_eq!(biomes.len(), Biome::ALL.len());
+    }
+}
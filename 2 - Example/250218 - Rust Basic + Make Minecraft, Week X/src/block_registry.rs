@@ -0,0 +1,356 @@
+use crate::block_texture_faces::BlockFaces;
+use crate::chunk::BlockID;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+lazy_static! {
+    // Every block of the game, described by `blocks.toml`
+    pub static ref BLOCK_REGISTRY: BlockRegistry =
+        BlockRegistry::from_toml(include_str!("../blocks.toml"))
+            .unwrap_or_else(|err| panic!("Invalid block manifest: {err}"));
+}
+
+// One entry of the manifest, see `blocks.toml` for the meaning and the default of each field
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockEntry {
+    id: u8,
+    name: String,
+    textures: Option<BlockFaces<String>>,
+    #[serde(default)]
+    transparent: bool,
+    #[serde(default = "default_solid")]
+    solid: bool,
+    occludes_ambient_occlusion: Option<bool>,
+    #[serde(default)]
+    light_emission: u8,
+    #[serde(default)]
+    hardness: f32,
+    tool: Option<ToolType>,
+    drops: Option<String>,
+    render_layer: Option<RenderLayer>,
+    #[serde(default = "default_max_stack_size")]
+    max_stack_size: u32,
+}
+
+fn default_solid() -> bool {
+    true
+}
+
+fn default_max_stack_size() -> u32 {
+    64
+}
+
+// Kind of tool that breaks a block faster than the hand
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolType {
+    Pickaxe,
+    Axe,
+    Shovel,
+}
+
+// Pass in which the faces of a block are drawn, the layers are drawn in this order
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderLayer {
+    // Without blending
+    Opaque = 0,
+    // Without blending, the fully transparent pixels of the texture are discarded
+    Cutout = 1,
+    // Blended with what is behind, the faces are drawn from the farthest to the closest
+    Translucent = 2,
+}
+
+impl RenderLayer {
+    pub const ALL: [RenderLayer; 3] = [
+        RenderLayer::Opaque,
+        RenderLayer::Cutout,
+        RenderLayer::Translucent,
+    ];
+}
+
+#[derive(Deserialize)]
+struct BlockManifest {
+    blocks: Vec<BlockEntry>,
+}
+
+pub struct BlockDefinition {
+    pub id: BlockID,
+    pub name: String,
+    // Paths of the textures, `None` for blocks that are never rendered
+    pub textures: Option<BlockFaces<String>>,
+    pub is_transparent: bool,
+    pub is_solid: bool,
+    pub occludes_ambient_occlusion: bool,
+    pub light_emission: u8,
+    // Negative for unbreakable blocks
+    pub hardness: f32,
+    pub tool: Option<ToolType>,
+    // `None` when nothing is dropped
+    pub drops: Option<BlockID>,
+    pub render_layer: RenderLayer,
+    pub max_stack_size: u32,
+}
+
+pub struct BlockRegistry {
+    // Indexed by block id
+    blocks: Vec<Option<BlockDefinition>>,
+    ids_by_name: HashMap<String, BlockID>,
+}
+
+impl BlockRegistry {
+    pub fn from_toml(manifest: &str) -> Result<Self, String> {
+        let manifest: BlockManifest = toml::from_str(manifest).map_err(|err| err.to_string())?;
+
+        let mut ids_by_name = HashMap::new();
+
+        for entry in manifest.blocks.iter() {
+            if ids_by_name
+                .insert(entry.name.clone(), BlockID(entry.id))
+                .is_some()
+            {
+                return Err(format!("Block name \"{}\" is used twice", entry.name));
+            }
+        }
+
+        let mut blocks: Vec<Option<BlockDefinition>> = (0..=u8::MAX).map(|_| None).collect();
+
+        for entry in manifest.blocks {
+            let id = entry.id as usize;
+
+            if blocks[id].is_some() {
+                return Err(format!("Block id {id} is used twice"));
+            }
+
+            if entry.light_emission > 15 {
+                return Err(format!(
+                    "Light emission of \"{}\" must be between 0 and 15",
+                    entry.name
+                ));
+            }
+
+            if entry.max_stack_size == 0 {
+                return Err(format!(
+                    "Max stack size of \"{}\" must be at least 1",
+                    entry.name
+                ));
+            }
+
+            let render_layer = match (entry.render_layer, entry.transparent) {
+                (Some(RenderLayer::Opaque) | None, false) => RenderLayer::Opaque,
+                (Some(render_layer), false) => {
+                    return Err(format!(
+                        "\"{}\" must be transparent to be drawn in the {render_layer:?} layer",
+                        entry.name
+                    ));
+                }
+                (Some(render_layer), true) => render_layer,
+                (None, true) => RenderLayer::Cutout,
+            };
+
+            let drops = match &entry.drops {
+                Some(name) => *ids_by_name.get(name).ok_or_else(|| {
+                    format!("\"{}\" drops the unknown block \"{name}\"", entry.name)
+                })?,
+                None => BlockID(entry.id),
+            };
+
+            blocks[id] = Some(BlockDefinition {
+                id: BlockID(entry.id),
+                textures: entry.textures,
+                is_transparent: entry.transparent,
+                is_solid: entry.solid,
+                occludes_ambient_occlusion: entry
+                    .occludes_ambient_occlusion
+                    .unwrap_or(!entry.transparent),
+                light_emission: entry.light_emission,
+                hardness: entry.hardness,
+                tool: entry.tool,
+                drops: Some(drops).filter(|drops| !drops.is_air()),
+                render_layer,
+                max_stack_size: entry.max_stack_size,
+                name: entry.name,
+            });
+        }
+
+        if !matches!(&blocks[BlockID::AIR.0 as usize], Some(air) if air.name == "air") {
+            return Err(format!("Block id {} must be \"air\"", BlockID::AIR.0));
+        }
+
+        Ok(Self {
+            blocks,
+            ids_by_name,
+        })
+    }
+
+    #[inline]
+    pub fn get(&self, block: BlockID) -> Option<&BlockDefinition> {
+        self.blocks[block.0 as usize].as_ref()
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<BlockID> {
+        self.ids_by_name.get(name).copied()
+    }
+
+    // Every registered block, sorted by id
+    pub fn iter(&self) -> impl Iterator<Item = &BlockDefinition> {
+        self.blocks.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_blocks_match_the_manifest() {
+        let builtin_blocks = [
+            (BlockID::AIR, "air"),
+            (BlockID::DIRT, "dirt"),
+            (BlockID::GRASS_BLOCK, "grass_block"),
+            (BlockID::STONE, "stone"),
+            (BlockID::COBBLESTONE, "cobblestone"),
+            (BlockID::BEDROCK, "bedrock"),
+            (BlockID::OBSIDIAN, "obsidian"),
+            (BlockID::OAK_LOG, "oak_log"),
+            (BlockID::OAK_LEAVES, "oak_leaves"),
+            (BlockID::OAK_PLANKS, "oak_planks"),
+            (BlockID::GLASS, "glass"),
+            (BlockID::DEBUG, "debug"),
+            (BlockID::DEBUG2, "debug2"),
+            (BlockID::GLOWSTONE, "glowstone"),
+            (BlockID::SAND, "sand"),
+            (BlockID::GRAVEL, "gravel"),
+            (BlockID::SNOW_BLOCK, "snow_block"),
+            (BlockID::SPRUCE_LOG, "spruce_log"),
+            (BlockID::SPRUCE_LEAVES, "spruce_leaves"),
+            (BlockID::COAL_ORE, "coal_ore"),
+            (BlockID::IRON_ORE, "iron_ore"),
+            (BlockID::GOLD_ORE, "gold_ore"),
+            (BlockID::DIAMOND_ORE, "diamond_ore"),
+            (BlockID::WATER, "water"),
+            (BlockID::LAVA, "lava"),
+            (BlockID::CRAFTING_TABLE, "crafting_table"),
+        ];
+
+        for (block, name) in builtin_blocks {
+            assert_eq!(BLOCK_REGISTRY.get_by_name(name), Some(block));
+            assert_eq!(block.name(), name);
+        }
+    }
+
+    #[test]
+    fn flags_are_read_from_the_manifest() {
+        assert!(BlockID::AIR.is_transparent());
+        assert!(!BlockID::AIR.is_solid());
+        assert!(!BlockID::AIR.occludes_ambient_occlusion());
+
+        assert!(BlockID::GLASS.is_transparent_not_air());
+        assert!(!BlockID::GLASS.occludes_ambient_occlusion());
+        assert!(BlockID::OAK_LEAVES.is_transparent_not_air());
+        assert!(BlockID::OAK_LEAVES.occludes_ambient_occlusion());
+
+        assert!(BlockID::STONE.is_opaque());
+        assert!(BlockID::STONE.is_solid());
+        assert!(BlockID::STONE.occludes_ambient_occlusion());
+
+        assert_eq!(
+            BlockID::STONE.definition().drops,
+            Some(BlockID::COBBLESTONE)
+        );
+        assert_eq!(BlockID::DIRT.definition().drops, Some(BlockID::DIRT));
+        assert_eq!(BlockID::GLASS.definition().drops, None);
+        assert!(BlockID::BEDROCK.definition().hardness < 0.0);
+        assert_eq!(BlockID::STONE.definition().tool, Some(ToolType::Pickaxe));
+        assert_eq!(BlockID::OAK_LOG.definition().tool, Some(ToolType::Axe));
+        assert_eq!(BlockID::GLASS.definition().tool, None);
+
+        assert_eq!(BlockID::DIRT.max_stack_size(), 64);
+        assert_eq!(BlockID::WATER.max_stack_size(), 1);
+
+        assert_eq!(BlockID::STONE.render_layer(), RenderLayer::Opaque);
+        assert_eq!(BlockID::GLASS.render_layer(), RenderLayer::Cutout);
+        assert_eq!(BlockID::OAK_LEAVES.render_layer(), RenderLayer::Cutout);
+        assert_eq!(BlockID::WATER.render_layer(), RenderLayer::Translucent);
+    }
+
+    #[test]
+    fn blocks_get_default_flags() {
+        let registry = BlockRegistry::from_toml(
+            r#"
+            [[blocks]]
+            id = 0
+            name = "air"
+            transparent = true
+            solid = false
+
+            [[blocks]]
+            id = 42
+            name = "lamp"
+            textures = { sides = "side.png", top = "top.png", bottom = "bottom.png" }
+            light_emission = 15
+            "#,
+        )
+        .unwrap();
+
+        let lamp = registry.get_by_name("lamp").unwrap();
+        let lamp = registry.get(lamp).unwrap();
+
+        assert_eq!(lamp.id.id(), 42);
+        assert!(!lamp.is_transparent);
+        assert!(lamp.is_solid);
+        assert!(lamp.occludes_ambient_occlusion);
+        assert_eq!(lamp.light_emission, 15);
+        assert_eq!(lamp.drops, Some(lamp.id));
+        assert_eq!(lamp.max_stack_size, 64);
+        assert_eq!(lamp.tool, None);
+        assert_eq!(lamp.render_layer, RenderLayer::Opaque);
+        assert!(matches!(
+            lamp.textures.as_ref(),
+            Some(BlockFaces::Sides { top, .. }) if top == "top.png"
+        ));
+
+        assert_eq!(registry.iter().count(), 2);
+        assert!(registry.get_by_name("dirt").is_none());
+    }
+
+    #[test]
+    fn invalid_manifests_are_rejected() {
+        let air = "[[blocks]]\nid = 0\nname = \"air\"\n";
+
+        // Duplicated id
+        assert!(
+            BlockRegistry::from_toml(&format!("{air}[[blocks]]\nid = 0\nname = \"b\"\n")).is_err()
+        );
+        // Duplicated name
+        assert!(
+            BlockRegistry::from_toml(&format!("{air}[[blocks]]\nid = 1\nname = \"air\"\n"))
+                .is_err()
+        );
+        // Unknown drop
+        assert!(BlockRegistry::from_toml(&format!(
+            "{air}[[blocks]]\nid = 1\nname = \"b\"\ndrops = \"c\"\n"
+        ))
+        .is_err());
+        // Light level out of range
+        assert!(BlockRegistry::from_toml(&format!(
+            "{air}[[blocks]]\nid = 1\nname = \"b\"\nlight_emission = 16\n"
+        ))
+        .is_err());
+        // Unknown tool
+        assert!(BlockRegistry::from_toml(&format!(
+            "{air}[[blocks]]\nid = 1\nname = \"b\"\ntool = \"hoe\"\n"
+        ))
+        .is_err());
+        // Translucent block that hides the blocks behind it
+        assert!(BlockRegistry::from_toml(&format!(
+            "{air}[[blocks]]\nid = 1\nname = \"b\"\nrender_layer = \"translucent\"\n"
+        ))
+        .is_err());
+        // Unknown field
+        assert!(BlockRegistry::from_toml(&format!("{air}colour = 1\n")).is_err());
+        // No air
+        assert!(BlockRegistry::from_toml("[[blocks]]\nid = 0\nname = \"b\"\n").is_err());
+    }
+}
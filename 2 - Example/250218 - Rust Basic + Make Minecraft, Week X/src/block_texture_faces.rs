@@ -1,6 +1,10 @@
 use crate::types::{TextureLayer, UVFaces};
+use serde::Deserialize;
 
-#[derive(Copy, Clone)]
+// In `blocks.toml`, a single value is used for every face and
+// the other variants are written as tables (e.g. `{ sides = ..., top = ..., bottom = ... }`)
+#[derive(Copy, Clone, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
 pub enum BlockFaces<T> {
     All(T),
     Sides {
@@ -18,6 +22,35 @@ pub enum BlockFaces<T> {
     },
 }
 
+impl<T> BlockFaces<T> {
+    // Applies `f` to the value of every face
+    pub fn map<'a, U>(&'a self, mut f: impl FnMut(&'a T) -> U) -> BlockFaces<U> {
+        match self {
+            BlockFaces::All(all) => BlockFaces::All(f(all)),
+            BlockFaces::Sides { sides, top, bottom } => BlockFaces::Sides {
+                sides: f(sides),
+                top: f(top),
+                bottom: f(bottom),
+            },
+            BlockFaces::Each {
+                top,
+                bottom,
+                front,
+                back,
+                left,
+                right,
+            } => BlockFaces::Each {
+                top: f(top),
+                bottom: f(bottom),
+                front: f(front),
+                back: f(back),
+                left: f(left),
+                right: f(right),
+            },
+        }
+    }
+}
+
 // Unpacks a BlockFaces<TextureLayer> instance and returns a tuple of UV coordinates for each face of the block
 impl BlockFaces<TextureLayer> {
     pub fn get_uv_of_every_face(&self) -> UVFaces {
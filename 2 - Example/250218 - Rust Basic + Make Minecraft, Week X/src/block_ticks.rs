@@ -0,0 +1,445 @@
+use crate::chunk::{BlockID, Chunk, ChunkColumn};
+use crate::chunk_manager::{BlockChange, ChunkManager, CHUNK_VOLUME};
+use crate::fluid::{update_fluid, Fluid};
+use crate::light::LightChannel;
+use crate::world_generation::position_hash;
+use crossbeam_channel::Receiver;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+// Blocks of every non-empty chunk of the lit columns that get a random tick at each tick
+pub const RANDOM_TICKS_PER_CHUNK: u32 = 3;
+
+// Scheduled ticks run in a single tick, the others wait for the next ticks
+const MAX_SCHEDULED_TICKS_PER_TICK: usize = 4096;
+
+// Grass needs this much light above it to spread, it dies when it is covered
+const GRASS_SPREAD_MIN_LIGHT: u8 = 9;
+
+// Leaves decay when no log can be reached within this many steps through leaves
+const LEAF_DECAY_DISTANCE: u32 = 4;
+
+const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+// Makes the blocks of a `ChunkManager` evolve, one tick at a time. Blocks are updated by:
+// - scheduled ticks: "update the block at (x, y, z) in N ticks", stored in the chunks so that
+//   they are saved with them. A block change schedules the blocks around it that react to
+//   their neighbours (the fluids).
+// - random ticks: a few random blocks of every chunk at each tick (grass, leaves).
+// The random blocks only depend on the seed, the tick and the chunk, so that a world evolves the
+// same way whatever the order in which its chunks are ticked.
+pub struct BlockTicks {
+    seed: u32,
+    current_tick: u64,
+    block_changes_rx: Receiver<BlockChange>,
+}
+
+impl BlockTicks {
+    // Only the changes made after the creation of the scheduler are seen
+    pub fn new(chunk_manager: &ChunkManager, seed: u32) -> Self {
+        Self {
+            seed,
+            current_tick: 0,
+            block_changes_rx: chunk_manager.subscribe_to_block_changes(),
+        }
+    }
+
+    pub fn current_tick(&self) -> u64 {
+        self.current_tick
+    }
+
+    // Whether a block change or a scheduled tick is waiting
+    pub fn has_scheduled_ticks(&self, chunk_manager: &ChunkManager) -> bool {
+        !self.block_changes_rx.is_empty()
+            || chunk_manager
+                .loaded_chunk_columns
+                .read()
+                .values()
+                .flat_map(|column| column.chunks.iter())
+                .any(|chunk| !chunk.scheduled_ticks.read().is_empty())
+    }
+
+    pub fn tick(&mut self, chunk_manager: &ChunkManager) {
+        let block_changes: Vec<_> = self.block_changes_rx.try_iter().collect();
+
+        for block_change in block_changes {
+            schedule_neighbour_updates(
+                chunk_manager,
+                block_change.x,
+                block_change.y,
+                block_change.z,
+            );
+        }
+
+        self.current_tick += 1;
+
+        // The columns are ticked in the same order every time
+        let mut columns: Vec<_> = chunk_manager
+            .loaded_chunk_columns
+            .read()
+            .iter()
+            .map(|(&xz, column)| (xz, Arc::clone(column)))
+            .collect();
+        columns.sort_unstable_by_key(|&(xz, _)| xz);
+
+        self.run_scheduled_ticks(chunk_manager, &columns);
+        self.run_random_ticks(chunk_manager, &columns);
+    }
+
+    fn run_scheduled_ticks(
+        &self,
+        chunk_manager: &ChunkManager,
+        columns: &[((i32, i32), Arc<ChunkColumn>)],
+    ) {
+        let mut due_blocks = Vec::new();
+
+        for &((chunk_x, chunk_z), ref column) in columns {
+            for (chunk_y, chunk) in column.chunks.iter().enumerate() {
+                if chunk.scheduled_ticks.read().is_empty() {
+                    continue;
+                }
+
+                // The due ticks over the limit stay at 0 and run at the next tick
+                chunk.scheduled_ticks.write().retain(|&index, delay| {
+                    *delay = delay.saturating_sub(1);
+
+                    if *delay > 0 || due_blocks.len() >= MAX_SCHEDULED_TICKS_PER_TICK {
+                        return true;
+                    }
+
+                    let (block_x, block_y, block_z) =
+                        Chunk::array_index_to_chunk_coords(index as usize);
+                    due_blocks.push(ChunkManager::get_global_coords((
+                        chunk_x,
+                        chunk_y as i32,
+                        chunk_z,
+                        block_x,
+                        block_y,
+                        block_z,
+                    )));
+
+                    false
+                });
+            }
+        }
+
+        for (x, y, z) in due_blocks {
+            run_scheduled_tick(chunk_manager, x, y, z);
+        }
+    }
+
+    fn run_random_ticks(
+        &self,
+        chunk_manager: &ChunkManager,
+        columns: &[((i32, i32), Arc<ChunkColumn>)],
+    ) {
+        for &((chunk_x, chunk_z), ref column) in columns {
+            // The columns being generated are left alone
+            if !*column.is_lit.read() {
+                continue;
+            }
+
+            for (chunk_y, chunk) in column.chunks.iter().enumerate() {
+                if chunk.is_empty() {
+                    continue;
+                }
+
+                let chunk_y = chunk_y as i32;
+                let mut rng =
+                    random_tick_rng(self.seed, self.current_tick, chunk_x, chunk_y, chunk_z);
+
+                for _ in 0..RANDOM_TICKS_PER_CHUNK {
+                    let (block_x, block_y, block_z) =
+                        Chunk::array_index_to_chunk_coords(rng.gen_range(0..CHUNK_VOLUME as usize));
+                    let (x, y, z) = ChunkManager::get_global_coords((
+                        chunk_x, chunk_y, chunk_z, block_x, block_y, block_z,
+                    ));
+
+                    run_random_tick(
+                        chunk_manager,
+                        chunk.get_block(block_x, block_y, block_z),
+                        x,
+                        y,
+                        z,
+                        &mut rng,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn random_tick_rng(seed: u32, tick: u64, chunk_x: i32, chunk_y: i32, chunk_z: i32) -> StdRng {
+    let tick_seed = position_hash(seed as u64, tick as i32, (tick >> 32) as i32);
+
+    StdRng::seed_from_u64(position_hash(
+        position_hash(tick_seed, chunk_y, 0),
+        chunk_x,
+        chunk_z,
+    ))
+}
+
+// Ticks between a change next to the block and its update, `None` when it doesn't react to its
+// neighbours
+fn neighbour_update_delay(block: BlockID) -> Option<u32> {
+    Fluid::from_block(block).map(Fluid::tick_delay)
+}
+
+// Schedules the blocks at (x, y, z) and around it that react to the change of a neighbour
+fn schedule_neighbour_updates(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) {
+    for (dx, dy, dz) in [(0, 0, 0)].into_iter().chain(NEIGHBOURS) {
+        let (x, y, z) = (x + dx, y + dy, z + dz);
+
+        if let Some(delay) = chunk_manager
+            .get_block(x, y, z)
+            .and_then(neighbour_update_delay)
+        {
+            chunk_manager.schedule_block_tick(x, y, z, delay);
+        }
+    }
+}
+
+fn run_scheduled_tick(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) {
+    if chunk_manager
+        .get_block(x, y, z)
+        .is_some_and(|block| block.is_fluid())
+    {
+        update_fluid(chunk_manager, x, y, z);
+    }
+}
+
+fn run_random_tick(
+    chunk_manager: &ChunkManager,
+    block: BlockID,
+    x: i32,
+    y: i32,
+    z: i32,
+    rng: &mut StdRng,
+) {
+    match block {
+        BlockID::GRASS_BLOCK => grow_grass(chunk_manager, x, y, z, rng),
+        BlockID::OAK_LEAVES | BlockID::SPRUCE_LEAVES => decay_leaves(chunk_manager, x, y, z),
+        _ => {}
+    }
+}
+
+fn is_covered(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> bool {
+    chunk_manager
+        .get_block(x, y, z)
+        .is_some_and(|block| block.is_opaque() || block.is_fluid())
+}
+
+fn light_at(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> u8 {
+    let sky_light = chunk_manager.get_light(x, y, z, LightChannel::Sky);
+    let block_light = chunk_manager.get_light(x, y, z, LightChannel::Block);
+
+    sky_light.max(block_light).unwrap_or(0)
+}
+
+// Covered grass turns into dirt, otherwise it spreads to a random dirt block around it that has
+// enough light
+fn grow_grass(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32, rng: &mut StdRng) {
+    if is_covered(chunk_manager, x, y + 1, z) {
+        chunk_manager.put_block(x, y, z, BlockID::DIRT);
+        return;
+    }
+
+    if light_at(chunk_manager, x, y + 1, z) < GRASS_SPREAD_MIN_LIGHT {
+        return;
+    }
+
+    let (x, y, z) = (
+        x + rng.gen_range(-1..=1),
+        y + rng.gen_range(-3..=1),
+        z + rng.gen_range(-1..=1),
+    );
+    let is_column_lit = chunk_manager
+        .get_column(x.div_euclid(16), z.div_euclid(16))
+        .is_some_and(|column| *column.is_lit.read());
+
+    if is_column_lit
+        && chunk_manager.get_block(x, y, z) == Some(BlockID::DIRT)
+        && !is_covered(chunk_manager, x, y + 1, z)
+        && light_at(chunk_manager, x, y + 1, z) >= GRASS_SPREAD_MIN_LIGHT
+    {
+        chunk_manager.put_block(x, y, z, BlockID::GRASS_BLOCK);
+    }
+}
+
+// Leaves that are too far from a log disappear. Placed leaves are not told apart from the ones
+// of the trees, so they decay as well.
+fn decay_leaves(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) {
+    let mut visited = HashSet::from([(x, y, z)]);
+    let mut queue = VecDeque::from([((x, y, z), 0)]);
+
+    while let Some(((x, y, z), distance)) = queue.pop_front() {
+        if distance == LEAF_DECAY_DISTANCE {
+            continue;
+        }
+
+        for (dx, dy, dz) in NEIGHBOURS {
+            let neighbour = (x + dx, y + dy, z + dz);
+
+            if !visited.insert(neighbour) {
+                continue;
+            }
+
+            match chunk_manager.get_block(neighbour.0, neighbour.1, neighbour.2) {
+                // The log could be in a column that isn't loaded
+                Some(BlockID::OAK_LOG | BlockID::SPRUCE_LOG) | None => return,
+                Some(BlockID::OAK_LEAVES | BlockID::SPRUCE_LEAVES) => {
+                    queue.push_back((neighbour, distance + 1));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    chunk_manager.put_block(x, y, z, BlockID::AIR);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{flat_world, light_flat_world};
+    use crate::world_generation::column_hash;
+
+    // A 3x3 columns world of dirt up to y = 3 with a 4x4 patch of grass in the middle, lit
+    fn dirt_world() -> ChunkManager {
+        let chunk_manager =
+            flat_world(&[BlockID::STONE, BlockID::DIRT, BlockID::DIRT, BlockID::DIRT]);
+
+        for x in 6..10 {
+            for z in 6..10 {
+                chunk_manager.set_block(x, 3, z, BlockID::GRASS_BLOCK);
+            }
+        }
+
+        light_flat_world(&chunk_manager);
+
+        chunk_manager
+    }
+
+    fn count_blocks(chunk_manager: &ChunkManager, block: BlockID) -> usize {
+        chunk_manager
+            .loaded_chunk_columns
+            .read()
+            .values()
+            .flat_map(|column| column.chunks.iter())
+            .flat_map(|chunk| chunk.blocks.read().to_vec())
+            .filter(|&other_block| other_block == block)
+            .count()
+    }
+
+    #[test]
+    fn scheduled_ticks_are_deduplicated() {
+        let chunk_manager = dirt_world();
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+        let scheduled_ticks = || {
+            let chunk = chunk_manager.get_chunk(0, 0, 0).unwrap();
+            let scheduled_ticks = chunk.scheduled_ticks.read().clone();
+
+            scheduled_ticks
+        };
+
+        assert!(chunk_manager.schedule_block_tick(1, 5, 2, 5));
+        assert!(!chunk_manager.schedule_block_tick(1, 5, 2, 2));
+        assert!(!chunk_manager.schedule_block_tick(1, 500, 2, 2));
+        assert_eq!(scheduled_ticks().len(), 1);
+        assert!(block_ticks.has_scheduled_ticks(&chunk_manager));
+
+        for _ in 0..4 {
+            block_ticks.tick(&chunk_manager);
+        }
+
+        assert_eq!(scheduled_ticks().values().next(), Some(&1));
+
+        block_ticks.tick(&chunk_manager);
+        assert!(scheduled_ticks().is_empty());
+        assert!(!block_ticks.has_scheduled_ticks(&chunk_manager));
+        assert_eq!(block_ticks.current_tick(), 5);
+    }
+
+    #[test]
+    fn grass_spreads_to_lit_dirt_and_dies_when_covered() {
+        let chunk_manager = dirt_world();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Dirt under a block doesn't get grass
+        chunk_manager.set_block(10, 4, 8, BlockID::STONE);
+
+        for _ in 0..500 {
+            grow_grass(&chunk_manager, 9, 3, 8, &mut rng);
+        }
+
+        assert_eq!(chunk_manager.get_block(9, 3, 9), Some(BlockID::GRASS_BLOCK));
+        assert_eq!(
+            chunk_manager.get_block(10, 3, 7),
+            Some(BlockID::GRASS_BLOCK)
+        );
+        assert_eq!(chunk_manager.get_block(10, 3, 8), Some(BlockID::DIRT));
+        assert_eq!(chunk_manager.get_block(9, 2, 9), Some(BlockID::DIRT));
+
+        chunk_manager.set_block(9, 4, 8, BlockID::WATER);
+        grow_grass(&chunk_manager, 9, 3, 8, &mut rng);
+        assert_eq!(chunk_manager.get_block(9, 3, 8), Some(BlockID::DIRT));
+    }
+
+    #[test]
+    fn leaves_decay_far_from_logs() {
+        let chunk_manager = ChunkManager::new();
+        chunk_manager.add_chunk_column((0, 0), Arc::new(ChunkColumn::new()));
+
+        chunk_manager.set_block(2, 5, 8, BlockID::OAK_LOG);
+
+        for x in 3..=9 {
+            chunk_manager.set_block(x, 5, 8, BlockID::OAK_LEAVES);
+        }
+
+        decay_leaves(&chunk_manager, 6, 5, 8);
+        assert_eq!(chunk_manager.get_block(6, 5, 8), Some(BlockID::OAK_LEAVES));
+
+        decay_leaves(&chunk_manager, 7, 5, 8);
+        assert_eq!(chunk_manager.get_block(7, 5, 8), Some(BlockID::AIR));
+
+        chunk_manager.set_block(2, 5, 8, BlockID::AIR);
+        decay_leaves(&chunk_manager, 3, 5, 8);
+        assert_eq!(chunk_manager.get_block(3, 5, 8), Some(BlockID::AIR));
+    }
+
+    #[test]
+    fn random_ticks_only_depend_on_the_seed() {
+        let run = |seed| {
+            let chunk_manager = dirt_world();
+            let mut block_ticks = BlockTicks::new(&chunk_manager, seed);
+
+            for _ in 0..2000 {
+                block_ticks.tick(&chunk_manager);
+            }
+
+            assert!(count_blocks(&chunk_manager, BlockID::GRASS_BLOCK) > 16);
+
+            let mut hashes: Vec<_> = chunk_manager
+                .loaded_chunk_columns
+                .read()
+                .iter()
+                .map(|(&xz, column)| (xz, column_hash(column)))
+                .collect();
+            hashes.sort();
+
+            hashes
+        };
+
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+    }
+}
@@ -1,43 +1,82 @@
+use crate::biome::Biome;
+use crate::block_registry::{BlockDefinition, RenderLayer, BLOCK_REGISTRY};
+#[cfg(feature = "client")]
+use crate::chunk_arena::ChunkArena;
 use crate::chunk_manager::{CHUNK_SIZE, CHUNK_VOLUME};
-use crate::gl_call;
+#[cfg(feature = "client")]
+use crate::constants::TRANSLUCENT_SORT_DISTANCE;
+use crate::fluid::MAX_FLUID_LEVEL;
+use crate::light::LightChannel;
+#[cfg(feature = "client")]
+use crate::meshing::sort_back_to_front;
+use crate::meshing::{build_greedy_mesh, lower_fluid_surface, ChunkMesh, MeshingMode};
 use crate::shapes::write_unit_cube_to_ptr;
 use crate::types::TexturePack;
+use crate::visibility::VisibilityGraph;
 use bit_vec::BitVec;
+use nalgebra_glm::Vec3;
 use parking_lot::RwLock;
 use rand::distributions::Standard;
 use rand::prelude::Distribution;
 use rand::random;
-use std::ptr::null;
-
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-pub enum BlockID {
-    Air,
-    Dirt,
-    GrassBlock,
-    Stone,
-    Cobblestone,
-    Bedrock,
-    Obsidian,
-    OakLog,
-    OakLeaves,
-    OakPlanks,
-    Glass,
-    Debug,
-    Debug2,
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Numeric id of a block, everything else about it is stored in `BLOCK_REGISTRY`
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct BlockID(pub(crate) u8);
+
+// Blocks that the engine refers to directly, they must match `blocks.toml`
+impl BlockID {
+    pub const AIR: BlockID = BlockID(0);
+    pub const DIRT: BlockID = BlockID(1);
+    pub const GRASS_BLOCK: BlockID = BlockID(2);
+    pub const STONE: BlockID = BlockID(3);
+    pub const COBBLESTONE: BlockID = BlockID(4);
+    pub const BEDROCK: BlockID = BlockID(5);
+    pub const OBSIDIAN: BlockID = BlockID(6);
+    pub const OAK_LOG: BlockID = BlockID(7);
+    pub const OAK_LEAVES: BlockID = BlockID(8);
+    pub const OAK_PLANKS: BlockID = BlockID(9);
+    pub const GLASS: BlockID = BlockID(10);
+    pub const DEBUG: BlockID = BlockID(11);
+    pub const DEBUG2: BlockID = BlockID(12);
+    pub const GLOWSTONE: BlockID = BlockID(13);
+    pub const SAND: BlockID = BlockID(14);
+    pub const GRAVEL: BlockID = BlockID(15);
+    pub const SNOW_BLOCK: BlockID = BlockID(16);
+    pub const SPRUCE_LOG: BlockID = BlockID(17);
+    pub const SPRUCE_LEAVES: BlockID = BlockID(18);
+    pub const COAL_ORE: BlockID = BlockID(19);
+    pub const IRON_ORE: BlockID = BlockID(20);
+    pub const GOLD_ORE: BlockID = BlockID(21);
+    pub const DIAMOND_ORE: BlockID = BlockID(22);
+    pub const WATER: BlockID = BlockID(23);
+    pub const LAVA: BlockID = BlockID(24);
+    pub const CRAFTING_TABLE: BlockID = BlockID(25);
 }
 
 impl BlockID {
+    // Every `BlockID` is registered since they can only be created by `from_id` or be a constant
+    #[inline]
+    pub fn definition(&self) -> &'static BlockDefinition {
+        BLOCK_REGISTRY
+            .get(*self)
+            .unwrap_or_else(|| panic!("Block id {} is not registered", self.0))
+    }
+
+    pub fn name(&self) -> &'static str {
+        &self.definition().name
+    }
+
     #[inline]
     pub fn is_air(&self) -> bool {
-        self == &BlockID::Air
+        *self == BlockID::AIR
     }
 
     #[inline]
     pub fn is_transparent(&self) -> bool {
-        match self {
-            BlockID::Air | BlockID::OakLeaves | BlockID::Glass => true,
-            _ => false,
-        }
+        self.definition().is_transparent
     }
 
     #[inline]
@@ -47,17 +86,56 @@ impl BlockID {
 
     #[inline]
     pub fn is_transparent_not_air(&self) -> bool {
-        match self {
-            BlockID::OakLeaves | BlockID::Glass => true,
-            _ => false,
-        }
+        !self.is_air() && self.is_transparent()
+    }
+
+    #[inline]
+    pub fn is_fluid(&self) -> bool {
+        *self == BlockID::WATER || *self == BlockID::LAVA
+    }
+
+    #[inline]
+    pub fn is_solid(&self) -> bool {
+        self.definition().is_solid
+    }
+
+    #[inline]
+    pub fn occludes_ambient_occlusion(&self) -> bool {
+        self.definition().occludes_ambient_occlusion
+    }
+
+    #[inline]
+    pub fn light_emission(&self) -> u8 {
+        self.definition().light_emission
+    }
+
+    #[inline]
+    pub fn render_layer(&self) -> RenderLayer {
+        self.definition().render_layer
+    }
+
+    #[inline]
+    pub fn max_stack_size(&self) -> u32 {
+        self.definition().max_stack_size
     }
 
+    // Numeric id of the block, used when the world is saved to disk
     #[inline]
-    pub fn is_transparent_no_leaves(&self) -> bool {
-        match self {
-            BlockID::Air | BlockID::Glass => true,
-            _ => false,
+    pub fn id(&self) -> u8 {
+        self.0
+    }
+
+    // Returns `None` if no block has this id
+    pub fn from_id(id: u8) -> Option<BlockID> {
+        BLOCK_REGISTRY.get(BlockID(id)).map(|block| block.id)
+    }
+}
+
+impl fmt::Debug for BlockID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match BLOCK_REGISTRY.get(*self) {
+            Some(block) => write!(f, "{}", block.name),
+            None => write!(f, "BlockID({})", self.0),
         }
     }
 }
@@ -66,83 +144,21 @@ impl Distribution<BlockID> for Standard {
     fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> BlockID {
         match rng.gen_range(1..4) {
             // 0 => BlockID::AIR,
-            1 => BlockID::Dirt,
-            2 => BlockID::Cobblestone,
-            3 => BlockID::Obsidian,
-            _ => BlockID::Air,
+            1 => BlockID::DIRT,
+            2 => BlockID::COBBLESTONE,
+            3 => BlockID::OBSIDIAN,
+            _ => BlockID::AIR,
         }
     }
 }
 
-fn create_vao_vbo() -> (u32, u32) {
-    let mut vao = 0;
-    gl_call!(gl::CreateVertexArrays(1, &mut vao));
-
-    // Position
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
-    gl_call!(gl::VertexArrayAttribFormat(
-        vao,
-        0,
-        3,
-        gl::FLOAT,
-        gl::FALSE,
-        0
-    ));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
-
-    // Texture coords
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
-    gl_call!(gl::VertexArrayAttribFormat(
-        vao,
-        1,
-        3,
-        gl::FLOAT,
-        gl::FALSE,
-        3 * std::mem::size_of::<f32>() as u32
-    ));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
-
-    // Normals
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 2));
-    gl_call!(gl::VertexArrayAttribFormat(
-        vao,
-        2,
-        3,
-        gl::FLOAT,
-        gl::FALSE,
-        6 * std::mem::size_of::<f32>() as u32
-    ));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 2, 0));
-
-    // Ambient Occlusion
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 3));
-    gl_call!(gl::VertexArrayAttribFormat(
-        vao,
-        3,
-        1,
-        gl::FLOAT,
-        gl::FALSE,
-        9 * std::mem::size_of::<f32>() as u32
-    ));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 3, 0));
-
-    let mut vbo = 0;
-    gl_call!(gl::CreateBuffers(1, &mut vbo));
-
-    gl_call!(gl::VertexArrayVertexBuffer(
-        vao,
-        0,
-        vbo,
-        0,
-        (10 * std::mem::size_of::<f32>()) as i32
-    ));
-
-    (vao, vbo)
-}
-
 pub struct ChunkColumn {
     pub highest_blocks: RwLock<Box<[u8; 16 * 16]>>,
+    // Indexed like `highest_blocks`
+    pub biomes: RwLock<Box<[Biome; 16 * 16]>>,
     pub has_foliage: RwLock<bool>,
+    // The light of the column was computed (see `light::light_column`)
+    pub is_lit: RwLock<bool>,
     pub chunks: Box<[Chunk; 16]>,
 }
 
@@ -150,7 +166,9 @@ impl ChunkColumn {
     pub fn new() -> Self {
         Self {
             highest_blocks: RwLock::new(Box::new([0; 16 * 16])),
+            biomes: RwLock::new(Box::new([Biome::default(); 16 * 16])),
             has_foliage: RwLock::new(false),
+            is_lit: RwLock::new(false),
             chunks: Box::new([
                 Chunk::empty(),
                 Chunk::empty(),
@@ -175,7 +193,9 @@ impl ChunkColumn {
     pub fn random() -> Self {
         Self {
             highest_blocks: RwLock::new(Box::new([0; 16 * 16])),
+            biomes: RwLock::new(Box::new([Biome::default(); 16 * 16])),
             has_foliage: RwLock::new(false),
+            is_lit: RwLock::new(false),
             chunks: Box::new([
                 Chunk::random(),
                 Chunk::random(),
@@ -200,7 +220,9 @@ impl ChunkColumn {
     pub fn full_of_block(block: BlockID) -> Self {
         Self {
             highest_blocks: RwLock::new(Box::new([0; 16 * 16])),
+            biomes: RwLock::new(Box::new([Biome::default(); 16 * 16])),
             has_foliage: RwLock::new(false),
+            is_lit: RwLock::new(false),
             chunks: Box::new([
                 Chunk::full_of_block(block),
                 Chunk::full_of_block(block),
@@ -225,28 +247,42 @@ impl ChunkColumn {
     pub fn alternating() -> Self {
         Self {
             highest_blocks: RwLock::new(Box::new([0; 16 * 16])),
+            biomes: RwLock::new(Box::new([Biome::default(); 16 * 16])),
             has_foliage: RwLock::new(false),
+            is_lit: RwLock::new(false),
             chunks: Box::new([
-                Chunk::full_of_block(BlockID::Dirt),
-                Chunk::full_of_block(BlockID::Cobblestone),
-                Chunk::full_of_block(BlockID::Dirt),
-                Chunk::full_of_block(BlockID::Cobblestone),
-                Chunk::full_of_block(BlockID::Dirt),
-                Chunk::full_of_block(BlockID::Cobblestone),
-                Chunk::full_of_block(BlockID::Dirt),
-                Chunk::full_of_block(BlockID::Cobblestone),
-                Chunk::full_of_block(BlockID::Dirt),
-                Chunk::full_of_block(BlockID::Cobblestone),
-                Chunk::full_of_block(BlockID::Dirt),
-                Chunk::full_of_block(BlockID::Cobblestone),
-                Chunk::full_of_block(BlockID::Dirt),
-                Chunk::full_of_block(BlockID::Cobblestone),
-                Chunk::full_of_block(BlockID::Dirt),
-                Chunk::full_of_block(BlockID::Cobblestone),
+                Chunk::full_of_block(BlockID::DIRT),
+                Chunk::full_of_block(BlockID::COBBLESTONE),
+                Chunk::full_of_block(BlockID::DIRT),
+                Chunk::full_of_block(BlockID::COBBLESTONE),
+                Chunk::full_of_block(BlockID::DIRT),
+                Chunk::full_of_block(BlockID::COBBLESTONE),
+                Chunk::full_of_block(BlockID::DIRT),
+                Chunk::full_of_block(BlockID::COBBLESTONE),
+                Chunk::full_of_block(BlockID::DIRT),
+                Chunk::full_of_block(BlockID::COBBLESTONE),
+                Chunk::full_of_block(BlockID::DIRT),
+                Chunk::full_of_block(BlockID::COBBLESTONE),
+                Chunk::full_of_block(BlockID::DIRT),
+                Chunk::full_of_block(BlockID::COBBLESTONE),
+                Chunk::full_of_block(BlockID::DIRT),
+                Chunk::full_of_block(BlockID::COBBLESTONE),
             ]),
         }
     }
 
+    // Empties the column so that it can be used again for another position
+    pub fn reset(&self) {
+        for chunk in self.chunks.iter() {
+            chunk.reset();
+        }
+
+        self.highest_blocks.write().fill(0);
+        self.biomes.write().fill(Biome::default());
+        *self.has_foliage.write() = false;
+        *self.is_lit.write() = false;
+    }
+
     #[inline]
     pub fn get_chunk(&self, y: i32) -> &Chunk {
         &self.chunks[y as usize]
@@ -274,10 +310,25 @@ pub struct Chunk {
     pub number_of_transparent_blocks: RwLock<u32>,
     pub active_faces: RwLock<BitVec>,
     pub ao_vertices: RwLock<Vec<[[u8; 4]; 6]>>,
-
-    pub vao: RwLock<u32>,
-    pub vbo: RwLock<u32>,
-    pub vertices_drawn: RwLock<u32>,
+    // Skylight in the high nibble and block light in the low nibble, for every block
+    pub light: RwLock<Box<[u8; CHUNK_VOLUME as usize]>>,
+    // Level of the fluid blocks (see `fluid.rs`), 0 for every other block
+    pub fluid_levels: RwLock<Box<[u8; CHUNK_VOLUME as usize]>>,
+    // Ticks left before the scheduled updates of its blocks (see `block_ticks.rs`), by block index
+    pub scheduled_ticks: RwLock<BTreeMap<u16, u32>>,
+    // Smooth light of the vertices of every face, packed like `light`
+    pub light_vertices: RwLock<Vec<[[u8; 4]; 6]>>,
+    // Faces that see each other through the chunk, recomputed with the mesh after a change
+    pub visibility: RwLock<VisibilityGraph>,
+    pub is_visibility_outdated: RwLock<bool>,
+
+    // First vertex and number of vertices of every render layer in the mesh of the chunk, once it
+    // is uploaded to the `ChunkArena`
+    pub layer_ranges: RwLock<[(u32, u32); 3]>,
+    // Kept to sort the translucent faces again when the camera moves, along with the position of
+    // the camera relative to the chunk when they were sorted
+    pub translucent_vertices: RwLock<Vec<f32>>,
+    pub translucent_sorted_from: RwLock<Option<Vec3>>,
 }
 
 impl Default for Chunk {
@@ -293,15 +344,23 @@ impl Chunk {
 
     pub fn reset(&self) {
         *self.is_generated.write() = false;
-        *self.blocks.write() = [BlockID::Air; CHUNK_VOLUME as usize];
+        *self.is_uploaded_to_gpu.write() = false;
+        *self.blocks.write() = [BlockID::AIR; CHUNK_VOLUME as usize];
         *self.number_of_opaque_blocks.write() = 0;
         *self.number_of_transparent_blocks.write() = 0;
-        *self.vertices_drawn.write() = 0;
+        self.light.write().fill(0);
+        self.fluid_levels.write().fill(0);
+        self.scheduled_ticks.write().clear();
+        *self.visibility.write() = VisibilityGraph::all();
+        *self.is_visibility_outdated.write() = false;
+        *self.layer_ranges.write() = [(0, 0); 3];
+        self.translucent_vertices.write().clear();
+        *self.translucent_sorted_from.write() = None;
     }
 
     pub fn full_of_block(block: BlockID) -> Self {
         let (opaque, transparent) = match block {
-            BlockID::Air => (0, 0),
+            BlockID::AIR => (0, 0),
             block => {
                 if block.is_transparent() {
                     (0, CHUNK_VOLUME)
@@ -319,15 +378,25 @@ impl Chunk {
             number_of_transparent_blocks: RwLock::new(transparent),
             active_faces: RwLock::new(BitVec::from_elem(6 * CHUNK_VOLUME as usize, false)),
             ao_vertices: RwLock::new(vec![[[0; 4]; 6]; CHUNK_VOLUME as usize]),
-            vao: RwLock::new(0),
-            vbo: RwLock::new(0),
-            vertices_drawn: RwLock::new(0),
+            light: RwLock::new(Box::new([0; CHUNK_VOLUME as usize])),
+            fluid_levels: RwLock::new(Box::new([0; CHUNK_VOLUME as usize])),
+            scheduled_ticks: RwLock::new(BTreeMap::new()),
+            light_vertices: RwLock::new(vec![[[0; 4]; 6]; CHUNK_VOLUME as usize]),
+            visibility: RwLock::new(if opaque == CHUNK_VOLUME {
+                VisibilityGraph::none()
+            } else {
+                VisibilityGraph::all()
+            }),
+            is_visibility_outdated: RwLock::new(false),
+            layer_ranges: RwLock::new([(0, 0); 3]),
+            translucent_vertices: RwLock::new(Vec::new()),
+            translucent_sorted_from: RwLock::new(None),
         }
     }
 
     // Creates an empty chunk with no blocks
     pub fn empty() -> Self {
-        Self::full_of_block(BlockID::Air)
+        Self::full_of_block(BlockID::AIR)
     }
 
     // Creates a chunk where every block is random
@@ -336,7 +405,7 @@ impl Chunk {
             is_generated: RwLock::new(false),
             is_uploaded_to_gpu: RwLock::new(false),
             blocks: RwLock::new({
-                let mut blocks = [BlockID::Air; CHUNK_VOLUME as usize];
+                let mut blocks = [BlockID::AIR; CHUNK_VOLUME as usize];
 
                 for i in 0..blocks.len() {
                     blocks[i] = random::<BlockID>();
@@ -348,9 +417,15 @@ impl Chunk {
             number_of_transparent_blocks: RwLock::new(0),
             active_faces: RwLock::new(BitVec::from_elem(6 * CHUNK_VOLUME as usize, false)),
             ao_vertices: RwLock::new(vec![[[0; 4]; 6]; CHUNK_VOLUME as usize]),
-            vao: RwLock::new(0),
-            vbo: RwLock::new(0),
-            vertices_drawn: RwLock::new(0),
+            light: RwLock::new(Box::new([0; CHUNK_VOLUME as usize])),
+            fluid_levels: RwLock::new(Box::new([0; CHUNK_VOLUME as usize])),
+            scheduled_ticks: RwLock::new(BTreeMap::new()),
+            light_vertices: RwLock::new(vec![[[0; 4]; 6]; CHUNK_VOLUME as usize]),
+            visibility: RwLock::new(VisibilityGraph::none()),
+            is_visibility_outdated: RwLock::new(false),
+            layer_ranges: RwLock::new([(0, 0); 3]),
+            translucent_vertices: RwLock::new(Vec::new()),
+            translucent_sorted_from: RwLock::new(None),
         }
     }
 
@@ -363,10 +438,21 @@ impl Chunk {
     }
 
     #[inline]
-    fn chunk_coords_to_array_index(x: u32, y: u32, z: u32) -> usize {
+    pub(crate) fn chunk_coords_to_array_index(x: u32, y: u32, z: u32) -> usize {
         (y * (CHUNK_SIZE * CHUNK_SIZE) + z * CHUNK_SIZE + x) as usize
     }
 
+    #[inline]
+    pub(crate) fn array_index_to_chunk_coords(index: usize) -> (u32, u32, u32) {
+        let index = index as u32;
+
+        (
+            index % CHUNK_SIZE,
+            index / (CHUNK_SIZE * CHUNK_SIZE),
+            (index / CHUNK_SIZE) % CHUNK_SIZE,
+        )
+    }
+
     #[inline]
     pub fn get_block(&self, x: u32, y: u32, z: u32) -> BlockID {
         self.blocks.read()[Chunk::chunk_coords_to_array_index(x, y, z)]
@@ -400,57 +486,136 @@ impl Chunk {
         }
 
         self.blocks.write()[index] = block;
+
+        if target.is_opaque() != block.is_opaque() {
+            *self.is_visibility_outdated.write() = true;
+        }
+
+        if target.is_fluid() {
+            self.fluid_levels.write()[index] = 0;
+        }
     }
 
-    pub fn unload_from_gpu(&self) {
-        *self.is_uploaded_to_gpu.write() = false;
+    // Recomputes the visibility graph if a block changed its opacity since the last time
+    pub fn update_visibility(&self) {
+        if !*self.is_visibility_outdated.read() {
+            return;
+        }
 
-        let vbo = *self.vbo.read();
+        let visibility = {
+            let blocks = self.blocks.read();
 
-        if vbo != 0 {
-            gl_call!(gl::NamedBufferData(vbo, 0, null(), gl::DYNAMIC_DRAW));
+            VisibilityGraph::compute(|x, y, z| {
+                blocks[Chunk::chunk_coords_to_array_index(x, y, z)].is_opaque()
+            })
+        };
+
+        *self.visibility.write() = visibility;
+        *self.is_visibility_outdated.write() = false;
+    }
+
+    // An outdated graph is treated as if every face saw the others
+    pub fn visibility(&self) -> VisibilityGraph {
+        if *self.is_visibility_outdated.read() {
+            VisibilityGraph::all()
+        } else {
+            *self.visibility.read()
         }
     }
 
-    pub fn upload_to_gpu(&self, texture_pack: &TexturePack) {
-        if *self.vao.read() == 0 && *self.vbo.read() == 0 {
-            let (vao, vbo) = create_vao_vbo();
-            *self.vao.write() = vao;
-            *self.vbo.write() = vbo;
+    #[inline]
+    pub fn get_fluid_level(&self, x: u32, y: u32, z: u32) -> u8 {
+        self.fluid_levels.read()[Chunk::chunk_coords_to_array_index(x, y, z)]
+    }
+
+    #[inline]
+    pub fn set_fluid_level(&self, x: u32, y: u32, z: u32, level: u8) {
+        debug_assert!(level <= MAX_FLUID_LEVEL, "Invalid fluid level {level}");
+
+        // Clamped in release builds too, the fluid simulation adds to the levels
+        self.fluid_levels.write()[Chunk::chunk_coords_to_array_index(x, y, z)] =
+            level.min(MAX_FLUID_LEVEL);
+    }
+
+    // Updates the block in `delay` ticks (at least 1), unless it is already scheduled. Returns
+    // whether it was scheduled.
+    pub fn schedule_tick(&self, x: u32, y: u32, z: u32, delay: u32) -> bool {
+        let index = Chunk::chunk_coords_to_array_index(x, y, z) as u16;
+        let mut scheduled_ticks = self.scheduled_ticks.write();
+
+        if scheduled_ticks.contains_key(&index) {
+            return false;
         }
 
-        let visible_faces_cnt = self
-            .active_faces
-            .read()
-            .iter()
-            .fold(0, |acc, b| acc + b as i32);
+        scheduled_ticks.insert(index, delay.max(1));
 
-        if visible_faces_cnt == 0 {
-            return;
+        true
+    }
+
+    #[inline]
+    pub fn get_light(&self, x: u32, y: u32, z: u32, channel: LightChannel) -> u8 {
+        let light = self.light.read()[Chunk::chunk_coords_to_array_index(x, y, z)];
+
+        match channel {
+            LightChannel::Sky => light >> 4,
+            LightChannel::Block => light & 0xF,
         }
+    }
 
-        // Initialize the VBO
-        gl_call!(gl::NamedBufferData(
-            *self.vbo.read(),
-            (6 * 10 * std::mem::size_of::<f32>() * visible_faces_cnt as usize) as isize,
-            null(),
-            gl::DYNAMIC_DRAW
-        ));
+    #[inline]
+    pub fn set_light(&self, x: u32, y: u32, z: u32, channel: LightChannel, level: u8) {
+        let light = &mut self.light.write()[Chunk::chunk_coords_to_array_index(x, y, z)];
 
-        // Map VBO to virtual memory
-        let vbo_ptr: *mut f32 =
-            gl_call!(gl::MapNamedBuffer(*self.vbo.read(), gl::WRITE_ONLY)) as *mut f32;
-        let mut vbo_offset = 0;
+        *light = match channel {
+            LightChannel::Sky => (*light & 0xF) | (level << 4),
+            LightChannel::Block => (*light & 0xF0) | level,
+        };
+    }
+
+    // Builds the vertices of the visible faces of the chunk (12 floats per vertex), split by
+    // render layer
+    // NOTE: The active faces, the AO and the smooth light must have been computed by
+    // `ChunkManager::update_blocks`
+    pub fn build_mesh(&self, texture_pack: &TexturePack, meshing_mode: MeshingMode) -> ChunkMesh {
+        if meshing_mode == MeshingMode::Greedy {
+            return build_greedy_mesh(self, texture_pack);
+        }
 
-        let mut vertices_drawn = 0;
         let sides_vec = &self.active_faces.read();
+        let blocks = self.blocks.read();
+
+        let mut visible_faces_cnt = [0; 3];
+
+        for (j, block) in blocks.iter().enumerate() {
+            if *block != BlockID::AIR {
+                visible_faces_cnt[block.render_layer() as usize] +=
+                    (0..6).filter(|&face| sides_vec[6 * j + face]).count();
+            }
+        }
+
+        let mut mesh = ChunkMesh {
+            layers: visible_faces_cnt.map(|cnt| vec![0.0; 6 * 12 * cnt]),
+        };
+
+        if visible_faces_cnt.iter().all(|&cnt| cnt == 0) {
+            return mesh;
+        }
+
+        let vertices_ptrs = mesh.layers.each_mut().map(|vertices| vertices.as_mut_ptr());
+        let mut vertices_offsets = [0; 3];
+
         let ao_vec = &self.ao_vertices.read();
+        let light_vec = &self.light_vertices.read();
+        let fluid_levels = self.fluid_levels.read();
         let mut j = 0;
 
+        // Layer, start and number of floats, y and level of the fluid blocks
+        let mut fluid_blocks = Vec::new();
+
         for (x, y, z) in BlockIterator::new() {
-            let block = self.get_block(x, y, z);
+            let block = blocks[j];
 
-            if block != BlockID::Air {
+            if block != BlockID::AIR {
                 let active_sides = [
                     sides_vec[6 * j],
                     sides_vec[6 * j + 1],
@@ -460,27 +625,109 @@ impl Chunk {
                     sides_vec[6 * j + 5],
                 ];
                 let ao_block = ao_vec[j];
+                let light_block = light_vec[j];
                 let uvs = texture_pack.get(&block).unwrap().clone();
                 let uvs = uvs.get_uv_of_every_face();
+                let layer = block.render_layer() as usize;
 
                 let copied_vertices = unsafe {
                     write_unit_cube_to_ptr(
-                        vbo_ptr.offset(vbo_offset),
+                        vertices_ptrs[layer].offset(vertices_offsets[layer]),
                         (x as f32, y as f32, z as f32),
                         uvs,
                         active_sides,
                         ao_block,
+                        light_block,
                     )
                 };
-                vertices_drawn += copied_vertices;
-                vbo_offset += copied_vertices as isize * 10; // 5 floats per vertex
+
+                if block.is_fluid() && active_sides[2] {
+                    fluid_blocks.push((
+                        layer,
+                        vertices_offsets[layer] as usize,
+                        copied_vertices as usize * 12,
+                        y,
+                        fluid_levels[j],
+                    ));
+                }
+
+                vertices_offsets[layer] += copied_vertices as isize * 12; // 12 floats per vertex
             }
 
             j += 1;
         }
 
-        *self.vertices_drawn.write() = vertices_drawn;
-        gl_call!(gl::UnmapNamedBuffer(*self.vbo.read()));
+        for (layer, start, length, y, level) in fluid_blocks {
+            lower_fluid_surface(
+                &mut mesh.layers[layer][start..start + length],
+                y as f32,
+                level,
+            );
+        }
+
+        mesh
+    }
+}
+
+#[cfg(feature = "client")]
+impl Chunk {
+    pub fn unload_from_gpu(&self, chunk_arena: &mut ChunkArena, coords: (i32, i32, i32)) {
+        *self.is_uploaded_to_gpu.write() = false;
+        *self.layer_ranges.write() = [(0, 0); 3];
+        self.translucent_vertices.write().clear();
+
+        chunk_arena.free(coords);
+    }
+
+    pub fn upload_to_gpu(
+        &self,
+        chunk_arena: &mut ChunkArena,
+        coords: (i32, i32, i32),
+        texture_pack: &TexturePack,
+        meshing_mode: MeshingMode,
+    ) {
+        // The layers follow each other in the arena
+        let mesh = self.build_mesh(texture_pack, meshing_mode);
+        chunk_arena.upload(coords, &mesh.layers.concat());
+
+        let mut first = 0;
+        *self.layer_ranges.write() = mesh.layers.each_ref().map(|layer| {
+            let count = (layer.len() / 12) as u32;
+            first += count;
+
+            (first - count, count)
+        });
+
+        // Sorted before they are drawn
+        let [_, _, translucent_vertices] = mesh.layers;
+        *self.translucent_vertices.write() = translucent_vertices;
+        *self.translucent_sorted_from.write() = None;
+    }
+
+    // Sorts the translucent faces from the farthest to the closest of the camera if they were
+    // never sorted or if the camera moved far enough since then
+    pub fn sort_translucent_faces(
+        &self,
+        chunk_arena: &ChunkArena,
+        coords: (i32, i32, i32),
+        camera_position: &Vec3,
+    ) {
+        let (first, count) = self.layer_ranges.read()[RenderLayer::Translucent as usize];
+        let mut sorted_from = self.translucent_sorted_from.write();
+
+        if count == 0
+            || sorted_from.is_some_and(|sorted_from| {
+                (sorted_from - camera_position).norm() < TRANSLUCENT_SORT_DISTANCE
+            })
+        {
+            return;
+        }
+
+        let mut vertices = self.translucent_vertices.write();
+        sort_back_to_front(&mut vertices, camera_position);
+        chunk_arena.write(coords, first, &vertices);
+
+        *sorted_from = Some(*camera_position);
     }
 }
 
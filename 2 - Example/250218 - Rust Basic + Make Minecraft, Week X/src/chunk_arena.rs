@@ -0,0 +1,258 @@
+use crate::arena_allocator::{Allocation, ArenaAllocator};
+use crate::constants::CHUNK_ARENA_INITIAL_VERTICES;
+use std::collections::HashMap;
+use std::ptr::null;
+
+const FLOATS_PER_VERTEX: usize = 12;
+const VERTEX_SIZE: usize = FLOATS_PER_VERTEX * std::mem::size_of::<f32>();
+
+// Same layout as `DrawArraysIndirectCommand` in the OpenGL specification
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct DrawArraysIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first: u32,
+    // Index of the position of the chunk in the buffer given to `ChunkArena::bind`
+    pub base_instance: u32,
+}
+
+fn create_vao(vbo: u32) -> u32 {
+    let mut vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+    // Position, texture coords, normals, ambient occlusion, skylight and block light
+    for (attribute, size, offset) in [(0, 3, 0), (1, 3, 3), (2, 3, 6), (3, 1, 9), (4, 2, 10)] {
+        gl_call!(gl::EnableVertexArrayAttrib(vao, attribute));
+        gl_call!(gl::VertexArrayAttribFormat(
+            vao,
+            attribute,
+            size,
+            gl::FLOAT,
+            gl::FALSE,
+            offset * std::mem::size_of::<f32>() as u32
+        ));
+        gl_call!(gl::VertexArrayAttribBinding(vao, attribute, 0));
+    }
+
+    gl_call!(gl::VertexArrayVertexBuffer(
+        vao,
+        0,
+        vbo,
+        0,
+        VERTEX_SIZE as i32
+    ));
+
+    vao
+}
+
+fn create_vertex_buffer(capacity: u32) -> u32 {
+    let mut vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut vbo));
+    gl_call!(gl::NamedBufferStorage(
+        vbo,
+        (capacity as usize * VERTEX_SIZE) as isize,
+        null(),
+        gl::DYNAMIC_STORAGE_BIT
+    ));
+
+    vbo
+}
+
+// A single vertex buffer shared by the meshes of every chunk, so that all of them are drawn with
+// one `glMultiDrawArraysIndirect` per render layer. The buffer doubles when it is full and is
+// halved when less than a quarter of it is used.
+pub struct ChunkArena {
+    vao: u32,
+    vbo: u32,
+    // Position of every chunk drawn during the frame, read by the vertex shader
+    chunk_positions_buffer: u32,
+    indirect_buffer: u32,
+    allocator: ArenaAllocator,
+    // In vertices, by chunk coordinates
+    allocations: HashMap<(i32, i32, i32), Allocation>,
+}
+
+impl ChunkArena {
+    pub fn new() -> Self {
+        let vbo = create_vertex_buffer(CHUNK_ARENA_INITIAL_VERTICES);
+
+        let mut chunk_positions_buffer = 0;
+        gl_call!(gl::CreateBuffers(1, &mut chunk_positions_buffer));
+        let mut indirect_buffer = 0;
+        gl_call!(gl::CreateBuffers(1, &mut indirect_buffer));
+
+        Self {
+            vao: create_vao(vbo),
+            vbo,
+            chunk_positions_buffer,
+            indirect_buffer,
+            allocator: ArenaAllocator::new(CHUNK_ARENA_INITIAL_VERTICES),
+            allocations: HashMap::new(),
+        }
+    }
+
+    // Moves the meshes to a buffer of `capacity` vertices, which must hold all of them
+    fn resize(&mut self, capacity: u32) {
+        let vbo = create_vertex_buffer(capacity);
+        gl_call!(gl::CopyNamedBufferSubData(
+            self.vbo,
+            vbo,
+            0,
+            0,
+            (capacity.min(self.allocator.capacity()) as usize * VERTEX_SIZE) as isize
+        ));
+        gl_call!(gl::DeleteBuffers(1, &self.vbo));
+        gl_call!(gl::VertexArrayVertexBuffer(
+            self.vao,
+            0,
+            vbo,
+            0,
+            VERTEX_SIZE as i32
+        ));
+
+        self.vbo = vbo;
+    }
+
+    // Moves the meshes to a buffer big enough for `capacity` vertices
+    fn grow(&mut self, capacity: u32) {
+        let mut new_capacity = self.allocator.capacity();
+
+        while new_capacity < capacity {
+            new_capacity *= 2;
+        }
+
+        info!("Growing the chunk arena to {new_capacity} vertices");
+
+        self.resize(new_capacity);
+        self.allocator.grow(new_capacity);
+    }
+
+    // Gives the memory of the freed meshes back once most of the buffer is unused, for example
+    // after a teleport or a lower render distance
+    pub fn shrink_if_unused(&mut self) {
+        let capacity = self.allocator.shrunk_capacity(CHUNK_ARENA_INITIAL_VERTICES);
+
+        if capacity < self.allocator.capacity() {
+            info!("Shrinking the chunk arena to {capacity} vertices");
+
+            self.resize(capacity);
+            self.allocator.shrink(capacity);
+        }
+    }
+
+    // Replaces the mesh of a chunk
+    pub fn upload(&mut self, chunk: (i32, i32, i32), vertices: &[f32]) {
+        self.free(chunk);
+
+        let size = (vertices.len() / FLOATS_PER_VERTEX) as u32;
+
+        if size == 0 {
+            return;
+        }
+
+        let allocation = match self.allocator.allocate(size) {
+            Some(allocation) => allocation,
+            None => {
+                self.grow(self.allocator.capacity() + size);
+                self.allocator.allocate(size).unwrap()
+            }
+        };
+
+        self.allocations.insert(chunk, allocation);
+        self.write(chunk, 0, vertices);
+    }
+
+    // Overwrites the vertices of a chunk from its vertex `first`
+    pub fn write(&self, chunk: (i32, i32, i32), first: u32, vertices: &[f32]) {
+        let allocation = match self.allocations.get(&chunk) {
+            Some(allocation) => allocation,
+            None => return,
+        };
+
+        debug_assert!(
+            first as usize + vertices.len() / FLOATS_PER_VERTEX <= allocation.size as usize
+        );
+
+        gl_call!(gl::NamedBufferSubData(
+            self.vbo,
+            ((allocation.offset + first) as usize * VERTEX_SIZE) as isize,
+            std::mem::size_of_val(vertices) as isize,
+            vertices.as_ptr() as *const _
+        ));
+    }
+
+    pub fn free(&mut self, chunk: (i32, i32, i32)) {
+        if let Some(allocation) = self.allocations.remove(&chunk) {
+            self.allocator.free(allocation);
+        }
+    }
+
+    // Frees the meshes of the chunks for which `keep` returns false
+    pub fn retain(&mut self, mut keep: impl FnMut(&(i32, i32, i32)) -> bool) {
+        let allocator = &mut self.allocator;
+
+        self.allocations.retain(|chunk, allocation| {
+            let is_kept = keep(chunk);
+
+            if !is_kept {
+                allocator.free(*allocation);
+            }
+
+            is_kept
+        });
+    }
+
+    // First vertex of the mesh of a chunk in the buffer
+    pub fn first_vertex(&self, chunk: (i32, i32, i32)) -> Option<u32> {
+        self.allocations
+            .get(&chunk)
+            .map(|allocation| allocation.offset)
+    }
+
+    // Binds the buffer and the positions of the chunks drawn during this frame (`xyz` and a
+    // padding), to which the draw commands refer with their base instance
+    pub fn bind(&self, chunk_positions: &[[f32; 4]]) {
+        gl_call!(gl::NamedBufferData(
+            self.chunk_positions_buffer,
+            std::mem::size_of_val(chunk_positions) as isize,
+            chunk_positions.as_ptr() as *const _,
+            gl::STREAM_DRAW
+        ));
+        gl_call!(gl::BindBufferBase(
+            gl::SHADER_STORAGE_BUFFER,
+            0,
+            self.chunk_positions_buffer
+        ));
+        gl_call!(gl::BindVertexArray(self.vao));
+    }
+
+    pub fn draw(&self, commands: &[DrawArraysIndirectCommand]) {
+        if commands.is_empty() {
+            return;
+        }
+
+        gl_call!(gl::NamedBufferData(
+            self.indirect_buffer,
+            std::mem::size_of_val(commands) as isize,
+            commands.as_ptr() as *const _,
+            gl::STREAM_DRAW
+        ));
+        gl_call!(gl::BindBuffer(
+            gl::DRAW_INDIRECT_BUFFER,
+            self.indirect_buffer
+        ));
+        gl_call!(gl::MultiDrawArraysIndirect(
+            gl::TRIANGLES,
+            null(),
+            commands.len() as i32,
+            0
+        ));
+    }
+}
+
+impl Default for ChunkArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
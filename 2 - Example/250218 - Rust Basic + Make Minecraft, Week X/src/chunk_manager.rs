@@ -1,20 +1,68 @@
+use crate::aabb::AABB;
 use crate::ambient_occlusion::compute_ao_of_block;
+use crate::biome::Biome;
+#[cfg(feature = "client")]
+use crate::block_registry::RenderLayer;
 use crate::chunk::{BlockID, Chunk, ChunkColumn};
-use crate::shader::ShaderProgram;
-use nalgebra::Matrix4;
+#[cfg(feature = "client")]
+use crate::chunk_arena::{ChunkArena, DrawArraysIndirectCommand};
+use crate::frustum::Frustum;
+use crate::light::{compute_smooth_light_of_block, update_light, LightChannel, MAX_LIGHT_LEVEL};
+use crate::visibility::{opposite_face, FACE_OFFSETS};
+use crossbeam_channel::{unbounded, Receiver, Sender};
 use nalgebra_glm::vec3;
+#[cfg(feature = "client")]
+use nalgebra_glm::Vec3;
 use owning_ref::OwningRef;
 use parking_lot::RwLock;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
 pub const CHUNK_SIZE: u32 = 16;
 pub const CHUNK_VOLUME: u32 = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
 
+// A block placed or broken with `ChunkManager::put_block`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlockChange {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub block: BlockID,
+    // Level of the fluids, 0 for the other blocks
+    pub level: u8,
+}
+
+// Whether the face of `block` that touches `neighbour` is rendered
+#[inline]
+fn is_face_visible(block: BlockID, neighbour: BlockID) -> bool {
+    // The faces inside a fluid are hidden, the surface under an opaque block too
+    if block.is_fluid() {
+        neighbour != block && neighbour.is_transparent()
+    } else {
+        neighbour.is_transparent()
+    }
+}
+
+// Chunks drawn, skipped by the frustum culling and hidden behind other chunks during the last
+// frame, logged with the FPS
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ChunkRenderStats {
+    pub drawn: u32,
+    pub culled: u32,
+    pub occluded: u32,
+}
+
+fn chunk_aabb(x: i32, y: i32, z: i32) -> AABB {
+    let mins = vec3(x as f32, y as f32, z as f32).scale(CHUNK_SIZE as f32);
+
+    AABB::new(mins, mins.add_scalar(CHUNK_SIZE as f32))
+}
+
 #[derive(Default)]
 pub struct ChunkManager {
     pub loaded_chunk_columns: RwLock<HashMap<(i32, i32), Arc<ChunkColumn>>>,
     pub(crate) block_changelist: RwLock<HashSet<(i32, BlockID, i32, i32, i32)>>,
+    block_change_subscribers: RwLock<Vec<Sender<BlockChange>>>,
 }
 
 impl ChunkManager {
@@ -22,9 +70,18 @@ impl ChunkManager {
         Self {
             loaded_chunk_columns: RwLock::new(HashMap::new()),
             block_changelist: RwLock::new(HashSet::new()),
+            block_change_subscribers: RwLock::new(Vec::new()),
         }
     }
 
+    // Returns a receiver of every block change made with `put_block` from now on
+    pub fn subscribe_to_block_changes(&self) -> Receiver<BlockChange> {
+        let (block_changes_tx, block_changes_rx) = unbounded();
+        self.block_change_subscribers.write().push(block_changes_tx);
+
+        block_changes_rx
+    }
+
     #[inline]
     pub fn get_column(&self, x: i32, z: i32) -> Option<Arc<ChunkColumn>> {
         self.loaded_chunk_columns
@@ -71,7 +128,7 @@ impl ChunkManager {
     pub fn single_chunk(&mut self) {
         self.add_chunk_column(
             (0, 0),
-            Arc::new(ChunkColumn::full_of_block(BlockID::Cobblestone)),
+            Arc::new(ChunkColumn::full_of_block(BlockID::COBBLESTONE)),
         );
     }
 
@@ -108,38 +165,141 @@ impl ChunkManager {
             .map(|chunk| chunk.get_block(block_x, block_y, block_z))
     }
 
-    // Replaces the block at (x, y, z) with `block`
-    fn set_block_internal(&self, priority: i32, x: i32, y: i32, z: i32, block: BlockID) -> bool {
+    pub fn get_biome(&self, x: i32, z: i32) -> Option<Biome> {
+        let (chunk_x, chunk_z) = (
+            x.div_euclid(CHUNK_SIZE as i32),
+            z.div_euclid(CHUNK_SIZE as i32),
+        );
+        let (block_x, block_z) = (
+            x.rem_euclid(CHUNK_SIZE as i32) as usize,
+            z.rem_euclid(CHUNK_SIZE as i32) as usize,
+        );
+
+        self.get_column(chunk_x, chunk_z)
+            .map(|column| column.biomes.read()[16 * block_z + block_x])
+    }
+
+    pub fn get_fluid_level(&self, x: i32, y: i32, z: i32) -> Option<u8> {
         let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
             ChunkManager::get_chunk_coords(x, y, z);
 
-        match self.get_chunk(chunk_x, chunk_y, chunk_z) {
+        self.get_chunk(chunk_x, chunk_y, chunk_z)
+            .map(|chunk| chunk.get_fluid_level(block_x, block_y, block_z))
+    }
+
+    // Updates the block at (x, y, z) in `delay` ticks, unless it is already scheduled (see
+    // `BlockTicks`). Returns whether it was scheduled.
+    pub fn schedule_block_tick(&self, x: i32, y: i32, z: i32, delay: u32) -> bool {
+        let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
+            ChunkManager::get_chunk_coords(x, y, z);
+
+        self.get_chunk(chunk_x, chunk_y, chunk_z)
+            .is_some_and(|chunk| chunk.schedule_tick(block_x, block_y, block_z, delay))
+    }
+
+    // Replaces the block at (x, y, z) with `block`, `level` is only kept for the fluids
+    fn set_block_internal(
+        &self,
+        priority: i32,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: BlockID,
+        level: u8,
+    ) -> bool {
+        let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
+            ChunkManager::get_chunk_coords(x, y, z);
+
+        let old_block = match self.get_chunk(chunk_x, chunk_y, chunk_z) {
             Some(chunk) => {
+                let old_block = chunk.get_block(block_x, block_y, block_z);
                 chunk.set_block(block_x, block_y, block_z, block);
 
+                if block.is_fluid() {
+                    chunk.set_fluid_level(block_x, block_y, block_z, level);
+                }
+
                 if *chunk.is_uploaded_to_gpu.read() {
                     self.block_changelist
                         .write()
                         .insert((priority, block, x, y, z));
                 }
 
-                true
+                old_block
+            }
+            None => return false,
+        };
+
+        update_light(self, priority, x, y, z, old_block, block);
+
+        true
+    }
+
+    pub fn get_light(&self, x: i32, y: i32, z: i32, channel: LightChannel) -> Option<u8> {
+        let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
+            ChunkManager::get_chunk_coords(x, y, z);
+
+        self.get_chunk(chunk_x, chunk_y, chunk_z)
+            .map(|chunk| chunk.get_light(block_x, block_y, block_z, channel))
+    }
+
+    // The blocks around are marked as changed so that their smooth light is computed again
+    pub(crate) fn set_light(
+        &self,
+        priority: i32,
+        x: i32,
+        y: i32,
+        z: i32,
+        channel: LightChannel,
+        level: u8,
+    ) {
+        let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
+            ChunkManager::get_chunk_coords(x, y, z);
+
+        if let Some(chunk) = self.get_chunk(chunk_x, chunk_y, chunk_z) {
+            chunk.set_light(block_x, block_y, block_z, channel, level);
+
+            if *chunk.is_uploaded_to_gpu.read() {
+                let block = chunk.get_block(block_x, block_y, block_z);
+                self.block_changelist
+                    .write()
+                    .insert((priority, block, x, y, z));
             }
-            None => false,
         }
     }
 
     pub fn set_block(&self, x: i32, y: i32, z: i32, block: BlockID) -> bool {
-        self.set_block_internal(0, x, y, z, block)
+        self.set_block_internal(0, x, y, z, block, 0)
     }
 
     pub fn put_block(&self, x: i32, y: i32, z: i32, block: BlockID) -> bool {
-        self.set_block_internal(1, x, y, z, block)
+        self.put_block_with_level(x, y, z, block, 0)
+    }
+
+    // Same as `put_block` for a fluid of the given level
+    pub fn put_block_with_level(&self, x: i32, y: i32, z: i32, block: BlockID, level: u8) -> bool {
+        if !self.set_block_internal(1, x, y, z, block, level) {
+            return false;
+        }
+
+        // Forget the subscribers that dropped their receiver
+        let block_change = BlockChange {
+            x,
+            y,
+            z,
+            block,
+            level: if block.is_fluid() { level } else { 0 },
+        };
+        self.block_change_subscribers
+            .write()
+            .retain(|subscriber| subscriber.send(block_change).is_ok());
+
+        true
     }
 
     pub fn is_solid_block_at(&self, x: i32, y: i32, z: i32) -> bool {
         self.get_block(x, y, z)
-            .filter(|&block| block != BlockID::Air)
+            .filter(|block| block.is_solid())
             .is_some()
     }
 
@@ -158,6 +318,7 @@ impl ChunkManager {
             }
         };
         let this_chunk = this_column.get_chunk(chunk_y);
+        this_chunk.update_visibility();
 
         if this_chunk.is_empty() {
             return;
@@ -192,7 +353,7 @@ impl ChunkManager {
                 ChunkManager::get_chunk_coords(world_x, world_y, world_z);
 
             if chunk_yn < 0 || chunk_yn >= 16 {
-                return BlockID::Air;
+                return BlockID::AIR;
             }
 
             if chunk_x == chunk_xn && chunk_z == chunk_zn {
@@ -206,11 +367,54 @@ impl ChunkManager {
                         .get_chunk(chunk_yn)
                         .get_block(block_x, block_y, block_z)
                 } else {
-                    BlockID::Air
+                    BlockID::AIR
                 }
             }
         }
 
+        // `None` for opaque blocks and unloaded columns, the sky is visible above the world
+        #[inline]
+        fn light_at(
+            column: &ChunkColumn,
+            neighbourhood: &[Option<Arc<ChunkColumn>>; 9],
+            chunk_x: i32,
+            chunk_z: i32,
+            world_x: i32,
+            world_y: i32,
+            world_z: i32,
+        ) -> Option<(u8, u8)> {
+            let to_index = |x: i32, z: i32| -> usize {
+                3 * (x - chunk_x + 1) as usize + (z - chunk_z + 1) as usize
+            };
+            let (chunk_xn, chunk_yn, chunk_zn, block_x, block_y, block_z) =
+                ChunkManager::get_chunk_coords(world_x, world_y, world_z);
+
+            if chunk_yn < 0 {
+                return None;
+            }
+
+            let column = if chunk_x == chunk_xn && chunk_z == chunk_zn {
+                column
+            } else {
+                neighbourhood[to_index(chunk_xn, chunk_zn)].as_ref()?
+            };
+
+            if chunk_yn >= 16 {
+                return Some((MAX_LIGHT_LEVEL, 0));
+            }
+
+            let chunk = column.get_chunk(chunk_yn);
+
+            if chunk.get_block(block_x, block_y, block_z).is_opaque() {
+                return None;
+            }
+
+            Some((
+                chunk.get_light(block_x, block_y, block_z, LightChannel::Sky),
+                chunk.get_light(block_x, block_y, block_z, LightChannel::Block),
+            ))
+        }
+
         #[inline]
         fn compute_active_faces(
             column: &ChunkColumn,
@@ -221,27 +425,38 @@ impl ChunkManager {
             y: i32,
             z: i32,
         ) -> [bool; 6] {
-            let right =
-                block_at(&column, &neighbourhood, chunk_x, chunk_z, x + 1, y, z).is_transparent();
-            let left =
-                block_at(&column, &neighbourhood, chunk_x, chunk_z, x - 1, y, z).is_transparent();
-            let top =
-                block_at(&column, &neighbourhood, chunk_x, chunk_z, x, y + 1, z).is_transparent();
-            let bottom =
-                block_at(&column, &neighbourhood, chunk_x, chunk_z, x, y - 1, z).is_transparent();
-            let front =
-                block_at(&column, &neighbourhood, chunk_x, chunk_z, x, y, z + 1).is_transparent();
-            let back =
-                block_at(&column, &neighbourhood, chunk_x, chunk_z, x, y, z - 1).is_transparent();
-
-            [right, left, top, bottom, front, back]
+            let block = block_at(column, neighbourhood, chunk_x, chunk_z, x, y, z);
+            let is_visible = |(dx, dy, dz): (i32, i32, i32)| {
+                let neighbour = block_at(
+                    column,
+                    neighbourhood,
+                    chunk_x,
+                    chunk_z,
+                    x + dx,
+                    y + dy,
+                    z + dz,
+                );
+
+                is_face_visible(block, neighbour)
+            };
+
+            [
+                (1, 0, 0),
+                (-1, 0, 0),
+                (0, 1, 0),
+                (0, -1, 0),
+                (0, 0, 1),
+                (0, 0, -1),
+            ]
+            .map(is_visible)
         }
 
         let mut active_faces = this_chunk.active_faces.write();
         let mut ao_vertices = this_chunk.ao_vertices.write();
+        let mut light_vertices = this_chunk.light_vertices.write();
 
         for (block_x, block_y, block_z) in blocks {
-            if this_chunk.get_block(block_x, block_y, block_z) == BlockID::Air {
+            if this_chunk.get_block(block_x, block_y, block_z) == BlockID::AIR {
                 continue;
             }
 
@@ -269,7 +484,7 @@ impl ChunkManager {
 
             // Ambient Occlusion
             let block_ao = compute_ao_of_block(&|rx: i32, ry: i32, rz: i32| {
-                !block_at(
+                block_at(
                     &this_column,
                     &neighbourhood,
                     chunk_x,
@@ -278,10 +493,23 @@ impl ChunkManager {
                     world_y + ry,
                     world_z + rz,
                 )
-                .is_transparent_no_leaves()
+                .occludes_ambient_occlusion()
             });
 
             ao_vertices[array_index] = block_ao;
+
+            // Smooth lighting
+            light_vertices[array_index] = compute_smooth_light_of_block(&|rx, ry, rz| {
+                light_at(
+                    &this_column,
+                    &neighbourhood,
+                    chunk_x,
+                    chunk_z,
+                    world_x + rx,
+                    world_y + ry,
+                    world_z + rz,
+                )
+            });
         }
     }
 
@@ -296,7 +524,7 @@ impl ChunkManager {
     ) {
         let chunk = self.get_chunk(chunk_x, chunk_y, chunk_z).unwrap();
 
-        if chunk.get_block(block_x, block_y, block_z) == BlockID::Air {
+        if chunk.get_block(block_x, block_y, block_z) == BlockID::AIR {
             return;
         }
 
@@ -320,84 +548,232 @@ impl ChunkManager {
         // Ambient Occlusion
         let block_ao = compute_ao_of_block(&|rx: i32, ry: i32, rz: i32| {
             self.get_block(world_x + rx, world_y + ry, world_z + rz)
-                .filter(|b| !b.is_transparent_no_leaves())
+                .filter(|b| b.occludes_ambient_occlusion())
                 .is_some()
         });
 
-        self.get_chunk(chunk_x, chunk_y, chunk_z)
-            .unwrap()
-            .ao_vertices
-            .write()[array_index] = block_ao;
+        // Smooth lighting
+        let block_light = compute_smooth_light_of_block(&|rx, ry, rz| {
+            let (x, y, z) = (world_x + rx, world_y + ry, world_z + rz);
+
+            match self.get_block(x, y, z) {
+                Some(block) if block.is_opaque() => None,
+                Some(_) => Some((
+                    self.get_light(x, y, z, LightChannel::Sky).unwrap(),
+                    self.get_light(x, y, z, LightChannel::Block).unwrap(),
+                )),
+                None if y >= 16 * CHUNK_SIZE as i32 => Some((MAX_LIGHT_LEVEL, 0)),
+                None => None,
+            }
+        });
+
+        chunk.ao_vertices.write()[array_index] = block_ao;
+        chunk.light_vertices.write()[array_index] = block_light;
     }
 
     // An active face is a block face next to a transparent block that needs to be rendered
     pub fn get_active_faces_of_block(&self, x: i32, y: i32, z: i32) -> [bool; 6] {
-        let right = self
-            .get_block(x + 1, y, z)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let left = self
-            .get_block(x - 1, y, z)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let top = self
-            .get_block(x, y + 1, z)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let bottom = self
-            .get_block(x, y - 1, z)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let front = self
-            .get_block(x, y, z + 1)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let back = self
-            .get_block(x, y, z - 1)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-
-        [right, left, top, bottom, front, back]
+        let block = self.get_block(x, y, z).unwrap_or(BlockID::AIR);
+
+        [
+            (1, 0, 0),
+            (-1, 0, 0),
+            (0, 1, 0),
+            (0, -1, 0),
+            (0, 0, 1),
+            (0, 0, -1),
+        ]
+        .map(
+            |(dx, dy, dz)| match self.get_block(x + dx, y + dy, z + dz) {
+                Some(neighbour) => is_face_visible(block, neighbour),
+                None => true,
+            },
+        )
     }
 
-    pub fn render_loaded_chunks(&self, program: &mut ShaderProgram) {
-        for ((x, z), chunk_column) in self.loaded_chunk_columns.read().iter() {
-            for (ref y, chunk) in chunk_column.chunks.iter().enumerate() {
-                // Skip rendering the chunk if there is nothing to draw
-                let vao = *chunk.vao.read();
+    // Walks from the chunk of the camera to its neighbours, only through the faces that see each
+    // other inside of every chunk (see `VisibilityGraph`) and never back towards the camera.
+    // Returns `None` when the column of the camera isn't loaded, every chunk may be visible then.
+    pub fn find_visible_chunks(
+        &self,
+        camera_chunk: (i32, i32, i32),
+        frustum: &Frustum,
+    ) -> Option<HashSet<(i32, i32, i32)>> {
+        let (x, y, z) = camera_chunk;
+        let start = (x, y.clamp(0, 15), z);
+        self.get_column(x, z)?;
+
+        let mut visible = HashSet::new();
+        visible.insert(start);
+
+        // The chunk, the face it was entered by and the directions taken to reach it
+        let mut queue = VecDeque::new();
+        queue.push_back((start, None, 0u8));
+
+        while let Some(((x, y, z), entered_face, directions)) = queue.pop_front() {
+            let visibility = match self.get_chunk(x, y, z) {
+                Some(chunk) => chunk.visibility(),
+                None => continue,
+            };
 
-                if !*chunk.is_uploaded_to_gpu.read() || chunk.is_empty() || vao == 0 {
+            for (face, (dx, dy, dz)) in FACE_OFFSETS.into_iter().enumerate() {
+                if directions & (1 << opposite_face(face)) != 0 {
                     continue;
                 }
 
-                let model_matrix = {
-                    let translate_matrix = Matrix4::new_translation(
-                        &vec3(*x as f32, *y as f32, *z as f32).scale(16.0),
-                    );
-                    let rotate_matrix = Matrix4::from_euler_angles(0.0f32, 0.0, 0.0);
-                    let scale_matrix =
-                        Matrix4::new_nonuniform_scaling(&vec3(1.0f32, 1.0f32, 1.0f32));
+                if let Some(entered_face) = entered_face {
+                    if !visibility.connects(entered_face, face) {
+                        continue;
+                    }
+                }
+
+                let neighbour = (x + dx, y + dy, z + dz);
+
+                if !(0..16).contains(&neighbour.1)
+                    || visible.contains(&neighbour)
+                    || self.get_column(neighbour.0, neighbour.2).is_none()
+                    || !frustum.intersects_aabb(&chunk_aabb(neighbour.0, neighbour.1, neighbour.2))
+                {
+                    continue;
+                }
+
+                visible.insert(neighbour);
+                queue.push_back((
+                    neighbour,
+                    Some(opposite_face(face)),
+                    directions | (1 << face),
+                ));
+            }
+        }
+
+        Some(visible)
+    }
 
-                    translate_matrix * rotate_matrix * scale_matrix
+    // Draws the chunks that have something to draw, that are in the view of the camera and that
+    // aren't hidden behind other chunks. Every chunk in the view is drawn without `visible_chunks`.
+    // The render layers are drawn one after the other with a single draw call each, the
+    // translucent one from the farthest chunk to the closest.
+    #[cfg(feature = "client")]
+    pub fn render_loaded_chunks(
+        &self,
+        chunk_arena: &ChunkArena,
+        frustum: &Frustum,
+        visible_chunks: Option<&HashSet<(i32, i32, i32)>>,
+        camera_position: &Vec3,
+    ) -> ChunkRenderStats {
+        let mut stats = ChunkRenderStats::default();
+
+        let loaded_chunk_columns = self.loaded_chunk_columns.read();
+        // Coordinates, position and first vertex in the arena of the chunks to draw
+        let mut chunks = Vec::new();
+
+        for (&(x, z), chunk_column) in loaded_chunk_columns.iter() {
+            for (y, chunk) in chunk_column.chunks.iter().enumerate() {
+                let coords = (x, y as i32, z);
+
+                // Skip rendering the chunk if there is nothing to draw
+                let first_vertex = match chunk_arena.first_vertex(coords) {
+                    Some(first_vertex) if *chunk.is_uploaded_to_gpu.read() && !chunk.is_empty() => {
+                        first_vertex
+                    }
+                    _ => continue,
                 };
 
-                gl_call!(gl::BindVertexArray(vao));
+                if !frustum.intersects_aabb(&chunk_aabb(x, y as i32, z)) {
+                    stats.culled += 1;
+                    continue;
+                }
+
+                if visible_chunks.is_some_and(|visible| !visible.contains(&coords)) {
+                    stats.occluded += 1;
+                    continue;
+                }
+
+                stats.drawn += 1;
+
+                let position = vec3(x as f32, y as f32, z as f32).scale(CHUNK_SIZE as f32);
+                chunks.push((coords, position, first_vertex, chunk));
+            }
+        }
+
+        // From the closest to the farthest
+        let distance = |position: &Vec3| {
+            (position.add_scalar(CHUNK_SIZE as f32 / 2.0) - camera_position).norm_squared()
+        };
+        chunks.sort_by(|(_, a, _, _), (_, b, _, _)| distance(a).total_cmp(&distance(b)));
+
+        // The draw commands refer to the positions with their base instance
+        let chunk_positions: Vec<_> = chunks
+            .iter()
+            .map(|(_, position, _, _)| [position.x, position.y, position.z, 0.0])
+            .collect();
+        chunk_arena.bind(&chunk_positions);
+
+        for render_layer in RenderLayer::ALL {
+            let is_translucent = render_layer == RenderLayer::Translucent;
+
+            // The translucent faces are blended over the others without hiding what is behind
+            if is_translucent {
+                gl_call!(gl::Enable(gl::BLEND));
+                gl_call!(gl::DepthMask(gl::FALSE));
+            } else {
+                gl_call!(gl::Disable(gl::BLEND));
+            }
+
+            let mut commands = Vec::new();
 
-                if vao == 0 {
-                    dbg!(vao);
-                    dbg!(*chunk.is_uploaded_to_gpu.read());
-                    dbg!(chunk.is_empty());
+            for (i, &(coords, position, first_vertex, chunk)) in chunks.iter().enumerate() {
+                let (first, count) = chunk.layer_ranges.read()[render_layer as usize];
+
+                if count == 0 {
+                    continue;
                 }
 
-                unsafe {
-                    program.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+                if is_translucent {
+                    chunk.sort_translucent_faces(
+                        chunk_arena,
+                        coords,
+                        &(camera_position - position),
+                    );
                 }
-                gl_call!(gl::DrawArrays(
-                    gl::TRIANGLES,
-                    0,
-                    *chunk.vertices_drawn.read() as i32
-                ));
+
+                commands.push(DrawArraysIndirectCommand {
+                    count,
+                    instance_count: 1,
+                    first: first_vertex + first,
+                    base_instance: i as u32,
+                });
             }
+
+            if is_translucent {
+                commands.reverse();
+            }
+
+            chunk_arena.draw(&commands);
         }
+
+        gl_call!(gl::DepthMask(gl::TRUE));
+        gl_call!(gl::Enable(gl::BLEND));
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::BlockID;
+    use crate::test_support::flat_world;
+
+    #[test]
+    fn the_surface_of_a_fluid_is_hidden_under_an_opaque_block() {
+        let chunk_manager = flat_world(&[BlockID::STONE, BlockID::WATER, BlockID::WATER]);
+        chunk_manager.put_block(0, 2, 0, BlockID::STONE);
+
+        // Faces: +x, -x, +y, -y, +z, -z
+        assert_eq!(chunk_manager.get_active_faces_of_block(0, 1, 0), [false; 6]);
+        assert_eq!(
+            chunk_manager.get_active_faces_of_block(1, 2, 0),
+            [false, false, true, false, false, false]
+        );
     }
 }
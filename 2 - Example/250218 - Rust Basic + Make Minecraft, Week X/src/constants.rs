@@ -1,4 +1,3 @@
-use rand::{thread_rng, RngCore};
 use std::time::Duration;
 
 // Logging
@@ -12,7 +11,6 @@ pub const WINDOW_WIDTH: u32 = 1000;
 pub const WINDOW_HEIGHT: u32 = 600;
 pub const NEAR_PLANE: f32 = 0.1;
 pub const FAR_PLANE: f32 = 1000.0;
-pub const BACKGROUND_COLOR: (f32, f32, f32, f32) = (0.74, 0.84, 1.0, 1.0);
 lazy_static! {
     pub static ref FOV: f32 = 80.0f32.to_radians();
 }
@@ -22,18 +20,31 @@ pub const GUI_SCALING: f32 = 2.0;
 pub const CROSSHAIR_SIZE: f32 = 40.0;
 pub const BLOCK_OUTLINE_WIDTH: f32 = 3.0;
 
+// World storage
+pub const WORLD_DIRECTORY: &str = "saves/world";
+
+// Networking
+pub const SERVER_PORT: u16 = 25565;
+pub const PLAYER_NAME: &str = "Player";
 lazy_static! {
-    pub static ref WORLD_SEED: u32 = {
-        let seed = thread_rng().next_u32();
-        println!("Seed: {seed}");
-        seed
-    };
+    pub static ref HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+    pub static ref KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(5);
+    pub static ref KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(30);
 }
+// Columns a client requests around its player, one more than the render distance so that the
+// rendered chunks can be lit. The server refuses the requests further than this.
+pub const REQUESTED_COLUMN_DISTANCE: i32 = RENDER_DISTANCE + 1;
+// Messages waiting to be written to a TCP connection, the connection is closed past this
+pub const OUTGOING_MESSAGE_QUEUE_SIZE: usize = 1024;
 
 // Rendering
 pub const RENDER_DISTANCE: i32 = 8;
 pub const ENABLE_FOG: bool = true;
 pub const CHUNK_UPLOADS_PER_FRAME: usize = 2;
+// Distance the camera moves before the translucent faces of the chunks are sorted again
+pub const TRANSLUCENT_SORT_DISTANCE: f32 = 1.0;
+// Vertices that fit in the buffer shared by the chunks before it needs to grow (48 MiB)
+pub const CHUNK_ARENA_INITIAL_VERTICES: u32 = 1 << 20;
 lazy_static! {
     pub static ref WORLD_GENERATION_THREAD_POOL_SIZE: usize = {
         let cpus = num_cpus::get();
@@ -82,3 +93,57 @@ lazy_static! {
     pub static ref FLYING_TRIGGER_INTERVAL: Duration = Duration::from_millis(250);
     pub static ref SPRINTING_TRIGGER_INTERVAL: Duration = Duration::from_millis(250);
 }
+
+// Dropped items
+pub const DROPPED_ITEM_SIZE: f32 = 0.25;
+pub const DROPPED_ITEM_MERGE_DISTANCE: f32 = 0.5;
+// Added to the bounding box of the players, on every side
+pub const DROPPED_ITEM_PICKUP_RANGE: f32 = 1.0;
+pub const DROPPED_ITEM_THROW_SPEED: f32 = 6.0;
+lazy_static! {
+    pub static ref BLOCK_DROP_PICKUP_DELAY: Duration = Duration::from_millis(500);
+    pub static ref THROWN_ITEM_PICKUP_DELAY: Duration = Duration::from_secs(2);
+    pub static ref DROPPED_ITEM_LIFETIME: Duration = Duration::from_secs(300);
+}
+
+// Mining
+// Seconds it takes to break a block by hand per point of hardness, like Minecraft
+pub const SECONDS_PER_HARDNESS: f32 = 1.5;
+// How many times faster the right tool breaks a block
+pub const TOOL_SPEED_MULTIPLIER: f32 = 4.0;
+pub const BREAK_STAGES: usize = 10;
+
+// Health and hunger, counted in halves of the icons drawn by the gui
+pub const MAX_HEALTH: u32 = 20;
+pub const MAX_FOOD: u32 = 20;
+// Falling from higher than this hurts, one point per extra block
+pub const SAFE_FALL_DISTANCE: f32 = 3.0;
+pub const VOID_DAMAGE: u32 = 4;
+// One point of food is lost every time the exhaustion reaches this
+pub const EXHAUSTION_PER_FOOD: f32 = 4.0;
+// Per block sprinted
+pub const SPRINTING_EXHAUSTION: f32 = 0.1;
+pub const JUMPING_EXHAUSTION: f32 = 0.05;
+pub const SPRINT_JUMPING_EXHAUSTION: f32 = 0.2;
+pub const REGENERATION_EXHAUSTION: f32 = 6.0;
+// The health regenerates when the food is at least this
+pub const REGENERATION_MIN_FOOD: u32 = 18;
+// Interval of the regeneration and the starvation
+pub const FOOD_TICK_SECONDS: f32 = 4.0;
+pub const VOID_DAMAGE_SECONDS: f32 = 0.5;
+
+// Day/night cycle
+lazy_static! {
+    pub static ref DAY_LENGTH: Duration = Duration::from_secs(20 * 60);
+}
+pub const DAY_SKY_COLOR: (f32, f32, f32) = (0.47, 0.65, 1.0);
+pub const NIGHT_SKY_COLOR: (f32, f32, f32) = (0.0, 0.0, 0.04);
+pub const DAY_FOG_COLOR: (f32, f32, f32) = (0.74, 0.84, 1.0);
+pub const NIGHT_FOG_COLOR: (f32, f32, f32) = (0.02, 0.02, 0.06);
+pub const SUNSET_COLOR: (f32, f32, f32) = (0.95, 0.45, 0.2);
+// The skylight is multiplied by this at night
+pub const MIN_SKYLIGHT: f32 = 0.2;
+// Distance of the sun and the moon from the camera, and their size
+pub const CELESTIAL_DISTANCE: f32 = 100.0;
+pub const SUN_SIZE: f32 = 30.0;
+pub const MOON_SIZE: f32 = 20.0;
@@ -0,0 +1,485 @@
+use crate::block_registry::{BlockRegistry, BLOCK_REGISTRY};
+use crate::chunk::BlockID;
+use crate::inventory::item::ItemStack;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+lazy_static! {
+    // Every crafting recipe of the game, described by `recipes.toml`
+    pub static ref RECIPE_REGISTRY: RecipeRegistry =
+        RecipeRegistry::from_toml(include_str!("../recipes.toml"), &BLOCK_REGISTRY)
+            .unwrap_or_else(|err| panic!("Invalid recipe manifest: {err}"));
+}
+
+// The largest grid, the one of the crafting table
+pub const MAX_CRAFTING_GRID_SIZE: usize = 3;
+
+// Entries of the manifest, see `recipes.toml` for the meaning of each field
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShapedEntry {
+    pattern: Vec<String>,
+    key: HashMap<String, String>,
+    result: String,
+    #[serde(default = "default_count")]
+    count: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShapelessEntry {
+    ingredients: Vec<String>,
+    result: String,
+    #[serde(default = "default_count")]
+    count: u32,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeManifest {
+    #[serde(default)]
+    shaped: Vec<ShapedEntry>,
+    #[serde(default)]
+    shapeless: Vec<ShapelessEntry>,
+}
+
+// Items of the non-empty part of a grid, row by row from the top left corner
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shape {
+    pub width: usize,
+    pub height: usize,
+    pub items: Vec<Option<BlockID>>,
+}
+
+impl Shape {
+    // Smallest shape holding every item of a `width` x `height` grid, `None` when it is empty
+    pub fn trimmed(width: usize, height: usize, items: &[Option<BlockID>]) -> Option<Self> {
+        let is_filled = |x: usize, y: usize| items[y * width + x].is_some();
+
+        let columns: Vec<_> = (0..width)
+            .filter(|&x| (0..height).any(|y| is_filled(x, y)))
+            .collect();
+        let rows: Vec<_> = (0..height)
+            .filter(|&y| (0..width).any(|x| is_filled(x, y)))
+            .collect();
+
+        let (left, right) = (*columns.first()?, *columns.last()?);
+        let (top, bottom) = (*rows.first()?, *rows.last()?);
+
+        Some(Self {
+            width: right - left + 1,
+            height: bottom - top + 1,
+            items: (top..=bottom)
+                .flat_map(|y| (left..=right).map(move |x| items[y * width + x]))
+                .collect(),
+        })
+    }
+
+    pub fn mirrored(&self) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            items: self
+                .items
+                .chunks(self.width)
+                .flat_map(|row| row.iter().rev().copied())
+                .collect(),
+        }
+    }
+}
+
+pub enum Recipe {
+    // The items must be laid out like the shape, anywhere in the grid and possibly mirrored
+    Shaped {
+        shape: Shape,
+        result: ItemStack,
+    },
+    // The items can be anywhere, sorted by id
+    Shapeless {
+        ingredients: Vec<BlockID>,
+        result: ItemStack,
+    },
+}
+
+impl Recipe {
+    pub fn result(&self) -> ItemStack {
+        match self {
+            Recipe::Shaped { result, .. } | Recipe::Shapeless { result, .. } => *result,
+        }
+    }
+
+    pub fn matches(&self, grid: &CraftingGrid) -> bool {
+        let Some(grid_shape) = grid.shape() else {
+            return false;
+        };
+
+        match self {
+            Recipe::Shaped { shape, .. } => *shape == grid_shape || shape.mirrored() == grid_shape,
+            Recipe::Shapeless { ingredients, .. } => {
+                let mut items: Vec<_> = grid_shape.items.into_iter().flatten().collect();
+                items.sort_by_key(BlockID::id);
+
+                *ingredients == items
+            }
+        }
+    }
+}
+
+pub struct RecipeRegistry {
+    recipes: Vec<Recipe>,
+}
+
+impl RecipeRegistry {
+    pub fn from_toml(manifest: &str, blocks: &BlockRegistry) -> Result<Self, String> {
+        let manifest: RecipeManifest = toml::from_str(manifest).map_err(|err| err.to_string())?;
+
+        let block = |name: &str| {
+            blocks
+                .get_by_name(name)
+                .filter(|block| !block.is_air())
+                .ok_or_else(|| format!("Unknown block \"{name}\" in a recipe"))
+        };
+        let result = |name: &str, count: u32| {
+            let item = block(name)?;
+
+            if count == 0 || count > blocks.get(item).unwrap().max_stack_size {
+                return Err(format!("Recipe of \"{name}\" crafts {count} of it"));
+            }
+
+            Ok(ItemStack::new(item, count))
+        };
+
+        let mut recipes = Vec::new();
+
+        for entry in manifest.shaped {
+            let height = entry.pattern.len();
+            let width = entry.pattern.first().map_or(0, |row| row.chars().count());
+
+            if !(1..=MAX_CRAFTING_GRID_SIZE).contains(&width)
+                || !(1..=MAX_CRAFTING_GRID_SIZE).contains(&height)
+                || entry.pattern.iter().any(|row| row.chars().count() != width)
+            {
+                return Err(format!(
+                    "Pattern of \"{}\" must be rows of the same length, at most 3x3",
+                    entry.result
+                ));
+            }
+
+            let mut key = HashMap::new();
+
+            for (symbol, name) in &entry.key {
+                let mut chars = symbol.chars();
+
+                match (chars.next(), chars.next()) {
+                    (Some(symbol), None) if symbol != ' ' => key.insert(symbol, block(name)?),
+                    _ => return Err(format!("Invalid key \"{symbol}\" in a recipe")),
+                };
+            }
+
+            let mut items = Vec::new();
+
+            for symbol in entry.pattern.iter().flat_map(|row| row.chars()) {
+                items.push(match symbol {
+                    ' ' => None,
+                    _ => Some(*key.get(&symbol).ok_or_else(|| {
+                        format!("\"{symbol}\" is not in the key of \"{}\"", entry.result)
+                    })?),
+                });
+            }
+
+            let shape = Shape::trimmed(width, height, &items)
+                .ok_or_else(|| format!("Pattern of \"{}\" is empty", entry.result))?;
+
+            recipes.push(Recipe::Shaped {
+                shape,
+                result: result(&entry.result, entry.count)?,
+            });
+        }
+
+        for entry in manifest.shapeless {
+            if !(1..=MAX_CRAFTING_GRID_SIZE.pow(2)).contains(&entry.ingredients.len()) {
+                return Err(format!(
+                    "Recipe of \"{}\" must have between 1 and 9 ingredients",
+                    entry.result
+                ));
+            }
+
+            let mut ingredients = entry
+                .ingredients
+                .iter()
+                .map(|name| block(name))
+                .collect::<Result<Vec<_>, _>>()?;
+            ingredients.sort_by_key(BlockID::id);
+
+            recipes.push(Recipe::Shapeless {
+                ingredients,
+                result: result(&entry.result, entry.count)?,
+            });
+        }
+
+        Ok(Self { recipes })
+    }
+
+    // Recipe crafting the items of the grid, the shaped recipes are tried first, in the order of
+    // the manifest
+    pub fn find(&self, grid: &CraftingGrid) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.matches(grid))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+}
+
+// Square grid in which the items are laid out, row by row from the top left corner
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CraftingGrid {
+    pub size: usize,
+    pub slots: Vec<Option<ItemStack>>,
+}
+
+impl CraftingGrid {
+    pub fn new(size: usize) -> Self {
+        assert!((1..=MAX_CRAFTING_GRID_SIZE).contains(&size));
+
+        Self {
+            size,
+            slots: vec![None; size * size],
+        }
+    }
+
+    pub fn shape(&self) -> Option<Shape> {
+        let items: Vec<_> = self
+            .slots
+            .iter()
+            .map(|slot| slot.map(|item_stack| item_stack.item))
+            .collect();
+
+        Shape::trimmed(self.size, self.size, &items)
+    }
+
+    // Uses one item of every slot
+    pub fn consume(&mut self) {
+        for slot in self.slots.iter_mut() {
+            if let Some(item_stack) = slot {
+                item_stack.amount -= 1;
+            }
+
+            *slot = slot.filter(|item_stack| item_stack.amount > 0);
+        }
+    }
+
+    // Empties the grid, the items go back to the player
+    pub fn take_all(&mut self) -> Vec<ItemStack> {
+        self.slots.iter_mut().filter_map(Option::take).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a grid from rows of block names, "" being an empty slot
+    fn grid(rows: &[&[&str]]) -> CraftingGrid {
+        let mut grid = CraftingGrid::new(rows.len());
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, name) in row.iter().enumerate() {
+                grid.slots[y * rows.len() + x] = BLOCK_REGISTRY
+                    .get_by_name(name)
+                    .map(|block| ItemStack::new(block, 1));
+            }
+        }
+
+        grid
+    }
+
+    fn registry(manifest: &str) -> Result<RecipeRegistry, String> {
+        RecipeRegistry::from_toml(manifest, &BLOCK_REGISTRY)
+    }
+
+    fn crafted(registry: &RecipeRegistry, rows: &[&[&str]]) -> Option<ItemStack> {
+        registry.find(&grid(rows)).map(Recipe::result)
+    }
+
+    #[test]
+    fn builtin_recipes_are_valid() {
+        assert!(!RECIPE_REGISTRY.is_empty());
+
+        assert_eq!(
+            crafted(&RECIPE_REGISTRY, &[&["", "oak_log"], &["", ""]]),
+            Some(ItemStack::new(BlockID::OAK_PLANKS, 4))
+        );
+        assert_eq!(
+            crafted(
+                &RECIPE_REGISTRY,
+                &[&["oak_planks", "oak_planks"], &["oak_planks", "oak_planks"]]
+            ),
+            Some(ItemStack::new(BlockID::CRAFTING_TABLE, 1))
+        );
+    }
+
+    #[test]
+    fn shaped_recipes_match_anywhere_in_the_grid() {
+        let registry = registry(
+            r###"
+            [[shaped]]
+            pattern = ["##", "# "]
+            key = { "#" = "stone" }
+            result = "obsidian"
+            "###,
+        )
+        .unwrap();
+        let obsidian = Some(ItemStack::new(BlockID::OBSIDIAN, 1));
+
+        assert_eq!(
+            crafted(&registry, &[&["stone", "stone"], &["stone", ""]]),
+            obsidian
+        );
+        assert_eq!(
+            crafted(
+                &registry,
+                &[&["", "", ""], &["", "stone", "stone"], &["", "stone", ""]]
+            ),
+            obsidian
+        );
+        assert_eq!(
+            crafted(
+                &registry,
+                &[&["stone", "stone", ""], &["stone", "", ""], &["", "", ""]]
+            ),
+            obsidian
+        );
+
+        // Mirrored left to right
+        assert_eq!(
+            crafted(&registry, &[&["stone", "stone"], &["", "stone"]]),
+            obsidian
+        );
+
+        // But not upside down, with another item or with an extra item
+        assert_eq!(
+            crafted(&registry, &[&["stone", ""], &["stone", "stone"]]),
+            None
+        );
+        assert_eq!(
+            crafted(&registry, &[&["stone", "stone"], &["dirt", ""]]),
+            None
+        );
+        assert_eq!(
+            crafted(
+                &registry,
+                &[
+                    &["stone", "stone", ""],
+                    &["stone", "", ""],
+                    &["", "", "stone"]
+                ]
+            ),
+            None
+        );
+        assert_eq!(crafted(&registry, &[&["", ""], &["", ""]]), None);
+    }
+
+    #[test]
+    fn shapes_ignore_the_empty_rows_and_columns_of_the_pattern() {
+        let registry = registry(
+            r###"
+            [[shaped]]
+            pattern = ["   ", " # ", " # "]
+            key = { "#" = "cobblestone" }
+            result = "stone"
+            count = 2
+            "###,
+        )
+        .unwrap();
+
+        assert_eq!(
+            crafted(&registry, &[&["cobblestone", ""], &["cobblestone", ""]]),
+            Some(ItemStack::new(BlockID::STONE, 2))
+        );
+        assert_eq!(
+            crafted(&registry, &[&["cobblestone", "cobblestone"], &["", ""]]),
+            None
+        );
+    }
+
+    #[test]
+    fn shapeless_recipes_match_the_ingredients_in_any_order() {
+        let registry = registry(
+            r#"
+            [[shapeless]]
+            ingredients = ["sand", "gravel", "sand"]
+            result = "glass"
+            "#,
+        )
+        .unwrap();
+        let glass = Some(ItemStack::new(BlockID::GLASS, 1));
+
+        assert_eq!(
+            crafted(
+                &registry,
+                &[&["gravel", "", ""], &["", "", "sand"], &["sand", "", ""]]
+            ),
+            glass
+        );
+        assert_eq!(
+            crafted(&registry, &[&["sand", "sand"], &["gravel", ""]]),
+            glass
+        );
+
+        assert_eq!(crafted(&registry, &[&["sand", "gravel"], &["", ""]]), None);
+        assert_eq!(
+            crafted(&registry, &[&["sand", "gravel"], &["sand", "sand"]]),
+            None
+        );
+    }
+
+    #[test]
+    fn crafting_consumes_one_item_of_every_slot() {
+        let mut grid = grid(&[&["oak_log", ""], &["", "dirt"]]);
+        grid.slots[0] = Some(ItemStack::new(BlockID::OAK_LOG, 3));
+
+        grid.consume();
+        assert_eq!(grid.slots[0], Some(ItemStack::new(BlockID::OAK_LOG, 2)));
+        assert_eq!(grid.slots[3], None);
+
+        assert_eq!(grid.take_all(), vec![ItemStack::new(BlockID::OAK_LOG, 2)]);
+        assert_eq!(grid.slots, vec![None; 4]);
+    }
+
+    #[test]
+    fn invalid_manifests_are_rejected() {
+        // Unknown block
+        assert!(registry("[[shapeless]]\ningredients = [\"b\"]\nresult = \"dirt\"\n").is_err());
+        assert!(registry("[[shapeless]]\ningredients = [\"dirt\"]\nresult = \"b\"\n").is_err());
+        // Too many crafted
+        assert!(registry(
+            "[[shapeless]]\ningredients = [\"dirt\"]\nresult = \"water\"\ncount = 2\n"
+        )
+        .is_err());
+        // Rows of different lengths
+        assert!(registry(
+            "[[shaped]]\npattern = [\"##\", \"#\"]\nkey = { \"#\" = \"dirt\" }\nresult = \"stone\"\n"
+        )
+        .is_err());
+        // Larger than the crafting table
+        assert!(registry(
+            "[[shaped]]\npattern = [\"####\"]\nkey = { \"#\" = \"dirt\" }\nresult = \"stone\"\n"
+        )
+        .is_err());
+        // Symbol missing from the key
+        assert!(registry(
+            "[[shaped]]\npattern = [\"#x\"]\nkey = { \"#\" = \"dirt\" }\nresult = \"stone\"\n"
+        )
+        .is_err());
+        // Empty pattern
+        assert!(registry(
+            "[[shaped]]\npattern = [\"  \"]\nkey = { \"#\" = \"dirt\" }\nresult = \"stone\"\n"
+        )
+        .is_err());
+    }
+}
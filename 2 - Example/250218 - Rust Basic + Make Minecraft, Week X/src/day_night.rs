@@ -0,0 +1,221 @@
+use crate::constants::{
+    DAY_FOG_COLOR, DAY_LENGTH, DAY_SKY_COLOR, MIN_SKYLIGHT, NIGHT_FOG_COLOR, NIGHT_SKY_COLOR,
+    SUNSET_COLOR,
+};
+use nalgebra_glm::{lerp, vec3, Vec3};
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+// The time of day is the fraction of the day that passed since sunrise:
+// 0 is sunrise, 0.25 noon, 0.5 sunset and 0.75 midnight
+
+pub const SUNRISE: f32 = 0.0;
+pub const NOON: f32 = 0.25;
+pub const SUNSET: f32 = 0.5;
+pub const MIDNIGHT: f32 = 0.75;
+
+// Angle of the sun around the Z axis, it rises in +X and is above the player at noon.
+// The moon is on the opposite side.
+pub fn sun_angle(time_of_day: f32) -> f32 {
+    time_of_day * 2.0 * PI
+}
+
+fn sun_height(time_of_day: f32) -> f32 {
+    sun_angle(time_of_day).sin()
+}
+
+// From 0 at night to 1 during the day, the transitions are around the sunrise and the sunset
+pub fn daylight(time_of_day: f32) -> f32 {
+    (sun_height(time_of_day) * 2.0 + 0.5).clamp(0.0, 1.0)
+}
+
+// Strength of the red glow while the sun is close to the horizon
+fn sunset_glow(time_of_day: f32) -> f32 {
+    (1.0 - sun_height(time_of_day).abs() / 0.3).max(0.0)
+}
+
+// Multiplies the skylight of the blocks, the block light doesn't change
+pub fn skylight_multiplier(time_of_day: f32) -> f32 {
+    MIN_SKYLIGHT + (1.0 - MIN_SKYLIGHT) * daylight(time_of_day)
+}
+
+fn color(color: (f32, f32, f32)) -> Vec3 {
+    vec3(color.0, color.1, color.2)
+}
+
+// Colour of the sky above the player
+pub fn sky_color(time_of_day: f32) -> Vec3 {
+    let sky_color = lerp(
+        &color(NIGHT_SKY_COLOR),
+        &color(DAY_SKY_COLOR),
+        daylight(time_of_day),
+    );
+
+    lerp(
+        &sky_color,
+        &color(SUNSET_COLOR),
+        0.4 * sunset_glow(time_of_day),
+    )
+}
+
+// Colour of the horizon, the far blocks fade into it
+pub fn fog_color(time_of_day: f32) -> Vec3 {
+    let fog_color = lerp(
+        &color(NIGHT_FOG_COLOR),
+        &color(DAY_FOG_COLOR),
+        daylight(time_of_day),
+    );
+
+    lerp(
+        &fog_color,
+        &color(SUNSET_COLOR),
+        0.6 * sunset_glow(time_of_day),
+    )
+}
+
+// Time of the world, advanced with the global timer so it stops while the timer is paused
+pub struct WorldTime {
+    day: u64,
+    time_of_day: f32,
+    day_length: Duration,
+    last_update: Option<Instant>,
+}
+
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self::new(*DAY_LENGTH)
+    }
+}
+
+impl WorldTime {
+    pub fn new(day_length: Duration) -> Self {
+        Self {
+            day: 0,
+            time_of_day: SUNRISE,
+            day_length,
+            last_update: None,
+        }
+    }
+
+    // Advances by the time elapsed since the last update
+    pub fn advance(&mut self, time: Instant) {
+        if let Some(last_update) = self.last_update {
+            self.advance_by(time.saturating_duration_since(last_update));
+        }
+
+        self.last_update = Some(time);
+    }
+
+    pub fn advance_by(&mut self, elapsed: Duration) {
+        let days = self.time_of_day as f64 + elapsed.as_secs_f64() / self.day_length.as_secs_f64();
+
+        self.day += days.floor() as u64;
+        self.time_of_day = days.fract() as f32;
+    }
+
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    pub fn day(&self) -> u64 {
+        self.day
+    }
+
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    pub fn is_day(&self) -> bool {
+        self.time_of_day < SUNSET
+    }
+
+    pub fn daylight(&self) -> f32 {
+        daylight(self.time_of_day)
+    }
+
+    pub fn skylight_multiplier(&self) -> f32 {
+        skylight_multiplier(self.time_of_day)
+    }
+
+    pub fn sun_angle(&self) -> f32 {
+        sun_angle(self.time_of_day)
+    }
+
+    pub fn sky_color(&self) -> Vec3 {
+        sky_color(self.time_of_day)
+    }
+
+    pub fn fog_color(&self) -> Vec3 {
+        fog_color(self.time_of_day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn the_sun_goes_around_the_world() {
+        assert_close(sun_angle(SUNRISE), 0.0);
+        assert_close(sun_angle(NOON), PI / 2.0);
+        assert_close(sun_angle(SUNSET), PI);
+        assert_close(sun_angle(MIDNIGHT), 3.0 * PI / 2.0);
+    }
+
+    #[test]
+    fn daylight_follows_the_sun() {
+        assert_close(daylight(NOON), 1.0);
+        assert_close(daylight(MIDNIGHT), 0.0);
+        assert_close(daylight(SUNRISE), 0.5);
+        assert_close(daylight(SUNSET), 0.5);
+
+        // It grows in the morning and fades in the evening
+        assert!(daylight(0.01) < daylight(0.02));
+        assert!(daylight(0.51) > daylight(0.52));
+
+        assert_close(skylight_multiplier(NOON), 1.0);
+        assert_close(skylight_multiplier(MIDNIGHT), MIN_SKYLIGHT);
+    }
+
+    #[test]
+    fn colors_go_from_day_to_night_through_the_sunset() {
+        assert_eq!(sky_color(NOON), color(DAY_SKY_COLOR));
+        assert_eq!(sky_color(MIDNIGHT), color(NIGHT_SKY_COLOR));
+        assert_eq!(fog_color(NOON), color(DAY_FOG_COLOR));
+        assert_eq!(fog_color(MIDNIGHT), color(NIGHT_FOG_COLOR));
+
+        // Redder at sunset
+        let sunset = fog_color(SUNSET);
+        let noon = fog_color(NOON);
+        assert!(sunset.x / sunset.z > noon.x / noon.z);
+    }
+
+    #[test]
+    fn world_time_wraps_around_the_days() {
+        let mut world_time = WorldTime::new(Duration::from_secs(20 * 60));
+        let start = Instant::now();
+
+        world_time.advance(start);
+        assert_eq!(world_time.time_of_day(), SUNRISE);
+
+        world_time.advance(start + Duration::from_secs(5 * 60));
+        assert_close(world_time.time_of_day(), NOON);
+        assert!(world_time.is_day());
+
+        world_time.advance(start + Duration::from_secs(35 * 60));
+        assert_eq!(world_time.day(), 1);
+        assert_close(world_time.time_of_day(), MIDNIGHT);
+        assert!(!world_time.is_day());
+
+        // A paused timer doesn't move, neither does the time of the world
+        world_time.advance(start + Duration::from_secs(35 * 60));
+        assert_close(world_time.time_of_day(), MIDNIGHT);
+
+        world_time.set_time_of_day(-0.75);
+        assert_close(world_time.time_of_day(), NOON);
+    }
+}
@@ -1,16 +1,36 @@
+use crate::game_mode::GameMode;
+use crate::inventory::dropped_item::DroppedItem;
+use crate::inventory::screen::InventoryScreen;
 use crate::inventory::Inventory;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
-use specs::{Component, DenseVecStorage, NullStorage};
+use crate::physics::{Collider, Gravity, Interpolator, PhysicsBody, Transform};
+use crate::player::PlayerState;
+use crate::vitals::{Health, Hunger};
+use specs::{Component, DenseVecStorage, NullStorage, VecStorage};
 
-impl Component for Interpolator<PlayerPhysicsState> {
+impl Component for Transform {
+    type Storage = VecStorage<Self>;
+}
+
+impl Component for Interpolator<PhysicsBody> {
     type Storage = DenseVecStorage<Self>;
 }
 
+impl Component for Gravity {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for Collider {
+    type Storage = NullStorage<Self>;
+}
+
 impl Component for PlayerState {
     type Storage = DenseVecStorage<Self>;
 }
 
+impl Component for GameMode {
+    type Storage = DenseVecStorage<Self>;
+}
+
 #[derive(Default)]
 pub struct MainHandItemChanged;
 
@@ -18,6 +38,30 @@ impl Component for MainHandItemChanged {
     type Storage = NullStorage<Self>;
 }
 
+// Keeps the chunks around it loaded like a player, e.g. the spawn area of the server
+#[derive(Default)]
+pub struct ChunkLoadAnchor;
+
+impl Component for ChunkLoadAnchor {
+    type Storage = NullStorage<Self>;
+}
+
 impl Component for Inventory {
     type Storage = DenseVecStorage<Self>;
 }
+
+impl Component for DroppedItem {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for InventoryScreen {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for Health {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for Hunger {
+    type Storage = DenseVecStorage<Self>;
+}
@@ -0,0 +1,56 @@
+use crate::block_ticks::BlockTicks;
+use crate::chunk_manager::ChunkManager;
+use crate::constants::PHYSICS_TICKRATE;
+use crate::timer::Timer;
+use specs::{Read, System};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// After a long frame, the ticks above this are skipped instead of being caught up
+const MAX_BLOCK_TICKS_PER_RUN: u32 = 10;
+
+// Ticks the blocks of the world at `PHYSICS_TICKRATE` ticks per second of the global time
+pub struct UpdateBlockTicks {
+    seed: u32,
+    // Created on the first run since it subscribes to the block changes of the chunk manager
+    block_ticks: Option<BlockTicks>,
+    last_tick: Option<Instant>,
+}
+
+impl UpdateBlockTicks {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            block_ticks: None,
+            last_tick: None,
+        }
+    }
+}
+
+impl<'a> System<'a> for UpdateBlockTicks {
+    type SystemData = (Read<'a, Timer>, Read<'a, Arc<ChunkManager>>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (global_timer, chunk_manager) = data;
+
+        let seed = self.seed;
+        let block_ticks = self
+            .block_ticks
+            .get_or_insert_with(|| BlockTicks::new(&chunk_manager, seed));
+        let last_tick = self.last_tick.get_or_insert(global_timer.time());
+        let tick_duration = Duration::from_secs_f32(1.0 / PHYSICS_TICKRATE);
+
+        let mut ticks = 0;
+
+        while global_timer.time().duration_since(*last_tick) >= tick_duration {
+            if ticks == MAX_BLOCK_TICKS_PER_RUN {
+                *last_tick = global_timer.time();
+                break;
+            }
+
+            block_ticks.tick(&chunk_manager);
+            *last_tick += tick_duration;
+            ticks += 1;
+        }
+    }
+}
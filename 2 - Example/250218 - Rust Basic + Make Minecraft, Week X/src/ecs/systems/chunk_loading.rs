@@ -1,25 +1,25 @@
-use crate::chunk::{BlockID, BlockIterator, Chunk, ChunkColumn};
+use crate::chunk::{BlockIterator, Chunk, ChunkColumn};
 use crate::chunk_manager::ChunkManager;
-use crate::constants::{
-    CHUNK_UPLOADS_PER_FRAME, RENDER_DISTANCE, WORLD_GENERATION_THREAD_POOL_SIZE, WORLD_SEED,
-};
-use crate::physics::Interpolator;
-use crate::player::PlayerPhysicsState;
-use crate::types::TexturePack;
+use crate::constants::{RENDER_DISTANCE, WORLD_GENERATION_THREAD_POOL_SIZE};
+use crate::ecs::components::ChunkLoadAnchor;
+use crate::light::light_column;
+use crate::physics::Transform;
+use crate::player::PlayerState;
+use crate::storage::WorldStorage;
+use crate::world_generation::WorldGenerator;
 use bit_vec::BitVec;
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use noise::{NoiseFn, SuperSimplex};
 use num_traits::abs;
 use parking_lot::RwLock;
-use specs::{Join, Read, ReadStorage, System};
+use specs::{Join, LendJoin, Read, ReadExpect, ReadStorage, System};
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::collections::{HashMap, VecDeque};
 use std::ops::Deref;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 #[derive(Eq)]
-struct PrioritizedItem<T> {
+pub struct PrioritizedItem<T> {
     pub item: T,
     pub priority: i32,
 }
@@ -50,8 +50,14 @@ impl<T> Deref for PrioritizedItem<T> {
     }
 }
 
+// Chunk whose mesh is ready to be uploaded to the GPU
+pub type ChunkUpload = PrioritizedItem<(i32, i32, i32)>;
+
 pub struct ChunkLoading {
-    noise_fn: SuperSimplex,
+    // `None` when the columns are received from a server (see `SyncWithServer`), they are
+    // already generated and lit and are only meshed here
+    world_generator: Option<Arc<dyn WorldGenerator>>,
+    seed: u32,
     chunk_column_pool: Arc<RwLock<Vec<Arc<ChunkColumn>>>>,
 
     request_chunk_columns_tx: Sender<()>,
@@ -60,91 +66,59 @@ pub struct ChunkLoading {
     requested_chunk_column_tx: Sender<Arc<ChunkColumn>>,
     requested_chunk_column_rx: Receiver<Arc<ChunkColumn>>,
 
-    upload_chunks_tx: Sender<PrioritizedItem<(i32, i32, i32)>>,
-    upload_chunks_rx: Receiver<PrioritizedItem<(i32, i32, i32)>>,
-
-    chunk_upload_priority_queue: BinaryHeap<PrioritizedItem<(i32, i32, i32)>>,
+    // Chunks are only meshed when somebody renders them, this is `None` on the server
+    upload_chunks_tx: Option<Sender<ChunkUpload>>,
 
     expand_chunks: Arc<RwLock<bool>>,
+    expanded_player_index: usize,
     world_generation_thread_pool: rayon::ThreadPool,
     player_interaction_thread_pool: rayon::ThreadPool,
 }
 
-fn compute_tree_placement_in_chunk(noise: &SuperSimplex, x: f64, z: f64) -> Vec<(u32, u32)> {
-    let mut maximums = Vec::new();
-
-    #[inline]
-    fn index(i: i32, j: i32) -> usize {
-        (18 * i + j) as usize
-    }
-
-    let mut samples: [f64; 18 * 18] = [0.0; 18 * 18];
-
-    for i in -1..=16 {
-        for j in -1..=16 {
-            let x = x + j as f64 * 0.075;
-            let z = z + i as f64 * 0.075;
-            samples[index(i + 1, j + 1)] = noise.get([x, z]);
-        }
+impl ChunkLoading {
+    pub fn new(
+        upload_chunks_tx: Option<Sender<ChunkUpload>>,
+        world_generator: Arc<dyn WorldGenerator>,
+        seed: u32,
+    ) -> Self {
+        Self::with_world_generator(upload_chunks_tx, Some(world_generator), seed)
     }
 
-    for i in 1..17 {
-        for j in 1..17 {
-            let center = samples[index(i, j)];
-            let is_max = (|| {
-                for i_new in i - 1..=i + 1 {
-                    for j_new in j - 1..=j + 1 {
-                        if i_new == i && j_new == j {
-                            continue;
-                        }
-
-                        if samples[index(i_new, j_new)] >= center {
-                            return false;
-                        }
-                    }
-                }
-
-                true
-            })();
-
-            if is_max {
-                maximums.push(((j - 1) as u32, (i - 1) as u32));
-            }
-        }
+    // Only meshes the columns that `SyncWithServer` receives
+    pub fn remote(upload_chunks_tx: Sender<ChunkUpload>) -> Self {
+        Self::with_world_generator(Some(upload_chunks_tx), None, 0)
     }
 
-    maximums
-}
-
-impl ChunkLoading {
-    pub fn new() -> Self {
+    fn with_world_generator(
+        upload_chunks_tx: Option<Sender<ChunkUpload>>,
+        world_generator: Option<Arc<dyn WorldGenerator>>,
+        seed: u32,
+    ) -> Self {
         let (request_chunk_columns_tx, request_chunk_columns_rx) = unbounded();
         let (requested_chunk_column_tx, requested_chunk_column_rx) = unbounded();
-        let (upload_chunks_tx, upload_chunks_rx) = unbounded();
-
-        Self {
-            noise_fn: SuperSimplex::new(*WORLD_SEED),
-            chunk_column_pool: Arc::new(RwLock::new({
-                let mut vec = Vec::new();
-                let matrix_width = (2 * (RENDER_DISTANCE + 2) + 1) as usize;
-
-                let reserved_columns = matrix_width * matrix_width;
-                vec.reserve(reserved_columns);
 
-                for _ in 0..reserved_columns {
-                    vec.push(Arc::new(ChunkColumn::new()));
-                }
+        // The received columns are not taken from the pool
+        let matrix_width = (2 * (RENDER_DISTANCE + 2) + 1) as usize;
+        let reserved_columns = match world_generator {
+            Some(_) => matrix_width * matrix_width,
+            None => 0,
+        };
 
-                vec
-            })),
+        Self {
+            world_generator,
+            seed,
+            chunk_column_pool: Arc::new(RwLock::new(
+                (0..reserved_columns)
+                    .map(|_| Arc::new(ChunkColumn::new()))
+                    .collect(),
+            )),
             request_chunk_columns_tx,
             request_chunk_columns_rx,
             requested_chunk_column_tx,
             requested_chunk_column_rx,
             upload_chunks_tx,
-            upload_chunks_rx,
-            chunk_upload_priority_queue: BinaryHeap::new(),
             expand_chunks: Arc::new(RwLock::new(true)),
+            expanded_player_index: 0,
             world_generation_thread_pool: rayon::ThreadPoolBuilder::new()
                 .stack_size(4 * 1024 * 1024)
                 .num_threads(*WORLD_GENERATION_THREAD_POOL_SIZE)
@@ -309,6 +283,17 @@ impl ChunkLoading {
         Vec::new()
     }
 
+    // The smooth light of a chunk depends on the light of the 8 columns around it
+    fn is_neighbourhood_lit(chunk_manager: &ChunkManager, x: i32, z: i32) -> bool {
+        (-1..=1).all(|dx| {
+            (-1..=1).all(|dz| {
+                chunk_manager
+                    .get_column(x + dx, z + dz)
+                    .is_some_and(|column| *column.is_lit.read())
+            })
+        })
+    }
+
     fn flood_fill_chunks(
         chunk_manager: &ChunkManager,
         x: i32,
@@ -370,10 +355,9 @@ impl ChunkLoading {
 
             let mut unloaded_chunks = Vec::new();
 
+            // The columns received from a server may not be there yet
             for &(x, y, z) in ring.iter() {
-                if y >= 0 && y < 16 {
-                    let chunk = chunk_manager.get_chunk(x, y, z).unwrap();
-
+                if let Some(chunk) = chunk_manager.get_chunk(x, y, z) {
                     if criteria(chunk.as_ref()) {
                         unloaded_chunks.push((x, y, z));
                     }
@@ -394,50 +378,62 @@ impl ChunkLoading {
 
 impl<'a> System<'a> for ChunkLoading {
     type SystemData = (
-        ReadStorage<'a, Interpolator<PlayerPhysicsState>>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, ChunkLoadAnchor>,
         Read<'a, Arc<ChunkManager>>,
-        Read<'a, TexturePack>,
+        // The columns are only saved and loaded when there is one
+        Option<ReadExpect<'a, Arc<WorldStorage>>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_physics_state, chunk_manager, texture_pack) = data;
-
-        for player_physics_state in player_physics_state.join() {
-            let state = player_physics_state.get_latest_state();
-            let (chunk_x, chunk_y, chunk_z, _, _, _) = ChunkManager::get_chunk_coords(
-                state.position.x as i32,
-                state.position.y as i32,
-                state.position.z as i32,
-            );
+        let (transform, player_state, chunk_load_anchor, chunk_manager, world_storage) = data;
+
+        // Every connected player and every anchor keeps the world loaded around them
+        let player_chunk_coords: Vec<_> =
+            (&transform, player_state.maybe(), chunk_load_anchor.maybe())
+                .join()
+                .filter(|(_, player_state, chunk_load_anchor)| {
+                    player_state.is_some() || chunk_load_anchor.is_some()
+                })
+                .map(|(transform, _, _)| {
+                    let (chunk_x, chunk_y, chunk_z, _, _, _) = ChunkManager::get_chunk_coords(
+                        transform.position.x as i32,
+                        transform.position.y as i32,
+                        transform.position.z as i32,
+                    );
 
-            // Remove distant chunk columns and unload their chunks
-            {
-                if *self.expand_chunks.read() {
-                    let mut columns_to_remove = Vec::new();
-
-                    for (&(x, z), column) in chunk_manager.loaded_chunk_columns.read().iter() {
-                        for (y, chunk) in column.chunks.iter().enumerate() {
-                            let y = y as i32;
-
-                            if abs(x - chunk_x) > RENDER_DISTANCE
-                                || abs(y - chunk_y) > RENDER_DISTANCE
-                                || abs(z - chunk_z) > RENDER_DISTANCE
-                            {
-                                chunk.unload_from_gpu();
-                            }
-                        }
+                    (chunk_x, chunk_y, chunk_z)
+                })
+                .collect();
+
+        // The world is expanded around one player at a time
+        self.expanded_player_index %= player_chunk_coords.len().max(1);
+
+        if let Some(&(chunk_x, chunk_y, chunk_z)) =
+            player_chunk_coords.get(self.expanded_player_index)
+        {
+            // Remove the chunk columns that are far from every player
+            // NOTE: Their chunks were already unloaded from the GPU (see `UploadChunks`) and
+            // they are saved by the world generation task before going back to the pool
+            let mut evicted_columns = Vec::new();
 
-                        if abs(x - chunk_x) > RENDER_DISTANCE + 2
+            // The columns received from a server are evicted by `SyncWithServer`
+            if self.world_generator.is_some() && *self.expand_chunks.read() {
+                let mut columns_to_remove = Vec::new();
+
+                for &(x, z) in chunk_manager.loaded_chunk_columns.read().keys() {
+                    if player_chunk_coords.iter().all(|&(chunk_x, _, chunk_z)| {
+                        abs(x - chunk_x) > RENDER_DISTANCE + 2
                             || abs(z - chunk_z) > RENDER_DISTANCE + 2
-                        {
-                            columns_to_remove.push((x, z));
-                        }
+                    }) {
+                        columns_to_remove.push((x, z));
                     }
+                }
 
-                    for xz in columns_to_remove {
-                        if let Some(column) = chunk_manager.remove_chunk_column(&xz) {
-                            self.chunk_column_pool.write().push(column);
-                        }
+                for xz in columns_to_remove {
+                    if let Some(column) = chunk_manager.remove_chunk_column(&xz) {
+                        evicted_columns.push((xz, column));
                     }
                 }
             }
@@ -450,13 +446,7 @@ impl<'a> System<'a> for ChunkLoading {
                 for _ in self.request_chunk_columns_rx.try_iter() {
                     let column = match self.chunk_column_pool.write().pop() {
                         Some(column) => {
-                            for chunk in column.chunks.iter() {
-                                chunk.reset();
-                            }
-
-                            column.highest_blocks.write().fill(0);
-                            *column.has_foliage.write() = false;
-
+                            column.reset();
                             column
                         }
                         None => Arc::new(ChunkColumn::new()),
@@ -472,323 +462,203 @@ impl<'a> System<'a> for ChunkLoading {
                 }
             }
 
-            // Chunk uploading
-            {
-                for priority_chunk in self.upload_chunks_rx.try_iter() {
-                    self.chunk_upload_priority_queue.push(priority_chunk);
-                }
-
-                for _ in 0..CHUNK_UPLOADS_PER_FRAME {
-                    if let Some(prioritized_chunk) = self.chunk_upload_priority_queue.pop() {
-                        let (chunk_x, chunk_y, chunk_z) = *prioritized_chunk;
-
-                        if let Some(chunk) = chunk_manager.get_chunk(chunk_x, chunk_y, chunk_z) {
-                            chunk.upload_to_gpu(&texture_pack);
-                            *chunk.is_uploaded_to_gpu.write() = true;
-                        }
-                    }
-                }
-            }
-
             if *self.expand_chunks.read() {
                 *self.expand_chunks.write() = false;
+                self.expanded_player_index += 1;
 
-                let noise_fn = self.noise_fn;
+                let world_generator = self.world_generator.clone();
+                let seed = self.seed;
                 let upload_chunks_tx = self.upload_chunks_tx.clone();
                 let chunk_manager = Arc::clone(&chunk_manager);
                 let expand_chunks = Arc::clone(&self.expand_chunks);
                 let request_chunk_columns_tx = self.request_chunk_columns_tx.clone();
                 let requested_chunk_column_rx = self.requested_chunk_column_rx.clone();
+                let chunk_column_pool = Arc::clone(&self.chunk_column_pool);
+                let world_storage = world_storage.as_deref().map(Arc::clone);
 
                 self.world_generation_thread_pool.spawn(move || {
-                    let new_columns = Self::flood_fill_unloaded_columns(
-                        &chunk_manager,
-                        chunk_x,
-                        chunk_z,
-                        RENDER_DISTANCE + 2,
-                    );
+                    // Load, generate and light the columns around the player
+                    if let Some(world_generator) = world_generator {
+                        // Save the evicted columns before they can be reused
+                        for ((x, z), column) in evicted_columns {
+                            if let Some(world_storage) = &world_storage {
+                                if let Err(err) = world_storage.save_column(x, z, &column) {
+                                    error!("Failed to save chunk column ({x}, {z}): {err}");
+                                }
+                            }
 
-                    for _ in 0..new_columns.len() {
-                        request_chunk_columns_tx.send(()).unwrap();
-                    }
+                            chunk_column_pool.write().push(column);
+                        }
 
-                    let mut unloaded_columns = Vec::new();
+                        let new_columns = Self::flood_fill_unloaded_columns(
+                            &chunk_manager,
+                            chunk_x,
+                            chunk_z,
+                            RENDER_DISTANCE + 2,
+                        );
 
-                    for (x, z) in new_columns {
-                        let column = match requested_chunk_column_rx.recv() {
-                            Ok(column) => column,
-                            Err(err) => {
-                                eprintln!("{err}");
-                                return;
-                            }
-                        };
+                        for _ in 0..new_columns.len() {
+                            request_chunk_columns_tx.send(()).unwrap();
+                        }
 
-                        unloaded_columns.push((x, z, column));
-                    }
+                        let mut unloaded_columns = Vec::new();
 
-                    // Terrain generation
-                    {
-                        let chunk_manager = Arc::clone(&chunk_manager);
+                        for (x, z) in new_columns {
+                            let column = match requested_chunk_column_rx.recv() {
+                                Ok(column) => column,
+                                Err(err) => {
+                                    eprintln!("{err}");
+                                    return;
+                                }
+                            };
 
-                        rayon::scope(move |_| {
-                            let cm = Arc::clone(&chunk_manager);
-
-                            rayon::scope(move |s| {
-                                for (x, z, column) in unloaded_columns {
-                                    let column = Arc::clone(&column);
-                                    let chunk_manager = Arc::clone(&cm);
-
-                                    s.spawn(move |_s| {
-                                        // Stone
-                                        for y in (0..16).rev() {
-                                            let y = 16 * y;
-                                            for block_y in 0..16 {
-                                                for block_x in 0..16 {
-                                                    for block_z in 0..16 {
-                                                        let x = 16 * x;
-                                                        let z = 16 * z;
-                                                        let scale = 90.0;
-
-                                                        // Scale the input for the noise function
-                                                        let (xf, yf, zf) = (
-                                                            (x + block_x as i32) as f64 / scale,
-                                                            (y + block_y as i32) as f64
-                                                                / (scale / 1.0),
-                                                            (z + block_z as i32) as f64 / scale,
-                                                        );
-
-                                                        let height = (y + block_y as i32) as f64;
-                                                        let noise = noise_fn.get([xf, yf, zf])
-                                                            * 64.0
-                                                            + 64.0
-                                                            + height * 1.7;
-
-                                                        if noise < 256.0 {
-                                                            column.set_block(
-                                                                block_x,
-                                                                y as u32 + block_y,
-                                                                block_z,
-                                                                BlockID::Stone,
-                                                            );
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
+                            unloaded_columns.push((x, z, column));
+                        }
 
-                                        // Grass and dirt
-                                        for block_x in 0..16 {
-                                            for block_z in 0..16 {
-                                                let y = column.highest_blocks.read()
-                                                    [16 * block_z + block_x]
-                                                    as i32;
-                                                let chunk_y = (y / 16) as i32;
-                                                let block_y = (y % 16) as usize;
-
-                                                column.get_chunk(chunk_y).set_block(
-                                                    block_x as u32,
-                                                    block_y as u32,
-                                                    block_z as u32,
-                                                    BlockID::GrassBlock,
-                                                );
-
-                                                for y in (y - 3)..y {
-                                                    let chunk_y = (y / 16) as i32;
-                                                    let block_y = (y % 16) as usize;
-                                                    let chunk = column.get_chunk(chunk_y);
-
-                                                    if chunk
-                                                        .get_block(
-                                                            block_x as u32,
-                                                            block_y as u32,
-                                                            block_z as u32,
-                                                        )
-                                                        .is_air()
-                                                    {
-                                                        continue;
-                                                    }
-
-                                                    chunk.set_block(
-                                                        block_x as u32,
-                                                        block_y as u32,
-                                                        block_z as u32,
-                                                        BlockID::Dirt,
-                                                    );
-                                                }
-                                            }
-                                        }
+                        // Terrain generation
+                        {
+                            let chunk_manager = Arc::clone(&chunk_manager);
 
-                                        // Bedrock
-                                        let chunk = column.get_chunk(0);
-
-                                        for block_x in 0..16 {
-                                            for block_z in 0..16 {
-                                                chunk.set_block(
-                                                    block_x,
-                                                    0,
-                                                    block_z,
-                                                    BlockID::Bedrock,
-                                                );
-                                                chunk.set_block(
-                                                    block_x,
-                                                    1,
-                                                    block_z,
-                                                    BlockID::Bedrock,
-                                                );
-                                                chunk.set_block(
-                                                    block_x,
-                                                    2,
-                                                    block_z,
-                                                    BlockID::Bedrock,
-                                                );
+                            rayon::scope(move |_| {
+                                let cm = Arc::clone(&chunk_manager);
+                                let generator = Arc::clone(&world_generator);
+
+                                rayon::scope(move |s| {
+                                    for (x, z, column) in unloaded_columns {
+                                        let column = Arc::clone(&column);
+                                        let chunk_manager = Arc::clone(&cm);
+                                        let world_storage = world_storage.clone();
+                                        let world_generator = Arc::clone(&generator);
+
+                                        s.spawn(move |_s| {
+                                            // Saved columns are loaded instead of being generated again
+                                            match world_storage
+                                                .map(|world_storage| world_storage.load_column(x, z, &column))
+                                            {
+                                                Some(Ok(true)) => {
+                                                    chunk_manager.add_chunk_column((x, z), column);
+                                                    return;
+                                                }
+                                                Some(Ok(false)) | None => {}
+                                                Some(Err(err)) => {
+                                                    error!(
+                                                        "Failed to load chunk column ({x}, {z}): {err}"
+                                                    )
+                                                }
                                             }
-                                        }
 
-                                        chunk_manager.add_chunk_column((x, z), column);
-                                    });
-                                }
-                            });
+                                            world_generator.generate_column(&column, x, z, seed);
 
-                            let chunk_manager = Arc::clone(&chunk_manager);
+                                            chunk_manager.add_chunk_column((x, z), column);
+                                        });
+                                    }
+                                });
 
-                            rayon::scope(|_| {
-                                let unfoliated_columns = Self::flood_fill_unfoliated_columns(
-                                    &chunk_manager,
-                                    chunk_x,
-                                    chunk_z,
-                                    RENDER_DISTANCE,
-                                );
-
-                                for (chunk_x, chunk_z) in unfoliated_columns {
-                                    let column =
-                                        chunk_manager.get_column(chunk_x, chunk_z).unwrap();
-                                    *column.has_foliage.write() = true;
-
-                                    // Trees
-                                    for (x, z) in compute_tree_placement_in_chunk(
-                                        &noise_fn,
-                                        (chunk_x * 16) as f64,
-                                        (chunk_z * 16) as f64,
-                                    ) {
-                                        let (x, z) = (x as usize, z as usize);
-                                        let y = column.highest_blocks.read()[16 * z + x] as i32;
-
-                                        let x = chunk_x * 16 + x as i32;
-                                        let z = chunk_z * 16 + z as i32;
-                                        let h = 5;
-
-                                        for i in y + 1..y + 1 + h {
-                                            chunk_manager.set_block(x, i, z, BlockID::OakLog);
-                                        }
+                                let chunk_manager = Arc::clone(&chunk_manager);
 
-                                        for yy in y + h - 2..=y + h - 1 {
-                                            for xx in x - 2..=x + 2 {
-                                                for zz in z - 2..=z + 2 {
-                                                    if xx != x || zz != z {
-                                                        chunk_manager.set_block(
-                                                            xx,
-                                                            yy,
-                                                            zz,
-                                                            BlockID::OakLeaves,
-                                                        );
-                                                    }
-                                                }
-                                            }
-                                        }
+                                rayon::scope(|_| {
+                                    // One column further than the chunks that are meshed, so that
+                                    // their neighbours can be lit before them
+                                    let unfoliated_columns = Self::flood_fill_unfoliated_columns(
+                                        &chunk_manager,
+                                        chunk_x,
+                                        chunk_z,
+                                        RENDER_DISTANCE + 1,
+                                    );
 
-                                        for xx in x - 1..=x + 1 {
-                                            for zz in z - 1..=z + 1 {
-                                                if xx != x || zz != z {
-                                                    chunk_manager.set_block(
-                                                        xx,
-                                                        y + h,
-                                                        zz,
-                                                        BlockID::OakLeaves,
-                                                    );
-                                                }
-                                            }
-                                        }
+                                    for (chunk_x, chunk_z) in unfoliated_columns {
+                                        let column =
+                                            chunk_manager.get_column(chunk_x, chunk_z).unwrap();
+                                        *column.has_foliage.write() = true;
 
-                                        chunk_manager.set_block(
-                                            x,
-                                            y + h + 1,
-                                            z,
-                                            BlockID::OakLeaves,
-                                        );
-                                        chunk_manager.set_block(
-                                            x + 1,
-                                            y + h + 1,
-                                            z,
-                                            BlockID::OakLeaves,
-                                        );
-                                        chunk_manager.set_block(
-                                            x - 1,
-                                            y + h + 1,
-                                            z,
-                                            BlockID::OakLeaves,
-                                        );
-                                        chunk_manager.set_block(
-                                            x,
-                                            y + h + 1,
-                                            z + 1,
-                                            BlockID::OakLeaves,
-                                        );
-                                        chunk_manager.set_block(
-                                            x,
-                                            y + h + 1,
-                                            z - 1,
-                                            BlockID::OakLeaves,
+                                        world_generator.decorate_column(
+                                            &chunk_manager,
+                                            chunk_x,
+                                            chunk_z,
+                                            seed,
                                         );
                                     }
-                                }
+                                });
                             });
-                        });
+                        }
+
+                        // Lighting, once the trees of the columns are placed
+                        {
+                            let unlit_columns: Vec<_> = chunk_manager
+                                .loaded_chunk_columns
+                                .read()
+                                .iter()
+                                .filter(|(&(x, z), column)| {
+                                    abs(x - chunk_x) <= RENDER_DISTANCE + 1
+                                        && abs(z - chunk_z) <= RENDER_DISTANCE + 1
+                                        && *column.has_foliage.read()
+                                        && !*column.is_lit.read()
+                                })
+                                .map(|(&xz, _)| xz)
+                                .collect();
+
+                            // NOTE: Columns are lit one at a time since their light flows into each other
+                            for (x, z) in unlit_columns {
+                                light_column(&chunk_manager, x, z);
+                            }
+                        }
                     }
 
-                    // Chunk face culling & AO
-                    let chunk_manager = Arc::clone(&chunk_manager);
+                    // Chunk face culling, AO & smooth lighting, only needed when the chunks are rendered
+                    if let Some(upload_chunks_tx) = upload_chunks_tx {
+                        let chunk_manager = Arc::clone(&chunk_manager);
 
-                    rayon::scope(move |s| {
-                        let new_chunks = Self::flood_fill_chunks(
-                            &chunk_manager,
-                            chunk_x,
-                            chunk_y,
-                            chunk_z,
-                            RENDER_DISTANCE,
-                        );
+                        rayon::scope(move |s| {
+                            let new_chunks = Self::flood_fill_chunks(
+                                &chunk_manager,
+                                chunk_x,
+                                chunk_y,
+                                chunk_z,
+                                RENDER_DISTANCE,
+                            );
 
-                        for (chunk_x, chunk_y, chunk_z) in new_chunks {
-                            let chunk_manager = Arc::clone(&chunk_manager);
-                            let send_chunks = upload_chunks_tx.clone();
+                            for (chunk_x, chunk_y, chunk_z) in new_chunks {
+                                let chunk_manager = Arc::clone(&chunk_manager);
+                                let send_chunks = upload_chunks_tx.clone();
+
+                                s.spawn(move |_| {
+                                    if let Some(chunk) =
+                                        chunk_manager.get_chunk(chunk_x, chunk_y, chunk_z)
+                                    {
+                                        if chunk.is_empty() {
+                                            *chunk.is_generated.write() = true;
+                                            *chunk.is_uploaded_to_gpu.write() = true;
+                                            return;
+                                        }
 
-                            s.spawn(move |_| {
-                                if let Some(chunk) =
-                                    chunk_manager.get_chunk(chunk_x, chunk_y, chunk_z)
-                                {
-                                    if chunk.is_empty() {
-                                        *chunk.is_generated.write() = true;
-                                        *chunk.is_uploaded_to_gpu.write() = true;
-                                        return;
-                                    }
+                                        // Meshed later, once the light around it is known
+                                        if !Self::is_neighbourhood_lit(
+                                            &chunk_manager,
+                                            chunk_x,
+                                            chunk_z,
+                                        ) {
+                                            return;
+                                        }
 
-                                    chunk_manager.update_blocks(
-                                        chunk_x,
-                                        chunk_y,
-                                        chunk_z,
-                                        BlockIterator::new(),
-                                    );
-                                    *chunk.is_generated.write() = true;
+                                        chunk_manager.update_blocks(
+                                            chunk_x,
+                                            chunk_y,
+                                            chunk_z,
+                                            BlockIterator::new(),
+                                        );
+                                        *chunk.is_generated.write() = true;
 
-                                    if let Err(err) = send_chunks.send(PrioritizedItem {
-                                        item: (chunk_x, chunk_y, chunk_z),
-                                        priority: 0,
-                                    }) {
-                                        error!("{err}");
+                                        if let Err(err) = send_chunks.send(PrioritizedItem {
+                                            item: (chunk_x, chunk_y, chunk_z),
+                                            priority: 0,
+                                        }) {
+                                            error!("{err}");
+                                        }
                                     }
-                                }
-                            });
-                        }
-                    });
+                                });
+                            }
+                        });
+                    }
 
                     *expand_chunks.write() = true;
                 });
@@ -796,6 +666,12 @@ impl<'a> System<'a> for ChunkLoading {
         }
 
         // Dirty chunks (changelist)
+        // NOTE: Only uploaded chunks have their changes tracked, so there is nothing to do on the server
+        let upload_chunks_tx = match &self.upload_chunks_tx {
+            Some(upload_chunks_tx) => upload_chunks_tx,
+            None => return,
+        };
+
         let mut changelist_per_chunk: HashMap<(i32, i32, i32), Vec<(i32, u32, u32, u32)>> =
             HashMap::new();
 
@@ -821,8 +697,12 @@ impl<'a> System<'a> for ChunkLoading {
 
         chunk_manager.block_changelist.write().clear();
 
-        for ((chunk_x, chunk_y, chunk_z), dirty_blocks) in changelist_per_chunk {
-            let send_chunks = self.upload_chunks_tx.clone();
+        for ((chunk_x, chunk_y, chunk_z), mut dirty_blocks) in changelist_per_chunk {
+            // Neighbouring changes (e.g. of the light) overlap
+            dirty_blocks.sort_unstable();
+            dirty_blocks.dedup();
+
+            let send_chunks = upload_chunks_tx.clone();
             let chunk_manager = Arc::clone(&chunk_manager);
             let highest_priority = dirty_blocks.iter().map(|block| block.0).max().unwrap_or(0);
             let thread_pool = if highest_priority == 0 {
@@ -853,3 +733,71 @@ impl<'a> System<'a> for ChunkLoading {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generation::superflat::SuperflatGenerator;
+    use nalgebra_glm::vec3;
+    use specs::{Builder, RunNow, World, WorldExt};
+
+    fn run_until(
+        world: &World,
+        chunk_loading: &mut ChunkLoading,
+        condition: impl Fn(&ChunkManager) -> bool,
+    ) {
+        let start = Instant::now();
+
+        while !condition(&world.read_resource::<Arc<ChunkManager>>()) {
+            assert!(
+                start.elapsed() < Duration::from_secs(60),
+                "Timed out loading the chunk columns"
+            );
+
+            chunk_loading.run_now(world);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn evicted_columns_are_reused_without_a_world_storage() {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<PlayerState>();
+        world.register::<ChunkLoadAnchor>();
+        world.insert(Arc::new(ChunkManager::new()));
+
+        let player = world
+            .create_entity()
+            .with(Transform::new(vec3(8.0, 10.0, 8.0)))
+            .with(PlayerState::new())
+            .build();
+
+        let mut chunk_loading = ChunkLoading::new(None, Arc::new(SuperflatGenerator::default()), 0);
+        run_until(&world, &mut chunk_loading, |chunk_manager| {
+            chunk_manager.get_column(0, 0).is_some()
+        });
+        let column = world
+            .read_resource::<Arc<ChunkManager>>()
+            .get_column(0, 0)
+            .unwrap();
+
+        // Far enough for every column to be evicted
+        let far_away = 16.0 * (2 * RENDER_DISTANCE + 8) as f32;
+        world
+            .write_storage::<Transform>()
+            .get_mut(player)
+            .unwrap()
+            .position = vec3(far_away, 10.0, 8.0);
+
+        // The column goes back to the pool and is used for one of the new columns
+        run_until(&world, &mut chunk_loading, |chunk_manager| {
+            chunk_manager.get_column(0, 0).is_none()
+                && chunk_manager
+                    .loaded_chunk_columns
+                    .read()
+                    .values()
+                    .any(|other_column| Arc::ptr_eq(other_column, &column))
+        });
+    }
+}
@@ -0,0 +1,117 @@
+use crate::chunk_arena::ChunkArena;
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{CHUNK_UPLOADS_PER_FRAME, RENDER_DISTANCE};
+use crate::ecs::systems::chunk_loading::{ChunkUpload, PrioritizedItem};
+use crate::meshing::MeshingMode;
+use crate::physics::Transform;
+use crate::player::PlayerState;
+use crate::types::TexturePack;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use num_traits::abs;
+use specs::{Join, Read, ReadStorage, System, WriteExpect};
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+// Uploads the chunks meshed by `ChunkLoading` to the GPU and frees the ones out of sight
+pub struct UploadChunks {
+    upload_chunks_rx: Receiver<ChunkUpload>,
+    chunk_upload_priority_queue: BinaryHeap<ChunkUpload>,
+    meshing_mode: MeshingMode,
+}
+
+impl UploadChunks {
+    // Returns the system and the sender to give to `ChunkLoading`
+    pub fn new() -> (Self, Sender<ChunkUpload>) {
+        let (upload_chunks_tx, upload_chunks_rx) = unbounded();
+
+        (
+            Self {
+                upload_chunks_rx,
+                chunk_upload_priority_queue: BinaryHeap::new(),
+                meshing_mode: MeshingMode::default(),
+            },
+            upload_chunks_tx,
+        )
+    }
+}
+
+impl<'a> System<'a> for UploadChunks {
+    type SystemData = (
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, PlayerState>,
+        Read<'a, Arc<ChunkManager>>,
+        Read<'a, TexturePack>,
+        Read<'a, MeshingMode>,
+        WriteExpect<'a, ChunkArena>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (transform, player_state, chunk_manager, texture_pack, meshing_mode, mut chunk_arena) =
+            data;
+
+        // Mesh everything that is on the GPU again when the meshing mode changes
+        if *meshing_mode != self.meshing_mode {
+            self.meshing_mode = *meshing_mode;
+
+            for (&(x, z), column) in chunk_manager.loaded_chunk_columns.read().iter() {
+                for (y, chunk) in column.chunks.iter().enumerate() {
+                    if *chunk.is_uploaded_to_gpu.read() && !chunk.is_empty() {
+                        self.chunk_upload_priority_queue.push(PrioritizedItem {
+                            item: (x, y as i32, z),
+                            priority: 0,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Unload the chunks that are too far away
+        // NOTE: This runs before `ChunkLoading` so that columns are unloaded before being evicted
+        for (transform, _) in (&transform, &player_state).join() {
+            let (chunk_x, chunk_y, chunk_z, _, _, _) = ChunkManager::get_chunk_coords(
+                transform.position.x as i32,
+                transform.position.y as i32,
+                transform.position.z as i32,
+            );
+
+            for (&(x, z), column) in chunk_manager.loaded_chunk_columns.read().iter() {
+                for (y, chunk) in column.chunks.iter().enumerate() {
+                    let y = y as i32;
+
+                    if *chunk.is_uploaded_to_gpu.read()
+                        && (abs(x - chunk_x) > RENDER_DISTANCE
+                            || abs(y - chunk_y) > RENDER_DISTANCE
+                            || abs(z - chunk_z) > RENDER_DISTANCE)
+                    {
+                        chunk.unload_from_gpu(&mut chunk_arena, (x, y, z));
+                    }
+                }
+            }
+        }
+
+        // Free the meshes of the columns evicted by `ChunkLoading` since the last frame
+        chunk_arena.retain(|&(x, _, z)| chunk_manager.get_column(x, z).is_some());
+        chunk_arena.shrink_if_unused();
+
+        // Chunk uploading
+        for priority_chunk in self.upload_chunks_rx.try_iter() {
+            self.chunk_upload_priority_queue.push(priority_chunk);
+        }
+
+        for _ in 0..CHUNK_UPLOADS_PER_FRAME {
+            if let Some(prioritized_chunk) = self.chunk_upload_priority_queue.pop() {
+                let (chunk_x, chunk_y, chunk_z) = *prioritized_chunk;
+
+                if let Some(chunk) = chunk_manager.get_chunk(chunk_x, chunk_y, chunk_z) {
+                    chunk.upload_to_gpu(
+                        &mut chunk_arena,
+                        (chunk_x, chunk_y, chunk_z),
+                        &texture_pack,
+                        self.meshing_mode,
+                    );
+                    *chunk.is_uploaded_to_gpu.write() = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use crate::constants::{DROPPED_ITEM_MERGE_DISTANCE, PHYSICS_TICKRATE};
+use crate::ecs::components::MainHandItemChanged;
+use crate::game_mode::GameMode;
+use crate::inventory::dropped_item::{dropped_item_physics_body, pickup_aabb, DroppedItem};
+use crate::inventory::Inventory;
+use crate::physics::{Collider, Gravity, Interpolator, Transform};
+use crate::player::PlayerState;
+use crate::timer::Timer;
+use nalgebra_glm::{distance, Vec3};
+use specs::{
+    Builder, Entities, Entity, Join, LazyUpdate, LendJoin, Read, ReadStorage, System, WriteStorage,
+};
+use std::collections::HashSet;
+
+// Creates a dropped item entity at the next `World::maintain`
+pub fn spawn_dropped_item(
+    entities: &Entities,
+    lazy_update: &LazyUpdate,
+    position: Vec3,
+    velocity: Vec3,
+    dropped_item: DroppedItem,
+) -> Entity {
+    let mut body = dropped_item_physics_body(position);
+    body.velocity = velocity;
+
+    lazy_update
+        .create_entity(entities)
+        .with(Transform::new(position))
+        .with(Interpolator::new(1.0 / PHYSICS_TICKRATE, body))
+        .with(Gravity::default())
+        .with(Collider)
+        .with(dropped_item)
+        .build()
+}
+
+// Despawns the old dropped items, merges the ones close to each other and gives them to the
+// players nearby. Their movement is done by `UpdatePhysics`.
+pub struct UpdateDroppedItems;
+
+impl<'a> System<'a> for UpdateDroppedItems {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Timer>,
+        WriteStorage<'a, DroppedItem>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, GameMode>,
+        WriteStorage<'a, Inventory>,
+        WriteStorage<'a, MainHandItemChanged>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            global_timer,
+            mut dropped_item,
+            transform,
+            player_state,
+            game_mode,
+            mut inventory,
+            mut main_hand_item_changed,
+        ) = data;
+
+        let time = global_timer.time();
+        let mut despawned = HashSet::new();
+
+        // Despawn the old items and the ones that fell below the world
+        for (entity, dropped_item, transform) in (&entities, &dropped_item, &transform).join() {
+            if dropped_item.has_expired(time) || transform.position.y < 0.0 {
+                despawned.insert(entity);
+            }
+        }
+
+        // The older items absorb the younger ones
+        let mut dropped_items: Vec<_> = (&entities, &dropped_item, &transform)
+            .join()
+            .filter(|(entity, _, _)| !despawned.contains(entity))
+            .map(|(entity, dropped_item, transform)| (entity, *dropped_item, transform.position))
+            .collect();
+        dropped_items
+            .sort_by_key(|(entity, dropped_item, _)| (dropped_item.spawned_at, entity.id()));
+
+        for j in 1..dropped_items.len() {
+            let (older, younger) = dropped_items.split_at_mut(j);
+            let (_, younger_item, younger_position) = &mut younger[0];
+
+            for (_, older_item, older_position) in older.iter_mut() {
+                if younger_item.item_stack.amount > 0
+                    && older_item.item_stack.amount > 0
+                    && distance(older_position, younger_position) <= DROPPED_ITEM_MERGE_DISTANCE
+                {
+                    older_item.merge(younger_item);
+                }
+            }
+        }
+
+        for (player, player_transform, _, game_mode, inventory) in (
+            &entities,
+            &transform,
+            &player_state,
+            game_mode.maybe(),
+            &mut inventory,
+        )
+            .join()
+        {
+            // The spectators go through the items
+            if game_mode.is_some_and(|game_mode| game_mode.is_spectating()) {
+                continue;
+            }
+
+            let pickup_aabb = pickup_aabb(&player_transform.position);
+            let selected_item = inventory.get_selected_item();
+
+            for (_, dropped_item, position) in dropped_items.iter_mut() {
+                if dropped_item.item_stack.amount == 0
+                    || !dropped_item.can_be_picked_up(time)
+                    || !pickup_aabb.intersects(&dropped_item_physics_body(*position).aabb)
+                {
+                    continue;
+                }
+
+                dropped_item.item_stack.amount = inventory
+                    .add_item(dropped_item.item_stack)
+                    .map_or(0, |overflow| overflow.amount);
+            }
+
+            if inventory.get_selected_item() != selected_item {
+                if let Err(err) = main_hand_item_changed.insert(player, MainHandItemChanged) {
+                    error!("{err}");
+                }
+            }
+        }
+
+        for (entity, new_dropped_item, _) in dropped_items {
+            if new_dropped_item.item_stack.amount == 0 {
+                despawned.insert(entity);
+            } else if let Some(dropped_item) = dropped_item.get_mut(entity) {
+                *dropped_item = new_dropped_item;
+            }
+        }
+
+        for entity in despawned {
+            if let Err(err) = entities.delete(entity) {
+                error!("{err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::BlockID;
+    use crate::ecs::systems::UpdatePhysics;
+    use crate::inventory::item::ItemStack;
+    use crate::physics::PhysicsBody;
+    use crate::test_support::create_world_with_floor;
+    use nalgebra_glm::vec3;
+    use specs::{RunNow, World, WorldExt};
+    use std::time::Duration;
+
+    // The players and the items need a few more components than the physics
+    fn create_world_with_items(floor_height: u32) -> World {
+        let mut world = create_world_with_floor(floor_height);
+        world.register::<Inventory>();
+        world.register::<MainHandItemChanged>();
+        world.register::<DroppedItem>();
+
+        world
+    }
+
+    fn drop_item(world: &mut World, position: Vec3, item_stack: ItemStack, pickup_delay: u64) {
+        let time = world.read_resource::<Timer>().time();
+        spawn_dropped_item(
+            &world.entities(),
+            &world.read_resource::<LazyUpdate>(),
+            position,
+            vec3(0.0, 0.0, 0.0),
+            DroppedItem::new(item_stack, time, Duration::from_secs(pickup_delay)),
+        );
+        world.maintain();
+    }
+
+    // Runs the physics and the dropped items for `seconds`, in steps of 0.25s (the longest frame)
+    fn simulate(world: &mut World, seconds: u32) {
+        for _ in 0..4 * seconds {
+            {
+                let mut timer = world.write_resource::<Timer>();
+                *timer = Timer::default();
+                let time = timer.time();
+                let mut physics_bodies = world.write_storage::<Interpolator<PhysicsBody>>();
+                let mut dropped_items = world.write_storage::<DroppedItem>();
+
+                for physics_body in (&mut physics_bodies).join() {
+                    physics_body.current_time = time - Duration::from_millis(250);
+                }
+
+                // Age the items by the same amount
+                for dropped_item in (&mut dropped_items).join() {
+                    dropped_item.spawned_at -= Duration::from_millis(250);
+                }
+            }
+
+            UpdatePhysics.run_now(world);
+            UpdateDroppedItems.run_now(world);
+            world.maintain();
+        }
+    }
+
+    fn dropped_items(world: &World) -> Vec<(ItemStack, Vec3)> {
+        (
+            &world.read_storage::<DroppedItem>(),
+            &world.read_storage::<Transform>(),
+        )
+            .join()
+            .map(|(dropped_item, transform)| (dropped_item.item_stack, transform.position))
+            .collect()
+    }
+
+    fn create_player(world: &mut World, position: Vec3) -> Entity {
+        let mut inventory = Inventory::new();
+        inventory.slots[0] = Some(ItemStack::new(BlockID::DIRT, 1));
+
+        world
+            .create_entity()
+            .with(PlayerState::new())
+            .with(Transform::new(position))
+            .with(inventory)
+            .build()
+    }
+
+    #[test]
+    fn dropped_items_fall_and_merge() {
+        let mut world = create_world_with_items(4);
+        drop_item(
+            &mut world,
+            vec3(4.0, 8.0, 4.0),
+            ItemStack::new(BlockID::DIRT, 2),
+            0,
+        );
+        drop_item(
+            &mut world,
+            vec3(4.2, 7.0, 4.1),
+            ItemStack::new(BlockID::DIRT, 3),
+            0,
+        );
+        drop_item(
+            &mut world,
+            vec3(4.1, 7.5, 4.0),
+            ItemStack::new(BlockID::STONE, 1),
+            0,
+        );
+        drop_item(
+            &mut world,
+            vec3(12.0, 6.0, 12.0),
+            ItemStack::new(BlockID::DIRT, 1),
+            0,
+        );
+
+        simulate(&mut world, 2);
+
+        let mut dropped_items = dropped_items(&world);
+        dropped_items.sort_by_key(|(item_stack, position)| (item_stack.amount, position.x as i32));
+
+        assert_eq!(dropped_items.len(), 3);
+        assert_eq!(dropped_items[0].0, ItemStack::new(BlockID::STONE, 1));
+        assert_eq!(dropped_items[1].0, ItemStack::new(BlockID::DIRT, 1));
+        assert_eq!(dropped_items[2].0, ItemStack::new(BlockID::DIRT, 5));
+
+        for (_, position) in dropped_items {
+            assert!((position.y - 4.0).abs() < 0.01, "{position:?}");
+        }
+    }
+
+    #[test]
+    fn players_pick_up_the_items_nearby_after_their_delay() {
+        let mut world = create_world_with_items(4);
+        let player = create_player(&mut world, vec3(5.0, 4.0, 5.0));
+        drop_item(
+            &mut world,
+            vec3(4.0, 4.0, 4.0),
+            ItemStack::new(BlockID::DIRT, 70),
+            2,
+        );
+        drop_item(
+            &mut world,
+            vec3(12.0, 4.0, 12.0),
+            ItemStack::new(BlockID::SAND, 1),
+            0,
+        );
+
+        simulate(&mut world, 1);
+
+        assert_eq!(dropped_items(&world).len(), 2);
+
+        simulate(&mut world, 2);
+
+        // The far item stays
+        let dropped_items = dropped_items(&world);
+        assert_eq!(dropped_items.len(), 1);
+        assert_eq!(dropped_items[0].0, ItemStack::new(BlockID::SAND, 1));
+
+        // The dirt of the hotbar is filled first, the rest goes in an empty slot
+        let inventories = world.read_storage::<Inventory>();
+        let inventory = inventories.get(player).unwrap();
+        assert_eq!(inventory.slots[0], Some(ItemStack::new(BlockID::DIRT, 64)));
+        assert_eq!(inventory.slots[9], Some(ItemStack::new(BlockID::DIRT, 7)));
+    }
+}
@@ -1,4 +1,5 @@
-use specs::System;
+use crate::chunk_manager::ChunkRenderStats;
+use specs::{Read, System};
 use std::time::Instant;
 
 const MAXIMUM_OPTIMAL_FRAMETIME: f32 = 1.0 / 60.0;
@@ -10,9 +11,9 @@ pub struct FpsCounter {
 }
 
 impl<'a> System<'a> for FpsCounter {
-    type SystemData = ();
+    type SystemData = Read<'a, ChunkRenderStats>;
 
-    fn run(&mut self, _data: Self::SystemData) {
+    fn run(&mut self, render_stats: Self::SystemData) {
         let current_time = Instant::now();
         self.nb_frames += 1;
 
@@ -27,7 +28,10 @@ impl<'a> System<'a> for FpsCounter {
         }
 
         if current_time.duration_since(self.last_second).as_secs_f32() >= 1.0 {
-            info!("{} FPS", self.nb_frames);
+            info!(
+                "{} FPS, {} chunks drawn, {} culled, {} occluded",
+                self.nb_frames, render_stats.drawn, render_stats.culled, render_stats.occluded
+            );
 
             self.nb_frames = 0;
             self.last_second = current_time;
@@ -2,8 +2,8 @@ use crate::constants::{FAR_PLANE, NEAR_PLANE, WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::ecs::components::MainHandItemChanged;
 use crate::inventory::Inventory;
 use crate::main_hand::MainHand;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
+use crate::physics::{Interpolator, Transform};
+use crate::player::PlayerState;
 use crate::timer::Timer;
 use crate::types::{Shaders, TexturePack};
 use crate::util::Forward;
@@ -52,24 +52,18 @@ impl<'a> System<'a> for RenderMainHand {
     type SystemData = (
         WriteStorage<'a, MainHand>,
         ReadStorage<'a, PlayerState>,
-        ReadStorage<'a, Interpolator<PlayerPhysicsState>>,
+        ReadStorage<'a, Transform>,
         Read<'a, TexturePack>,
         Read<'a, Timer>,
         Write<'a, Shaders>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (
-            mut main_hand,
-            player_state,
-            player_physics_state,
-            texture_pack,
-            global_timer,
-            mut shaders,
-        ) = data;
-
-        for (player_state, player_physics_state, main_hand) in
-            (&player_state, &player_physics_state, &mut main_hand).join()
+        let (mut main_hand, player_state, transform, texture_pack, global_timer, mut shaders) =
+            data;
+
+        for (player_state, transform, main_hand) in
+            (&player_state, &transform, &mut main_hand).join()
         {
             if main_hand.begin_switch {
                 main_hand.begin_switch = false;
@@ -101,8 +95,7 @@ impl<'a> System<'a> for RenderMainHand {
             }
 
             let view_matrix = {
-                let player_physics_state = player_physics_state.get_interpolated_state();
-                let camera_position = player_physics_state.position
+                let camera_position = transform.position
                     + vec3(
                         0.0,
                         *player_state.camera_height.get_interpolated_state(),
@@ -119,7 +112,7 @@ impl<'a> System<'a> for RenderMainHand {
 
             main_hand.update_if_dirty(&texture_pack);
 
-            let player_pos = player_physics_state.get_interpolated_state().position;
+            let player_pos = transform.position;
             let camera_height = *player_state.camera_height.get_interpolated_state();
             let camera_pos = player_pos + vec3(0.0, camera_height, 0.0);
 
@@ -1,9 +1,16 @@
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::input::InputCache;
+use crate::inventory::screen::InventoryScreen;
+use crate::meshing::MeshingMode;
+use crate::storage::WorldStorage;
 use crate::timer::Timer;
-use glfw::{Action, Context, Glfw, Key, Window, WindowEvent};
-use specs::{System, Write};
+use glfw::{Action, Context, CursorMode, Glfw, Key, Window, WindowEvent};
+use nalgebra_glm::vec2;
+use specs::{Join, Read, ReadExpect, ReadStorage, System, Write};
 use std::process::exit;
 use std::sync::mpsc::Receiver;
+use std::sync::Arc;
 
 pub struct ReadWindowEvents {
     pub glfw: Glfw,
@@ -12,12 +19,38 @@ pub struct ReadWindowEvents {
 }
 
 impl<'a> System<'a> for ReadWindowEvents {
-    type SystemData = (Write<'a, InputCache>, Write<'a, Timer>);
+    type SystemData = (
+        Write<'a, InputCache>,
+        Write<'a, Timer>,
+        Write<'a, MeshingMode>,
+        Read<'a, Arc<ChunkManager>>,
+        // Missing when the world is received from a server, which saves it
+        Option<ReadExpect<'a, Arc<WorldStorage>>>,
+        ReadStorage<'a, InventoryScreen>,
+    );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut input_cache, mut global_timer) = data;
+        let (
+            mut input_cache,
+            mut global_timer,
+            mut meshing_mode,
+            chunk_manager,
+            world_storage,
+            inventory_screen,
+        ) = data;
+
+        // Escape closes the open screen instead of the game
+        let is_screen_open = inventory_screen
+            .join()
+            .any(|inventory_screen| inventory_screen.is_open);
 
         if self.window.should_close() {
+            if let Some(Err(err)) =
+                world_storage.map(|world_storage| world_storage.save_loaded_columns(&chunk_manager))
+            {
+                error!("Failed to save the world: {err}");
+            }
+
             exit(0);
         }
 
@@ -30,7 +63,7 @@ impl<'a> System<'a> for ReadWindowEvents {
             input_cache.handle_event(&event);
 
             match event {
-                WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
+                WindowEvent::Key(Key::Escape, _, Action::Press, _) if !is_screen_open => {
                     self.window.set_should_close(true);
                 }
                 WindowEvent::Key(Key::P, _, Action::Press, _) => {
@@ -40,8 +73,32 @@ impl<'a> System<'a> for ReadWindowEvents {
                         global_timer.pause();
                     }
                 }
+                WindowEvent::Key(Key::G, _, Action::Press, _) => {
+                    *meshing_mode = meshing_mode.toggled();
+                    info!("Meshing mode: {:?}", *meshing_mode);
+                }
                 _ => {}
             }
         }
+
+        // The mouse moves freely over the screens and controls the camera otherwise. The cursor
+        // position jumps when the mode changes, so it must not be seen as a movement.
+        let cursor_mode = if is_screen_open {
+            CursorMode::Normal
+        } else {
+            CursorMode::Disabled
+        };
+
+        if self.window.get_cursor_mode() != cursor_mode {
+            self.window.set_cursor_mode(cursor_mode);
+
+            if cursor_mode == CursorMode::Normal {
+                self.window
+                    .set_cursor_pos(WINDOW_WIDTH as f64 / 2.0, WINDOW_HEIGHT as f64 / 2.0);
+            }
+
+            let (x, y) = self.window.get_cursor_pos();
+            input_cache.last_cursor_pos = vec2(x, y);
+        }
     }
 }
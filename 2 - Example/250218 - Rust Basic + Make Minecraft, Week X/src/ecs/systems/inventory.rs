@@ -1,13 +1,153 @@
 use crate::chunk_manager::ChunkManager;
+use crate::constants::{
+    DROPPED_ITEM_THROW_SPEED, PLAYER_EYES_HEIGHT, THROWN_ITEM_PICKUP_DELAY, WINDOW_HEIGHT,
+};
 use crate::ecs::components::MainHandItemChanged;
+use crate::ecs::systems::dropped_items::spawn_dropped_item;
 use crate::input::InputCache;
+use crate::inventory::dropped_item::DroppedItem;
 use crate::inventory::item::ItemStack;
+use crate::inventory::screen::{InventoryScreen, SlotClick};
 use crate::inventory::Inventory;
+use crate::physics::Transform;
 use crate::player::PlayerState;
+use crate::timer::Timer;
+use crate::util::Forward;
 use glfw::{Action, Key, MouseButton, WindowEvent};
-use specs::{Entities, Join, Read, ReadStorage, System, WriteStorage};
+use nalgebra_glm::vec3;
+use specs::{Entities, Join, LazyUpdate, LendJoin, Read, ReadStorage, System, WriteStorage};
 use std::sync::Arc;
 
+// Throws a stack where the player is looking
+fn throw_item(
+    item_stack: ItemStack,
+    player_state: &PlayerState,
+    transform: &Transform,
+    entities: &Entities,
+    lazy_update: &LazyUpdate,
+    global_timer: &Timer,
+) {
+    let position = transform.position + vec3(0.0, PLAYER_EYES_HEIGHT - 0.3, 0.0);
+    let velocity = player_state.rotation.forward().normalize() * DROPPED_ITEM_THROW_SPEED;
+
+    spawn_dropped_item(
+        entities,
+        lazy_update,
+        position,
+        velocity,
+        DroppedItem::new(item_stack, global_timer.time(), *THROWN_ITEM_PICKUP_DELAY),
+    );
+}
+
+// Opens and closes the inventory screen and moves the stacks when its slots are clicked
+pub struct HandleInventoryScreenInput;
+
+impl<'a> System<'a> for HandleInventoryScreenInput {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
+        Read<'a, Timer>,
+        Read<'a, InputCache>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Transform>,
+        WriteStorage<'a, InventoryScreen>,
+        WriteStorage<'a, Inventory>,
+        WriteStorage<'a, MainHandItemChanged>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            lazy_update,
+            global_timer,
+            input_cache,
+            player_state,
+            transform,
+            mut inventory_screen,
+            mut inventory,
+            mut main_hand_item_changed,
+        ) = data;
+
+        for (entity, player_state, transform, inventory_screen, inventory) in (
+            &entities,
+            &player_state,
+            &transform,
+            &mut inventory_screen,
+            &mut inventory,
+        )
+            .join()
+        {
+            let selected_item = inventory.get_selected_item();
+            let mut thrown_items = Vec::new();
+
+            for event in input_cache.events.iter() {
+                match event {
+                    WindowEvent::Key(Key::E, _, Action::Press, _) if !inventory_screen.is_open => {
+                        inventory_screen.open();
+                    }
+                    WindowEvent::Key(Key::E | Key::Escape, _, Action::Press, _)
+                        if inventory_screen.is_open =>
+                    {
+                        // The carried stack and the crafting grid go back to the inventory, or to
+                        // the ground if it is full
+                        for item_stack in inventory_screen.close() {
+                            thrown_items.extend(inventory.add_item(item_stack));
+                        }
+                    }
+                    WindowEvent::MouseButton(button, Action::Press, _)
+                        if inventory_screen.is_open =>
+                    {
+                        let click = match button {
+                            MouseButton::Button1
+                                if input_cache.is_key_pressed(Key::LeftShift)
+                                    || input_cache.is_key_pressed(Key::RightShift) =>
+                            {
+                                SlotClick::QuickMove
+                            }
+                            MouseButton::Button1 => SlotClick::Left,
+                            MouseButton::Button2 => SlotClick::Right,
+                            _ => continue,
+                        };
+                        let cursor = cursor_position(&input_cache);
+
+                        match inventory_screen.slot_at(cursor.0, cursor.1) {
+                            Some(slot) => inventory_screen.click_slot(inventory, slot, click),
+                            // Clicking outside of the slots throws the carried stack
+                            None => thrown_items.extend(inventory_screen.cursor_stack.take()),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            for item_stack in thrown_items {
+                throw_item(
+                    item_stack,
+                    player_state,
+                    transform,
+                    &entities,
+                    &lazy_update,
+                    &global_timer,
+                );
+            }
+
+            if inventory.get_selected_item() != selected_item {
+                if let Err(err) = main_hand_item_changed.insert(entity, MainHandItemChanged) {
+                    error!("{err}");
+                }
+            }
+        }
+    }
+}
+
+// Position of the mouse in window coordinates, from the bottom left corner like the GUI
+pub fn cursor_position(input_cache: &InputCache) -> (f32, f32) {
+    (
+        input_cache.last_cursor_pos.x as f32,
+        WINDOW_HEIGHT as f32 - input_cache.last_cursor_pos.y as f32,
+    )
+}
+
 pub struct InventoryHandleInput;
 
 impl InventoryHandleInput {
@@ -22,9 +162,13 @@ impl InventoryHandleInput {
 impl<'a> System<'a> for InventoryHandleInput {
     type SystemData = (
         Entities<'a>,
+        Read<'a, LazyUpdate>,
+        Read<'a, Timer>,
         Read<'a, InputCache>,
         Read<'a, Arc<ChunkManager>>,
         ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, InventoryScreen>,
         WriteStorage<'a, Inventory>,
         WriteStorage<'a, MainHandItemChanged>,
     );
@@ -32,14 +176,31 @@ impl<'a> System<'a> for InventoryHandleInput {
     fn run(&mut self, data: Self::SystemData) {
         let (
             entities,
+            lazy_update,
+            global_timer,
             input_cache,
             chunk_manager,
             player_state,
+            transform,
+            inventory_screen,
             mut inventory,
             mut main_hand_item_changed,
         ) = data;
 
-        for (entity, inventory, player_state) in (&entities, &mut inventory, &player_state).join() {
+        for (entity, inventory, player_state, transform, inventory_screen) in (
+            &entities,
+            &mut inventory,
+            &player_state,
+            &transform,
+            inventory_screen.maybe(),
+        )
+            .join()
+        {
+            // The mouse and the keys are used by the inventory screen
+            if inventory_screen.is_some_and(|inventory_screen| inventory_screen.is_open) {
+                continue;
+            }
+
             let mut f = || {
                 if let Err(e) = main_hand_item_changed.insert(entity, MainHandItemChanged) {
                     error!("{e}");
@@ -66,6 +227,20 @@ impl<'a> System<'a> for InventoryHandleInput {
                             }
                         }
                     }
+                    // Throw one item of the selected stack where the player is looking
+                    WindowEvent::Key(Key::Q, _, Action::Press, _) => {
+                        if let Some(item_stack) = inventory.take_from_selected_slot(1) {
+                            throw_item(
+                                item_stack,
+                                player_state,
+                                transform,
+                                &entities,
+                                &lazy_update,
+                                &global_timer,
+                            );
+                            f();
+                        }
+                    }
                     WindowEvent::Key(Key::Num1, _, Action::Press, _) => {
                         Self::select_item(inventory, 0, &mut f)
                     }
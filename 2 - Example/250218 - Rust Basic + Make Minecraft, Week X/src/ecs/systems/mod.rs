@@ -1,29 +1,54 @@
+pub mod block_ticks;
 pub mod chunk_loading;
+#[cfg(feature = "client")]
+pub mod chunk_uploading;
+pub mod dropped_items;
 pub mod fps_counter;
+#[cfg(feature = "client")]
 pub mod hand;
+#[cfg(feature = "client")]
 pub mod input;
+#[cfg(feature = "client")]
 pub mod inventory;
 pub mod physics;
+#[cfg(feature = "client")]
 pub mod player;
+#[cfg(feature = "client")]
 pub mod rendering;
+pub mod server_sync;
+pub mod vitals;
 
+use crate::day_night::WorldTime;
 use crate::timer::Timer;
 use specs::{System, Write};
 
+pub use block_ticks::*;
+pub use dropped_items::*;
 pub use fps_counter::*;
+#[cfg(feature = "client")]
 pub use hand::*;
+#[cfg(feature = "client")]
 pub use input::*;
+#[cfg(feature = "client")]
 pub use inventory::*;
 pub use physics::*;
+#[cfg(feature = "client")]
 pub use player::*;
+#[cfg(feature = "client")]
 pub use rendering::*;
+pub use server_sync::*;
+pub use vitals::*;
 
+// Also advances the time of the world, which stops with the global timer
 pub struct AdvanceGlobalTime;
 
 impl<'a> System<'a> for AdvanceGlobalTime {
-    type SystemData = Write<'a, Timer>;
+    type SystemData = (Write<'a, Timer>, Write<'a, WorldTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut global_timer, mut world_time) = data;
 
-    fn run(&mut self, mut global_timer: Self::SystemData) {
         global_timer.tick();
+        world_time.advance(global_timer.time());
     }
 }
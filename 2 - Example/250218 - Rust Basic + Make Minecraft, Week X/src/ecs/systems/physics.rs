@@ -1,119 +1,446 @@
 use crate::chunk_manager::ChunkManager;
-use crate::constants::{GRAVITY, PLAYER_HALF_WIDTH};
-use crate::input::InputCache;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
+use crate::constants::{JUMPING_EXHAUSTION, SPRINTING_EXHAUSTION, SPRINT_JUMPING_EXHAUSTION};
+use crate::game_mode::GameMode;
+use crate::physics::{Collider, Gravity, Interpolator, PhysicsBody, Transform};
+use crate::player::PlayerState;
 use crate::timer::Timer;
-use nalgebra_glm::vec3;
+use crate::vitals::{fall_damage, Health, Hunger};
+use nalgebra_glm::{vec3, Vec3};
 use num_traits::Zero;
-use specs::{Join, Read, System, WriteStorage};
+use specs::{Join, LendJoin, Read, ReadStorage, System, WriteStorage};
 use std::sync::Arc;
 
-pub struct UpdatePlayerPhysics;
+// Moves every entity that has a physics body: the players, the items, the mobs...
+pub struct UpdatePhysics;
 
-impl<'a> System<'a> for UpdatePlayerPhysics {
+// Moves the body by `velocity * dt` one axis at a time, stopping it at the solid blocks.
+// Returns whether it landed on a block.
+fn move_and_collide(
+    body: &mut PhysicsBody,
+    dt: f32,
+    chunk_manager: &ChunkManager,
+    player_state: Option<&PlayerState>,
+) -> bool {
+    let will_hit_ground = |body: &PhysicsBody| {
+        let mut body = body.clone();
+        let v_y = vec3(0.0, body.velocity.y, 0.0);
+
+        body.aabb.translate(&(v_y * dt));
+
+        let colliding_block = body.get_colliding_block_coords(chunk_manager);
+
+        if let Some(colliding_block) = colliding_block {
+            body.separate_from_block(&v_y, &colliding_block)
+        } else {
+            false
+        }
+    };
+
+    // We are using the Separated Axis Theorem
+    // We decompose the velocity vector into 3 vectors for each dimension
+    // For each one, we move the entity and do the collision detection/resolution
+    let mut is_on_ground = false;
+
+    let separated_axis: [Vec3; 3] = [
+        vec3(body.velocity.x, 0.0, 0.0),
+        vec3(0.0, 0.0, body.velocity.z),
+        vec3(0.0, body.velocity.y, 0.0),
+    ];
+
+    for v in &separated_axis {
+        let backup = body.clone();
+
+        body.aabb.translate(&(v * dt));
+        let block_collided = body.get_colliding_block_coords(chunk_manager);
+
+        // Collision resolution
+        if let Some(block_collided) = block_collided {
+            is_on_ground |= body.separate_from_block(v, &block_collided);
+        }
+
+        // If the player is sneaking and is not on the ground, the player should not be able to move
+        if player_state.is_some_and(|player_state| player_state.movement_input.sneak)
+            && body.is_on_ground
+            && !will_hit_ground(body)
+            && body.velocity.y < 0.0
+        {
+            *body = backup;
+
+            if !v.x.is_zero() {
+                body.velocity.x = 0.0;
+            }
+
+            if !v.z.is_zero() {
+                body.velocity.z = 0.0;
+            }
+        }
+    }
+
+    is_on_ground
+}
+
+impl<'a> System<'a> for UpdatePhysics {
     type SystemData = (
         Read<'a, Timer>,
-        Read<'a, InputCache>,
         Read<'a, Arc<ChunkManager>>,
-        WriteStorage<'a, Interpolator<PlayerPhysicsState>>,
+        WriteStorage<'a, Interpolator<PhysicsBody>>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Gravity>,
+        ReadStorage<'a, Collider>,
         WriteStorage<'a, PlayerState>,
+        ReadStorage<'a, GameMode>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, Hunger>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (global_timer, input_cache, chunk_manager, mut player_physics_state, mut player_state) =
-            data;
+        let (
+            global_timer,
+            chunk_manager,
+            mut physics_body,
+            mut transform,
+            gravity,
+            collider,
+            mut player_state,
+            game_mode,
+            mut health,
+            mut hunger,
+        ) = data;
 
-        for (player_physics_state, player_state) in
-            (&mut player_physics_state, &mut player_state).join()
+        for (
+            physics_body,
+            transform,
+            gravity,
+            collider,
+            mut player_state,
+            game_mode,
+            mut health,
+            mut hunger,
+        ) in (
+            &mut physics_body,
+            &mut transform,
+            gravity.maybe(),
+            collider.maybe(),
+            (&mut player_state).maybe(),
+            game_mode.maybe(),
+            (&mut health).maybe(),
+            (&mut hunger).maybe(),
+        )
+            .join()
         {
-            player_physics_state.step(
+            let game_mode = game_mode.copied().unwrap_or(GameMode::FOR_NON_PLAYERS);
+            let has_collisions = collider.is_some() && !game_mode.is_spectating();
+
+            physics_body.step(
                 global_timer.time(),
-                &mut |player: &PlayerPhysicsState, _t: f32, dt: f32| {
-                    let mut player = player.clone();
+                &mut |body: &PhysicsBody, _t: f32, dt: f32| {
+                    let mut body = body.clone();
+                    let is_flying = player_state
+                        .as_ref()
+                        .is_some_and(|player_state| player_state.is_flying);
+                    let was_on_ground = body.is_on_ground;
+                    let start_position = body.position();
 
-                    if !player_state.is_flying {
-                        player.acceleration.y += GRAVITY;
-                    }
+                    if let Some(player_state) = player_state.as_mut() {
+                        let jump_last_executed = player_state.jump_last_executed;
+                        player_state.apply_movement_input(&mut body);
 
-                    player.apply_keyboard_movement(player_state, &input_cache);
-                    player.velocity += player.acceleration * dt;
-                    player.apply_friction(dt, &player_state);
-                    player.limit_velocity(&player_state);
+                        if let Some(hunger) = hunger.as_mut().filter(|_| game_mode.has_vitals()) {
+                            if player_state.jump_last_executed != jump_last_executed {
+                                hunger.exhaust(if player_state.is_sprinting {
+                                    SPRINT_JUMPING_EXHAUSTION
+                                } else {
+                                    JUMPING_EXHAUSTION
+                                });
+                            }
+                        }
+                    }
 
-                    let will_hit_ground = |player: &PlayerPhysicsState| {
-                        let mut player = player.clone();
-                        let v_y = vec3(0.0, player.velocity.y, 0.0);
+                    if let Some(gravity) = gravity {
+                        if !is_flying {
+                            body.acceleration.y += gravity.acceleration;
+                        }
+                    }
 
-                        player.aabb.translate(&(v_y * dt));
+                    body.velocity += body.acceleration * dt;
+                    body.apply_friction(dt, is_flying);
 
-                        let colliding_block = player.get_colliding_block_coords(&chunk_manager);
+                    if let Some(player_state) = player_state.as_ref() {
+                        player_state.limit_velocity(&mut body);
+                    }
 
-                        if let Some(colliding_block) = colliding_block {
-                            player.separate_from_block(&v_y, &colliding_block)
-                        } else {
-                            false
-                        }
-                    };
+                    body.limit_fall_speed();
 
-                    // We are using the Separated Axis Theorem
-                    // We decompose the velocity vector into 3 vectors for each dimension
-                    // For each one, we move the entity and do the collision detection/resolution
-                    let mut is_player_on_ground = false;
+                    // Velocity before the collisions stop the body
+                    let vertical_velocity = body.velocity.y;
 
-                    let separated_axis = &[
-                        vec3(player.velocity.x, 0.0, 0.0),
-                        vec3(0.0, 0.0, player.velocity.z),
-                        vec3(0.0, player.velocity.y, 0.0),
-                    ];
+                    if has_collisions {
+                        body.is_on_ground = move_and_collide(
+                            &mut body,
+                            dt,
+                            &chunk_manager,
+                            player_state.as_deref(),
+                        );
+                    } else {
+                        let translation = body.velocity * dt;
+                        body.aabb.translate(&translation);
+                        body.is_on_ground = false;
+                    }
 
-                    for v in separated_axis {
-                        let backup = player.clone();
+                    if body.is_on_ground {
+                        if let Some(player_state) = player_state.as_mut() {
+                            player_state.is_flying = false;
+                        }
+                    }
 
-                        player.aabb.translate(&(v * dt));
-                        let block_collided = player.get_colliding_block_coords(&chunk_manager);
+                    if let Some(health) = health.as_mut() {
+                        if body.is_on_ground && !was_on_ground {
+                            if !health.is_spawning && game_mode.has_vitals() && !is_flying {
+                                health.damage(fall_damage(vertical_velocity));
+                            }
 
-                        // Collision resolution
-                        if let Some(block_collided) = block_collided {
-                            is_player_on_ground |= player.separate_from_block(&v, &block_collided);
+                            health.is_spawning = false;
                         }
+                    }
 
-                        // If the player is sneaking and is not on the ground, the player should not be able to move
-                        if input_cache.is_key_pressed(glfw::Key::LeftShift)
-                            && player_state.is_on_ground
-                            && !will_hit_ground(&player)
-                            && player.velocity.y < 0.0
+                    if let (Some(player_state), Some(hunger)) =
+                        (player_state.as_ref(), hunger.as_mut())
+                    {
+                        if player_state.is_sprinting && body.is_on_ground && game_mode.has_vitals()
                         {
-                            player = backup;
-
-                            if !v.x.is_zero() {
-                                player.velocity.x = 0.0;
-                            }
-
-                            if !v.z.is_zero() {
-                                player.velocity.z = 0.0;
-                            }
+                            let distance = (body.position() - start_position).xz().norm();
+                            hunger.exhaust(SPRINTING_EXHAUSTION * distance);
                         }
                     }
 
-                    player_state.is_on_ground = is_player_on_ground;
+                    // Reset the acceleration
+                    body.acceleration = vec3(0.0, 0.0, 0.0);
 
-                    if player_state.is_on_ground {
-                        player_state.is_flying = false;
-                    }
+                    body
+                },
+            );
 
-                    // Update the position of the player and reset the acceleration
-                    player.position.x = player.aabb.mins.x + PLAYER_HALF_WIDTH;
-                    player.position.y = player.aabb.mins.y;
-                    player.position.z = player.aabb.mins.z + PLAYER_HALF_WIDTH;
+            transform.position = physics_body.get_interpolated_state().position();
+        }
+    }
+}
 
-                    player.acceleration.x = 0.0;
-                    player.acceleration.y = 0.0;
-                    player.acceleration.z = 0.0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::BlockID;
+    use crate::constants::MAX_HEALTH;
+    use crate::player::{player_physics_body, MovementInput};
+    use crate::test_support::create_world_with_floor;
+    use specs::{Builder, Entity, RunNow, World, WorldExt};
+    use std::time::Duration;
 
-                    player
-                },
-            )
+    // Runs the physics for `seconds` without any window, in steps of 0.25s (the longest frame)
+    fn simulate(world: &mut World, seconds: u32) {
+        for _ in 0..4 * seconds {
+            {
+                let time = world.read_resource::<Timer>().time();
+                let mut physics_bodies = world.write_storage::<Interpolator<PhysicsBody>>();
+
+                for physics_body in (&mut physics_bodies).join() {
+                    physics_body.current_time = time - Duration::from_millis(250);
+                }
+            }
+
+            UpdatePhysics.run_now(world);
         }
     }
+
+    fn create_player(world: &mut World, player_state: PlayerState, position: Vec3) -> Entity {
+        world
+            .create_entity()
+            .with(player_state)
+            .with(Transform::new(position))
+            .with(Interpolator::new(1.0 / 60.0, player_physics_body(position)))
+            .with(Gravity::default())
+            .with(Collider)
+            .build()
+    }
+
+    fn position_of(world: &World, entity: Entity) -> Vec3 {
+        world
+            .read_storage::<Interpolator<PhysicsBody>>()
+            .get(entity)
+            .unwrap()
+            .get_latest_state()
+            .position()
+    }
+
+    #[test]
+    fn player_falls_and_lands_on_the_ground() {
+        let mut world = create_world_with_floor(4);
+        let player = create_player(&mut world, PlayerState::new(), vec3(8.0, 10.0, 8.0));
+
+        simulate(&mut world, 2);
+
+        let position = position_of(&world, player);
+        let physics_bodies = world.read_storage::<Interpolator<PhysicsBody>>();
+
+        assert!(
+            physics_bodies
+                .get(player)
+                .unwrap()
+                .get_latest_state()
+                .is_on_ground
+        );
+        assert!((position.y - 4.0).abs() < 1e-4, "{position:?}");
+
+        // The transform follows the body
+        let transform = world.read_storage::<Transform>();
+        assert!((transform.get(player).unwrap().position - position).norm() < 1e-4);
+    }
+
+    fn fall_on_the_ground(world: &mut World, height: f32, is_spawning: bool) -> Health {
+        let player = create_player(world, PlayerState::new(), vec3(8.0, 4.0 + height, 8.0));
+        let mut health = Health::new();
+        health.is_spawning = is_spawning;
+        world
+            .write_storage::<Health>()
+            .insert(player, health)
+            .unwrap();
+
+        simulate(world, 3);
+
+        *world.read_storage::<Health>().get(player).unwrap()
+    }
+
+    #[test]
+    fn falling_hurts_the_player_when_landing() {
+        let mut world = create_world_with_floor(4);
+
+        assert_eq!(
+            fall_on_the_ground(&mut world, 2.0, false).points,
+            MAX_HEALTH
+        );
+
+        let health = fall_on_the_ground(&mut world, 12.0, false);
+        assert!(
+            (MAX_HEALTH - 10..=MAX_HEALTH - 8).contains(&health.points),
+            "{health:?}"
+        );
+        assert!(!health.is_spawning);
+
+        // Falling to the ground after spawning doesn't hurt
+        assert_eq!(
+            fall_on_the_ground(&mut world, 100.0, true).points,
+            MAX_HEALTH
+        );
+        assert!(fall_on_the_ground(&mut world, 100.0, false).is_dead());
+    }
+
+    #[test]
+    fn sprinting_and_jumping_exhaust_the_player() {
+        let mut world = create_world_with_floor(4);
+        let mut player_state = PlayerState::new();
+        player_state.is_sprinting = true;
+        player_state.movement_input = MovementInput {
+            forward: true,
+            jump: true,
+            ..MovementInput::default()
+        };
+        let player = create_player(&mut world, player_state, vec3(8.0, 4.0, 8.0));
+        world
+            .write_storage::<Hunger>()
+            .insert(player, Hunger::new())
+            .unwrap();
+
+        simulate(&mut world, 1);
+
+        let hunger = *world.read_storage::<Hunger>().get(player).unwrap();
+        assert!(hunger.exhaustion > SPRINT_JUMPING_EXHAUSTION, "{hunger:?}");
+    }
+
+    #[test]
+    fn spectators_fly_through_the_blocks() {
+        let mut world = create_world_with_floor(4);
+        let mut player_state = PlayerState::new();
+        player_state.switch_game_mode(GameMode::Spectator);
+        player_state.movement_input = MovementInput {
+            sneak: true,
+            ..MovementInput::default()
+        };
+        let player = create_player(&mut world, player_state, vec3(8.0, 6.0, 8.0));
+        world
+            .write_storage::<GameMode>()
+            .insert(player, GameMode::Spectator)
+            .unwrap();
+
+        simulate(&mut world, 1);
+
+        // Flying down with sneak, into the floor
+        assert!(position_of(&world, player).y < 2.0);
+        assert!(
+            world
+                .read_storage::<PlayerState>()
+                .get(player)
+                .unwrap()
+                .is_flying
+        );
+    }
+
+    #[test]
+    fn player_walks_forward_without_keyboard() {
+        let mut world = create_world_with_floor(4);
+        let mut player_state = PlayerState::new();
+        player_state.movement_input = MovementInput {
+            forward: true,
+            ..MovementInput::default()
+        };
+        let player = create_player(&mut world, player_state, vec3(8.0, 4.0, 8.0));
+
+        simulate(&mut world, 1);
+
+        let position = position_of(&world, player);
+
+        // The player looks towards +X by default
+        assert!(position.x > 10.0, "{position:?}");
+        assert!((position.z - 8.0).abs() < 1e-4, "{position:?}");
+        assert!((position.y - 4.0).abs() < 1e-4, "{position:?}");
+    }
+
+    #[test]
+    fn other_entities_get_the_same_physics() {
+        let mut world = create_world_with_floor(4);
+        let chunk_manager = Arc::clone(&world.read_resource::<Arc<ChunkManager>>());
+
+        // Fluids don't stop the entities
+        chunk_manager.set_block(3, 4, 3, BlockID::WATER);
+
+        let mut create_box = |x: f32, has_gravity: bool, has_collider: bool| {
+            let position = vec3(x, 10.0, 3.5);
+            let mut builder =
+                world
+                    .create_entity()
+                    .with(Transform::new(position))
+                    .with(Interpolator::new(
+                        1.0 / 60.0,
+                        PhysicsBody::new(position, 0.125, 0.25),
+                    ));
+
+            if has_gravity {
+                builder = builder.with(Gravity::default());
+            }
+
+            if has_collider {
+                builder = builder.with(Collider);
+            }
+
+            builder.build()
+        };
+
+        let falling_box = create_box(3.5, true, true);
+        let floating_box = create_box(5.5, false, true);
+        let ghost_box = create_box(7.5, true, false);
+
+        simulate(&mut world, 2);
+
+        assert!((position_of(&world, falling_box).y - 4.0).abs() < 1e-4);
+        assert!((position_of(&world, floating_box).y - 10.0).abs() < 1e-4);
+        assert!(position_of(&world, ghost_box).y < 0.0);
+    }
 }
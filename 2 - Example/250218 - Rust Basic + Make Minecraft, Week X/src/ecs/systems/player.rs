@@ -2,21 +2,30 @@ use crate::aabb::{get_block_aabb, AABB};
 use crate::chunk::BlockID;
 use crate::chunk_manager::ChunkManager;
 use crate::constants::{
-    FAR_PLANE, FLYING_TRIGGER_INTERVAL, FOV, JUMP_IMPULSE, NEAR_PLANE, PLAYER_EYES_HEIGHT,
-    REACH_DISTANCE, SPRINTING_TRIGGER_INTERVAL, WINDOW_HEIGHT, WINDOW_WIDTH,
+    BLOCK_DROP_PICKUP_DELAY, DROPPED_ITEM_SIZE, FAR_PLANE, FLYING_TRIGGER_INTERVAL, FOV,
+    JUMP_IMPULSE, NEAR_PLANE, PLAYER_EYES_HEIGHT, REACH_DISTANCE, SPRINTING_TRIGGER_INTERVAL,
+    WINDOW_HEIGHT, WINDOW_WIDTH,
 };
+use crate::ecs::components::MainHandItemChanged;
+use crate::ecs::systems::dropped_items::spawn_dropped_item;
+use crate::game_mode::GameMode;
 use crate::input::InputCache;
+use crate::inventory::dropped_item::DroppedItem;
+use crate::inventory::item::ItemStack;
+use crate::inventory::screen::InventoryScreen;
 use crate::inventory::Inventory;
+use crate::mining::advance_breaking;
 use crate::particle_system::ParticleSystem;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
+use crate::physics::{Interpolator, PhysicsBody, Transform};
+use crate::player::{MovementInput, PlayerState};
 use crate::raycast;
 use crate::timer::Timer;
 use crate::types::{ParticleSystems, TexturePack};
 use crate::util::Forward;
 use nalgebra::Vector3;
 use nalgebra_glm::{vec3, IVec3};
-use specs::{Join, Read, ReadStorage, System, Write, WriteStorage};
+use rand::random;
+use specs::{Entities, Join, LazyUpdate, LendJoin, Read, ReadStorage, System, Write, WriteStorage};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -26,16 +35,33 @@ impl<'a> System<'a> for HandlePlayerInput {
     type SystemData = (
         Read<'a, InputCache>,
         WriteStorage<'a, PlayerState>,
-        WriteStorage<'a, Interpolator<PlayerPhysicsState>>,
+        WriteStorage<'a, Interpolator<PhysicsBody>>,
+        ReadStorage<'a, InventoryScreen>,
+        WriteStorage<'a, GameMode>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (input_cache, mut player_state, mut player_physics_state) = data;
-
-        for (player_state, player_physics_state) in
-            (&mut player_state, &mut player_physics_state).join()
+        let (input_cache, mut player_state, mut physics_body, inventory_screen, mut game_mode) =
+            data;
+
+        for (player_state, physics_body, inventory_screen, game_mode) in (
+            &mut player_state,
+            &mut physics_body,
+            inventory_screen.maybe(),
+            &mut game_mode,
+        )
+            .join()
         {
-            let player_physics_state = player_physics_state.get_latest_state_mut();
+            // The player stands still while a screen is open, the keys released meanwhile are
+            // not seen
+            if inventory_screen.is_some_and(|inventory_screen| inventory_screen.is_open) {
+                player_state.is_sneaking = false;
+                player_state.is_sprinting = false;
+                player_state.movement_input = MovementInput::default();
+                continue;
+            }
+
+            let body = physics_body.get_latest_state_mut();
 
             for event in &input_cache.events {
                 match event {
@@ -52,6 +78,7 @@ impl<'a> System<'a> for HandlePlayerInput {
                             player_state.fly_throttle = false;
                         } else if Instant::now().duration_since(player_state.fly_last_toggled)
                             < *FLYING_TRIGGER_INTERVAL
+                            && game_mode.can_fly()
                         {
                             player_state.is_flying = !player_state.is_flying;
                             println!("Flying: {}", player_state.is_flying);
@@ -61,12 +88,18 @@ impl<'a> System<'a> for HandlePlayerInput {
                         player_state.fly_last_toggled = Instant::now();
 
                         // Player physics state
-                        if player_state.is_on_ground {
-                            player_physics_state.velocity.y = *JUMP_IMPULSE;
+                        if body.is_on_ground {
+                            body.velocity.y = *JUMP_IMPULSE;
                             player_state.jump_last_executed = Instant::now();
                         }
                     }
 
+                    glfw::WindowEvent::Key(glfw::Key::F4, _, glfw::Action::Press, _) => {
+                        *game_mode = game_mode.next();
+                        player_state.switch_game_mode(*game_mode);
+                        info!("Game mode: {game_mode:?}");
+                    }
+
                     // Cancel sneaking
                     glfw::WindowEvent::Key(glfw::Key::LeftShift, _, glfw::Action::Release, _) => {
                         player_state.is_sneaking = false;
@@ -96,7 +129,7 @@ impl<'a> System<'a> for HandlePlayerInput {
             }
 
             // Sneaking
-            if input_cache.is_key_pressed(glfw::Key::LeftShift) && player_state.is_on_ground {
+            if input_cache.is_key_pressed(glfw::Key::LeftShift) && body.is_on_ground {
                 player_state.is_sneaking = true;
                 player_state.is_sprinting = false;
             }
@@ -108,6 +141,16 @@ impl<'a> System<'a> for HandlePlayerInput {
             {
                 player_state.is_sprinting = true;
             }
+
+            // Movement, applied by the physics
+            player_state.movement_input = MovementInput {
+                forward: input_cache.is_key_pressed(glfw::Key::W),
+                backward: input_cache.is_key_pressed(glfw::Key::S),
+                left: input_cache.is_key_pressed(glfw::Key::A),
+                right: input_cache.is_key_pressed(glfw::Key::D),
+                jump: input_cache.is_key_pressed(glfw::Key::Space),
+                sneak: input_cache.is_key_pressed(glfw::Key::LeftShift),
+            };
         }
     }
 }
@@ -119,14 +162,15 @@ impl<'a> System<'a> for UpdatePlayerState {
         Read<'a, Timer>,
         Write<'a, Arc<ChunkManager>>,
         WriteStorage<'a, PlayerState>,
-        ReadStorage<'a, Interpolator<PlayerPhysicsState>>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, GameMode>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (global_timer, chunk_manager, mut player_state, player_physics_state) = data;
+        let (global_timer, chunk_manager, mut player_state, transform, game_mode) = data;
 
-        for (player_state, player_physics_state) in
-            (&mut player_state, &player_physics_state).join()
+        for (player_state, transform, game_mode) in
+            (&mut player_state, &transform, game_mode.maybe()).join()
         {
             let t = global_timer.time();
 
@@ -158,31 +202,32 @@ impl<'a> System<'a> for UpdatePlayerState {
 
             player_state.fov.interpolate_fov(t, target_fov);
 
-            // Targeted block
-            player_state.targeted_block = {
-                let is_solid_block_at =
-                    |x: i32, y: i32, z: i32| chunk_manager.is_solid_block_at(x, y, z);
-
-                let forward = player_state.rotation.forward();
-                let player = player_physics_state.get_interpolated_state();
-
-                raycast::raycast(
-                    &is_solid_block_at,
-                    &(player.position
-                        + vec3(
-                            0.0,
-                            *player_state.camera_height.get_interpolated_state(),
-                            0.0,
-                        )),
-                    &forward.normalize(),
-                    REACH_DISTANCE,
-                )
-            };
+            // Targeted block, the spectators can't touch the blocks
+            player_state.targeted_block =
+                if game_mode.is_some_and(|game_mode| !game_mode.interacts_with_blocks()) {
+                    None
+                } else {
+                    let is_solid_block_at =
+                        |x: i32, y: i32, z: i32| chunk_manager.is_solid_block_at(x, y, z);
+
+                    let forward = player_state.rotation.forward();
+
+                    raycast::raycast(
+                        &is_solid_block_at,
+                        &(transform.position
+                            + vec3(
+                                0.0,
+                                *player_state.camera_height.get_interpolated_state(),
+                                0.0,
+                            )),
+                        &forward.normalize(),
+                        REACH_DISTANCE,
+                    )
+                };
 
             // View and projection matrix
             player_state.view_matrix = {
-                let player_physics_state = player_physics_state.get_interpolated_state();
-                let camera_position = player_physics_state.position
+                let camera_position = transform.position
                     + vec3(
                         0.0,
                         *player_state.camera_height.get_interpolated_state(),
@@ -214,30 +259,60 @@ pub struct PlaceAndBreakBlocks;
 
 impl<'a> System<'a> for PlaceAndBreakBlocks {
     type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
+        Read<'a, Timer>,
         Write<'a, Arc<ChunkManager>>,
         Write<'a, ParticleSystems>,
         Read<'a, InputCache>,
         Read<'a, TexturePack>,
         WriteStorage<'a, PlayerState>,
-        ReadStorage<'a, Interpolator<PlayerPhysicsState>>,
-        ReadStorage<'a, Inventory>,
+        ReadStorage<'a, Interpolator<PhysicsBody>>,
+        WriteStorage<'a, Inventory>,
+        WriteStorage<'a, MainHandItemChanged>,
+        WriteStorage<'a, InventoryScreen>,
+        ReadStorage<'a, GameMode>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
+            entities,
+            lazy_update,
+            global_timer,
             chunk_manager,
             mut particle_systems,
             input_cache,
             texture_pack,
             mut player_state,
-            player_physics_state,
-            inventory,
+            physics_body,
+            mut inventory,
+            mut main_hand_item_changed,
+            mut inventory_screen,
+            game_mode,
         ) = data;
 
-        for (player_state, player_physics_state, inventory) in
-            (&mut player_state, &player_physics_state, &inventory).join()
+        for (player, player_state, physics_body, inventory, mut inventory_screen, game_mode) in (
+            &entities,
+            &mut player_state,
+            &physics_body,
+            &mut inventory,
+            (&mut inventory_screen).maybe(),
+            &game_mode,
+        )
+            .join()
         {
-            let player_physics_state = player_physics_state.get_latest_state();
+            // The clicks are for the slots of the screen
+            if inventory_screen
+                .as_ref()
+                .is_some_and(|inventory_screen| inventory_screen.is_open)
+                || !game_mode.interacts_with_blocks()
+            {
+                player_state.block_breaking = None;
+                continue;
+            }
+
+            let body = physics_body.get_latest_state();
+            let selected_item = inventory.get_selected_item();
 
             for event in &input_cache.events {
                 match event {
@@ -245,26 +320,40 @@ impl<'a> System<'a> for PlaceAndBreakBlocks {
                         player_state.block_placing_last_executed = Instant::now();
 
                         match button {
-                            glfw::MouseButton::Button1 => {
-                                if let Some(((x, y, z), _)) = &player_state.targeted_block {
-                                    let mut particle_system =
-                                        particle_systems.get_mut("block_particles").unwrap();
-                                    break_block(
-                                        (*x, *y, *z),
+                            // In survival, the block is mined while the button is held below
+                            glfw::MouseButton::Button1 if game_mode.breaks_instantly() => {
+                                if let Some((coords, _)) = player_state.targeted_block {
+                                    break_and_drop_block(
+                                        coords,
                                         &chunk_manager,
-                                        &mut particle_system,
+                                        &mut particle_systems,
                                         &texture_pack,
+                                        &entities,
+                                        &lazy_update,
+                                        &global_timer,
                                     );
                                 }
                             }
                             glfw::MouseButton::Button2 => {
                                 if let Some(((x, y, z), normal)) = &player_state.targeted_block {
+                                    // Crafting tables are used instead, unless sneaking
+                                    if chunk_manager.get_block(*x, *y, *z)
+                                        == Some(BlockID::CRAFTING_TABLE)
+                                        && !player_state.is_sneaking
+                                    {
+                                        if let Some(inventory_screen) = &mut inventory_screen {
+                                            inventory_screen.open_crafting_table();
+                                            continue;
+                                        }
+                                    }
+
                                     place_block(
                                         (*x, *y, *z),
                                         &normal,
-                                        &player_physics_state.aabb,
-                                        &inventory,
+                                        &body.aabb,
+                                        inventory,
                                         &chunk_manager,
+                                        *game_mode,
                                     );
                                 }
                             }
@@ -285,14 +374,18 @@ impl<'a> System<'a> for PlaceAndBreakBlocks {
                     >= 0.25
                 {
                     if input_cache.is_mouse_button_pressed(glfw::MouseButtonLeft) {
-                        if let Some(((x, y, z), _)) = &player_state.targeted_block {
-                            let mut particle_system =
-                                particle_systems.get_mut("block_particles").unwrap();
-                            break_block(
-                                (*x, *y, *z),
+                        if let Some((coords, _)) = player_state
+                            .targeted_block
+                            .filter(|_| game_mode.breaks_instantly())
+                        {
+                            break_and_drop_block(
+                                coords,
                                 &chunk_manager,
-                                &mut particle_system,
+                                &mut particle_systems,
                                 &texture_pack,
+                                &entities,
+                                &lazy_update,
+                                &global_timer,
                             );
                         }
 
@@ -302,9 +395,10 @@ impl<'a> System<'a> for PlaceAndBreakBlocks {
                             place_block(
                                 (*x, *y, *z),
                                 &normal,
-                                &player_physics_state.aabb,
-                                &inventory,
+                                &body.aabb,
+                                inventory,
                                 &chunk_manager,
+                                *game_mode,
                             );
                         }
 
@@ -312,20 +406,60 @@ impl<'a> System<'a> for PlaceAndBreakBlocks {
                     }
                 }
             }
+
+            // Survival mining, the progress is lost when the button is released or the player
+            // looks at another block
+            if !game_mode.breaks_instantly() {
+                match player_state.targeted_block {
+                    Some(((x, y, z), _))
+                        if input_cache.is_mouse_button_pressed(glfw::MouseButtonLeft) =>
+                    {
+                        let block = chunk_manager.get_block(x, y, z).unwrap_or(BlockID::AIR);
+
+                        // None of the items is a tool yet, the blocks are mined by hand
+                        if advance_breaking(
+                            &mut player_state.block_breaking,
+                            (x, y, z),
+                            block,
+                            None,
+                            global_timer.time(),
+                        ) {
+                            break_and_drop_block(
+                                (x, y, z),
+                                &chunk_manager,
+                                &mut particle_systems,
+                                &texture_pack,
+                                &entities,
+                                &lazy_update,
+                                &global_timer,
+                            );
+                        }
+                    }
+                    _ => player_state.block_breaking = None,
+                }
+            }
+
+            // The last block of the stack was placed
+            if inventory.get_selected_item() != selected_item {
+                if let Err(err) = main_hand_item_changed.insert(player, MainHandItemChanged) {
+                    error!("{err}");
+                }
+            }
         }
     }
 }
 
+// Returns the broken block
 fn break_block(
     (x, y, z): (i32, i32, i32),
     chunk_manager: &ChunkManager,
     particle_system: &mut ParticleSystem,
     uv_map: &TexturePack,
-) {
+) -> Option<BlockID> {
     let block = chunk_manager.get_block(x, y, z).unwrap();
 
-    if block != BlockID::Air {
-        chunk_manager.put_block(x, y, z, BlockID::Air);
+    if block != BlockID::AIR {
+        chunk_manager.put_block(x, y, z, BlockID::AIR);
         particle_system.spawn_block_breaking_particles(
             vec3(x as f32, y as f32, z as f32),
             &uv_map,
@@ -333,15 +467,68 @@ fn break_block(
         );
 
         info!("Destroyed block at ({x} {y} {z})");
+
+        return Some(block);
+    }
+
+    None
+}
+
+fn break_and_drop_block(
+    coords: (i32, i32, i32),
+    chunk_manager: &ChunkManager,
+    particle_systems: &mut ParticleSystems,
+    texture_pack: &TexturePack,
+    entities: &Entities,
+    lazy_update: &LazyUpdate,
+    global_timer: &Timer,
+) {
+    let particle_system = particle_systems.get_mut("block_particles").unwrap();
+
+    if let Some(block) = break_block(coords, chunk_manager, particle_system, texture_pack) {
+        drop_block(coords, block, entities, lazy_update, global_timer);
     }
 }
 
+// Pops what a broken block drops (see `blocks.toml`) out of its centre
+fn drop_block(
+    (x, y, z): (i32, i32, i32),
+    block: BlockID,
+    entities: &Entities,
+    lazy_update: &LazyUpdate,
+    global_timer: &Timer,
+) {
+    let Some(item) = block.definition().drops else {
+        return;
+    };
+
+    let position = vec3(
+        x as f32 + 0.5,
+        y as f32 + 0.5 - DROPPED_ITEM_SIZE / 2.0,
+        z as f32 + 0.5,
+    );
+    let velocity = vec3(random::<f32>() - 0.5, 4.0, random::<f32>() - 0.5);
+
+    spawn_dropped_item(
+        entities,
+        lazy_update,
+        position,
+        velocity,
+        DroppedItem::new(
+            ItemStack::new(item, 1),
+            global_timer.time(),
+            *BLOCK_DROP_PICKUP_DELAY,
+        ),
+    );
+}
+
 fn place_block(
     (x, y, z): (i32, i32, i32),
     normal: &IVec3,
     player_aabb: &AABB,
-    inventory: &Inventory,
+    inventory: &mut Inventory,
     chunk_manager: &ChunkManager,
+    game_mode: GameMode,
 ) {
     let adjacent_block = IVec3::new(x, y, z) + normal;
     let adjacent_block_aabb = get_block_aabb(&vec3(
@@ -351,8 +538,17 @@ fn place_block(
     ));
 
     if !player_aabb.intersects(&adjacent_block_aabb) {
-        if let Some(block) = inventory.get_selected_item() {
-            chunk_manager.put_block(adjacent_block.x, adjacent_block.y, adjacent_block.z, block);
+        // The blocks are infinite in creative
+        let item = if game_mode.consumes_items() {
+            inventory
+                .take_from_selected_slot(1)
+                .map(|item_stack| item_stack.item)
+        } else {
+            inventory.get_selected_item()
+        };
+
+        if let Some(item) = item {
+            chunk_manager.put_block(adjacent_block.x, adjacent_block.y, adjacent_block.z, item);
         }
 
         info!(
@@ -1,51 +1,231 @@
-use crate::chunk_manager::ChunkManager;
-use crate::constants::{BACKGROUND_COLOR, BLOCK_OUTLINE_WIDTH, ENABLE_FOG, RENDER_DISTANCE};
+use crate::chunk::BlockID;
+use crate::chunk_arena::ChunkArena;
+use crate::chunk_manager::{ChunkManager, ChunkRenderStats, CHUNK_SIZE};
+use crate::constants::{
+    BLOCK_OUTLINE_WIDTH, BREAK_STAGES, DROPPED_ITEM_SIZE, ENABLE_FOG, RENDER_DISTANCE,
+};
+use crate::crafting::MAX_CRAFTING_GRID_SIZE;
+use crate::day_night::WorldTime;
+use crate::ecs::systems::inventory::cursor_position;
+use crate::frustum::Frustum;
+use crate::game_mode::GameMode;
 use crate::gui::{
-    create_block_outline_vao, create_crosshair_vao, create_hotbar_selection_vao, create_hotbar_vao,
-    draw_crosshair,
+    create_block_crack_vao, create_block_outline_vao, create_crosshair_vao, create_digit_vaos,
+    create_hotbar_selection_vao, create_hotbar_vao, create_slot_vao, create_status_icon_vaos,
+    draw_crosshair, draw_status_bar,
 };
-use crate::inventory::Inventory;
+use crate::input::InputCache;
+use crate::inventory::dropped_item::DroppedItem;
+use crate::inventory::item_render::ItemRender;
+use crate::inventory::screen::InventoryScreen;
+use crate::inventory::{Inventory, HOTBAR_SIZE, INVENTORY_SIZE};
+use crate::physics::Transform;
 use crate::player::PlayerState;
+use crate::sky::{create_celestial_vao, create_sky_vao, draw_sky, draw_sun_and_moon};
 use crate::timer::Timer;
 use crate::types::{ParticleSystems, Shaders, TexturePack};
+use crate::vitals::{Health, Hunger};
 use nalgebra::Matrix4;
 use nalgebra_glm::vec3;
-use specs::{Join, Read, ReadStorage, System, Write, WriteStorage};
+use specs::{Join, LendJoin, Read, ReadExpect, ReadStorage, System, Write};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+// Clears the screen with the sky of the time of day, the sun and the moon. Nothing is drawn
+// in the depth buffer so that everything else is in front of them.
+pub struct RenderSky {
+    sky_vao: u32,
+    celestial_vao: u32,
+}
+
+impl RenderSky {
+    pub fn new() -> Self {
+        Self {
+            sky_vao: create_sky_vao(),
+            celestial_vao: create_celestial_vao(),
+        }
+    }
+}
+
+impl Default for RenderSky {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> System<'a> for RenderSky {
+    type SystemData = (
+        Read<'a, WorldTime>,
+        ReadStorage<'a, PlayerState>,
+        Write<'a, Shaders>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (world_time, player_state, mut shaders) = data;
+
+        let fog_color = world_time.fog_color();
+        gl_call!(gl::ClearColor(fog_color.x, fog_color.y, fog_color.z, 1.0));
+        gl_call!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
+        gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+
+        gl_call!(gl::Disable(gl::DEPTH_TEST));
+        gl_call!(gl::Disable(gl::CULL_FACE));
+
+        for player_state in (&player_state).join() {
+            let sky_shader = shaders.get_mut("sky_shader").unwrap();
+            draw_sky(
+                self.sky_vao,
+                &world_time,
+                &player_state.view_matrix,
+                &player_state.projection_matrix,
+                sky_shader,
+            );
+
+            let celestial_shader = shaders.get_mut("celestial_shader").unwrap();
+            draw_sun_and_moon(
+                self.celestial_vao,
+                &world_time,
+                &player_state.view_matrix,
+                &player_state.projection_matrix,
+                celestial_shader,
+            );
+        }
+
+        gl_call!(gl::Enable(gl::CULL_FACE));
+        gl_call!(gl::Enable(gl::DEPTH_TEST));
+    }
+}
+
 pub struct RenderChunks;
 
 impl<'a> System<'a> for RenderChunks {
     type SystemData = (
+        Read<'a, WorldTime>,
         ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Transform>,
         Read<'a, Arc<ChunkManager>>,
+        ReadExpect<'a, ChunkArena>,
         Write<'a, Shaders>,
+        Write<'a, ChunkRenderStats>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_state, chunk_manager, mut shaders) = data;
+        let (
+            world_time,
+            player_state,
+            transform,
+            chunk_manager,
+            chunk_arena,
+            mut shaders,
+            mut render_stats,
+        ) = data;
 
-        let mut voxel_shader = shaders.get_mut("voxel_shader").unwrap();
+        let voxel_shader = shaders.get_mut("voxel_shader").unwrap();
         voxel_shader.use_program();
         voxel_shader.set_uniform1i("array_texture", 0);
 
-        let (r, g, b, a) = BACKGROUND_COLOR;
         voxel_shader.set_uniform1i("enable_fog", ENABLE_FOG as i32);
-        voxel_shader.set_uniform3f("sky_color", &[r, g, b]);
+        voxel_shader.set_uniform3f("fog_color", world_time.fog_color().as_slice());
+        voxel_shader.set_uniform1f("skylight_multiplier", world_time.skylight_multiplier());
         voxel_shader.set_uniform1f("render_distance", RENDER_DISTANCE as f32);
 
-        gl_call!(gl::ClearColor(r, g, b, a));
-        gl_call!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
-        gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
-
-        for player_state in (&player_state).join() {
+        for (player_state, transform) in (&player_state, &transform).join() {
             unsafe {
                 voxel_shader.set_uniform_matrix4fv("view", player_state.view_matrix.as_ptr());
                 voxel_shader
                     .set_uniform_matrix4fv("projection", player_state.projection_matrix.as_ptr());
             }
 
-            chunk_manager.render_loaded_chunks(&mut voxel_shader);
+            let frustum =
+                Frustum::from_matrix(&(player_state.projection_matrix * player_state.view_matrix));
+
+            // The chunks hidden behind the others aren't drawn
+            let camera_position = transform.position
+                + vec3(
+                    0.0,
+                    *player_state.camera_height.get_interpolated_state(),
+                    0.0,
+                );
+            let camera_chunk = (
+                (camera_position.x / CHUNK_SIZE as f32).floor() as i32,
+                (camera_position.y / CHUNK_SIZE as f32).floor() as i32,
+                (camera_position.z / CHUNK_SIZE as f32).floor() as i32,
+            );
+            let visible_chunks = chunk_manager.find_visible_chunks(camera_chunk, &frustum);
+
+            *render_stats = chunk_manager.render_loaded_chunks(
+                &chunk_arena,
+                &frustum,
+                visible_chunks.as_ref(),
+                &camera_position,
+            );
+        }
+    }
+}
+
+// Draws the dropped items as small spinning blocks floating above the ground
+pub struct RenderDroppedItems {
+    item_renders: HashMap<BlockID, ItemRender>,
+}
+
+impl Default for RenderDroppedItems {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderDroppedItems {
+    pub fn new() -> Self {
+        Self {
+            item_renders: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> System<'a> for RenderDroppedItems {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, TexturePack>,
+        Write<'a, Shaders>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, DroppedItem>,
+        ReadStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (global_timer, texture_pack, mut shaders, player_state, dropped_item, transform) = data;
+
+        let hand_shader = shaders.get_mut("hand_shader").unwrap();
+
+        for player_state in (&player_state).join() {
+            for (dropped_item, transform) in (&dropped_item, &transform).join() {
+                let item = dropped_item.item_stack.item;
+                let item_render = self.item_renders.entry(item).or_insert_with(|| {
+                    let mut item_render = ItemRender::new();
+                    item_render.update_vbo(item, &texture_pack);
+
+                    item_render
+                });
+
+                let age = dropped_item.age(global_timer.time()).as_secs_f32();
+                let model_matrix = {
+                    let bobbing = 0.05 * ((age * 2.0).sin() + 1.0);
+                    let translate_matrix = Matrix4::new_translation(
+                        &(transform.position + vec3(0.0, DROPPED_ITEM_SIZE / 2.0 + bobbing, 0.0)),
+                    );
+                    let rotate_matrix = nalgebra_glm::rotation(age, &vec3(0.0, 1.0, 0.0));
+                    let scale_matrix = Matrix4::new_scaling(DROPPED_ITEM_SIZE);
+
+                    translate_matrix * rotate_matrix * scale_matrix
+                };
+
+                item_render.draw_in_world(
+                    &model_matrix,
+                    &player_state.view_matrix,
+                    &player_state.projection_matrix,
+                    hand_shader,
+                );
+            }
         }
     }
 }
@@ -127,10 +307,69 @@ impl<'a> System<'a> for RenderBlockOutline {
     }
 }
 
-pub(crate) struct RenderGUI {
+// Crack drawn on the block that the player is breaking
+pub struct RenderBlockBreaking {
+    vao: u32,
+}
+
+impl RenderBlockBreaking {
+    pub fn new() -> Self {
+        Self {
+            vao: create_block_crack_vao(),
+        }
+    }
+}
+
+impl Default for RenderBlockBreaking {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> System<'a> for RenderBlockBreaking {
+    type SystemData = (ReadStorage<'a, PlayerState>, Write<'a, Shaders>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_state, mut shaders) = data;
+
+        for player_state in (&player_state).join() {
+            if let Some(block_breaking) = &player_state.block_breaking {
+                let (x, y, z) = block_breaking.coords;
+                let model_matrix = Matrix4::new_translation(&vec3(x as f32, y as f32, z as f32));
+
+                let crack_shader = shaders.get_mut("crack_shader").unwrap();
+                crack_shader.use_program();
+                unsafe {
+                    crack_shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+                    crack_shader.set_uniform_matrix4fv("view", player_state.view_matrix.as_ptr());
+                    crack_shader.set_uniform_matrix4fv(
+                        "projection",
+                        player_state.projection_matrix.as_ptr(),
+                    );
+                }
+                crack_shader.set_uniform1i("stage", block_breaking.stage() as i32);
+                crack_shader.set_uniform1i("stages", BREAK_STAGES as i32);
+                crack_shader.set_uniform1i("tex", 4);
+
+                gl_call!(gl::BindVertexArray(self.vao));
+                gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 36));
+            }
+        }
+    }
+}
+
+pub struct RenderGUI {
     crosshair_vao: u32,
     hotbar_vao: u32,
     hotbar_selection_vao: u32,
+    slot_vao: u32,
+    hotbar_item_renders: Vec<ItemRender>,
+    // One per slot of the inventory screen, with the largest crafting grid, and one for the stack
+    // carried by the mouse
+    inventory_item_renders: Vec<ItemRender>,
+    digit_vaos: [u32; 10],
+    heart_vaos: [u32; 3],
+    food_vaos: [u32; 3],
 }
 
 impl RenderGUI {
@@ -139,6 +378,14 @@ impl RenderGUI {
             crosshair_vao: create_crosshair_vao(),
             hotbar_vao: create_hotbar_vao(),
             hotbar_selection_vao: create_hotbar_selection_vao(),
+            slot_vao: create_slot_vao(),
+            hotbar_item_renders: (0..HOTBAR_SIZE).map(|_| ItemRender::new()).collect(),
+            inventory_item_renders: (0..INVENTORY_SIZE + MAX_CRAFTING_GRID_SIZE.pow(2) + 2)
+                .map(|_| ItemRender::new())
+                .collect(),
+            digit_vaos: create_digit_vaos(),
+            heart_vaos: create_status_icon_vaos(0),
+            food_vaos: create_status_icon_vaos(27),
         }
     }
 }
@@ -146,25 +393,94 @@ impl RenderGUI {
 impl<'a> System<'a> for RenderGUI {
     type SystemData = (
         Read<'a, TexturePack>,
+        Read<'a, InputCache>,
         Write<'a, Shaders>,
-        WriteStorage<'a, Inventory>,
+        ReadStorage<'a, Inventory>,
+        ReadStorage<'a, InventoryScreen>,
+        ReadStorage<'a, GameMode>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Hunger>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (texture_pack, mut shaders, mut inventory) = data;
+        let (
+            texture_pack,
+            input_cache,
+            mut shaders,
+            inventory,
+            inventory_screen,
+            game_mode,
+            health,
+            hunger,
+        ) = data;
 
-        for inventory in (&mut inventory).join() {
+        for (inventory, inventory_screen, game_mode, health, hunger) in (
+            &inventory,
+            inventory_screen.maybe(),
+            game_mode.maybe(),
+            health.maybe(),
+            hunger.maybe(),
+        )
+            .join()
+        {
             let mut gui_shader = shaders.get_mut("gui_shader").unwrap();
             draw_crosshair(self.crosshair_vao, &mut gui_shader);
             gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
 
             gl_call!(gl::Disable(gl::DEPTH_TEST));
-            inventory.update_dirty_items(&texture_pack);
             inventory.draw_hotbar(self.hotbar_vao, &mut gui_shader);
             inventory.draw_hotbar_selection_box(self.hotbar_selection_vao, &mut gui_shader);
 
+            if game_mode
+                .copied()
+                .unwrap_or(GameMode::FOR_NON_PLAYERS)
+                .has_vitals()
+            {
+                if let Some(health) = health {
+                    draw_status_bar(&self.heart_vaos, health.points, false, gui_shader);
+                }
+
+                if let Some(hunger) = hunger {
+                    draw_status_bar(&self.food_vaos, hunger.food, true, gui_shader);
+                }
+            }
+
             let mut item_shader = shaders.get_mut("item_shader").unwrap();
-            inventory.draw_hotbar_items(&mut item_shader);
+            inventory.draw_hotbar_items(
+                &mut self.hotbar_item_renders,
+                &texture_pack,
+                &mut item_shader,
+            );
+
+            let gui_shader = shaders.get_mut("gui_shader").unwrap();
+            inventory.draw_hotbar_item_amounts(&self.digit_vaos, gui_shader);
+
+            if let Some(inventory_screen) =
+                inventory_screen.filter(|inventory_screen| inventory_screen.is_open)
+            {
+                let cursor = cursor_position(&input_cache);
+
+                let gui_shader = shaders.get_mut("gui_shader").unwrap();
+                inventory_screen.draw_slots(
+                    self.hotbar_vao,
+                    self.hotbar_selection_vao,
+                    self.slot_vao,
+                    cursor,
+                    gui_shader,
+                );
+
+                let item_shader = shaders.get_mut("item_shader").unwrap();
+                inventory_screen.draw_items(
+                    inventory,
+                    &mut self.inventory_item_renders,
+                    cursor,
+                    &texture_pack,
+                    item_shader,
+                );
+
+                let gui_shader = shaders.get_mut("gui_shader").unwrap();
+                inventory_screen.draw_item_amounts(inventory, &self.digit_vaos, cursor, gui_shader);
+            }
             gl_call!(gl::Enable(gl::DEPTH_TEST));
         }
     }
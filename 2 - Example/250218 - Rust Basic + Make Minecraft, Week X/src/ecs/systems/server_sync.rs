@@ -0,0 +1,145 @@
+use crate::chunk_manager::BlockChange;
+use crate::constants::{RENDER_DISTANCE, REQUESTED_COLUMN_DISTANCE};
+use crate::inventory::item::ItemStack;
+use crate::inventory::{Inventory, INVENTORY_SIZE};
+use crate::network::client::Client;
+use crate::network::protocol::{is_column_in_range, player_column};
+use crate::physics::Transform;
+use crate::player::PlayerState;
+use crossbeam_channel::Receiver;
+use nalgebra_glm::Vec3;
+use specs::{Join, ReadStorage, System};
+use std::collections::HashSet;
+use std::io;
+use std::sync::Arc;
+
+// Drives the world of a client connected to a server: the columns around the player are
+// requested from the server, and what the player does is sent to it. The blocks changed by the
+// player are applied right away and sent to the server, which broadcasts them back.
+pub struct SyncWithServer {
+    client: Client,
+    block_changes_rx: Receiver<BlockChange>,
+    requested_columns: HashSet<(i32, i32)>,
+    // Last position and rotation sent
+    last_move: Option<(Vec3, Vec3)>,
+    // Last selected slot and slots sent
+    last_inventory: Option<(usize, [Option<ItemStack>; INVENTORY_SIZE])>,
+}
+
+impl SyncWithServer {
+    pub fn new(client: Client) -> Self {
+        Self {
+            block_changes_rx: client.chunk_manager.subscribe_to_block_changes(),
+            client,
+            requested_columns: HashSet::new(),
+            last_move: None,
+            last_inventory: None,
+        }
+    }
+
+    fn sync(
+        &mut self,
+        transform: &Transform,
+        player_state: &PlayerState,
+        inventory: &Inventory,
+    ) -> io::Result<()> {
+        // The position first, the server checks the reach of the block changes with it
+        let player_move = (transform.position, player_state.rotation);
+
+        if self.last_move != Some(player_move) {
+            self.client.move_player(player_move.0, player_move.1)?;
+            self.last_move = Some(player_move);
+        }
+
+        // Everything changed since the last update was done by the player
+        for block_change in self.block_changes_rx.try_iter() {
+            self.client.put_block(
+                block_change.x,
+                block_change.y,
+                block_change.z,
+                block_change.block,
+            )?;
+
+            // The server takes the placed blocks from its copy of the inventory, which is sent
+            // again even if it didn't change here (e.g. in creative)
+            if !block_change.block.is_air() {
+                self.last_inventory = None;
+            }
+        }
+
+        let inventory_state = (inventory.selected_hotbar_slot, inventory.slots);
+
+        if self.last_inventory != Some(inventory_state) {
+            self.client.sync_inventory(inventory)?;
+            self.last_inventory = Some(inventory_state);
+        }
+
+        // The changes applied by the client come from the server, they are not sent back
+        self.client.update()?;
+        self.block_changes_rx.try_iter().for_each(drop);
+
+        let chunk_manager = Arc::clone(&self.client.chunk_manager);
+        let center = player_column(&transform.position);
+
+        // Forget the columns left behind
+        let far_columns: Vec<_> = chunk_manager
+            .loaded_chunk_columns
+            .read()
+            .keys()
+            .filter(|&&(x, z)| {
+                (x - center.0).abs() > RENDER_DISTANCE + 2
+                    || (z - center.1).abs() > RENDER_DISTANCE + 2
+            })
+            .copied()
+            .collect();
+
+        for xz in far_columns {
+            chunk_manager.remove_chunk_column(&xz);
+        }
+
+        self.requested_columns.retain(|&(x, z)| {
+            is_column_in_range(center, x, z) && chunk_manager.get_column(x, z).is_none()
+        });
+
+        // Request the missing columns, the closest first
+        let mut missing_columns = Vec::new();
+
+        for x in center.0 - REQUESTED_COLUMN_DISTANCE..=center.0 + REQUESTED_COLUMN_DISTANCE {
+            for z in center.1 - REQUESTED_COLUMN_DISTANCE..=center.1 + REQUESTED_COLUMN_DISTANCE {
+                if !self.requested_columns.contains(&(x, z))
+                    && chunk_manager.get_column(x, z).is_none()
+                {
+                    missing_columns.push((x, z));
+                }
+            }
+        }
+
+        missing_columns.sort_by_key(|&(x, z)| (x - center.0).abs() + (z - center.1).abs());
+
+        for (x, z) in missing_columns {
+            self.client.request_chunk_column(x, z)?;
+            self.requested_columns.insert((x, z));
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> System<'a> for SyncWithServer {
+    type SystemData = (
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Inventory>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (transform, player_state, inventory) = data;
+
+        for (transform, player_state, inventory) in (&transform, &player_state, &inventory).join() {
+            if let Err(err) = self.sync(transform, player_state, inventory) {
+                error!("Lost the connection to the server: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::game_mode::GameMode;
+use crate::physics::{Interpolator, PhysicsBody, Transform};
+use crate::player::{player_physics_body, PlayerState};
+use crate::timer::Timer;
+use crate::vitals::{update_hunger, update_void_damage, Health, Hunger, SpawnPoint};
+use specs::{Entities, Join, LendJoin, Read, ReadExpect, ReadStorage, System, WriteStorage};
+use std::time::Instant;
+
+// Hurts and heals the entities with a health, respawns the dead players at the spawn point and
+// despawns the other dead entities. The fall damage and the exhaustion are done by `UpdatePhysics`.
+#[derive(Default)]
+pub struct UpdateVitals {
+    last_update: Option<Instant>,
+}
+
+impl UpdateVitals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for UpdateVitals {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Timer>,
+        ReadExpect<'a, SpawnPoint>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, Hunger>,
+        WriteStorage<'a, Interpolator<PhysicsBody>>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, PlayerState>,
+        ReadStorage<'a, GameMode>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            global_timer,
+            spawn_point,
+            mut health,
+            mut hunger,
+            mut physics_body,
+            mut transform,
+            mut player_state,
+            game_mode,
+        ) = data;
+
+        let time = global_timer.time();
+        let dt = self.last_update.map_or(0.0, |last_update| {
+            time.saturating_duration_since(last_update).as_secs_f32()
+        });
+        self.last_update = Some(time);
+
+        for (entity, health, mut hunger, physics_body, transform, player_state, game_mode) in (
+            &entities,
+            &mut health,
+            (&mut hunger).maybe(),
+            &mut physics_body,
+            &mut transform,
+            (&mut player_state).maybe(),
+            game_mode.maybe(),
+        )
+            .join()
+        {
+            let game_mode = game_mode.copied().unwrap_or(GameMode::FOR_NON_PLAYERS);
+
+            // Even the players in creative die in the void, but not the spectators
+            let is_in_void = transform.position.y < 0.0 && !game_mode.is_spectating();
+            update_void_damage(health, is_in_void, dt);
+
+            if let Some(hunger) = hunger.as_mut().filter(|_| game_mode.has_vitals()) {
+                update_hunger(health, hunger, dt);
+            }
+
+            if !health.is_dead() {
+                continue;
+            }
+
+            let Some(player_state) = player_state else {
+                if let Err(err) = entities.delete(entity) {
+                    error!("{err}");
+                }
+                continue;
+            };
+
+            info!("A player died, respawning at {:?}", spawn_point.0);
+
+            let body = player_physics_body(spawn_point.0);
+            physics_body.previous_state = body.clone();
+            physics_body.current_state = body.clone();
+            physics_body.interpolated_state = body;
+            transform.position = spawn_point.0;
+
+            *health = Health::new();
+
+            if let Some(hunger) = hunger.as_mut() {
+                **hunger = Hunger::new();
+            }
+
+            player_state.is_flying = false;
+            player_state.block_breaking = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{MAX_FOOD, MAX_HEALTH};
+    use nalgebra_glm::vec3;
+    use specs::{Builder, RunNow, World, WorldExt};
+    use std::time::Duration;
+
+    fn vitals_world() -> World {
+        let mut world = World::new();
+        world.register::<Health>();
+        world.register::<Hunger>();
+        world.register::<Interpolator<PhysicsBody>>();
+        world.register::<Transform>();
+        world.register::<PlayerState>();
+        world.register::<GameMode>();
+        world.insert(Timer::default());
+        world.insert(SpawnPoint(vec3(8.0, 100.0, 8.0)));
+        world
+    }
+
+    // Three seconds in the void are enough to die
+    fn run_for_three_seconds(world: &World) {
+        let time = world.read_resource::<Timer>().time();
+        let mut system = UpdateVitals {
+            last_update: Some(time - Duration::from_secs(3)),
+        };
+        system.run_now(world);
+    }
+
+    #[test]
+    fn dead_players_respawn_at_the_spawn_point() {
+        let mut world = vitals_world();
+
+        let position = vec3(8.0, -10.0, 8.0);
+        let mut hunger = Hunger::new();
+        hunger.food = 3;
+        let player = world
+            .create_entity()
+            .with(PlayerState::new())
+            .with(Transform::new(position))
+            .with(Interpolator::new(1.0 / 60.0, player_physics_body(position)))
+            .with(Health::new())
+            .with(hunger)
+            .build();
+
+        run_for_three_seconds(&world);
+
+        let health = world.read_storage::<Health>();
+        let hunger = world.read_storage::<Hunger>();
+        let physics_bodies = world.read_storage::<Interpolator<PhysicsBody>>();
+        let transform = world.read_storage::<Transform>();
+
+        assert_eq!(health.get(player).unwrap().points, MAX_HEALTH);
+        assert_eq!(hunger.get(player).unwrap().food, MAX_FOOD);
+        assert_eq!(
+            transform.get(player).unwrap().position,
+            vec3(8.0, 100.0, 8.0)
+        );
+        assert_eq!(
+            physics_bodies
+                .get(player)
+                .unwrap()
+                .get_latest_state()
+                .position(),
+            vec3(8.0, 100.0, 8.0)
+        );
+    }
+
+    #[test]
+    fn dead_entities_which_are_not_players_are_despawned() {
+        let mut world = vitals_world();
+
+        let position = vec3(8.0, -10.0, 8.0);
+        let entity = world
+            .create_entity()
+            .with(Transform::new(position))
+            .with(Interpolator::new(1.0 / 60.0, player_physics_body(position)))
+            .with(Health::new())
+            .build();
+
+        run_for_three_seconds(&world);
+        world.maintain();
+
+        assert!(!world.is_alive(entity));
+    }
+}
@@ -0,0 +1,408 @@
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+
+// A fluid block has a level: 0 for a source, which never disappears by itself, and up to
+// `MAX_FLUID_LEVEL` for the flowing blocks, the higher the further from the source
+pub const MAX_FLUID_LEVEL: u8 = 7;
+
+const HORIZONTAL_NEIGHBOURS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Fluid {
+    Water,
+    Lava,
+}
+
+impl Fluid {
+    pub fn from_block(block: BlockID) -> Option<Fluid> {
+        match block {
+            BlockID::WATER => Some(Fluid::Water),
+            BlockID::LAVA => Some(Fluid::Lava),
+            _ => None,
+        }
+    }
+
+    pub fn block(self) -> BlockID {
+        match self {
+            Fluid::Water => BlockID::WATER,
+            Fluid::Lava => BlockID::LAVA,
+        }
+    }
+
+    // Levels lost by each block of horizontal flow
+    fn level_step(self) -> u8 {
+        match self {
+            Fluid::Water => 1,
+            Fluid::Lava => 2,
+        }
+    }
+
+    // Ticks between a change next to the fluid and its update
+    pub fn tick_delay(self) -> u32 {
+        match self {
+            Fluid::Water => 5,
+            Fluid::Lava => 30,
+        }
+    }
+}
+
+// Height of the surface of a fluid block that has no fluid on top of it, between 0 and 1
+pub fn fluid_surface_height(level: u8) -> f32 {
+    (MAX_FLUID_LEVEL + 1 - level.min(MAX_FLUID_LEVEL)) as f32 / (MAX_FLUID_LEVEL + 2) as f32
+}
+
+fn fluid_at(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> Option<(Fluid, u8)> {
+    let fluid = Fluid::from_block(chunk_manager.get_block(x, y, z)?)?;
+
+    Some((fluid, chunk_manager.get_fluid_level(x, y, z)?))
+}
+
+// A fluid falls into the air, into the flowing blocks of the same fluid, and lava falls into
+// water (which turns into stone)
+fn can_flow_down(chunk_manager: &ChunkManager, fluid: Fluid, x: i32, y: i32, z: i32) -> bool {
+    match fluid_at(chunk_manager, x, y - 1, z) {
+        Some((below, level)) if below == fluid => level > 0,
+        Some((below, _)) => fluid == Fluid::Lava && below == Fluid::Water,
+        None => chunk_manager.get_block(x, y - 1, z) == Some(BlockID::AIR),
+    }
+}
+
+// Level that a flowing block should have given the blocks around it, `None` if nothing feeds it
+fn fed_level(chunk_manager: &ChunkManager, fluid: Fluid, x: i32, y: i32, z: i32) -> Option<u8> {
+    // Falling fluid
+    if matches!(fluid_at(chunk_manager, x, y + 1, z), Some((above, _)) if above == fluid) {
+        return Some(1);
+    }
+
+    let mut sources = 0;
+    let mut lowest_level = None;
+
+    for (dx, dz) in HORIZONTAL_NEIGHBOURS {
+        let (neighbour_x, neighbour_z) = (x + dx, z + dz);
+
+        match fluid_at(chunk_manager, neighbour_x, y, neighbour_z) {
+            Some((neighbour, level))
+                if neighbour == fluid
+                    && !can_flow_down(chunk_manager, fluid, neighbour_x, y, neighbour_z) =>
+            {
+                sources += (level == 0) as u32;
+                lowest_level = Some(lowest_level.map_or(level, |lowest: u8| lowest.min(level)));
+            }
+            _ => {}
+        }
+    }
+
+    // Water between two sources becomes a source when it rests on something
+    if fluid == Fluid::Water && sources >= 2 {
+        let is_resting = match chunk_manager.get_block(x, y - 1, z) {
+            Some(BlockID::WATER) => chunk_manager.get_fluid_level(x, y - 1, z) == Some(0),
+            Some(block) => block.is_solid(),
+            None => false,
+        };
+
+        if is_resting {
+            return Some(0);
+        }
+    }
+
+    lowest_level
+        .map(|level| level + fluid.level_step())
+        .filter(|&level| level <= MAX_FLUID_LEVEL)
+}
+
+// Applies the flow rules to the fluid at (x, y, z), it is run by the scheduled ticks of
+// `BlockTicks`. The changes are made with `put_block`, which schedules the next updates and
+// sends them to the players.
+pub fn update_fluid(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) {
+    let (fluid, mut level) = match fluid_at(chunk_manager, x, y, z) {
+        Some(fluid) => fluid,
+        None => return,
+    };
+
+    // Lava touching water hardens
+    if fluid == Fluid::Lava
+        && [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, 0, 1), (0, 0, -1)]
+            .iter()
+            .any(|(dx, dy, dz)| {
+                chunk_manager.get_block(x + dx, y + dy, z + dz) == Some(BlockID::WATER)
+            })
+    {
+        let block = if level == 0 {
+            BlockID::OBSIDIAN
+        } else {
+            BlockID::COBBLESTONE
+        };
+        chunk_manager.put_block(x, y, z, block);
+
+        return;
+    }
+
+    // Flowing blocks follow the blocks that feed them
+    if level > 0 {
+        match fed_level(chunk_manager, fluid, x, y, z) {
+            Some(fed_level) if fed_level == level => {}
+            Some(fed_level) => {
+                chunk_manager.put_block_with_level(x, y, z, fluid.block(), fed_level);
+                level = fed_level;
+            }
+            None => {
+                chunk_manager.put_block(x, y, z, BlockID::AIR);
+                return;
+            }
+        }
+    }
+
+    if can_flow_down(chunk_manager, fluid, x, y, z) {
+        match fluid_at(chunk_manager, x, y - 1, z) {
+            Some((Fluid::Water, _)) if fluid == Fluid::Lava => {
+                chunk_manager.put_block(x, y - 1, z, BlockID::STONE);
+            }
+            Some((_, 1)) => {}
+            _ => {
+                chunk_manager.put_block_with_level(x, y - 1, z, fluid.block(), 1);
+            }
+        }
+
+        return;
+    }
+
+    let spread_level = level + fluid.level_step();
+
+    if spread_level > MAX_FLUID_LEVEL {
+        return;
+    }
+
+    for (dx, dz) in HORIZONTAL_NEIGHBOURS {
+        let (x, z) = (x + dx, z + dz);
+
+        match chunk_manager.get_block(x, y, z) {
+            Some(BlockID::AIR) => {
+                chunk_manager.put_block_with_level(x, y, z, fluid.block(), spread_level);
+            }
+            Some(BlockID::WATER) if fluid == Fluid::Lava => {
+                chunk_manager.put_block(x, y, z, BlockID::STONE);
+            }
+            Some(block)
+                if block == fluid.block()
+                    && chunk_manager
+                        .get_fluid_level(x, y, z)
+                        .is_some_and(|level| level > spread_level) =>
+            {
+                chunk_manager.put_block_with_level(x, y, z, fluid.block(), spread_level);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_ticks::BlockTicks;
+    use crate::test_support::flat_world;
+
+    fn run_until_still(block_ticks: &mut BlockTicks, chunk_manager: &ChunkManager) {
+        for _ in 0..2000 {
+            block_ticks.tick(chunk_manager);
+
+            if !block_ticks.has_scheduled_ticks(chunk_manager) {
+                return;
+            }
+        }
+
+        panic!("The fluids never stopped moving");
+    }
+
+    fn block_and_level(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> (BlockID, u8) {
+        (
+            chunk_manager.get_block(x, y, z).unwrap(),
+            chunk_manager.get_fluid_level(x, y, z).unwrap(),
+        )
+    }
+
+    #[test]
+    fn water_spreads_from_a_source() {
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+
+        chunk_manager.put_block(0, 1, 0, BlockID::WATER);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        assert_eq!(
+            block_and_level(&chunk_manager, 0, 1, 0),
+            (BlockID::WATER, 0)
+        );
+
+        // The level grows by one per block of distance (in the taxicab geometry)
+        for distance in 1..=MAX_FLUID_LEVEL as i32 {
+            for (x, z) in [(distance, 0), (0, -distance), (distance - 1, 1)] {
+                assert_eq!(
+                    block_and_level(&chunk_manager, x, 1, z),
+                    (BlockID::WATER, (x.abs() + z.abs()) as u8)
+                );
+            }
+        }
+
+        assert!(chunk_manager.get_block(8, 1, 0).unwrap().is_air());
+        assert!(chunk_manager.get_block(4, 1, 4).unwrap().is_air());
+        assert!(chunk_manager.get_block(0, 2, 0).unwrap().is_air());
+    }
+
+    #[test]
+    fn lava_spreads_slower_and_less_than_water() {
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+
+        chunk_manager.put_block(0, 1, 0, BlockID::LAVA);
+        chunk_manager.put_block(10, 1, 0, BlockID::WATER);
+
+        for _ in 0..Fluid::Lava.tick_delay() + 1 {
+            block_ticks.tick(&chunk_manager);
+        }
+
+        assert!(chunk_manager.get_block(-1, 1, 0) == Some(BlockID::LAVA));
+        assert!(chunk_manager.get_block(-2, 1, 0).unwrap().is_air());
+        assert!(chunk_manager.get_block(10 + 4, 1, 0) == Some(BlockID::WATER));
+
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        assert_eq!(
+            block_and_level(&chunk_manager, -3, 1, 0),
+            (BlockID::LAVA, 6)
+        );
+        assert!(chunk_manager.get_block(-4, 1, 0).unwrap().is_air());
+    }
+
+    #[test]
+    fn fluids_fall_and_spread_at_the_bottom() {
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+
+        // A source on a pillar
+        for y in 1..=4 {
+            chunk_manager.put_block(0, y, 0, BlockID::STONE);
+        }
+
+        chunk_manager.put_block(0, 5, 0, BlockID::WATER);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        // It flows down on each side of the pillar, the falling blocks have the level 1
+        assert_eq!(
+            block_and_level(&chunk_manager, 1, 5, 0),
+            (BlockID::WATER, 1)
+        );
+        assert_eq!(
+            block_and_level(&chunk_manager, 1, 3, 0),
+            (BlockID::WATER, 1)
+        );
+        assert_eq!(
+            block_and_level(&chunk_manager, 1, 1, 0),
+            (BlockID::WATER, 1)
+        );
+        assert_eq!(
+            block_and_level(&chunk_manager, 2, 1, 0),
+            (BlockID::WATER, 2)
+        );
+        assert!(chunk_manager.get_block(2, 5, 0).unwrap().is_air());
+    }
+
+    #[test]
+    fn flowing_water_dries_up_without_its_source() {
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+
+        chunk_manager.put_block(0, 1, 0, BlockID::WATER);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        chunk_manager.put_block(0, 1, 0, BlockID::AIR);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        for x in -8..=8 {
+            for z in -8..=8 {
+                assert!(chunk_manager.get_block(x, 1, z).unwrap().is_air());
+            }
+        }
+    }
+
+    #[test]
+    fn water_between_two_sources_becomes_a_source() {
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+
+        chunk_manager.put_block(0, 1, 0, BlockID::WATER);
+        chunk_manager.put_block(2, 1, 0, BlockID::WATER);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        assert_eq!(
+            block_and_level(&chunk_manager, 1, 1, 0),
+            (BlockID::WATER, 0)
+        );
+
+        // Removing one of the first sources doesn't dry anything up
+        chunk_manager.put_block(0, 1, 0, BlockID::AIR);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        assert_eq!(
+            block_and_level(&chunk_manager, 0, 1, 0),
+            (BlockID::WATER, 1)
+        );
+        assert_eq!(
+            block_and_level(&chunk_manager, 2, 1, 0),
+            (BlockID::WATER, 0)
+        );
+    }
+
+    #[test]
+    fn lava_hardens_when_it_meets_water() {
+        // Water flowing next to a lava source makes obsidian
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+
+        chunk_manager.put_block(0, 1, 0, BlockID::LAVA);
+        chunk_manager.put_block(2, 1, 0, BlockID::WATER);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        assert_eq!(chunk_manager.get_block(0, 1, 0), Some(BlockID::OBSIDIAN));
+
+        // Water flowing next to flowing lava makes cobblestone
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+
+        chunk_manager.put_block(0, 1, 0, BlockID::LAVA);
+        run_until_still(&mut block_ticks, &chunk_manager);
+        assert_eq!(block_and_level(&chunk_manager, 0, 1, 3), (BlockID::LAVA, 6));
+
+        chunk_manager.put_block(0, 1, 5, BlockID::WATER);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        assert_eq!(chunk_manager.get_block(0, 1, 3), Some(BlockID::COBBLESTONE));
+        assert_eq!(
+            block_and_level(&chunk_manager, 0, 1, 4),
+            (BlockID::WATER, 1)
+        );
+
+        // Lava falling on water makes stone
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        let mut block_ticks = BlockTicks::new(&chunk_manager, 0);
+
+        chunk_manager.put_block(0, 1, 0, BlockID::WATER);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        chunk_manager.put_block(3, 3, 0, BlockID::LAVA);
+        run_until_still(&mut block_ticks, &chunk_manager);
+
+        assert_eq!(chunk_manager.get_block(3, 1, 0), Some(BlockID::STONE));
+        assert_eq!(chunk_manager.get_block(3, 3, 0), Some(BlockID::LAVA));
+    }
+
+    #[test]
+    fn surface_height_goes_down_with_the_level() {
+        assert!(fluid_surface_height(0) < 1.0);
+
+        for level in 0..MAX_FLUID_LEVEL {
+            assert!(fluid_surface_height(level + 1) < fluid_surface_height(level));
+        }
+
+        assert!(fluid_surface_height(MAX_FLUID_LEVEL) > 0.0);
+    }
+}
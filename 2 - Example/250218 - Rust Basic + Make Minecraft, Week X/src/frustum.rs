@@ -0,0 +1,138 @@
+use crate::aabb::AABB;
+use nalgebra_glm::{vec3, Mat4, Vec3};
+
+// Points p with `normal.dot(p) + distance >= 0` are on the inner side of the plane
+#[derive(Copy, Clone, Debug)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl Plane {
+    fn new(a: f32, b: f32, c: f32, d: f32) -> Self {
+        let normal = vec3(a, b, c);
+        let length = normal.norm();
+
+        Self {
+            normal: normal / length,
+            distance: d / length,
+        }
+    }
+
+    pub fn signed_distance(&self, point: &Vec3) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+}
+
+// Volume seen by the camera, bounded by the left, right, bottom, top, near and far planes
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    // Extracts the planes from the rows of `projection * view` (Gribb and Hartmann)
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let plane = |p: nalgebra_glm::Vec4| Plane::new(p.x, p.y, p.z, p.w);
+
+        Self {
+            planes: [
+                plane(w + x),
+                plane(w - x),
+                plane(w + y),
+                plane(w - y),
+                plane(w + z),
+                plane(w - z),
+            ],
+        }
+    }
+
+    // Conservative: a box outside of the frustum but across the extension of two of its planes
+    // is kept
+    pub fn intersects_aabb(&self, aabb: &AABB) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner the furthest along the normal
+            let corner = vec3(
+                if plane.normal.x >= 0.0 {
+                    aabb.maxs.x
+                } else {
+                    aabb.mins.x
+                },
+                if plane.normal.y >= 0.0 {
+                    aabb.maxs.y
+                } else {
+                    aabb.mins.y
+                },
+                if plane.normal.z >= 0.0 {
+                    aabb.maxs.z
+                } else {
+                    aabb.mins.z
+                },
+            );
+
+            plane.signed_distance(&corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+
+    // Camera at `position` looking towards `target`, with a 90° field of view
+    fn frustum(position: Vec3, target: Vec3) -> Frustum {
+        let view = nalgebra_glm::look_at(&position, &target, &Vector3::y());
+        let projection = nalgebra_glm::perspective(1.0, 90.0f32.to_radians(), 0.1, 100.0);
+
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    fn cube(x: f32, y: f32, z: f32, size: f32) -> AABB {
+        AABB::new(vec3(x, y, z), vec3(x + size, y + size, z + size))
+    }
+
+    #[test]
+    fn planes_are_normalized() {
+        let frustum = frustum(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0));
+
+        for plane in &frustum.planes {
+            assert!((plane.normal.norm() - 1.0).abs() < 1e-4);
+        }
+
+        // The near plane is 0.1 in front of the camera
+        let near = &frustum.planes[4];
+        assert!((near.signed_distance(&vec3(0.0, 0.0, -0.1))).abs() < 1e-3);
+    }
+
+    #[test]
+    fn only_the_boxes_in_front_of_the_camera_are_visible() {
+        let frustum = frustum(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0));
+
+        // In front, behind, beyond the far plane
+        assert!(frustum.intersects_aabb(&cube(-1.0, -1.0, -10.0, 2.0)));
+        assert!(!frustum.intersects_aabb(&cube(-1.0, -1.0, 10.0, 2.0)));
+        assert!(!frustum.intersects_aabb(&cube(-1.0, -1.0, -120.0, 2.0)));
+
+        // Outside of the field of view on each side, and across its edge
+        assert!(!frustum.intersects_aabb(&cube(20.0, -1.0, -10.0, 2.0)));
+        assert!(!frustum.intersects_aabb(&cube(-22.0, -1.0, -10.0, 2.0)));
+        assert!(!frustum.intersects_aabb(&cube(-1.0, 20.0, -10.0, 2.0)));
+        assert!(frustum.intersects_aabb(&cube(9.0, -1.0, -10.0, 2.0)));
+
+        // The camera is inside
+        assert!(frustum.intersects_aabb(&cube(-8.0, -8.0, -8.0, 16.0)));
+    }
+
+    #[test]
+    fn the_frustum_follows_the_camera() {
+        let frustum = frustum(vec3(100.0, 70.0, 100.0), vec3(101.0, 70.0, 100.0));
+
+        // The camera looks towards +X
+        assert!(frustum.intersects_aabb(&cube(112.0, 64.0, 96.0, 16.0)));
+        assert!(!frustum.intersects_aabb(&cube(80.0, 64.0, 96.0, 16.0)));
+        assert!(!frustum.intersects_aabb(&cube(96.0, 64.0, 130.0, 16.0)));
+    }
+}
@@ -0,0 +1,90 @@
+// Rules a player plays by, switched at runtime with F4
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    // Items are consumed, blocks take time to break, the player can be hurt and can't fly
+    #[default]
+    Survival,
+    // Infinite blocks, instant breaking and flight with a double press of space
+    Creative,
+    // Always flying through the blocks, without touching them
+    Spectator,
+}
+
+impl GameMode {
+    // Rules of the entities without a game mode, such as the dropped items
+    pub const FOR_NON_PLAYERS: GameMode = GameMode::Survival;
+
+    // Order of the switch with F4
+    pub fn next(self) -> Self {
+        match self {
+            GameMode::Survival => GameMode::Creative,
+            GameMode::Creative => GameMode::Spectator,
+            GameMode::Spectator => GameMode::Survival,
+        }
+    }
+
+    // Placing a block takes it from the inventory
+    pub fn consumes_items(self) -> bool {
+        self == GameMode::Survival
+    }
+
+    // Fall damage, hunger and the bars of the gui
+    pub fn has_vitals(self) -> bool {
+        self == GameMode::Survival
+    }
+
+    pub fn breaks_instantly(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    // Flight toggled with a double press of space
+    pub fn can_fly(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    // No-clip: the player goes through the blocks and can't stop flying
+    pub fn is_spectating(self) -> bool {
+        self == GameMode::Spectator
+    }
+
+    pub fn interacts_with_blocks(self) -> bool {
+        self != GameMode::Spectator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switching_cycles_through_every_mode() {
+        let mut game_mode = GameMode::default();
+        assert_eq!(game_mode, GameMode::Survival);
+
+        let mut seen = vec![];
+
+        for _ in 0..3 {
+            seen.push(game_mode);
+            game_mode = game_mode.next();
+        }
+
+        assert_eq!(game_mode, GameMode::Survival);
+        assert_eq!(
+            seen,
+            [GameMode::Survival, GameMode::Creative, GameMode::Spectator]
+        );
+    }
+
+    #[test]
+    fn only_survival_has_consequences() {
+        assert!(GameMode::Survival.consumes_items() && GameMode::Survival.has_vitals());
+        assert!(!GameMode::Survival.can_fly() && !GameMode::Survival.breaks_instantly());
+
+        assert!(!GameMode::Creative.consumes_items() && !GameMode::Creative.has_vitals());
+        assert!(GameMode::Creative.can_fly() && GameMode::Creative.breaks_instantly());
+        assert!(GameMode::Creative.interacts_with_blocks());
+
+        assert!(!GameMode::Spectator.can_fly() && GameMode::Spectator.is_spectating());
+        assert!(!GameMode::Spectator.interacts_with_blocks());
+    }
+}
@@ -1,6 +1,8 @@
-use crate::constants::{CROSSHAIR_SIZE, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::constants::{
+    BLOCK_TEXTURE_SIZE, BREAK_STAGES, CROSSHAIR_SIZE, GUI_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
 use crate::shader::ShaderProgram;
-use crate::shapes::{block_outline, quad};
+use crate::shapes::{block_outline, centered_unit_cube, quad};
 use image::ColorType;
 use nalgebra::Matrix4;
 use nalgebra_glm::vec3;
@@ -326,3 +328,503 @@ pub fn create_hotbar_selection_vao() -> u32 {
 
     vao
 }
+
+// One slot of the hotbar with its border, for the slots outside of the rows of the inventory
+pub fn create_slot_vao() -> u32 {
+    // Setup VAO
+    let mut vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+    // Position
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        0,
+        3,
+        gl::FLOAT,
+        gl::FALSE,
+        0
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+    // Texture coords
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        1,
+        2,
+        gl::FLOAT,
+        gl::FALSE,
+        3 * std::mem::size_of::<f32>() as u32
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+    // Setup VBO
+    let mut vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut vbo));
+
+    gl_call!(gl::VertexArrayVertexBuffer(
+        vao,
+        0,
+        vbo,
+        0,
+        (5 * std::mem::size_of::<f32>()) as i32
+    ));
+    gl_call!(gl::NamedBufferData(
+        vbo,
+        (30 * std::mem::size_of::<f32>()) as isize,
+        quad((0.0, 0.0, 22.0 / 256.0, 22.0 / 256.0)).as_ptr() as *const c_void,
+        gl::STATIC_DRAW
+    ));
+
+    vao
+}
+
+// Pixels of the digits 0 to 9 drawn on the item stacks, 3x5 pixels with one row per byte
+const DIGIT_GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+// Each digit has one more pixel on the right and at the bottom for its shadow
+const DIGIT_WIDTH: u32 = 4;
+const DIGIT_HEIGHT: u32 = 6;
+
+fn is_digit_pixel(digit: usize, x: u32, y: u32) -> bool {
+    x < 3 && y < 5 && DIGIT_GLYPHS[digit][y as usize] & (0b100 >> x) != 0
+}
+
+// The digits side by side, white with a dark shadow like the text of Minecraft
+pub fn create_digits_texture() -> u32 {
+    let width = 10 * DIGIT_WIDTH;
+    let mut pixels = Vec::with_capacity((width * DIGIT_HEIGHT * 4) as usize);
+
+    for y in 0..DIGIT_HEIGHT {
+        for x in 0..width {
+            let (digit, x) = ((x / DIGIT_WIDTH) as usize, x % DIGIT_WIDTH);
+
+            let pixel = if is_digit_pixel(digit, x, y) {
+                [255, 255, 255, 255]
+            } else if x > 0 && y > 0 && is_digit_pixel(digit, x - 1, y - 1) {
+                [63, 63, 63, 255]
+            } else {
+                [0, 0, 0, 0]
+            };
+
+            pixels.extend_from_slice(&pixel);
+        }
+    }
+
+    let mut id = 0;
+    gl_call!(gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id));
+    gl_call!(gl::TextureParameteri(
+        id,
+        gl::TEXTURE_MIN_FILTER,
+        gl::NEAREST as i32
+    ));
+    gl_call!(gl::TextureParameteri(
+        id,
+        gl::TEXTURE_MAG_FILTER,
+        gl::NEAREST as i32
+    ));
+    gl_call!(gl::TextureStorage2D(
+        id,
+        1,
+        gl::RGBA8,
+        width as i32,
+        DIGIT_HEIGHT as i32
+    ));
+    gl_call!(gl::TextureSubImage2D(
+        id,
+        0,
+        0,
+        0,
+        width as i32,
+        DIGIT_HEIGHT as i32,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        pixels.as_ptr() as *mut c_void
+    ));
+
+    id
+}
+
+// One quad per digit of the digits texture
+pub fn create_digit_vaos() -> [u32; 10] {
+    let mut vaos = [0; 10];
+
+    for (digit, vao) in vaos.iter_mut().enumerate() {
+        gl_call!(gl::CreateVertexArrays(1, vao));
+
+        // Position
+        gl_call!(gl::EnableVertexArrayAttrib(*vao, 0));
+        gl_call!(gl::VertexArrayAttribFormat(
+            *vao,
+            0,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            0
+        ));
+        gl_call!(gl::VertexArrayAttribBinding(*vao, 0, 0));
+
+        // Texture coords
+        gl_call!(gl::EnableVertexArrayAttrib(*vao, 1));
+        gl_call!(gl::VertexArrayAttribFormat(
+            *vao,
+            1,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            3 * std::mem::size_of::<f32>() as u32
+        ));
+        gl_call!(gl::VertexArrayAttribBinding(*vao, 1, 0));
+
+        // Setup VBO
+        let mut vbo = 0;
+        gl_call!(gl::CreateBuffers(1, &mut vbo));
+
+        gl_call!(gl::VertexArrayVertexBuffer(
+            *vao,
+            0,
+            vbo,
+            0,
+            (5 * std::mem::size_of::<f32>()) as i32
+        ));
+        gl_call!(gl::NamedBufferData(
+            vbo,
+            (30 * std::mem::size_of::<f32>()) as isize,
+            quad((digit as f32 / 10.0, 0.0, (digit + 1) as f32 / 10.0, 1.0)).as_ptr()
+                as *const c_void,
+            gl::STATIC_DRAW
+        ));
+    }
+
+    vaos
+}
+
+// Draws `number` with its last digit ending at `right` and its bottom at `bottom`, in pixels
+pub fn draw_number(
+    digit_vaos: &[u32; 10],
+    number: u32,
+    right: f32,
+    bottom: f32,
+    shader: &mut ShaderProgram,
+) {
+    let digit_width = DIGIT_WIDTH as f32 * GUI_SCALING;
+    let digit_height = DIGIT_HEIGHT as f32 * GUI_SCALING;
+    let projection_matrix = nalgebra_glm::ortho(
+        0.0,
+        WINDOW_WIDTH as f32,
+        0.0,
+        WINDOW_HEIGHT as f32,
+        -5.0,
+        5.0,
+    );
+
+    shader.use_program();
+    unsafe {
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+    }
+    shader.set_uniform1i("tex", 3);
+
+    for (i, digit) in number.to_string().bytes().rev().enumerate() {
+        let model_matrix = {
+            let translate_matrix = Matrix4::new_translation(&vec3(
+                right - (i as f32 + 0.5) * digit_width,
+                bottom + digit_height / 2.0,
+                0.0,
+            ));
+            let scale_matrix =
+                Matrix4::new_nonuniform_scaling(&vec3(digit_width, digit_height, 1.0));
+
+            translate_matrix * scale_matrix
+        };
+
+        unsafe {
+            shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+        }
+
+        gl_call!(gl::BindVertexArray(digit_vaos[(digit - b'0') as usize]));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+    }
+}
+
+// Status bars above the hotbar, made of 9x9 icons of the gui icons texture
+const STATUS_ICON_SIZE: f32 = 9.0;
+// The icons overlap by one pixel
+const STATUS_ICON_SPACING: f32 = 8.0;
+const STATUS_ICONS: u32 = 10;
+
+// Empty, full and half icons of a status bar, from the row `y` of the gui icons texture
+pub fn create_status_icon_vaos(y: u32) -> [u32; 3] {
+    let mut vaos = [0; 3];
+
+    for (vao, x) in vaos.iter_mut().zip([16, 52, 61]) {
+        gl_call!(gl::CreateVertexArrays(1, vao));
+
+        // Position
+        gl_call!(gl::EnableVertexArrayAttrib(*vao, 0));
+        gl_call!(gl::VertexArrayAttribFormat(
+            *vao,
+            0,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            0
+        ));
+        gl_call!(gl::VertexArrayAttribBinding(*vao, 0, 0));
+
+        // Texture coords
+        gl_call!(gl::EnableVertexArrayAttrib(*vao, 1));
+        gl_call!(gl::VertexArrayAttribFormat(
+            *vao,
+            1,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            3 * std::mem::size_of::<f32>() as u32
+        ));
+        gl_call!(gl::VertexArrayAttribBinding(*vao, 1, 0));
+
+        // Setup VBO
+        let mut vbo = 0;
+        gl_call!(gl::CreateBuffers(1, &mut vbo));
+
+        gl_call!(gl::VertexArrayVertexBuffer(
+            *vao,
+            0,
+            vbo,
+            0,
+            (5 * std::mem::size_of::<f32>()) as i32
+        ));
+        gl_call!(gl::NamedBufferData(
+            vbo,
+            (30 * std::mem::size_of::<f32>()) as isize,
+            quad((
+                x as f32 / 256.0,
+                y as f32 / 256.0,
+                (x as f32 + STATUS_ICON_SIZE) / 256.0,
+                (y as f32 + STATUS_ICON_SIZE) / 256.0
+            ))
+            .as_ptr() as *const c_void,
+            gl::STATIC_DRAW
+        ));
+    }
+
+    vaos
+}
+
+// Draws `points` as 10 icons worth 2 points each, above the left half of the hotbar or above its
+// right half filled from the right like the hunger bar of Minecraft
+pub fn draw_status_bar(
+    icon_vaos: &[u32; 3],
+    points: u32,
+    is_right_side: bool,
+    shader: &mut ShaderProgram,
+) {
+    let icon_size = STATUS_ICON_SIZE * GUI_SCALING;
+    let spacing = STATUS_ICON_SPACING * GUI_SCALING;
+    // The hotbar is 182 pixels wide and 22 pixels high
+    let hotbar_left = WINDOW_WIDTH as f32 / 2.0 - 91.0 * GUI_SCALING;
+    let hotbar_right = WINDOW_WIDTH as f32 / 2.0 + 91.0 * GUI_SCALING;
+    let center_y = (30.0 + STATUS_ICON_SIZE / 2.0) * GUI_SCALING;
+    let projection_matrix = nalgebra_glm::ortho(
+        0.0,
+        WINDOW_WIDTH as f32,
+        0.0,
+        WINDOW_HEIGHT as f32,
+        -5.0,
+        5.0,
+    );
+
+    shader.use_program();
+    unsafe {
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+    }
+    shader.set_uniform1i("tex", 1);
+
+    for i in 0..STATUS_ICONS {
+        let center_x = if is_right_side {
+            hotbar_right - i as f32 * spacing - icon_size / 2.0
+        } else {
+            hotbar_left + i as f32 * spacing + icon_size / 2.0
+        };
+        let model_matrix = {
+            let translate_matrix = Matrix4::new_translation(&vec3(center_x, center_y, 0.0));
+            let scale_matrix = Matrix4::new_nonuniform_scaling(&vec3(icon_size, icon_size, 1.0));
+
+            translate_matrix * scale_matrix
+        };
+
+        unsafe {
+            shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+        }
+
+        // The empty icon is drawn behind the full or half one
+        let filling = if points > 2 * i + 1 {
+            Some(icon_vaos[1])
+        } else if points == 2 * i + 1 {
+            Some(icon_vaos[2])
+        } else {
+            None
+        };
+
+        for vao in std::iter::once(icon_vaos[0]).chain(filling) {
+            gl_call!(gl::BindVertexArray(vao));
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+        }
+    }
+}
+
+// Pixels of the crack texture in the order they crack: a few random walks from the centre of the
+// block, the same for every game
+fn crack_pixel_order() -> Vec<(u32, u32)> {
+    let mut seed = 0x2545_f491_u32;
+    let mut random = move |range: u32| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed % range
+    };
+
+    let mut order = Vec::new();
+
+    for _ in 0..6 {
+        let (mut x, mut y) = (6 + random(4) as i32, 6 + random(4) as i32);
+        let (dx, dy) = (random(3) as i32 - 1, random(3) as i32 - 1);
+
+        for _ in 0..14 {
+            if !order.contains(&(x as u32, y as u32)) {
+                order.push((x as u32, y as u32));
+            }
+
+            // Mostly straight, with a few kinks
+            x += if random(3) == 0 {
+                random(3) as i32 - 1
+            } else {
+                dx
+            };
+            y += if random(3) == 0 {
+                random(3) as i32 - 1
+            } else {
+                dy
+            };
+
+            if !(0..BLOCK_TEXTURE_SIZE as i32).contains(&x)
+                || !(0..BLOCK_TEXTURE_SIZE as i32).contains(&y)
+            {
+                break;
+            }
+        }
+    }
+
+    order
+}
+
+// The `BREAK_STAGES` stages of the crack drawn on a block being broken, side by side, each one
+// with more cracked pixels than the previous one
+pub fn create_crack_texture() -> u32 {
+    let size = BLOCK_TEXTURE_SIZE;
+    let width = size * BREAK_STAGES as u32;
+    let order = crack_pixel_order();
+    let mut pixels = vec![0u8; (width * size * 4) as usize];
+
+    for stage in 0..BREAK_STAGES {
+        let cracked_pixels = order.len() * (stage + 1) / BREAK_STAGES;
+
+        for &(x, y) in &order[..cracked_pixels] {
+            let index = ((y * width + stage as u32 * size + x) * 4) as usize;
+            pixels[index..index + 4].copy_from_slice(&[20, 20, 20, 200]);
+        }
+    }
+
+    let mut id = 0;
+    gl_call!(gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id));
+    gl_call!(gl::TextureParameteri(
+        id,
+        gl::TEXTURE_MIN_FILTER,
+        gl::NEAREST as i32
+    ));
+    gl_call!(gl::TextureParameteri(
+        id,
+        gl::TEXTURE_MAG_FILTER,
+        gl::NEAREST as i32
+    ));
+    gl_call!(gl::TextureStorage2D(
+        id,
+        1,
+        gl::RGBA8,
+        width as i32,
+        size as i32
+    ));
+    gl_call!(gl::TextureSubImage2D(
+        id,
+        0,
+        0,
+        0,
+        width as i32,
+        size as i32,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        pixels.as_ptr() as *mut c_void
+    ));
+
+    id
+}
+
+// Unit cube with the texture coordinates of each face
+pub fn create_block_crack_vao() -> u32 {
+    let vertices = centered_unit_cube(0.0, 0.0, 0.0, (0, 0, 0, 0, 0, 0));
+    // Position, texture coordinates with the layer and normal
+    let stride = 9 * std::mem::size_of::<f32>();
+
+    // Setup VAO
+    let mut vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+    // Position
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        0,
+        3,
+        gl::FLOAT,
+        gl::FALSE,
+        0
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+    // Texture coords
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        1,
+        2,
+        gl::FLOAT,
+        gl::FALSE,
+        3 * std::mem::size_of::<f32>() as u32
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+    // Setup VBO
+    let mut vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut vbo));
+
+    gl_call!(gl::VertexArrayVertexBuffer(vao, 0, vbo, 0, stride as i32));
+    gl_call!(gl::NamedBufferData(
+        vbo,
+        (vertices.len() * std::mem::size_of::<f32>()) as isize,
+        vertices.as_ptr() as *const c_void,
+        gl::STATIC_DRAW
+    ));
+
+    vao
+}
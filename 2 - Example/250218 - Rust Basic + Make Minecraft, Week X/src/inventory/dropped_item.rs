@@ -0,0 +1,65 @@
+use crate::aabb::AABB;
+use crate::constants::{
+    DROPPED_ITEM_LIFETIME, DROPPED_ITEM_PICKUP_RANGE, DROPPED_ITEM_SIZE, PLAYER_HALF_WIDTH,
+    PLAYER_HEIGHT,
+};
+use crate::inventory::item::ItemStack;
+use crate::physics::PhysicsBody;
+use nalgebra_glm::{vec3, Vec3};
+use std::time::{Duration, Instant};
+
+// Item lying in the world. Its entity also has a physics body so that it falls and collides.
+#[derive(Copy, Clone, Debug)]
+pub struct DroppedItem {
+    pub item_stack: ItemStack,
+    pub spawned_at: Instant,
+    // The players can't pick it up before, so that a thrown item leaves the player first
+    pub pickup_delay: Duration,
+}
+
+impl DroppedItem {
+    pub fn new(item_stack: ItemStack, spawned_at: Instant, pickup_delay: Duration) -> Self {
+        Self {
+            item_stack,
+            spawned_at,
+            pickup_delay,
+        }
+    }
+
+    pub fn age(&self, time: Instant) -> Duration {
+        time.saturating_duration_since(self.spawned_at)
+    }
+
+    pub fn can_be_picked_up(&self, time: Instant) -> bool {
+        self.age(time) >= self.pickup_delay
+    }
+
+    pub fn has_expired(&self, time: Instant) -> bool {
+        self.age(time) >= *DROPPED_ITEM_LIFETIME
+    }
+
+    // Moves as many items of `other` as the stack can hold into this one
+    pub fn merge(&mut self, other: &mut DroppedItem) {
+        self.item_stack.merge(&mut other.item_stack);
+    }
+}
+
+// Physics body of a dropped item lying at `position`
+pub fn dropped_item_physics_body(position: Vec3) -> PhysicsBody {
+    PhysicsBody::new(position, DROPPED_ITEM_SIZE / 2.0, DROPPED_ITEM_SIZE)
+}
+
+// Region around a player standing at `position` in which they pick up the dropped items
+pub fn pickup_aabb(position: &Vec3) -> AABB {
+    let half_width = PLAYER_HALF_WIDTH + DROPPED_ITEM_PICKUP_RANGE;
+
+    AABB::new(
+        position - vec3(half_width, DROPPED_ITEM_PICKUP_RANGE / 2.0, half_width),
+        position
+            + vec3(
+                half_width,
+                PLAYER_HEIGHT + DROPPED_ITEM_PICKUP_RANGE / 2.0,
+                half_width,
+            ),
+    )
+}
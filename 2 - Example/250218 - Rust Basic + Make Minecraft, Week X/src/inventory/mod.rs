@@ -1,15 +1,31 @@
+pub mod dropped_item;
 pub mod item;
+#[cfg(feature = "client")]
+pub mod item_render;
+pub mod screen;
+#[cfg(feature = "client")]
+pub mod screen_render;
 
 use crate::chunk::BlockID;
+#[cfg(feature = "client")]
 use crate::constants::{GUI_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH};
+#[cfg(feature = "client")]
+use crate::gui::draw_number;
 use crate::inventory::item::ItemStack;
+#[cfg(feature = "client")]
+use crate::inventory::item_render::ItemRender;
+#[cfg(feature = "client")]
 use crate::shader::ShaderProgram;
+#[cfg(feature = "client")]
 use crate::types::TexturePack;
+#[cfg(feature = "client")]
 use nalgebra::Matrix4;
+#[cfg(feature = "client")]
 use nalgebra_glm::vec3;
+use std::ops::Range;
 
-const INVENTORY_SIZE: usize = 36;
-const HOTBAR_SIZE: usize = 9;
+pub const INVENTORY_SIZE: usize = 36;
+pub const HOTBAR_SIZE: usize = 9;
 
 pub struct Inventory {
     pub slots: [Option<ItemStack>; INVENTORY_SIZE],
@@ -27,15 +43,21 @@ impl Inventory {
         Self {
             slots: {
                 let mut slots = [None; INVENTORY_SIZE];
-                slots[0] = Some(ItemStack::new(BlockID::Dirt, 1));
-                slots[1] = Some(ItemStack::new(BlockID::GrassBlock, 1));
-                slots[2] = Some(ItemStack::new(BlockID::Cobblestone, 1));
-                slots[3] = Some(ItemStack::new(BlockID::OakLog, 1));
-                slots[4] = Some(ItemStack::new(BlockID::OakPlanks, 1));
-                slots[5] = Some(ItemStack::new(BlockID::OakLeaves, 1));
-                slots[6] = Some(ItemStack::new(BlockID::Glass, 1));
-                slots[7] = Some(ItemStack::new(BlockID::Obsidian, 1));
-                slots[8] = Some(ItemStack::new(BlockID::Debug, 1));
+                let starting_items = [
+                    BlockID::DIRT,
+                    BlockID::GRASS_BLOCK,
+                    BlockID::COBBLESTONE,
+                    BlockID::OAK_LOG,
+                    BlockID::OAK_PLANKS,
+                    BlockID::OAK_LEAVES,
+                    BlockID::GLASS,
+                    BlockID::OBSIDIAN,
+                    BlockID::GLOWSTONE,
+                ];
+
+                for (slot, item) in slots.iter_mut().zip(starting_items) {
+                    *slot = Some(ItemStack::new(item, item.max_stack_size()));
+                }
 
                 slots
             },
@@ -47,6 +69,98 @@ impl Inventory {
         self.slots[self.selected_hotbar_slot].map(|item_stack| item_stack.item)
     }
 
+    // Fills the stacks of the same item first, then the empty slots. Returns what didn't fit.
+    pub fn add_item(&mut self, item_stack: ItemStack) -> Option<ItemStack> {
+        self.add_item_to_slots(item_stack, 0..INVENTORY_SIZE)
+    }
+
+    // Whether `add_item` would take the whole stack
+    pub fn can_add_item(&self, item_stack: ItemStack) -> bool {
+        let mut inventory = Inventory {
+            slots: self.slots,
+            selected_hotbar_slot: self.selected_hotbar_slot,
+        };
+
+        inventory.add_item(item_stack).is_none()
+    }
+
+    // Same as `add_item` with only the slots of `range`
+    pub fn add_item_to_slots(
+        &mut self,
+        mut item_stack: ItemStack,
+        range: Range<usize>,
+    ) -> Option<ItemStack> {
+        for slot in self.slots[range.clone()].iter_mut().flatten() {
+            slot.merge(&mut item_stack);
+
+            if item_stack.amount == 0 {
+                return None;
+            }
+        }
+
+        for slot in self.slots[range].iter_mut() {
+            if slot.is_none() {
+                let mut new_stack = ItemStack::new(item_stack.item, 0);
+                new_stack.merge(&mut item_stack);
+                *slot = Some(new_stack);
+
+                if item_stack.amount == 0 {
+                    return None;
+                }
+            }
+        }
+
+        Some(item_stack)
+    }
+
+    // Removes up to `amount` items from a slot, which becomes empty with its stack
+    pub fn take_from_slot(&mut self, index: usize, amount: u32) -> Option<ItemStack> {
+        let item_stack = self.slots[index].as_mut()?;
+        let taken = item_stack.take(amount);
+
+        if item_stack.amount == 0 {
+            self.slots[index] = None;
+        }
+
+        Some(taken).filter(|taken| taken.amount > 0)
+    }
+
+    pub fn take_from_selected_slot(&mut self, amount: u32) -> Option<ItemStack> {
+        self.take_from_slot(self.selected_hotbar_slot, amount)
+    }
+
+    // Removes the larger half of the stack of a slot
+    pub fn split_slot(&mut self, index: usize) -> Option<ItemStack> {
+        let amount = self.slots[index]?.amount.div_ceil(2);
+
+        self.take_from_slot(index, amount)
+    }
+
+    // Moves as many items as possible from a slot to another one holding the same item or nothing
+    pub fn merge_slots(&mut self, from: usize, to: usize) {
+        if from == to {
+            return;
+        }
+
+        let Some(mut from_stack) = self.slots[from] else {
+            return;
+        };
+
+        match &mut self.slots[to] {
+            Some(to_stack) => to_stack.merge(&mut from_stack),
+            to_slot @ None => {
+                *to_slot = Some(from_stack);
+                from_stack.amount = 0;
+            }
+        }
+
+        self.slots[from] = Some(from_stack).filter(|from_stack| from_stack.amount > 0);
+    }
+
+    pub fn swap_slots(&mut self, a: usize, b: usize) {
+        self.slots.swap(a, b);
+    }
+
     pub fn select_item(&mut self, index: usize) {
         self.selected_hotbar_slot = index;
     }
@@ -66,15 +180,10 @@ impl Inventory {
             self.selected_hotbar_slot -= 1;
         }
     }
+}
 
-    pub fn update_dirty_items(&mut self, texture_pack: &TexturePack) {
-        for slot in self.slots.iter_mut() {
-            if let Some(item_stack) = slot {
-                item_stack.update_if_dirty(&texture_pack);
-            }
-        }
-    }
-
+#[cfg(feature = "client")]
+impl Inventory {
     pub fn draw_hotbar(&self, vao: u32, shader: &mut ShaderProgram) {
         let model_matrix = {
             let translate_matrix =
@@ -141,21 +250,151 @@ impl Inventory {
         gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
     }
 
-    pub fn draw_hotbar_items(&self, shader: &mut ShaderProgram) {
+    // `item_renders` holds one render per hotbar slot
+    pub fn draw_hotbar_items(
+        &self,
+        item_renders: &mut [ItemRender],
+        texture_pack: &TexturePack,
+        shader: &mut ShaderProgram,
+    ) {
         let inter_slot_spacing = 20.0;
         let hotbar_left_margin = WINDOW_WIDTH as f32 / 2.0 - 4.0 * inter_slot_spacing * GUI_SCALING;
 
         let mut x = 0;
         let y = 11;
 
-        for slot in self.slots.iter() {
+        for (slot, item_render) in self.slots[..HOTBAR_SIZE].iter().zip(item_renders) {
             if let Some(slot) = slot {
                 let item_x_pos = hotbar_left_margin + (x as f32) * inter_slot_spacing * GUI_SCALING;
-                slot.item_render
-                    .draw(item_x_pos, y as f32 * GUI_SCALING, shader);
+                item_render.update_vbo_if_changed(slot.item, texture_pack);
+                item_render.draw(item_x_pos, y as f32 * GUI_SCALING, shader);
             }
 
             x += 1;
         }
     }
+
+    // Amount of the stacks of the hotbar, in the bottom right corner of their slot
+    pub fn draw_hotbar_item_amounts(&self, digit_vaos: &[u32; 10], shader: &mut ShaderProgram) {
+        let inter_slot_spacing = 20.0;
+        let hotbar_left_margin = WINDOW_WIDTH as f32 / 2.0 - 4.0 * inter_slot_spacing * GUI_SCALING;
+
+        for (x, slot) in self.slots[..HOTBAR_SIZE].iter().enumerate() {
+            if let Some(slot) = slot.filter(|slot| slot.amount > 1) {
+                let item_x_pos = hotbar_left_margin + (x as f32) * inter_slot_spacing * GUI_SCALING;
+                draw_number(
+                    digit_vaos,
+                    slot.amount,
+                    item_x_pos + 9.0 * GUI_SCALING,
+                    2.0 * GUI_SCALING,
+                    shader,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::inventory_with;
+
+    #[test]
+    fn added_items_fill_the_stacks_before_the_empty_slots() {
+        let mut inventory = inventory_with(&[
+            (0, BlockID::DIRT, 60),
+            (1, BlockID::STONE, 10),
+            (4, BlockID::DIRT, 63),
+        ]);
+
+        assert_eq!(inventory.add_item(ItemStack::new(BlockID::DIRT, 70)), None);
+        assert_eq!(inventory.slots[0], Some(ItemStack::new(BlockID::DIRT, 64)));
+        assert_eq!(inventory.slots[1], Some(ItemStack::new(BlockID::STONE, 10)));
+        assert_eq!(inventory.slots[2], Some(ItemStack::new(BlockID::DIRT, 64)));
+        assert_eq!(inventory.slots[3], Some(ItemStack::new(BlockID::DIRT, 1)));
+        assert_eq!(inventory.slots[4], Some(ItemStack::new(BlockID::DIRT, 64)));
+        assert_eq!(inventory.slots[5], None);
+    }
+
+    #[test]
+    fn added_items_that_dont_fit_are_returned() {
+        let mut inventory = inventory_with(&[]);
+
+        assert_eq!(
+            inventory.add_item(ItemStack::new(BlockID::WATER, INVENTORY_SIZE as u32 + 2)),
+            Some(ItemStack::new(BlockID::WATER, 2))
+        );
+        assert!(inventory
+            .slots
+            .iter()
+            .all(|slot| *slot == Some(ItemStack::new(BlockID::WATER, 1))));
+
+        // Full stacks of another item can't take anything either
+        let mut inventory = inventory_with(&[]);
+        inventory.add_item(ItemStack::new(BlockID::STONE, 64 * INVENTORY_SIZE as u32));
+        assert_eq!(
+            inventory.add_item(ItemStack::new(BlockID::DIRT, 1)),
+            Some(ItemStack::new(BlockID::DIRT, 1))
+        );
+    }
+
+    #[test]
+    fn taking_the_last_items_empties_the_slot() {
+        let mut inventory = inventory_with(&[(0, BlockID::DIRT, 2)]);
+
+        assert_eq!(
+            inventory.take_from_selected_slot(1),
+            Some(ItemStack::new(BlockID::DIRT, 1))
+        );
+        assert_eq!(inventory.get_selected_item(), Some(BlockID::DIRT));
+        assert_eq!(
+            inventory.take_from_selected_slot(5),
+            Some(ItemStack::new(BlockID::DIRT, 1))
+        );
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.take_from_selected_slot(1), None);
+    }
+
+    #[test]
+    fn slots_are_split_merged_and_swapped() {
+        let mut inventory = inventory_with(&[
+            (0, BlockID::DIRT, 5),
+            (1, BlockID::DIRT, 62),
+            (2, BlockID::STONE, 1),
+        ]);
+
+        // The larger half is taken
+        assert_eq!(
+            inventory.split_slot(0),
+            Some(ItemStack::new(BlockID::DIRT, 3))
+        );
+        assert_eq!(inventory.slots[0], Some(ItemStack::new(BlockID::DIRT, 2)));
+        assert_eq!(
+            inventory.split_slot(2),
+            Some(ItemStack::new(BlockID::STONE, 1))
+        );
+        assert_eq!(inventory.slots[2], None);
+        assert_eq!(inventory.split_slot(2), None);
+
+        // Merging stops at the max stack size
+        inventory.slots[0] = Some(ItemStack::new(BlockID::DIRT, 5));
+        inventory.merge_slots(0, 1);
+        assert_eq!(inventory.slots[0], Some(ItemStack::new(BlockID::DIRT, 3)));
+        assert_eq!(inventory.slots[1], Some(ItemStack::new(BlockID::DIRT, 64)));
+
+        // Everything moves to an empty slot
+        inventory.merge_slots(0, 3);
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.slots[3], Some(ItemStack::new(BlockID::DIRT, 3)));
+
+        // Different items aren't merged
+        inventory.slots[2] = Some(ItemStack::new(BlockID::STONE, 1));
+        inventory.merge_slots(2, 3);
+        assert_eq!(inventory.slots[2], Some(ItemStack::new(BlockID::STONE, 1)));
+        assert_eq!(inventory.slots[3], Some(ItemStack::new(BlockID::DIRT, 3)));
+
+        inventory.swap_slots(2, 3);
+        assert_eq!(inventory.slots[2], Some(ItemStack::new(BlockID::DIRT, 3)));
+        assert_eq!(inventory.slots[3], Some(ItemStack::new(BlockID::STONE, 1)));
+    }
 }
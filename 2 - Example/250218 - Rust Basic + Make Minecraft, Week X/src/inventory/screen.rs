@@ -0,0 +1,538 @@
+use crate::constants::{GUI_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::crafting::{CraftingGrid, Recipe, MAX_CRAFTING_GRID_SIZE, RECIPE_REGISTRY};
+use crate::inventory::item::ItemStack;
+use crate::inventory::{Inventory, HOTBAR_SIZE, INVENTORY_SIZE};
+
+// Distances between the centres of the slots, in pixels of the hotbar texture
+pub const SLOT_SPACING: f32 = 20.0;
+pub const ROW_SPACING: f32 = 22.0;
+// Clickable part of a slot
+const SLOT_SIZE: f32 = 18.0;
+// Space between the main inventory and the hotbar
+const HOTBAR_GAP: f32 = 6.0;
+// Space between the crafting grid and the main inventory
+const CRAFTING_GAP: f32 = 8.0;
+// Height of the 4 rows of the inventory
+const INVENTORY_HEIGHT: f32 = 4.0 * ROW_SPACING + HOTBAR_GAP;
+
+// Size of the crafting grid of the inventory, the crafting table has the largest one
+pub const INVENTORY_CRAFTING_GRID_SIZE: usize = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SlotClick {
+    Left,
+    Right,
+    // Shift + left click, moves the stack between the hotbar and the main inventory
+    QuickMove,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScreenSlot {
+    Inventory(usize),
+    CraftingGrid(usize),
+    // Shows what the grid crafts, taking it uses the ingredients
+    CraftingResult,
+}
+
+// Screen showing every slot of the inventory and a crafting grid, the player moves the stacks
+// around with the mouse
+pub struct InventoryScreen {
+    pub is_open: bool,
+    // Stack carried by the mouse
+    pub cursor_stack: Option<ItemStack>,
+    pub crafting_grid: CraftingGrid,
+}
+
+impl Default for InventoryScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InventoryScreen {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            cursor_stack: None,
+            crafting_grid: CraftingGrid::new(INVENTORY_CRAFTING_GRID_SIZE),
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open_with_crafting_grid(INVENTORY_CRAFTING_GRID_SIZE);
+    }
+
+    pub fn open_crafting_table(&mut self) {
+        self.open_with_crafting_grid(MAX_CRAFTING_GRID_SIZE);
+    }
+
+    fn open_with_crafting_grid(&mut self, size: usize) {
+        self.is_open = true;
+        self.crafting_grid = CraftingGrid::new(size);
+    }
+
+    // Returns the stack carried by the mouse and the items of the crafting grid, they have to go
+    // back to the inventory
+    pub fn close(&mut self) -> Vec<ItemStack> {
+        self.is_open = false;
+
+        self.cursor_stack
+            .take()
+            .into_iter()
+            .chain(self.crafting_grid.take_all())
+            .collect()
+    }
+
+    pub fn crafting_result(&self) -> Option<ItemStack> {
+        RECIPE_REGISTRY
+            .find(&self.crafting_grid)
+            .map(Recipe::result)
+    }
+
+    // Every slot of the screen
+    pub fn slots(&self) -> impl Iterator<Item = ScreenSlot> {
+        (0..INVENTORY_SIZE)
+            .map(ScreenSlot::Inventory)
+            .chain((0..self.crafting_grid.slots.len()).map(ScreenSlot::CraftingGrid))
+            .chain([ScreenSlot::CraftingResult])
+    }
+
+    pub fn stack(&self, inventory: &Inventory, slot: ScreenSlot) -> Option<ItemStack> {
+        match slot {
+            ScreenSlot::Inventory(index) => inventory.slots[index],
+            ScreenSlot::CraftingGrid(index) => self.crafting_grid.slots[index],
+            ScreenSlot::CraftingResult => self.crafting_result(),
+        }
+    }
+
+    pub fn click_slot(&mut self, inventory: &mut Inventory, slot: ScreenSlot, click: SlotClick) {
+        match (slot, click) {
+            (ScreenSlot::Inventory(index), SlotClick::QuickMove) => {
+                if let Some(item_stack) = inventory.slots[index].take() {
+                    let range = if index < HOTBAR_SIZE {
+                        HOTBAR_SIZE..INVENTORY_SIZE
+                    } else {
+                        0..HOTBAR_SIZE
+                    };
+
+                    inventory.slots[index] = inventory.add_item_to_slots(item_stack, range);
+                }
+            }
+            (ScreenSlot::Inventory(index), _) => {
+                click_stack(&mut inventory.slots[index], &mut self.cursor_stack, click);
+            }
+
+            (ScreenSlot::CraftingGrid(index), SlotClick::QuickMove) => {
+                if let Some(item_stack) = self.crafting_grid.slots[index].take() {
+                    self.crafting_grid.slots[index] = inventory.add_item(item_stack);
+                }
+            }
+            (ScreenSlot::CraftingGrid(index), _) => {
+                click_stack(
+                    &mut self.crafting_grid.slots[index],
+                    &mut self.cursor_stack,
+                    click,
+                );
+            }
+
+            // Crafts as many times as possible straight into the inventory
+            (ScreenSlot::CraftingResult, SlotClick::QuickMove) => {
+                while let Some(result) = self
+                    .crafting_result()
+                    .filter(|&result| inventory.can_add_item(result))
+                {
+                    inventory.add_item(result);
+                    self.crafting_grid.consume();
+                }
+            }
+            // Crafts once if the result fits on the carried stack
+            (ScreenSlot::CraftingResult, _) => {
+                let Some(mut result) = self.crafting_result() else {
+                    return;
+                };
+
+                match &mut self.cursor_stack {
+                    None => self.cursor_stack = Some(result),
+                    Some(cursor_stack)
+                        if cursor_stack.item == result.item
+                            && cursor_stack.amount + result.amount
+                                <= cursor_stack.max_stack_size() =>
+                    {
+                        cursor_stack.merge(&mut result);
+                    }
+                    Some(_) => return,
+                }
+
+                self.crafting_grid.consume();
+            }
+        }
+    }
+
+    // Centre of a slot in window coordinates (from the bottom left corner): the 3 rows of the main
+    // inventory are above the hotbar and the crafting grid above them, with its result on the
+    // right. The inventory is centred in the window.
+    pub fn slot_center(&self, slot: ScreenSlot) -> (f32, f32) {
+        let grid_size = self.crafting_grid.size;
+        let grid_row_y = |row: usize| {
+            INVENTORY_HEIGHT / 2.0
+                + CRAFTING_GAP
+                + (grid_size - row - 1) as f32 * SLOT_SPACING
+                + SLOT_SPACING / 2.0
+        };
+
+        let (x, y) = match slot {
+            ScreenSlot::Inventory(index) => {
+                let column = (index % HOTBAR_SIZE) as f32;
+                let row = index / HOTBAR_SIZE;
+
+                let y = if row == 0 {
+                    -INVENTORY_HEIGHT / 2.0 + ROW_SPACING / 2.0
+                } else {
+                    INVENTORY_HEIGHT / 2.0 - ROW_SPACING / 2.0 - (row - 1) as f32 * ROW_SPACING
+                };
+
+                ((column - 4.0) * SLOT_SPACING, y)
+            }
+            ScreenSlot::CraftingGrid(index) => {
+                let column = index % grid_size;
+                let row = index / grid_size;
+
+                (
+                    (column as f32 - grid_size as f32) * SLOT_SPACING,
+                    grid_row_y(row),
+                )
+            }
+            ScreenSlot::CraftingResult => (
+                2.0 * SLOT_SPACING,
+                (grid_row_y(0) + grid_row_y(grid_size - 1)) / 2.0,
+            ),
+        };
+
+        (
+            WINDOW_WIDTH as f32 / 2.0 + x * GUI_SCALING,
+            WINDOW_HEIGHT as f32 / 2.0 + y * GUI_SCALING,
+        )
+    }
+
+    // Slot under a point in window coordinates (from the bottom left corner)
+    pub fn slot_at(&self, x: f32, y: f32) -> Option<ScreenSlot> {
+        let half_size = SLOT_SIZE / 2.0 * GUI_SCALING;
+
+        self.slots().find(|&slot| {
+            let (center_x, center_y) = self.slot_center(slot);
+
+            (x - center_x).abs() <= half_size && (y - center_y).abs() <= half_size
+        })
+    }
+}
+
+// Left click: picks up, puts down, merges or swaps the whole stacks.
+// Right click: picks up half of the stack or puts down one item.
+fn click_stack(slot: &mut Option<ItemStack>, cursor: &mut Option<ItemStack>, click: SlotClick) {
+    match (click, slot.as_mut(), cursor.as_mut()) {
+        (SlotClick::Left, Some(slot_stack), Some(cursor_stack))
+            if slot_stack.item == cursor_stack.item =>
+        {
+            slot_stack.merge(cursor_stack);
+        }
+        (SlotClick::Left, _, _) => std::mem::swap(slot, cursor),
+
+        (SlotClick::Right, Some(slot_stack), None) => *cursor = Some(slot_stack.split()),
+        (SlotClick::Right, None, Some(cursor_stack)) => *slot = Some(cursor_stack.take(1)),
+        (SlotClick::Right, Some(slot_stack), Some(cursor_stack))
+            if slot_stack.item == cursor_stack.item =>
+        {
+            let mut item_stack = cursor_stack.take(1);
+            slot_stack.merge(&mut item_stack);
+            cursor_stack.amount += item_stack.amount;
+        }
+        (SlotClick::Right, Some(_), Some(_)) => std::mem::swap(slot, cursor),
+        (SlotClick::Right, None, None) | (SlotClick::QuickMove, _, _) => {}
+    }
+
+    *slot = slot.filter(|item_stack| item_stack.amount > 0);
+    *cursor = cursor.filter(|item_stack| item_stack.amount > 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::BLOCK_REGISTRY;
+    use crate::chunk::BlockID;
+    use crate::test_support::inventory_with;
+
+    #[test]
+    fn slots_are_found_under_the_mouse() {
+        let mut screen = InventoryScreen::new();
+
+        for crafting_grid_size in [INVENTORY_CRAFTING_GRID_SIZE, MAX_CRAFTING_GRID_SIZE] {
+            screen.open_with_crafting_grid(crafting_grid_size);
+
+            for slot in screen.slots() {
+                let (x, y) = screen.slot_center(slot);
+
+                assert_eq!(screen.slot_at(x, y), Some(slot));
+                assert_eq!(
+                    screen.slot_at(x + 8.0 * GUI_SCALING, y - 8.0 * GUI_SCALING),
+                    Some(slot)
+                );
+                assert!(
+                    y < WINDOW_HEIGHT as f32 - SLOT_SIZE * GUI_SCALING,
+                    "{slot:?}"
+                );
+            }
+        }
+
+        let center = |index| screen.slot_center(ScreenSlot::Inventory(index));
+
+        // The hotbar is the bottom row, the crafting grid is above the inventory
+        assert!(center(0).1 < center(27).1);
+        assert!(center(27).1 < center(18).1);
+        assert!(center(18).1 < center(9).1);
+        assert!(center(9).1 < screen.slot_center(ScreenSlot::CraftingGrid(8)).1);
+        assert_eq!(center(0).0, center(9).0);
+
+        // Between two slots and outside the screen
+        let (x, y) = center(10);
+        assert_eq!(
+            screen.slot_at(x + SLOT_SPACING / 2.0 * GUI_SCALING, y),
+            None
+        );
+        assert_eq!(screen.slot_at(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn left_clicks_pick_up_put_down_merge_and_swap_stacks() {
+        let mut screen = InventoryScreen::new();
+        let mut inventory = inventory_with(&[
+            (0, BlockID::DIRT, 10),
+            (1, BlockID::DIRT, 60),
+            (2, BlockID::STONE, 3),
+        ]);
+
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(0), SlotClick::Left);
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(screen.cursor_stack, Some(ItemStack::new(BlockID::DIRT, 10)));
+
+        // Only 4 fit
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(1), SlotClick::Left);
+        assert_eq!(inventory.slots[1], Some(ItemStack::new(BlockID::DIRT, 64)));
+        assert_eq!(screen.cursor_stack, Some(ItemStack::new(BlockID::DIRT, 6)));
+
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(2), SlotClick::Left);
+        assert_eq!(inventory.slots[2], Some(ItemStack::new(BlockID::DIRT, 6)));
+        assert_eq!(screen.cursor_stack, Some(ItemStack::new(BlockID::STONE, 3)));
+
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(5), SlotClick::Left);
+        assert_eq!(inventory.slots[5], Some(ItemStack::new(BlockID::STONE, 3)));
+        assert_eq!(screen.cursor_stack, None);
+
+        // Clicking an empty slot with nothing does nothing
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(6), SlotClick::Left);
+        assert_eq!(inventory.slots[6], None);
+        assert_eq!(screen.cursor_stack, None);
+    }
+
+    #[test]
+    fn right_clicks_split_stacks_and_put_down_one_item() {
+        let mut screen = InventoryScreen::new();
+        let mut inventory = inventory_with(&[(0, BlockID::DIRT, 7), (1, BlockID::STONE, 1)]);
+
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(0), SlotClick::Right);
+        assert_eq!(inventory.slots[0], Some(ItemStack::new(BlockID::DIRT, 3)));
+        assert_eq!(screen.cursor_stack, Some(ItemStack::new(BlockID::DIRT, 4)));
+
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(4), SlotClick::Right);
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(4), SlotClick::Right);
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(0), SlotClick::Right);
+        assert_eq!(inventory.slots[4], Some(ItemStack::new(BlockID::DIRT, 2)));
+        assert_eq!(inventory.slots[0], Some(ItemStack::new(BlockID::DIRT, 4)));
+        assert_eq!(screen.cursor_stack, Some(ItemStack::new(BlockID::DIRT, 1)));
+
+        // Another item is swapped
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(1), SlotClick::Right);
+        assert_eq!(inventory.slots[1], Some(ItemStack::new(BlockID::DIRT, 1)));
+        assert_eq!(screen.cursor_stack, Some(ItemStack::new(BlockID::STONE, 1)));
+
+        // The last item empties the cursor
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(2), SlotClick::Right);
+        assert_eq!(inventory.slots[2], Some(ItemStack::new(BlockID::STONE, 1)));
+        assert_eq!(screen.cursor_stack, None);
+    }
+
+    #[test]
+    fn quick_moves_go_between_the_hotbar_and_the_main_inventory() {
+        let mut screen = InventoryScreen::new();
+        let mut inventory = inventory_with(&[
+            (0, BlockID::DIRT, 10),
+            (3, BlockID::STONE, 5),
+            (20, BlockID::DIRT, 60),
+        ]);
+
+        // Fills the stack of the main inventory, then its first empty slot
+        screen.click_slot(
+            &mut inventory,
+            ScreenSlot::Inventory(0),
+            SlotClick::QuickMove,
+        );
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.slots[20], Some(ItemStack::new(BlockID::DIRT, 64)));
+        assert_eq!(inventory.slots[9], Some(ItemStack::new(BlockID::DIRT, 6)));
+
+        screen.click_slot(
+            &mut inventory,
+            ScreenSlot::Inventory(9),
+            SlotClick::QuickMove,
+        );
+        assert_eq!(inventory.slots[9], None);
+        assert_eq!(inventory.slots[0], Some(ItemStack::new(BlockID::DIRT, 6)));
+        assert_eq!(inventory.slots[3], Some(ItemStack::new(BlockID::STONE, 5)));
+
+        // What doesn't fit stays
+        for index in HOTBAR_SIZE..INVENTORY_SIZE {
+            inventory.slots[index] = Some(ItemStack::new(BlockID::GLASS, 64));
+        }
+        screen.click_slot(
+            &mut inventory,
+            ScreenSlot::Inventory(3),
+            SlotClick::QuickMove,
+        );
+        assert_eq!(inventory.slots[3], Some(ItemStack::new(BlockID::STONE, 5)));
+        assert_eq!(screen.cursor_stack, None);
+    }
+
+    #[test]
+    fn closing_gives_back_the_carried_stack_and_the_crafting_grid() {
+        let mut screen = InventoryScreen::new();
+        let mut inventory = inventory_with(&[(0, BlockID::DIRT, 10)]);
+
+        screen.open();
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(0), SlotClick::Left);
+
+        screen.click_slot(
+            &mut inventory,
+            ScreenSlot::CraftingGrid(3),
+            SlotClick::Right,
+        );
+
+        assert_eq!(
+            screen.close(),
+            vec![
+                ItemStack::new(BlockID::DIRT, 9),
+                ItemStack::new(BlockID::DIRT, 1)
+            ]
+        );
+        assert!(!screen.is_open);
+        assert_eq!(screen.cursor_stack, None);
+        assert_eq!(
+            screen.crafting_grid,
+            CraftingGrid::new(INVENTORY_CRAFTING_GRID_SIZE)
+        );
+    }
+
+    #[test]
+    fn clicking_the_result_crafts_with_the_items_of_the_grid() {
+        let mut screen = InventoryScreen::new();
+        let mut inventory = inventory_with(&[(0, BlockID::OAK_LOG, 2)]);
+
+        screen.open();
+        assert_eq!(screen.crafting_result(), None);
+
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(0), SlotClick::Left);
+        screen.click_slot(&mut inventory, ScreenSlot::CraftingGrid(1), SlotClick::Left);
+        assert_eq!(
+            screen.crafting_result(),
+            Some(ItemStack::new(BlockID::OAK_PLANKS, 4))
+        );
+
+        screen.click_slot(&mut inventory, ScreenSlot::CraftingResult, SlotClick::Left);
+        screen.click_slot(&mut inventory, ScreenSlot::CraftingResult, SlotClick::Left);
+        assert_eq!(
+            screen.cursor_stack,
+            Some(ItemStack::new(BlockID::OAK_PLANKS, 8))
+        );
+        assert_eq!(screen.crafting_grid.slots[1], None);
+        assert_eq!(screen.crafting_result(), None);
+
+        // The crafting table needs 4 planks in a square
+        for index in [0, 1, 2] {
+            screen.click_slot(
+                &mut inventory,
+                ScreenSlot::CraftingGrid(index),
+                SlotClick::Right,
+            );
+        }
+        assert_eq!(screen.crafting_result(), None);
+        screen.click_slot(
+            &mut inventory,
+            ScreenSlot::CraftingGrid(3),
+            SlotClick::Right,
+        );
+        assert_eq!(
+            screen.crafting_result(),
+            Some(ItemStack::new(BlockID::CRAFTING_TABLE, 1))
+        );
+
+        // Another item on the cursor blocks the crafting
+        screen.click_slot(&mut inventory, ScreenSlot::Inventory(5), SlotClick::Left);
+        screen.cursor_stack = Some(ItemStack::new(BlockID::DIRT, 1));
+        screen.click_slot(&mut inventory, ScreenSlot::CraftingResult, SlotClick::Left);
+        assert_eq!(screen.cursor_stack, Some(ItemStack::new(BlockID::DIRT, 1)));
+        assert!(screen.crafting_grid.slots.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn quick_moving_the_result_crafts_until_the_inventory_is_full() {
+        let mut screen = InventoryScreen::new();
+        let mut inventory = inventory_with(&[]);
+
+        screen.open_crafting_table();
+        screen.crafting_grid.slots[4] = Some(ItemStack::new(BlockID::OAK_LOG, 20));
+
+        // 80 planks
+        screen.click_slot(
+            &mut inventory,
+            ScreenSlot::CraftingResult,
+            SlotClick::QuickMove,
+        );
+        assert_eq!(
+            inventory.slots[0],
+            Some(ItemStack::new(BlockID::OAK_PLANKS, 64))
+        );
+        assert_eq!(
+            inventory.slots[1],
+            Some(ItemStack::new(BlockID::OAK_PLANKS, 16))
+        );
+        assert_eq!(screen.crafting_grid.slots[4], None);
+
+        // Only 2 more fit
+        for index in 1..INVENTORY_SIZE {
+            inventory.slots[index] = Some(ItemStack::new(BlockID::GLASS, 64));
+        }
+        inventory.slots[0] = Some(ItemStack::new(BlockID::OAK_PLANKS, 56));
+        screen.crafting_grid.slots[0] = Some(ItemStack::new(BlockID::OAK_LOG, 5));
+
+        screen.click_slot(
+            &mut inventory,
+            ScreenSlot::CraftingResult,
+            SlotClick::QuickMove,
+        );
+        assert_eq!(
+            inventory.slots[0],
+            Some(ItemStack::new(BlockID::OAK_PLANKS, 64))
+        );
+        assert_eq!(
+            screen.crafting_grid.slots[0],
+            Some(ItemStack::new(BlockID::OAK_LOG, 3))
+        );
+
+        // The grid of the crafting table is 3x3
+        for index in 0..9 {
+            screen.crafting_grid.slots[index] = Some(ItemStack::new(BlockID::DIAMOND_ORE, 1));
+        }
+        assert_eq!(
+            screen.crafting_result().map(|result| result.item),
+            BLOCK_REGISTRY.get_by_name("diamond_block")
+        );
+    }
+}
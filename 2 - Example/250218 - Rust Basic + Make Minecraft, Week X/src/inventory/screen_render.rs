@@ -0,0 +1,141 @@
+use crate::constants::{GUI_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::gui::draw_number;
+use crate::inventory::item::ItemStack;
+use crate::inventory::item_render::ItemRender;
+use crate::inventory::screen::{InventoryScreen, ScreenSlot};
+use crate::inventory::{Inventory, HOTBAR_SIZE, INVENTORY_SIZE};
+use crate::shader::ShaderProgram;
+use crate::types::TexturePack;
+use nalgebra::Matrix4;
+use nalgebra_glm::{vec3, Mat4};
+
+fn gui_projection_matrix() -> Mat4 {
+    nalgebra_glm::ortho(
+        0.0,
+        WINDOW_WIDTH as f32,
+        0.0,
+        WINDOW_HEIGHT as f32,
+        -5.0,
+        5.0,
+    )
+}
+
+// Draws a textured quad of `width` x `height` pixels of the GUI textures centred on (x, y)
+fn draw_gui_quad(
+    vao: u32,
+    (x, y): (f32, f32),
+    width: f32,
+    height: f32,
+    shader: &mut ShaderProgram,
+) {
+    let model_matrix = {
+        let translate_matrix = Matrix4::new_translation(&vec3(x, y, 0.0));
+        let scale_matrix =
+            Matrix4::new_nonuniform_scaling(&vec3(width * GUI_SCALING, height * GUI_SCALING, 1.0));
+
+        translate_matrix * scale_matrix
+    };
+    let projection_matrix = gui_projection_matrix();
+
+    shader.use_program();
+    unsafe {
+        shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+    }
+    shader.set_uniform1i("tex", 2);
+
+    gl_call!(gl::BindVertexArray(vao));
+    gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+}
+
+impl InventoryScreen {
+    // The rows of the inventory use the texture of the hotbar and the other slots one slot of it,
+    // the slot under the mouse is highlighted with the selection box of the hotbar. `cursor` is in
+    // window coordinates.
+    pub fn draw_slots(
+        &self,
+        hotbar_vao: u32,
+        hotbar_selection_vao: u32,
+        slot_vao: u32,
+        cursor: (f32, f32),
+        shader: &mut ShaderProgram,
+    ) {
+        for first_slot in (0..INVENTORY_SIZE).step_by(HOTBAR_SIZE) {
+            let (_, y) = self.slot_center(ScreenSlot::Inventory(first_slot));
+            draw_gui_quad(
+                hotbar_vao,
+                (WINDOW_WIDTH as f32 / 2.0, y),
+                182.0,
+                22.0,
+                shader,
+            );
+        }
+
+        for slot in self
+            .slots()
+            .filter(|slot| !matches!(slot, ScreenSlot::Inventory(_)))
+        {
+            draw_gui_quad(slot_vao, self.slot_center(slot), 22.0, 22.0, shader);
+        }
+
+        if let Some(slot) = self.slot_at(cursor.0, cursor.1) {
+            draw_gui_quad(
+                hotbar_selection_vao,
+                self.slot_center(slot),
+                24.0,
+                24.0,
+                shader,
+            );
+        }
+    }
+
+    // `item_renders` holds one render per slot, in the order of `slots`, and a last one for the
+    // carried stack
+    pub fn draw_items(
+        &self,
+        inventory: &Inventory,
+        item_renders: &mut [ItemRender],
+        cursor: (f32, f32),
+        texture_pack: &TexturePack,
+        shader: &mut ShaderProgram,
+    ) {
+        let stacks = self
+            .slots()
+            .map(|slot| (self.stack(inventory, slot), self.slot_center(slot)))
+            .chain([(self.cursor_stack, cursor)]);
+
+        for ((item_stack, (x, y)), item_render) in stacks.zip(item_renders) {
+            if let Some(item_stack) = item_stack {
+                item_render.update_vbo_if_changed(item_stack.item, texture_pack);
+                item_render.draw(x, y, shader);
+            }
+        }
+    }
+
+    pub fn draw_item_amounts(
+        &self,
+        inventory: &Inventory,
+        digit_vaos: &[u32; 10],
+        cursor: (f32, f32),
+        shader: &mut ShaderProgram,
+    ) {
+        let stacks = self
+            .slots()
+            .map(|slot| (self.stack(inventory, slot), self.slot_center(slot)))
+            .chain([(self.cursor_stack, cursor)]);
+
+        for (item_stack, (x, y)) in stacks {
+            if let Some(ItemStack { amount, .. }) =
+                item_stack.filter(|item_stack| item_stack.amount > 1)
+            {
+                draw_number(
+                    digit_vaos,
+                    amount,
+                    x + 9.0 * GUI_SCALING,
+                    y - 9.0 * GUI_SCALING,
+                    shader,
+                );
+            }
+        }
+    }
+}
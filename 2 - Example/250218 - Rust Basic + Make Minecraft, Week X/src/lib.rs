@@ -0,0 +1,68 @@
+#[macro_use]
+extern crate lazy_static;
+
+#[macro_use]
+extern crate log;
+extern crate specs;
+
+// Everything behind the `client` feature needs GLFW and/or an OpenGL context,
+// the rest is the simulation core shared with the headless server
+#[cfg(feature = "client")]
+#[macro_use]
+pub mod debugging;
+pub mod aabb;
+pub mod ambient_occlusion;
+pub mod arena_allocator;
+pub mod biome;
+pub mod block_registry;
+pub mod block_texture_faces;
+pub mod block_ticks;
+pub mod chunk;
+#[cfg(feature = "client")]
+pub mod chunk_arena;
+pub mod chunk_manager;
+pub mod constants;
+pub mod crafting;
+pub mod day_night;
+pub mod ecs;
+pub mod fluid;
+pub mod frustum;
+pub mod game_mode;
+#[cfg(feature = "client")]
+pub mod gui;
+#[cfg(feature = "client")]
+pub mod input;
+pub mod inventory;
+pub mod light;
+#[cfg(feature = "client")]
+pub mod main_hand;
+pub mod meshing;
+pub mod mining;
+pub mod network;
+#[cfg(feature = "client")]
+pub mod particle_system;
+pub mod physics;
+pub mod player;
+pub mod raycast;
+#[cfg(feature = "client")]
+pub mod renderer;
+#[cfg(feature = "client")]
+pub mod shader;
+pub mod shapes;
+#[cfg(feature = "client")]
+pub mod sky;
+pub mod storage;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "client")]
+pub mod texture;
+#[cfg(feature = "client")]
+pub mod texture_pack;
+pub mod timer;
+pub mod types;
+pub mod util;
+pub mod visibility;
+pub mod vitals;
+#[cfg(feature = "client")]
+pub mod window;
+pub mod world_generation;
@@ -0,0 +1,532 @@
+use crate::ambient_occlusion::{ao_index, AO_AFFECTED_VERTICES};
+use crate::chunk::{BlockID, BlockIterator, ChunkColumn};
+use crate::chunk_manager::{ChunkManager, CHUNK_SIZE};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+const WORLD_HEIGHT: i32 = 16 * CHUNK_SIZE as i32;
+
+const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LightChannel {
+    // Light of the sky, it goes down through transparent blocks without getting dimmer
+    Sky,
+    // Light emitted by blocks like glowstone
+    Block,
+}
+
+// Light given by a block to its neighbour in the direction `dy`
+#[inline]
+fn propagated_level(channel: LightChannel, level: u8, dy: i32) -> u8 {
+    if channel == LightChannel::Sky && dy == -1 && level == MAX_LIGHT_LEVEL {
+        MAX_LIGHT_LEVEL
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+#[inline]
+fn block_and_light(
+    chunk_manager: &ChunkManager,
+    x: i32,
+    y: i32,
+    z: i32,
+    channel: LightChannel,
+) -> Option<(BlockID, u8)> {
+    let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
+        ChunkManager::get_chunk_coords(x, y, z);
+
+    chunk_manager
+        .get_chunk(chunk_x, chunk_y, chunk_z)
+        .map(|chunk| {
+            (
+                chunk.get_block(block_x, block_y, block_z),
+                chunk.get_light(block_x, block_y, block_z, channel),
+            )
+        })
+}
+
+// Flood fills the light of every queued block into the transparent blocks around it
+fn propagate_light(
+    chunk_manager: &ChunkManager,
+    priority: i32,
+    channel: LightChannel,
+    mut queue: VecDeque<(i32, i32, i32)>,
+) {
+    while let Some((x, y, z)) = queue.pop_front() {
+        let level = match chunk_manager.get_light(x, y, z, channel) {
+            Some(level) if level > 1 => level,
+            _ => continue,
+        };
+
+        for (dx, dy, dz) in NEIGHBOURS {
+            let (x, y, z) = (x + dx, y + dy, z + dz);
+            let new_level = propagated_level(channel, level, dy);
+
+            match block_and_light(chunk_manager, x, y, z, channel) {
+                Some((block, old_level)) if block.is_transparent() && old_level < new_level => {
+                    chunk_manager.set_light(priority, x, y, z, channel, new_level);
+                    queue.push_back((x, y, z));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Darkens every block that was lit through (x, y, z), returns the blocks that are lit by
+// something else and must spread their light again
+fn remove_light(
+    chunk_manager: &ChunkManager,
+    priority: i32,
+    channel: LightChannel,
+    x: i32,
+    y: i32,
+    z: i32,
+) -> VecDeque<(i32, i32, i32)> {
+    let mut relight_queue = VecDeque::new();
+
+    let level = chunk_manager.get_light(x, y, z, channel).unwrap_or(0);
+
+    if level == 0 {
+        return relight_queue;
+    }
+
+    chunk_manager.set_light(priority, x, y, z, channel, 0);
+
+    let mut queue = VecDeque::new();
+    let mut light_sources = Vec::new();
+    queue.push_back((x, y, z, level));
+
+    while let Some((x, y, z, level)) = queue.pop_front() {
+        for (dx, dy, dz) in NEIGHBOURS {
+            let (x, y, z) = (x + dx, y + dy, z + dz);
+
+            let (block, neighbour_level) = match block_and_light(chunk_manager, x, y, z, channel) {
+                Some((block, neighbour_level)) if neighbour_level > 0 => (block, neighbour_level),
+                _ => continue,
+            };
+
+            if neighbour_level < level
+                || (neighbour_level == MAX_LIGHT_LEVEL
+                    && propagated_level(channel, level, dy) == MAX_LIGHT_LEVEL)
+            {
+                chunk_manager.set_light(priority, x, y, z, channel, 0);
+                queue.push_back((x, y, z, neighbour_level));
+
+                if channel == LightChannel::Block && block.light_emission() > 0 {
+                    light_sources.push((x, y, z, block.light_emission()));
+                }
+            } else {
+                relight_queue.push_back((x, y, z));
+            }
+        }
+    }
+
+    // Emissive blocks that were darkened shine again
+    for (x, y, z, emission) in light_sources {
+        chunk_manager.set_light(priority, x, y, z, channel, emission);
+        relight_queue.push_back((x, y, z));
+    }
+
+    relight_queue
+}
+
+// Updates the light around (x, y, z) after `old_block` was replaced by `new_block`,
+// the light keeps spreading across chunk and column boundaries
+pub fn update_light(
+    chunk_manager: &ChunkManager,
+    priority: i32,
+    x: i32,
+    y: i32,
+    z: i32,
+    old_block: BlockID,
+    new_block: BlockID,
+) {
+    if old_block.is_transparent() == new_block.is_transparent()
+        && old_block.light_emission() == new_block.light_emission()
+    {
+        return;
+    }
+
+    for channel in [LightChannel::Sky, LightChannel::Block] {
+        let mut queue = remove_light(chunk_manager, priority, channel, x, y, z);
+
+        if channel == LightChannel::Block && new_block.light_emission() > 0 {
+            chunk_manager.set_light(priority, x, y, z, channel, new_block.light_emission());
+            queue.push_back((x, y, z));
+        }
+
+        // Let the light around flow into the block
+        if new_block.is_transparent() {
+            if channel == LightChannel::Sky && y == WORLD_HEIGHT - 1 {
+                chunk_manager.set_light(priority, x, y, z, channel, MAX_LIGHT_LEVEL);
+                queue.push_back((x, y, z));
+            }
+
+            for (dx, dy, dz) in NEIGHBOURS {
+                if let Some(level) = chunk_manager.get_light(x + dx, y + dy, z + dz, channel) {
+                    if level > 1 {
+                        queue.push_back((x + dx, y + dy, z + dz));
+                    }
+                }
+            }
+        }
+
+        propagate_light(chunk_manager, priority, channel, queue);
+    }
+}
+
+// Lowest y from which the sky is visible at (x, z), `WORLD_HEIGHT` if the top block is opaque
+fn sky_exposure_height(column: &ChunkColumn, x: u32, z: u32) -> i32 {
+    let mut y = WORLD_HEIGHT;
+
+    while y > 0 {
+        let block = column
+            .get_chunk((y - 1) / 16)
+            .get_block(x, (y - 1) as u32 % 16, z);
+
+        if block.is_opaque() {
+            break;
+        }
+
+        y -= 1;
+    }
+
+    y
+}
+
+// Lights a column that was just generated, loaded or received. The light of its loaded
+// neighbours flows into it and its own light flows into them.
+pub fn light_column(chunk_manager: &ChunkManager, column_x: i32, column_z: i32) {
+    let column = match chunk_manager.get_column(column_x, column_z) {
+        Some(column) => column,
+        None => return,
+    };
+
+    let neighbours: [Option<Arc<ChunkColumn>>; 4] = [
+        chunk_manager.get_column(column_x + 1, column_z),
+        chunk_manager.get_column(column_x - 1, column_z),
+        chunk_manager.get_column(column_x, column_z + 1),
+        chunk_manager.get_column(column_x, column_z - 1),
+    ];
+
+    // Sky exposure of the column with a border of 1 block taken from the neighbours,
+    // `None` where the neighbour isn't loaded
+    let mut exposure_heights = [[None; 18]; 18];
+
+    for x in -1..=16_i32 {
+        for z in -1..=16_i32 {
+            let column = match (x, z) {
+                (16, 0..=15) => neighbours[0].as_ref(),
+                (-1, 0..=15) => neighbours[1].as_ref(),
+                (0..=15, 16) => neighbours[2].as_ref(),
+                (0..=15, -1) => neighbours[3].as_ref(),
+                (0..=15, 0..=15) => Some(&column),
+                _ => None,
+            };
+
+            exposure_heights[(x + 1) as usize][(z + 1) as usize] = column.map(|column| {
+                sky_exposure_height(column, x.rem_euclid(16) as u32, z.rem_euclid(16) as u32)
+            });
+        }
+    }
+
+    let (origin_x, origin_z) = (16 * column_x, 16 * column_z);
+    let mut sky_queue = VecDeque::new();
+    let mut block_queue = VecDeque::new();
+
+    for x in 0..16 {
+        for z in 0..16 {
+            let exposure_height = exposure_heights[(x + 1) as usize][(z + 1) as usize].unwrap();
+
+            for y in exposure_height..WORLD_HEIGHT {
+                column.get_chunk(y / 16).set_light(
+                    x as u32,
+                    y as u32 % 16,
+                    z as u32,
+                    LightChannel::Sky,
+                    MAX_LIGHT_LEVEL,
+                );
+            }
+
+            // Only the part of the sky that is next to a darker block needs to spread
+            let highest_neighbour = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .iter()
+                .filter_map(|(dx, dz)| {
+                    exposure_heights[(x + 1 + dx) as usize][(z + 1 + dz) as usize]
+                })
+                .max()
+                .unwrap_or(0);
+
+            for y in exposure_height..highest_neighbour.min(WORLD_HEIGHT) {
+                sky_queue.push_back((origin_x + x, y, origin_z + z));
+            }
+
+            // The light coming from the neighbours below their own sky
+            for (i, (dx, dz)) in [(1, 0), (-1, 0), (0, 1), (0, -1)].into_iter().enumerate() {
+                let (neighbour_x, neighbour_z) = (x + dx, z + dz);
+
+                if (0..16).contains(&neighbour_x) && (0..16).contains(&neighbour_z) {
+                    continue;
+                }
+
+                let neighbour = match &neighbours[i] {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+
+                for y in 0..exposure_height {
+                    let chunk = neighbour.get_chunk(y / 16);
+                    let (block_x, block_y, block_z) = (
+                        neighbour_x.rem_euclid(16) as u32,
+                        y as u32 % 16,
+                        neighbour_z.rem_euclid(16) as u32,
+                    );
+                    let position = (origin_x + neighbour_x, y, origin_z + neighbour_z);
+
+                    if chunk.get_light(block_x, block_y, block_z, LightChannel::Sky) > 1 {
+                        sky_queue.push_back(position);
+                    }
+
+                    if chunk.get_light(block_x, block_y, block_z, LightChannel::Block) > 1 {
+                        block_queue.push_back(position);
+                    }
+                }
+            }
+        }
+    }
+
+    // Emissive blocks
+    for (chunk_y, chunk) in column.chunks.iter().enumerate() {
+        if chunk.is_empty() {
+            continue;
+        }
+
+        for (x, y, z) in BlockIterator::new() {
+            let emission = chunk.get_block(x, y, z).light_emission();
+
+            if emission > 0 {
+                chunk.set_light(x, y, z, LightChannel::Block, emission);
+                block_queue.push_back((
+                    origin_x + x as i32,
+                    16 * chunk_y as i32 + y as i32,
+                    origin_z + z as i32,
+                ));
+            }
+        }
+    }
+
+    propagate_light(chunk_manager, 0, LightChannel::Sky, sky_queue);
+    propagate_light(chunk_manager, 0, LightChannel::Block, block_queue);
+
+    *column.is_lit.write() = true;
+}
+
+// Smooth light of the 4 vertices of each face, packed like the light of a block
+// (skylight in the high nibble). Every vertex gets the average light of the transparent
+// blocks in front of it, `light_at` returns `None` for opaque blocks.
+pub fn compute_smooth_light_of_block(
+    light_at: &dyn Fn(i32, i32, i32) -> Option<(u8, u8)>,
+) -> [[u8; 4]; 6] {
+    let mut sums = [[(0, 0, 0); 4]; 6];
+
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                if x == 0 && y == 0 && z == 0 {
+                    continue;
+                }
+
+                let is_corner = x != 0 && y != 0 && z != 0;
+
+                if let Some((sky, block)) = light_at(x, y, z) {
+                    let idx = ao_index((x, y, z));
+
+                    for i in idx..idx + if is_corner { 3 } else { 4 } {
+                        let (face, vertex) = AO_AFFECTED_VERTICES[i];
+                        let sum = &mut sums[face as usize][vertex as usize];
+
+                        sum.0 += sky as u32;
+                        sum.1 += block as u32;
+                        sum.2 += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    sums.map(|face| {
+        face.map(|(sky, block, count)| {
+            if count == 0 {
+                return 0;
+            }
+
+            // Rounded averages
+            let sky = (sky + count / 2) / count;
+            let block = (block + count / 2) / count;
+
+            ((sky << 4) | block) as u8
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{flat_world, light_flat_world};
+
+    // A 3x3 columns world with a stone floor at y = 0 and the sky above
+    fn lit_flat_world() -> ChunkManager {
+        let chunk_manager = flat_world(&[BlockID::STONE]);
+        light_flat_world(&chunk_manager);
+
+        chunk_manager
+    }
+
+    fn sky(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> u8 {
+        chunk_manager.get_light(x, y, z, LightChannel::Sky).unwrap()
+    }
+
+    fn block(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> u8 {
+        chunk_manager
+            .get_light(x, y, z, LightChannel::Block)
+            .unwrap()
+    }
+
+    #[test]
+    fn sky_lights_everything_above_the_ground() {
+        let chunk_manager = lit_flat_world();
+
+        assert_eq!(sky(&chunk_manager, 5, 255, 5), MAX_LIGHT_LEVEL);
+        assert_eq!(sky(&chunk_manager, 5, 1, 5), MAX_LIGHT_LEVEL);
+        assert_eq!(sky(&chunk_manager, -10, 1, 30), MAX_LIGHT_LEVEL);
+        assert_eq!(sky(&chunk_manager, 5, 0, 5), 0);
+        assert_eq!(block(&chunk_manager, 5, 1, 5), 0);
+    }
+
+    #[test]
+    fn roofs_cast_shadows_that_fade_from_the_edges() {
+        let chunk_manager = lit_flat_world();
+
+        // A 9x9 roof at y = 4 around (0, 0), across 4 columns
+        for x in -4..=4 {
+            for z in -4..=4 {
+                chunk_manager.set_block(x, 4, z, BlockID::STONE);
+            }
+        }
+
+        // The light comes from the sides of the roof, 5 blocks away from the center
+        assert_eq!(sky(&chunk_manager, 0, 3, 0), MAX_LIGHT_LEVEL - 5);
+        assert_eq!(sky(&chunk_manager, 0, 1, 0), MAX_LIGHT_LEVEL - 5);
+        assert_eq!(sky(&chunk_manager, -4, 2, 0), MAX_LIGHT_LEVEL - 1);
+        assert_eq!(sky(&chunk_manager, 4, 2, -4), MAX_LIGHT_LEVEL - 1);
+        assert_eq!(sky(&chunk_manager, 0, 5, 0), MAX_LIGHT_LEVEL);
+
+        // Opening a hole in the roof lets the sky in again
+        chunk_manager.set_block(0, 4, 0, BlockID::AIR);
+
+        assert_eq!(sky(&chunk_manager, 0, 4, 0), MAX_LIGHT_LEVEL);
+        assert_eq!(sky(&chunk_manager, 0, 1, 0), MAX_LIGHT_LEVEL);
+        assert_eq!(sky(&chunk_manager, 1, 1, 0), MAX_LIGHT_LEVEL - 1);
+        assert_eq!(sky(&chunk_manager, 1, 3, 1), MAX_LIGHT_LEVEL - 2);
+
+        // And closing it brings the shadow back
+        chunk_manager.set_block(0, 4, 0, BlockID::GLASS);
+        assert_eq!(sky(&chunk_manager, 0, 1, 0), MAX_LIGHT_LEVEL);
+
+        chunk_manager.set_block(0, 4, 0, BlockID::STONE);
+        assert_eq!(sky(&chunk_manager, 0, 1, 0), MAX_LIGHT_LEVEL - 5);
+        assert_eq!(sky(&chunk_manager, 1, 3, 1), MAX_LIGHT_LEVEL - 4);
+    }
+
+    #[test]
+    fn block_light_spreads_across_columns_and_goes_away() {
+        let chunk_manager = lit_flat_world();
+        let glowstone = BlockID::GLOWSTONE;
+        assert_eq!(glowstone.light_emission(), MAX_LIGHT_LEVEL);
+
+        // On the border between 2 columns
+        chunk_manager.set_block(15, 1, 8, glowstone);
+
+        assert_eq!(block(&chunk_manager, 15, 1, 8), MAX_LIGHT_LEVEL);
+        assert_eq!(block(&chunk_manager, 16, 1, 8), MAX_LIGHT_LEVEL - 1);
+        assert_eq!(block(&chunk_manager, 20, 1, 8), MAX_LIGHT_LEVEL - 5);
+        assert_eq!(block(&chunk_manager, 15, 5, 4), MAX_LIGHT_LEVEL - 8);
+        assert_eq!(block(&chunk_manager, 15, 1, 24), 0);
+        // The floor below isn't lit
+        assert_eq!(block(&chunk_manager, 15, 0, 8), 0);
+
+        // A wall blocks the light, it has to go around it
+        for y in 1..=3 {
+            for z in 6..=10 {
+                chunk_manager.set_block(17, y, z, BlockID::STONE);
+            }
+        }
+
+        assert_eq!(block(&chunk_manager, 18, 1, 8), MAX_LIGHT_LEVEL - 9);
+
+        // A second light source keeps its own light when the first one is broken
+        chunk_manager.set_block(24, 1, 8, glowstone);
+        chunk_manager.set_block(15, 1, 8, BlockID::AIR);
+
+        assert_eq!(block(&chunk_manager, 16, 1, 8), MAX_LIGHT_LEVEL - 14);
+        assert_eq!(block(&chunk_manager, 15, 1, 8), 0);
+        assert_eq!(block(&chunk_manager, 18, 1, 8), MAX_LIGHT_LEVEL - 6);
+        assert_eq!(block(&chunk_manager, 23, 1, 8), MAX_LIGHT_LEVEL - 1);
+    }
+
+    #[test]
+    fn neighbours_lit_later_share_their_light() {
+        let chunk_manager = ChunkManager::new();
+
+        // A lit column with a glowstone next to an unlit one, where a dark cave opens
+        let glowstone = BlockID::GLOWSTONE;
+        let lit_column = ChunkColumn::full_of_block(BlockID::STONE);
+        lit_column.set_block(15, 10, 8, glowstone);
+        chunk_manager.add_chunk_column((0, 0), Arc::new(lit_column));
+        light_column(&chunk_manager, 0, 0);
+
+        let cave_column = ChunkColumn::full_of_block(BlockID::STONE);
+
+        for x in 0..4 {
+            cave_column.set_block(x, 10, 8, BlockID::AIR);
+        }
+
+        chunk_manager.add_chunk_column((1, 0), Arc::new(cave_column));
+        light_column(&chunk_manager, 1, 0);
+
+        assert_eq!(block(&chunk_manager, 16, 10, 8), MAX_LIGHT_LEVEL - 1);
+        assert_eq!(block(&chunk_manager, 19, 10, 8), MAX_LIGHT_LEVEL - 4);
+        assert_eq!(sky(&chunk_manager, 19, 10, 8), 0);
+    }
+
+    #[test]
+    fn smooth_light_averages_the_transparent_blocks_in_front_of_each_vertex() {
+        // Everything is lit at 12 except an opaque block and a darker one above the block
+        let smooth_light = compute_smooth_light_of_block(&|x, y, z| match (x, y, z) {
+            (1, 1, 1) => None,
+            (0, 1, 0) => Some((8, 4)),
+            _ => Some((12, 0)),
+        });
+
+        const TOP: usize = 2;
+        const RIGHT: usize = 0;
+
+        // Top vertex 1 is in front of (0, 1, 0), (1, 1, 0), (0, 1, 1) and the opaque (1, 1, 1)
+        assert_eq!(smooth_light[TOP][1], (11 << 4) | 1);
+        // Top vertex 3 is in front of (0, 1, 0) and three blocks at 12
+        assert_eq!(smooth_light[TOP][3], (11 << 4) | 1);
+        assert_eq!(smooth_light[RIGHT][0], 12 << 4);
+    }
+}
@@ -1,67 +1,63 @@
-#[macro_use]
-extern crate lazy_static;
-
 extern crate pretty_env_logger;
 #[macro_use]
 extern crate log;
-extern crate specs;
 
-#[macro_use]
-pub mod debugging;
-pub mod aabb;
-pub mod ambient_occlusion;
-pub mod block_texture_faces;
-pub mod chunk;
-pub mod chunk_manager;
-pub mod constants;
-pub mod ecs;
-pub mod gui;
-pub mod input;
-pub mod inventory;
-pub mod main_hand;
-pub mod particle_system;
-pub mod physics;
-pub mod player;
-pub mod raycast;
-pub mod renderer;
-pub mod shader;
-pub mod shapes;
-pub mod texture;
-pub mod texture_pack;
-pub mod timer;
-pub mod types;
-pub mod util;
-pub mod window;
-
-use crate::chunk_manager::ChunkManager;
-use crate::debugging::*;
-use crate::physics::Interpolator;
-use crate::shader::ShaderProgram;
-use std::collections::HashMap;
-// use glfw::ffi::glfwSwapInterval;
-use crate::constants::*;
-use crate::ecs::components::*;
-use crate::ecs::systems::chunk_loading::ChunkLoading;
-use crate::ecs::systems::*;
-use crate::gui::{create_gui_icons_texture, create_widgets_texture};
-use crate::input::InputCache;
-use crate::inventory::Inventory;
-use crate::main_hand::MainHand;
-use crate::particle_system::ParticleSystem;
-use crate::player::{PlayerPhysicsState, PlayerState};
-use crate::texture_pack::generate_array_texture;
-use crate::timer::Timer;
-use crate::types::Shaders;
-use crate::window::create_window;
-use ecs::systems::fps_counter::FpsCounter;
+use minecraft::chunk_arena::ChunkArena;
+use minecraft::chunk_manager::ChunkManager;
+use minecraft::constants::*;
+use minecraft::day_night::WorldTime;
+use minecraft::debugging::*;
+use minecraft::ecs::components::*;
+use minecraft::ecs::systems::chunk_loading::ChunkLoading;
+use minecraft::ecs::systems::chunk_uploading::UploadChunks;
+use minecraft::ecs::systems::fps_counter::FpsCounter;
+use minecraft::ecs::systems::*;
+use minecraft::game_mode::GameMode;
+use minecraft::gl_call;
+use minecraft::gui::{
+    create_crack_texture, create_digits_texture, create_gui_icons_texture, create_widgets_texture,
+};
+use minecraft::input::InputCache;
+use minecraft::inventory::dropped_item::DroppedItem;
+use minecraft::inventory::screen::InventoryScreen;
+use minecraft::inventory::Inventory;
+use minecraft::main_hand::MainHand;
+use minecraft::meshing::MeshingMode;
+use minecraft::network::client::Client;
+use minecraft::network::transport::TcpConnection;
+use minecraft::particle_system::ParticleSystem;
+use minecraft::physics::{Collider, Gravity, Interpolator, PhysicsBody, Transform};
+use minecraft::player::{player_physics_body, PlayerState};
+use minecraft::shader::ShaderProgram;
+use minecraft::storage::world_settings::{WorldArguments, WorldSettings};
+use minecraft::storage::WorldStorage;
+use minecraft::texture_pack::generate_array_texture;
+use minecraft::timer::Timer;
+use minecraft::types::Shaders;
+use minecraft::vitals::{Health, Hunger, SpawnPoint};
+use minecraft::window::create_window;
+use minecraft::world_generation::{world_generator_from_name, WorldGenerator};
 use nalgebra_glm::vec3;
 use parking_lot::deadlock;
 use specs::{Builder, DispatcherBuilder, World, WorldExt};
+use std::collections::HashMap;
 use std::os::raw::c_void;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+// Where the world comes from
+enum WorldSource {
+    // Loaded from the saves or generated, and simulated here
+    Local {
+        seed: u32,
+        world_generator: Arc<dyn WorldGenerator>,
+        world_storage: Arc<WorldStorage>,
+    },
+    // Received from a server, see `SyncWithServer`
+    Remote(Client),
+}
+
 fn main() {
     thread::spawn(move || loop {
         thread::sleep(Duration::from_secs(10));
@@ -86,14 +82,78 @@ fn main() {
     let mut log_builder = pretty_env_logger::formatted_builder();
     log_builder.parse_filters(LOG_LEVEL.as_str()).init();
 
+    // Usage: minecraft [--seed <number or text>] [--generator <see `world_generator_from_name`>]
+    //                  [--connect <host:port>]
+    // The seed and the generator of an existing world can't be changed. With `--connect`, the
+    // world is received from a server and nothing is saved locally.
+    let arguments =
+        WorldArguments::parse(std::env::args().skip(1)).unwrap_or_else(|err| panic!("{err}"));
+    let chunk_manager = Arc::new(ChunkManager::new());
+
+    let world_source = match &arguments.connect {
+        Some(address) => {
+            let client = TcpConnection::connect(address.as_str())
+                .and_then(|connection| {
+                    Client::connect(
+                        Box::new(connection),
+                        PLAYER_NAME,
+                        Arc::clone(&chunk_manager),
+                    )
+                })
+                .unwrap_or_else(|err| panic!("Failed to connect to {address}: {err}"));
+            info!("Connected to {address} as player {}", client.player_id);
+
+            WorldSource::Remote(client)
+        }
+        None => {
+            let world_settings = WorldSettings::open(
+                WORLD_DIRECTORY,
+                arguments.seed,
+                arguments.generator.as_deref(),
+            )
+            .expect("Failed to read the world settings");
+            info!(
+                "Seed: {}, world generator: {}",
+                world_settings.seed, world_settings.generator
+            );
+            let world_generator = world_generator_from_name(&world_settings.generator)
+                .unwrap_or_else(|err| panic!("{err}"));
+
+            WorldSource::Local {
+                seed: world_settings.seed,
+                world_generator,
+                world_storage: Arc::new(
+                    WorldStorage::open(WORLD_DIRECTORY)
+                        .expect("Failed to open the world directory"),
+                ),
+            }
+        }
+    };
+
+    let spawn_position = match &world_source {
+        WorldSource::Local { .. } => vec3(8.0, 195.0, 8.0),
+        WorldSource::Remote(client) => client.spawn_position,
+    };
+
     let mut world = World::new();
     world.register::<PlayerState>();
-    world.register::<Interpolator<PlayerPhysicsState>>();
+    world.register::<GameMode>();
+    world.register::<ChunkLoadAnchor>();
+    world.register::<Transform>();
+    world.register::<Interpolator<PhysicsBody>>();
+    world.register::<Gravity>();
+    world.register::<Collider>();
     world.register::<Inventory>();
+    world.register::<Health>();
+    world.register::<Hunger>();
+    world.register::<InventoryScreen>();
     world.register::<MainHand>();
     world.register::<MainHandItemChanged>();
+    world.register::<DroppedItem>();
 
-    let mut dispatcher = DispatcherBuilder::new()
+    let (upload_chunks, upload_chunks_tx) = UploadChunks::new();
+
+    let mut dispatcher_builder = DispatcherBuilder::new()
         .with_thread_local({
             let (glfw, window, events) = create_window(WINDOW_WIDTH, WINDOW_HEIGHT, WINDOW_NAME);
 
@@ -126,16 +186,44 @@ fn main() {
                 events,
             }
         })
+        .with_thread_local(HandleInventoryScreenInput)
         .with_thread_local(InventoryHandleInput)
         .with_thread_local(HandlePlayerInput)
         .with_thread_local(UpdatePlayerState)
         .with_thread_local(PlaceAndBreakBlocks)
-        .with_thread_local(UpdatePlayerPhysics)
+        .with_thread_local(UpdatePhysics)
+        .with_thread_local(UpdateVitals::new())
+        .with_thread_local(UpdateDroppedItems);
+
+    // The server simulates the blocks of the world it sends
+    let chunk_loading = match world_source {
+        WorldSource::Local {
+            seed,
+            world_generator,
+            world_storage,
+        } => {
+            dispatcher_builder.add_thread_local(UpdateBlockTicks::new(seed));
+            world.insert(world_storage);
+
+            ChunkLoading::new(Some(upload_chunks_tx), world_generator, seed)
+        }
+        WorldSource::Remote(client) => {
+            dispatcher_builder.add_thread_local(SyncWithServer::new(client));
+
+            ChunkLoading::remote(upload_chunks_tx)
+        }
+    };
+
+    let mut dispatcher = dispatcher_builder
         .with_thread_local(UpdateMainHand)
-        .with_thread_local(ChunkLoading::new())
+        .with_thread_local(upload_chunks)
+        .with_thread_local(chunk_loading)
+        .with_thread_local(RenderSky::new())
         .with_thread_local(RenderChunks)
+        .with_thread_local(RenderDroppedItems::new())
         .with_thread_local(RenderParticles)
         .with_thread_local(RenderBlockOutline::new())
+        .with_thread_local(RenderBlockBreaking::new())
         .with_thread_local(RenderMainHand::new())
         .with_thread_local(RenderGUI::new())
         .with_thread_local(AdvanceGlobalTime)
@@ -144,6 +232,8 @@ fn main() {
 
     world.insert(InputCache::default());
     world.insert(Timer::default());
+    world.insert(WorldTime::default());
+    world.insert(MeshingMode::default());
     world.insert({
         let (item_array_texture, texture_pack) = generate_array_texture();
         gl_call!(gl::BindTextureUnit(0, item_array_texture));
@@ -182,10 +272,23 @@ fn main() {
             "hand_shader",
             ShaderProgram::compile("src/shaders/hand.vert", "src/shaders/hand.frag"),
         );
+        shaders_resource.insert(
+            "crack_shader",
+            ShaderProgram::compile("src/shaders/crack.vert", "src/shaders/crack.frag"),
+        );
+        shaders_resource.insert(
+            "sky_shader",
+            ShaderProgram::compile("src/shaders/sky.vert", "src/shaders/sky.frag"),
+        );
+        shaders_resource.insert(
+            "celestial_shader",
+            ShaderProgram::compile("src/shaders/celestial.vert", "src/shaders/celestial.frag"),
+        );
 
         shaders_resource
     });
-    world.insert(Arc::new(ChunkManager::new()));
+    world.insert(chunk_manager);
+    world.insert(ChunkArena::new());
 
     {
         let gui_icons_texture = create_gui_icons_texture();
@@ -195,16 +298,32 @@ fn main() {
         let gui_widgets_texture = create_widgets_texture();
         gl_call!(gl::ActiveTexture(gl::TEXTURE2));
         gl_call!(gl::BindTexture(gl::TEXTURE_2D, gui_widgets_texture));
+
+        let digits_texture = create_digits_texture();
+        gl_call!(gl::ActiveTexture(gl::TEXTURE3));
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, digits_texture));
+
+        let crack_texture = create_crack_texture();
+        gl_call!(gl::ActiveTexture(gl::TEXTURE4));
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, crack_texture));
     }
 
+    world.insert(SpawnPoint(spawn_position));
     let _player = world
         .create_entity()
         .with(PlayerState::new())
+        .with(GameMode::Creative)
+        .with(Transform::new(spawn_position))
         .with(Interpolator::new(
             1.0 / PHYSICS_TICKRATE,
-            PlayerPhysicsState::new_at_position(vec3(8.0, 195.0, 8.0)),
+            player_physics_body(spawn_position),
         ))
+        .with(Gravity::default())
+        .with(Collider)
         .with(Inventory::new())
+        .with(Health::new())
+        .with(Hunger::new())
+        .with(InventoryScreen::new())
         .with(MainHand::new())
         .with(MainHandItemChanged)
         .build();
@@ -212,5 +331,7 @@ fn main() {
     // Loop until the user closes the window
     loop {
         dispatcher.dispatch(&world);
+        // Creates and deletes the entities of this frame
+        world.maintain();
     }
 }
@@ -0,0 +1,572 @@
+use crate::block_registry::RenderLayer;
+use crate::chunk::{BlockID, BlockIterator, Chunk};
+use crate::chunk_manager::CHUNK_SIZE;
+use crate::fluid::fluid_surface_height;
+use crate::types::TexturePack;
+use nalgebra_glm::{vec3, Vec3};
+
+// How the faces of a chunk are turned into vertices, it can be changed while playing
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MeshingMode {
+    // One quad per visible face
+    #[default]
+    Naive,
+    // Coplanar faces that look the same are merged into bigger quads
+    Greedy,
+}
+
+impl MeshingMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            MeshingMode::Naive => MeshingMode::Greedy,
+            MeshingMode::Greedy => MeshingMode::Naive,
+        }
+    }
+}
+
+// Vertices of a chunk, 12 floats per vertex, indexed by `RenderLayer`
+#[derive(Clone, Debug, Default)]
+pub struct ChunkMesh {
+    pub layers: [Vec<f32>; 3],
+}
+
+impl ChunkMesh {
+    pub fn layer(&self, render_layer: RenderLayer) -> &[f32] {
+        &self.layers[render_layer as usize]
+    }
+
+    fn layer_mut(&mut self, render_layer: RenderLayer) -> &mut Vec<f32> {
+        &mut self.layers[render_layer as usize]
+    }
+
+    // Number of vertices of every layer together
+    pub fn vertex_count(&self) -> usize {
+        self.layers.iter().map(Vec::len).sum::<usize>() / FLOATS_PER_VERTEX
+    }
+}
+
+// Same order as the active faces and the AO
+const RIGHT: usize = 0;
+const LEFT: usize = 1;
+const TOP: usize = 2;
+const BOTTOM: usize = 3;
+const FRONT: usize = 4;
+const BACK: usize = 5;
+
+// Corners of the face of a unit cube with their texture coordinates,
+// they match the vertices written by `write_unit_cube_to_ptr`
+#[rustfmt::skip]
+const FACE_CORNERS: [[([f32; 3], [f32; 2]); 4]; 6] = [
+    [([1.0, 0.0, 1.0], [0.0, 0.0]), ([1.0, 0.0, 0.0], [1.0, 0.0]), ([1.0, 1.0, 0.0], [1.0, 1.0]), ([1.0, 1.0, 1.0], [0.0, 1.0])],
+    [([0.0, 0.0, 0.0], [0.0, 0.0]), ([0.0, 0.0, 1.0], [1.0, 0.0]), ([0.0, 1.0, 1.0], [1.0, 1.0]), ([0.0, 1.0, 0.0], [0.0, 1.0])],
+    [([0.0, 1.0, 1.0], [0.0, 0.0]), ([1.0, 1.0, 1.0], [1.0, 0.0]), ([1.0, 1.0, 0.0], [1.0, 1.0]), ([0.0, 1.0, 0.0], [0.0, 1.0])],
+    [([0.0, 0.0, 0.0], [0.0, 0.0]), ([1.0, 0.0, 0.0], [1.0, 0.0]), ([1.0, 0.0, 1.0], [1.0, 1.0]), ([0.0, 0.0, 1.0], [0.0, 1.0])],
+    [([0.0, 0.0, 1.0], [0.0, 0.0]), ([1.0, 0.0, 1.0], [1.0, 0.0]), ([1.0, 1.0, 1.0], [1.0, 1.0]), ([0.0, 1.0, 1.0], [0.0, 1.0])],
+    [([1.0, 0.0, 0.0], [0.0, 0.0]), ([0.0, 0.0, 0.0], [1.0, 0.0]), ([0.0, 1.0, 0.0], [1.0, 1.0]), ([1.0, 1.0, 0.0], [0.0, 1.0])],
+];
+
+const FACE_NORMALS: [[f32; 3]; 6] = [
+    [1.0, 0.0, 0.0],
+    [-1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, -1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 0.0, -1.0],
+];
+
+// Axis of the normal and axes along which the texture coordinates u and v grow
+const FACE_AXES: [(usize, usize, usize); 6] = [
+    (0, 2, 1),
+    (0, 2, 1),
+    (1, 0, 2),
+    (1, 0, 2),
+    (2, 0, 1),
+    (2, 0, 1),
+];
+
+// Two faces can be merged when they have the same key and their light and AO are the same
+// on every vertex
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct FaceKey {
+    render_layer: RenderLayer,
+    layer: u32,
+    ao: [u8; 4],
+    light: [u8; 4],
+}
+
+impl FaceKey {
+    fn is_uniform(&self) -> bool {
+        self.ao.iter().all(|&ao| ao == self.ao[0])
+            && self.light.iter().all(|&light| light == self.light[0])
+    }
+}
+
+const FLOATS_PER_VERTEX: usize = 12;
+const FLOATS_PER_QUAD: usize = 6 * FLOATS_PER_VERTEX;
+
+// Centre of a quad written by `write_quad`, its first and third vertices are opposite corners
+fn quad_center(quad: &[f32]) -> Vec3 {
+    let corner = |i: usize| vec3(quad[i], quad[i + 1], quad[i + 2]);
+
+    (corner(0) + corner(2 * FLOATS_PER_VERTEX)) / 2.0
+}
+
+// Sorts the quads of a translucent mesh from the farthest to the closest of the camera, so that
+// they are blended over each other in the right order. The camera is relative to the chunk.
+pub fn sort_back_to_front(vertices: &mut [f32], camera_position: &Vec3) {
+    let mut quads: Vec<_> = vertices
+        .chunks(FLOATS_PER_QUAD)
+        .map(|quad| {
+            (
+                (quad_center(quad) - camera_position).norm_squared(),
+                quad.to_vec(),
+            )
+        })
+        .collect();
+
+    quads.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+    for (sorted, (_, quad)) in vertices.chunks_mut(FLOATS_PER_QUAD).zip(quads) {
+        sorted.copy_from_slice(&quad);
+    }
+}
+
+// Moves the top vertices of a fluid block at `block_y` (12 floats per vertex) down to its surface,
+// which is only done when there is no fluid on top of it
+pub(crate) fn lower_fluid_surface(vertices: &mut [f32], block_y: f32, level: u8) {
+    let surface_y = block_y + fluid_surface_height(level);
+
+    for vertex in vertices.chunks_mut(FLOATS_PER_VERTEX) {
+        if vertex[1] == block_y + 1.0 {
+            vertex[1] = surface_y;
+        }
+    }
+}
+
+// Texture layer of a face of a block
+fn face_layer(texture_pack: &TexturePack, block: BlockID, face: usize) -> u32 {
+    let (front, back, top, bottom, left, right) =
+        texture_pack.get(&block).unwrap().get_uv_of_every_face();
+
+    [right, left, top, bottom, front, back][face]
+}
+
+// Writes a quad of `size` blocks (the size along the normal is 1) with a texture repeated on
+// every block, 6 vertices of 12 floats
+fn write_quad(
+    vertices: &mut Vec<f32>,
+    face: usize,
+    position: [f32; 3],
+    size: [f32; 3],
+    key: &FaceKey,
+) {
+    let (_, u_axis, v_axis) = FACE_AXES[face];
+    let normal = FACE_NORMALS[face];
+
+    for i in [0, 1, 2, 2, 3, 0] {
+        let (corner, uv) = FACE_CORNERS[face][i];
+        let light = key.light[i];
+
+        vertices.extend_from_slice(&[
+            position[0] + corner[0] * size[0],
+            position[1] + corner[1] * size[1],
+            position[2] + corner[2] * size[2],
+            uv[0] * size[u_axis],
+            uv[1] * size[v_axis],
+            key.layer as f32,
+            normal[0],
+            normal[1],
+            normal[2],
+            key.ao[i] as f32,
+            (light >> 4) as f32 / 15.0,
+            (light & 0xF) as f32 / 15.0,
+        ]);
+    }
+}
+
+// Builds the same vertices as `Chunk::build_mesh` in naive mode, but the faces that can be
+// merged become a single quad
+pub fn build_greedy_mesh(chunk: &Chunk, texture_pack: &TexturePack) -> ChunkMesh {
+    let mut mesh = ChunkMesh::default();
+
+    let active_faces = chunk.active_faces.read();
+    let ao_vertices = chunk.ao_vertices.read();
+    let light_vertices = chunk.light_vertices.read();
+    let blocks = chunk.blocks.read();
+
+    let size = CHUNK_SIZE as usize;
+    let mut mask: Vec<Option<FaceKey>> = vec![None; size * size];
+
+    for face in [RIGHT, LEFT, TOP, BOTTOM, FRONT, BACK] {
+        let (normal_axis, u_axis, v_axis) = FACE_AXES[face];
+
+        for slice in 0..size {
+            // Faces of the slice, indexed by (u, v)
+            for v in 0..size {
+                for u in 0..size {
+                    let mut xyz = [0; 3];
+                    xyz[normal_axis] = slice;
+                    xyz[u_axis] = u;
+                    xyz[v_axis] = v;
+
+                    let index = xyz[1] * size * size + xyz[2] * size + xyz[0];
+                    let block = blocks[index];
+
+                    // The fluids are meshed apart since their faces don't always fill a block
+                    mask[v * size + u] = if block != BlockID::AIR
+                        && !block.is_fluid()
+                        && active_faces[6 * index + face]
+                    {
+                        Some(FaceKey {
+                            render_layer: block.render_layer(),
+                            layer: face_layer(texture_pack, block, face),
+                            ao: ao_vertices[index][face],
+                            light: light_vertices[index][face],
+                        })
+                    } else {
+                        None
+                    };
+                }
+            }
+
+            // Grow every face as far as possible along u, then along v
+            for v in 0..size {
+                let mut u = 0;
+
+                while u < size {
+                    let key = match mask[v * size + u] {
+                        Some(key) => key,
+                        None => {
+                            u += 1;
+                            continue;
+                        }
+                    };
+
+                    let (mut width, mut height) = (1, 1);
+
+                    if key.is_uniform() {
+                        while u + width < size && mask[v * size + u + width] == Some(key) {
+                            width += 1;
+                        }
+
+                        while v + height < size
+                            && (u..u + width).all(|u| mask[(v + height) * size + u] == Some(key))
+                        {
+                            height += 1;
+                        }
+                    }
+
+                    for v in v..v + height {
+                        mask[v * size + u..v * size + u + width].fill(None);
+                    }
+
+                    let mut position = [0.0; 3];
+                    position[normal_axis] = slice as f32;
+                    position[u_axis] = u as f32;
+                    position[v_axis] = v as f32;
+
+                    let mut quad_size = [1.0; 3];
+                    quad_size[u_axis] = width as f32;
+                    quad_size[v_axis] = height as f32;
+
+                    write_quad(
+                        mesh.layer_mut(key.render_layer),
+                        face,
+                        position,
+                        quad_size,
+                        &key,
+                    );
+
+                    u += width;
+                }
+            }
+        }
+    }
+
+    // One quad per face of the fluids
+    let fluid_levels = chunk.fluid_levels.read();
+
+    for (x, y, z) in BlockIterator::new() {
+        let index = (y * CHUNK_SIZE * CHUNK_SIZE + z * CHUNK_SIZE + x) as usize;
+        let block = blocks[index];
+
+        if !block.is_fluid() {
+            continue;
+        }
+
+        let vertices = mesh.layer_mut(block.render_layer());
+        let start = vertices.len();
+
+        for face in [RIGHT, LEFT, TOP, BOTTOM, FRONT, BACK] {
+            if active_faces[6 * index + face] {
+                let key = FaceKey {
+                    render_layer: block.render_layer(),
+                    layer: face_layer(texture_pack, block, face),
+                    ao: ao_vertices[index][face],
+                    light: light_vertices[index][face],
+                };
+
+                write_quad(
+                    vertices,
+                    face,
+                    [x as f32, y as f32, z as f32],
+                    [1.0; 3],
+                    &key,
+                );
+            }
+        }
+
+        if active_faces[6 * index + TOP] {
+            lower_fluid_surface(&mut vertices[start..], y as f32, fluid_levels[index]);
+        }
+    }
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::BLOCK_REGISTRY;
+    use crate::block_texture_faces::BlockFaces;
+    use crate::chunk::ChunkColumn;
+    use crate::chunk_manager::ChunkManager;
+    use std::sync::Arc;
+
+    fn texture_pack() -> TexturePack {
+        BLOCK_REGISTRY
+            .iter()
+            .map(|block| (block.id, BlockFaces::All(block.id.id() as u32)))
+            .collect()
+    }
+
+    // Meshes the chunk (0, 0, 0) of a single column filled by `fill`
+    fn meshed_chunk(fill: impl Fn(&ChunkColumn)) -> Arc<ChunkColumn> {
+        let chunk_manager = ChunkManager::new();
+        let column = Arc::new(ChunkColumn::new());
+        fill(&column);
+
+        chunk_manager.add_chunk_column((0, 0), Arc::clone(&column));
+        chunk_manager.update_blocks(0, 0, 0, BlockIterator::new());
+
+        column
+    }
+
+    fn mesh_layer(chunk: &Chunk, meshing_mode: MeshingMode, render_layer: RenderLayer) -> Vec<f32> {
+        chunk.build_mesh(&texture_pack(), meshing_mode).layers[render_layer as usize].clone()
+    }
+
+    // Total area of the triangles of a mesh, in blocks
+    fn covered_area(vertices: &[f32]) -> f32 {
+        vertices
+            .chunks(3 * FLOATS_PER_VERTEX)
+            .map(|triangle| {
+                let vertex =
+                    |i: usize| nalgebra_glm::make_vec3(&triangle[i * FLOATS_PER_VERTEX..][..3]);
+                let (a, b, c) = (vertex(0), vertex(1), vertex(2));
+
+                (b - a).cross(&(c - a)).norm() / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn greedy_mesh_merges_a_flat_plane_into_one_quad_per_side() {
+        let column = meshed_chunk(|column| {
+            for x in 0..16 {
+                for z in 0..16 {
+                    column.set_block(x, 5, z, BlockID::STONE);
+                }
+            }
+        });
+        let chunk = column.get_chunk(0);
+
+        let naive = mesh_layer(chunk, MeshingMode::Naive, RenderLayer::Opaque);
+        let greedy = mesh_layer(chunk, MeshingMode::Greedy, RenderLayer::Opaque);
+
+        // Top and bottom faces of 256 blocks, 16 faces on each side
+        assert_eq!(naive.len(), (2 * 256 + 4 * 16) * 6 * FLOATS_PER_VERTEX);
+        assert_eq!(greedy.len(), 6 * 6 * FLOATS_PER_VERTEX);
+        assert_eq!(covered_area(&naive), covered_area(&greedy));
+
+        // The texture is repeated on every block of the top quad
+        let top_quad = &greedy[2 * 6 * FLOATS_PER_VERTEX..3 * 6 * FLOATS_PER_VERTEX];
+        let max_u = top_quad
+            .chunks(FLOATS_PER_VERTEX)
+            .map(|vertex| vertex[3])
+            .fold(0.0, f32::max);
+        assert_eq!(max_u, 16.0);
+        assert!(top_quad
+            .chunks(FLOATS_PER_VERTEX)
+            .all(|vertex| vertex[1] == 6.0 && vertex[5] == BlockID::STONE.id() as f32));
+    }
+
+    #[test]
+    fn faces_that_look_different_are_not_merged() {
+        // A checkerboard of 2 blocks, then a plane whose AO changes under a block on top of it
+        let column = meshed_chunk(|column| {
+            for x in 0..16 {
+                for z in 0..16 {
+                    let block = if (x + z) % 2 == 0 {
+                        BlockID::STONE
+                    } else {
+                        BlockID::DIRT
+                    };
+                    column.set_block(x, 0, z, block);
+                    column.set_block(x, 8, z, BlockID::STONE);
+                }
+            }
+
+            column.set_block(8, 9, 8, BlockID::STONE);
+        });
+        let chunk = column.get_chunk(0);
+
+        let naive = mesh_layer(chunk, MeshingMode::Naive, RenderLayer::Opaque);
+        let greedy = mesh_layer(chunk, MeshingMode::Greedy, RenderLayer::Opaque);
+
+        assert!(greedy.len() < naive.len());
+        assert_eq!(covered_area(&naive), covered_area(&greedy));
+
+        let top_quads_at = |vertices: &[f32], y: f32| {
+            vertices
+                .chunks(6 * FLOATS_PER_VERTEX)
+                .filter(|quad| quad[1] == y && quad[7] == 1.0)
+                .count()
+        };
+
+        // Every block of the checkerboard keeps its own top face
+        assert_eq!(top_quads_at(&greedy, 1.0), 256);
+        // The 8 faces around the block on top of the plane are darkened by its AO
+        assert!(top_quads_at(&greedy, 9.0) > 8);
+        assert!(top_quads_at(&greedy, 9.0) < 20);
+    }
+
+    #[test]
+    fn greedy_mesh_of_single_faces_matches_the_naive_mesh() {
+        let column = meshed_chunk(|column| {
+            column.set_block(3, 4, 5, BlockID::GRASS_BLOCK);
+        });
+        let chunk = column.get_chunk(0);
+
+        let mut naive: Vec<_> = mesh_layer(chunk, MeshingMode::Naive, RenderLayer::Opaque)
+            .chunks(6 * FLOATS_PER_VERTEX)
+            .map(|quad| quad.to_vec())
+            .collect();
+        let mut greedy: Vec<_> = mesh_layer(chunk, MeshingMode::Greedy, RenderLayer::Opaque)
+            .chunks(6 * FLOATS_PER_VERTEX)
+            .map(|quad| quad.to_vec())
+            .collect();
+
+        // Only the order of the faces is different
+        naive.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        greedy.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(naive, greedy);
+    }
+
+    #[test]
+    fn fluid_surfaces_are_lowered_by_their_level() {
+        let column = meshed_chunk(|column| {
+            column.set_block(3, 4, 5, BlockID::WATER);
+            column.get_chunk(0).set_fluid_level(3, 4, 5, 3);
+            column.set_block(4, 4, 5, BlockID::WATER);
+            column.get_chunk(0).set_fluid_level(4, 4, 5, 3);
+
+            // A falling block fills its whole height
+            column.set_block(8, 4, 8, BlockID::WATER);
+            column.set_block(8, 5, 8, BlockID::WATER);
+            column.get_chunk(0).set_fluid_level(8, 4, 8, 1);
+            column.get_chunk(0).set_fluid_level(8, 5, 8, 1);
+        });
+        let chunk = column.get_chunk(0);
+        let surface_y = 4.0 + fluid_surface_height(3);
+
+        for meshing_mode in [MeshingMode::Naive, MeshingMode::Greedy] {
+            let vertices = mesh_layer(chunk, meshing_mode, RenderLayer::Translucent);
+            let quads: Vec<_> = vertices.chunks(6 * FLOATS_PER_VERTEX).collect();
+
+            // 2 blocks side by side without the faces between them, then 2 blocks on top of
+            // each other
+            assert_eq!(quads.len(), 2 * 5 + 2 * 5);
+
+            let heights_at = |x: f32, z: f32| {
+                let mut heights: Vec<_> = vertices
+                    .chunks(FLOATS_PER_VERTEX)
+                    .filter(|vertex| {
+                        (x..=x + 1.0).contains(&vertex[0]) && (z..=z + 1.0).contains(&vertex[2])
+                    })
+                    .map(|vertex| vertex[1])
+                    .collect();
+                heights.sort_by(f32::total_cmp);
+                heights.dedup();
+
+                heights
+            };
+
+            assert_eq!(heights_at(3.0, 5.0), vec![4.0, surface_y]);
+            assert_eq!(
+                heights_at(8.0, 8.0),
+                vec![4.0, 5.0, 5.0 + fluid_surface_height(1)]
+            );
+        }
+    }
+
+    #[test]
+    fn blocks_are_meshed_in_their_render_layer() {
+        let column = meshed_chunk(|column| {
+            column.set_block(1, 1, 1, BlockID::STONE);
+            column.set_block(5, 1, 1, BlockID::GLASS);
+            column.set_block(9, 1, 1, BlockID::OAK_LEAVES);
+            column.set_block(13, 1, 1, BlockID::WATER);
+        });
+        let chunk = column.get_chunk(0);
+
+        for meshing_mode in [MeshingMode::Naive, MeshingMode::Greedy] {
+            let mesh = chunk.build_mesh(&texture_pack(), meshing_mode);
+
+            assert_eq!(mesh.layer(RenderLayer::Opaque).len(), 6 * FLOATS_PER_QUAD);
+            assert_eq!(
+                mesh.layer(RenderLayer::Cutout).len(),
+                2 * 6 * FLOATS_PER_QUAD
+            );
+            assert_eq!(
+                mesh.layer(RenderLayer::Translucent).len(),
+                6 * FLOATS_PER_QUAD
+            );
+            assert!(mesh
+                .layer(RenderLayer::Translucent)
+                .chunks(FLOATS_PER_VERTEX)
+                .all(|vertex| (13.0..=14.0).contains(&vertex[0])));
+        }
+    }
+
+    #[test]
+    fn translucent_quads_are_sorted_from_back_to_front() {
+        let column = meshed_chunk(|column| {
+            for x in 0..16 {
+                column.set_block(x, 1, 1, BlockID::WATER);
+            }
+        });
+        let chunk = column.get_chunk(0);
+        let mut vertices = mesh_layer(chunk, MeshingMode::Naive, RenderLayer::Translucent);
+        let quad_count = vertices.len() / FLOATS_PER_QUAD;
+
+        let distances = |vertices: &[f32], camera_position: &Vec3| -> Vec<f32> {
+            vertices
+                .chunks(FLOATS_PER_QUAD)
+                .map(|quad| (quad_center(quad) - camera_position).norm())
+                .collect()
+        };
+        let is_sorted = |distances: Vec<f32>| distances.windows(2).all(|pair| pair[0] >= pair[1]);
+
+        for camera_position in [vec3(-4.0, 2.0, 1.5), vec3(20.0, 5.0, -3.0)] {
+            sort_back_to_front(&mut vertices, &camera_position);
+
+            assert_eq!(vertices.len() / FLOATS_PER_QUAD, quad_count);
+            assert!(is_sorted(distances(&vertices, &camera_position)));
+        }
+
+        // The closest quad to a camera at +X is at the end of the line of water
+        let last_quad = &vertices[vertices.len() - FLOATS_PER_QUAD..];
+        assert!(quad_center(last_quad).x > 15.0);
+    }
+}
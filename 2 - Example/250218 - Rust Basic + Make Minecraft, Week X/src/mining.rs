@@ -0,0 +1,216 @@
+use crate::block_registry::ToolType;
+use crate::chunk::BlockID;
+use crate::constants::{BREAK_STAGES, SECONDS_PER_HARDNESS, TOOL_SPEED_MULTIPLIER};
+use std::time::{Duration, Instant};
+
+// Time it takes to break a block with `tool` (`None` for the hand), `None` if it is unbreakable
+pub fn break_duration(block: BlockID, tool: Option<ToolType>) -> Option<Duration> {
+    let definition = block.definition();
+
+    if definition.hardness < 0.0 {
+        return None;
+    }
+
+    let speed = if tool.is_some() && tool == definition.tool {
+        TOOL_SPEED_MULTIPLIER
+    } else {
+        1.0
+    };
+
+    Some(Duration::from_secs_f32(
+        definition.hardness * SECONDS_PER_HARDNESS / speed,
+    ))
+}
+
+// Block being broken by a player holding the button on it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlockBreaking {
+    pub coords: (i32, i32, i32),
+    pub block: BlockID,
+    // From 0 to 1, the block breaks at 1
+    pub progress: f32,
+    pub last_update: Instant,
+}
+
+impl BlockBreaking {
+    pub fn new(coords: (i32, i32, i32), block: BlockID, time: Instant) -> Self {
+        Self {
+            coords,
+            block,
+            progress: 0.0,
+            last_update: time,
+        }
+    }
+
+    // Crack texture to draw on the block, from 0 to `BREAK_STAGES - 1`
+    pub fn stage(&self) -> usize {
+        ((self.progress * BREAK_STAGES as f32) as usize).min(BREAK_STAGES - 1)
+    }
+}
+
+// Breaks `block` at `coords` for the time elapsed since the last call, starting over when the
+// targeted block changed. Returns whether the block is broken, `breaking` is then reset.
+pub fn advance_breaking(
+    breaking: &mut Option<BlockBreaking>,
+    coords: (i32, i32, i32),
+    block: BlockID,
+    tool: Option<ToolType>,
+    time: Instant,
+) -> bool {
+    let Some(duration) = break_duration(block, tool) else {
+        *breaking = None;
+        return false;
+    };
+
+    let state = match breaking {
+        Some(state) if state.coords == coords && state.block == block => state,
+        _ => breaking.insert(BlockBreaking::new(coords, block, time)),
+    };
+
+    let elapsed = time.saturating_duration_since(state.last_update);
+    state.last_update = time;
+    state.progress = if duration.is_zero() {
+        1.0
+    } else {
+        state.progress + elapsed.as_secs_f32() / duration.as_secs_f32()
+    };
+
+    if state.progress >= 1.0 {
+        *breaking = None;
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after(start: Instant, seconds: f32) -> Instant {
+        start + Duration::from_secs_f32(seconds)
+    }
+
+    #[test]
+    fn break_duration_depends_on_the_hardness_and_the_tool() {
+        assert_eq!(
+            break_duration(BlockID::STONE, None),
+            Some(Duration::from_secs_f32(2.25))
+        );
+        assert_eq!(
+            break_duration(BlockID::STONE, Some(ToolType::Pickaxe)),
+            Some(Duration::from_secs_f32(2.25 / TOOL_SPEED_MULTIPLIER))
+        );
+        // The wrong tool is no better than the hand
+        assert_eq!(
+            break_duration(BlockID::STONE, Some(ToolType::Shovel)),
+            break_duration(BlockID::STONE, None)
+        );
+        assert!(break_duration(BlockID::OBSIDIAN, None) > break_duration(BlockID::DIRT, None));
+        assert_eq!(break_duration(BlockID::BEDROCK, None), None);
+    }
+
+    #[test]
+    fn progress_accumulates_until_the_block_breaks() {
+        let start = Instant::now();
+        let mut breaking = None;
+
+        // Dirt breaks in 0.75s
+        assert!(!advance_breaking(
+            &mut breaking,
+            (1, 2, 3),
+            BlockID::DIRT,
+            None,
+            start
+        ));
+        assert_eq!(breaking.unwrap().stage(), 0);
+
+        assert!(!advance_breaking(
+            &mut breaking,
+            (1, 2, 3),
+            BlockID::DIRT,
+            None,
+            after(start, 0.3)
+        ));
+        assert_eq!(breaking.unwrap().stage(), 4);
+
+        assert!(!advance_breaking(
+            &mut breaking,
+            (1, 2, 3),
+            BlockID::DIRT,
+            None,
+            after(start, 0.7)
+        ));
+        assert_eq!(breaking.unwrap().stage(), BREAK_STAGES - 1);
+
+        assert!(advance_breaking(
+            &mut breaking,
+            (1, 2, 3),
+            BlockID::DIRT,
+            None,
+            after(start, 0.8)
+        ));
+        assert_eq!(breaking, None);
+    }
+
+    #[test]
+    fn progress_resets_when_the_target_changes() {
+        let start = Instant::now();
+        let mut breaking = None;
+
+        advance_breaking(&mut breaking, (0, 0, 0), BlockID::DIRT, None, start);
+        advance_breaking(
+            &mut breaking,
+            (0, 0, 0),
+            BlockID::DIRT,
+            None,
+            after(start, 0.5),
+        );
+
+        // Another block
+        assert!(!advance_breaking(
+            &mut breaking,
+            (0, 0, 1),
+            BlockID::DIRT,
+            None,
+            after(start, 0.6)
+        ));
+        assert_eq!(breaking.unwrap().coords, (0, 0, 1));
+        assert_eq!(breaking.unwrap().progress, 0.0);
+
+        // The block was replaced
+        advance_breaking(
+            &mut breaking,
+            (0, 0, 1),
+            BlockID::SAND,
+            None,
+            after(start, 0.7),
+        );
+        assert_eq!(breaking.unwrap().block, BlockID::SAND);
+        assert_eq!(breaking.unwrap().progress, 0.0);
+    }
+
+    #[test]
+    fn unbreakable_and_instant_blocks() {
+        let start = Instant::now();
+        let mut breaking = None;
+
+        assert!(!advance_breaking(
+            &mut breaking,
+            (0, 0, 0),
+            BlockID::BEDROCK,
+            None,
+            after(start, 1000.0)
+        ));
+        assert_eq!(breaking, None);
+
+        // No hardness
+        assert!(advance_breaking(
+            &mut breaking,
+            (0, 0, 0),
+            BlockID::DEBUG,
+            None,
+            start
+        ));
+    }
+}
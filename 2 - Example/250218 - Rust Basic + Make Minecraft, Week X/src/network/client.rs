@@ -0,0 +1,145 @@
+use crate::chunk::{BlockID, ChunkColumn};
+use crate::chunk_manager::ChunkManager;
+use crate::constants::HANDSHAKE_TIMEOUT;
+use crate::inventory::Inventory;
+use crate::light::light_column;
+use crate::network::protocol::{Message, PROTOCOL_VERSION};
+use crate::network::transport::Connection;
+use crate::storage::decode_column;
+use nalgebra_glm::Vec3;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+
+// Client side of a connection, it mirrors the columns and the players sent by the server
+pub struct Client {
+    connection: Box<dyn Connection>,
+    pub player_id: u32,
+    pub spawn_position: Vec3,
+    pub chunk_manager: Arc<ChunkManager>,
+    // Position and rotation of every other player
+    pub other_players: HashMap<u32, (Vec3, Vec3)>,
+}
+
+impl Client {
+    // Performs the handshake, blocks until the server accepts or refuses it
+    pub fn connect(
+        mut connection: Box<dyn Connection>,
+        player_name: &str,
+        chunk_manager: Arc<ChunkManager>,
+    ) -> io::Result<Self> {
+        connection.send(&Message::Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            player_name: player_name.to_string(),
+        })?;
+
+        match connection.receive_timeout(*HANDSHAKE_TIMEOUT)? {
+            Some(Message::HandshakeAccepted {
+                player_id,
+                spawn_position,
+            }) => Ok(Self {
+                connection,
+                player_id,
+                spawn_position,
+                chunk_manager,
+                other_players: HashMap::new(),
+            }),
+            Some(Message::Disconnect { reason }) => {
+                Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason))
+            }
+            Some(message) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Expected the handshake to be accepted, received {message:?}"),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "The server didn't answer the handshake",
+            )),
+        }
+    }
+
+    pub fn request_chunk_column(&mut self, x: i32, z: i32) -> io::Result<()> {
+        self.connection.send(&Message::RequestChunkColumn { x, z })
+    }
+
+    // The block is only changed once the server broadcasts it back
+    pub fn put_block(&mut self, x: i32, y: i32, z: i32, block: BlockID) -> io::Result<()> {
+        self.connection.send(&Message::BlockChange {
+            x,
+            y,
+            z,
+            block,
+            level: 0,
+        })
+    }
+
+    pub fn move_player(&mut self, position: Vec3, rotation: Vec3) -> io::Result<()> {
+        self.connection.send(&Message::PlayerMove {
+            player_id: self.player_id,
+            position,
+            rotation,
+        })
+    }
+
+    pub fn sync_inventory(&mut self, inventory: &Inventory) -> io::Result<()> {
+        self.connection.send(&Message::InventorySync {
+            selected_hotbar_slot: inventory.selected_hotbar_slot as u8,
+            slots: inventory.slots.to_vec(),
+        })
+    }
+
+    pub fn disconnect(mut self, reason: &str) -> io::Result<()> {
+        self.connection.send(&Message::Disconnect {
+            reason: reason.to_string(),
+        })
+    }
+
+    // Handles everything received since the last call, never blocks.
+    // Returns an error once the connection is lost or closed by the server.
+    pub fn update(&mut self) -> io::Result<()> {
+        while let Some(message) = self.connection.try_receive()? {
+            match message {
+                Message::ChunkColumnData { x, z, data } => {
+                    let column = ChunkColumn::new();
+                    decode_column(&data, &column)?;
+
+                    self.chunk_manager.remove_chunk_column(&(x, z));
+                    self.chunk_manager
+                        .add_chunk_column((x, z), Arc::new(column));
+                    light_column(&self.chunk_manager, x, z);
+                }
+                Message::BlockChange {
+                    x,
+                    y,
+                    z,
+                    block,
+                    level,
+                } => {
+                    self.chunk_manager
+                        .put_block_with_level(x, y, z, block, level);
+                }
+                Message::PlayerMove {
+                    player_id,
+                    position,
+                    rotation,
+                } => {
+                    if player_id != self.player_id {
+                        self.other_players.insert(player_id, (position, rotation));
+                    }
+                }
+                Message::PlayerLeft { player_id } => {
+                    self.other_players.remove(&player_id);
+                }
+                Message::KeepAlive { id } => {
+                    self.connection.send(&Message::KeepAlive { id })?;
+                }
+                Message::Disconnect { reason } => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionAborted, reason));
+                }
+                message => warn!("Unexpected message from the server: {message:?}"),
+            }
+        }
+
+        Ok(())
+    }
+}
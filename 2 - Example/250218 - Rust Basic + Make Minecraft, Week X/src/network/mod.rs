@@ -0,0 +1,6 @@
+// Client/server protocol, the transports it runs over and both of its ends
+pub mod client;
+pub mod protocol;
+pub mod server;
+pub mod server_world;
+pub mod transport;
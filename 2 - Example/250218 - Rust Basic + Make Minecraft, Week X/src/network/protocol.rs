@@ -0,0 +1,519 @@
+use crate::chunk::BlockID;
+use crate::constants::REQUESTED_COLUMN_DISTANCE;
+use crate::fluid::MAX_FLUID_LEVEL;
+use crate::inventory::item::ItemStack;
+use nalgebra_glm::{vec3, Vec3};
+use std::io::{self, Read, Write};
+
+// Bumped every time a message is added or its layout changes,
+// clients with another version are disconnected during the handshake
+pub const PROTOCOL_VERSION: u16 = 3;
+
+// Big enough for an uncompressed chunk column (see `storage::encode_column`)
+pub const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+const HANDSHAKE: u8 = 0;
+const HANDSHAKE_ACCEPTED: u8 = 1;
+const DISCONNECT: u8 = 2;
+const KEEP_ALIVE: u8 = 3;
+const REQUEST_CHUNK_COLUMN: u8 = 4;
+const CHUNK_COLUMN_DATA: u8 = 5;
+const BLOCK_CHANGE: u8 = 6;
+const PLAYER_MOVE: u8 = 7;
+const PLAYER_LEFT: u8 = 8;
+const INVENTORY_SYNC: u8 = 9;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    // Client -> server, first message of every connection
+    Handshake {
+        protocol_version: u16,
+        player_name: String,
+    },
+    // Server -> client, answer to a valid handshake
+    HandshakeAccepted {
+        player_id: u32,
+        spawn_position: Vec3,
+    },
+    // Both ways, the connection is closed right after
+    Disconnect {
+        reason: String,
+    },
+    // Server -> client, sent regularly and answered with the same id
+    KeepAlive {
+        id: u64,
+    },
+    // Client -> server, answered with `ChunkColumnData` once the column is loaded
+    RequestChunkColumn {
+        x: i32,
+        z: i32,
+    },
+    // Server -> client, `data` is produced by `storage::encode_column`
+    ChunkColumnData {
+        x: i32,
+        z: i32,
+        data: Vec<u8>,
+    },
+    // Client -> server to place or break a block, server -> clients to apply it
+    BlockChange {
+        x: i32,
+        y: i32,
+        z: i32,
+        block: BlockID,
+        // Level of the fluids, 0 for the other blocks
+        level: u8,
+    },
+    // Client -> server with its own id, server -> clients to move the other players
+    PlayerMove {
+        player_id: u32,
+        position: Vec3,
+        rotation: Vec3,
+    },
+    // Server -> clients
+    PlayerLeft {
+        player_id: u32,
+    },
+    // Client -> server
+    InventorySync {
+        selected_hotbar_slot: u8,
+        slots: Vec<Option<ItemStack>>,
+    },
+}
+
+// Column of a player at `position`
+pub fn player_column(position: &Vec3) -> (i32, i32) {
+    (
+        (position.x.floor() as i32).div_euclid(16),
+        (position.z.floor() as i32).div_euclid(16),
+    )
+}
+
+// Whether a player in the column `center` may request the column (x, z)
+pub fn is_column_in_range(center: (i32, i32), x: i32, z: i32) -> bool {
+    (x - center.0).abs() <= REQUESTED_COLUMN_DISTANCE
+        && (z - center.1).abs() <= REQUESTED_COLUMN_DISTANCE
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Every value is written in big-endian order, strings and byte arrays are prefixed by their length
+struct MessageWriter {
+    data: Vec<u8>,
+}
+
+impl MessageWriter {
+    fn put_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_i32(&mut self, value: i32) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn put_vec3(&mut self, value: &Vec3) {
+        for component in value.iter() {
+            self.data.extend_from_slice(&component.to_be_bytes());
+        }
+    }
+
+    fn put_bytes(&mut self, bytes: &[u8]) {
+        self.put_u32(bytes.len() as u32);
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn put_string(&mut self, string: &str) {
+        self.put_bytes(string.as_bytes());
+    }
+}
+
+struct MessageReader<'a> {
+    data: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> MessageReader<'a> {
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.cursor..self.cursor + length)
+            .ok_or_else(|| invalid_data("Truncated message".to_string()))?;
+        self.cursor += length;
+
+        Ok(bytes)
+    }
+
+    fn get_u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn get_u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn get_u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn get_u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn get_i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn get_f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn get_vec3(&mut self) -> io::Result<Vec3> {
+        Ok(vec3(self.get_f32()?, self.get_f32()?, self.get_f32()?))
+    }
+
+    fn get_bytes(&mut self) -> io::Result<&'a [u8]> {
+        let length = self.get_u32()? as usize;
+        self.take(length)
+    }
+
+    fn get_string(&mut self) -> io::Result<String> {
+        String::from_utf8(self.get_bytes()?.to_vec())
+            .map_err(|_| invalid_data("Invalid UTF-8 string".to_string()))
+    }
+
+    fn get_block(&mut self) -> io::Result<BlockID> {
+        let id = self.get_u8()?;
+        BlockID::from_id(id).ok_or_else(|| invalid_data(format!("Unknown block id {id}")))
+    }
+
+    fn get_fluid_level(&mut self) -> io::Result<u8> {
+        let level = self.get_u8()?;
+
+        if level > MAX_FLUID_LEVEL {
+            return Err(invalid_data(format!("Invalid fluid level {level}")));
+        }
+
+        Ok(level)
+    }
+}
+
+impl Message {
+    // Serializes the message as its type followed by its fields
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = MessageWriter { data: Vec::new() };
+
+        match self {
+            Message::Handshake {
+                protocol_version,
+                player_name,
+            } => {
+                writer.put_u8(HANDSHAKE);
+                writer.put_u16(*protocol_version);
+                writer.put_string(player_name);
+            }
+            Message::HandshakeAccepted {
+                player_id,
+                spawn_position,
+            } => {
+                writer.put_u8(HANDSHAKE_ACCEPTED);
+                writer.put_u32(*player_id);
+                writer.put_vec3(spawn_position);
+            }
+            Message::Disconnect { reason } => {
+                writer.put_u8(DISCONNECT);
+                writer.put_string(reason);
+            }
+            Message::KeepAlive { id } => {
+                writer.put_u8(KEEP_ALIVE);
+                writer.put_u64(*id);
+            }
+            Message::RequestChunkColumn { x, z } => {
+                writer.put_u8(REQUEST_CHUNK_COLUMN);
+                writer.put_i32(*x);
+                writer.put_i32(*z);
+            }
+            Message::ChunkColumnData { x, z, data } => {
+                writer.put_u8(CHUNK_COLUMN_DATA);
+                writer.put_i32(*x);
+                writer.put_i32(*z);
+                writer.put_bytes(data);
+            }
+            Message::BlockChange {
+                x,
+                y,
+                z,
+                block,
+                level,
+            } => {
+                writer.put_u8(BLOCK_CHANGE);
+                writer.put_i32(*x);
+                writer.put_i32(*y);
+                writer.put_i32(*z);
+                writer.put_u8(block.id());
+                writer.put_u8(*level);
+            }
+            Message::PlayerMove {
+                player_id,
+                position,
+                rotation,
+            } => {
+                writer.put_u8(PLAYER_MOVE);
+                writer.put_u32(*player_id);
+                writer.put_vec3(position);
+                writer.put_vec3(rotation);
+            }
+            Message::PlayerLeft { player_id } => {
+                writer.put_u8(PLAYER_LEFT);
+                writer.put_u32(*player_id);
+            }
+            Message::InventorySync {
+                selected_hotbar_slot,
+                slots,
+            } => {
+                writer.put_u8(INVENTORY_SYNC);
+                writer.put_u8(*selected_hotbar_slot);
+                writer.put_u8(slots.len() as u8);
+
+                for slot in slots {
+                    match slot {
+                        Some(item_stack) => {
+                            writer.put_u8(1);
+                            writer.put_u8(item_stack.item.id());
+                            writer.put_u32(item_stack.amount);
+                        }
+                        None => writer.put_u8(0),
+                    }
+                }
+            }
+        }
+
+        writer.data
+    }
+
+    pub fn decode(data: &[u8]) -> io::Result<Message> {
+        let mut reader = MessageReader { data, cursor: 0 };
+
+        let message = match reader.get_u8()? {
+            HANDSHAKE => Message::Handshake {
+                protocol_version: reader.get_u16()?,
+                player_name: reader.get_string()?,
+            },
+            HANDSHAKE_ACCEPTED => Message::HandshakeAccepted {
+                player_id: reader.get_u32()?,
+                spawn_position: reader.get_vec3()?,
+            },
+            DISCONNECT => Message::Disconnect {
+                reason: reader.get_string()?,
+            },
+            KEEP_ALIVE => Message::KeepAlive {
+                id: reader.get_u64()?,
+            },
+            REQUEST_CHUNK_COLUMN => Message::RequestChunkColumn {
+                x: reader.get_i32()?,
+                z: reader.get_i32()?,
+            },
+            CHUNK_COLUMN_DATA => Message::ChunkColumnData {
+                x: reader.get_i32()?,
+                z: reader.get_i32()?,
+                data: reader.get_bytes()?.to_vec(),
+            },
+            BLOCK_CHANGE => Message::BlockChange {
+                x: reader.get_i32()?,
+                y: reader.get_i32()?,
+                z: reader.get_i32()?,
+                block: reader.get_block()?,
+                level: reader.get_fluid_level()?,
+            },
+            PLAYER_MOVE => Message::PlayerMove {
+                player_id: reader.get_u32()?,
+                position: reader.get_vec3()?,
+                rotation: reader.get_vec3()?,
+            },
+            PLAYER_LEFT => Message::PlayerLeft {
+                player_id: reader.get_u32()?,
+            },
+            INVENTORY_SYNC => {
+                let selected_hotbar_slot = reader.get_u8()?;
+                let slot_count = reader.get_u8()?;
+                let mut slots = Vec::with_capacity(slot_count as usize);
+
+                for _ in 0..slot_count {
+                    slots.push(match reader.get_u8()? {
+                        0 => None,
+                        _ => Some(ItemStack::new(reader.get_block()?, reader.get_u32()?)),
+                    });
+                }
+
+                Message::InventorySync {
+                    selected_hotbar_slot,
+                    slots,
+                }
+            }
+            message_type => {
+                return Err(invalid_data(format!("Unknown message type {message_type}")))
+            }
+        };
+
+        if reader.cursor != data.len() {
+            return Err(invalid_data(format!(
+                "{} trailing bytes after the message",
+                data.len() - reader.cursor
+            )));
+        }
+
+        Ok(message)
+    }
+}
+
+// Writes a message prefixed by its length
+pub fn write_message(writer: &mut impl Write, message: &Message) -> io::Result<()> {
+    let data = message.encode();
+
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(&data)?;
+    writer.flush()
+}
+
+// Reads a message written by `write_message`, blocks until it is complete
+pub fn read_message(reader: &mut impl Read) -> io::Result<Message> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+
+    if length > MAX_MESSAGE_SIZE {
+        return Err(invalid_data(format!(
+            "Message of {length} bytes is too large"
+        )));
+    }
+
+    let mut data = vec![0; length];
+    reader.read_exact(&mut data)?;
+
+    Message::decode(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_messages() -> Vec<Message> {
+        vec![
+            Message::Handshake {
+                protocol_version: PROTOCOL_VERSION,
+                player_name: "Steve".to_string(),
+            },
+            Message::HandshakeAccepted {
+                player_id: 7,
+                spawn_position: vec3(8.0, 195.0, -8.5),
+            },
+            Message::Disconnect {
+                reason: "Server closed".to_string(),
+            },
+            Message::KeepAlive { id: u64::MAX - 1 },
+            Message::RequestChunkColumn { x: -3, z: 12 },
+            Message::ChunkColumnData {
+                x: 1,
+                z: -1,
+                data: vec![1, 2, 3, 4],
+            },
+            Message::BlockChange {
+                x: -100,
+                y: 64,
+                z: 3,
+                block: BlockID::WATER,
+                level: 5,
+            },
+            Message::PlayerMove {
+                player_id: 2,
+                position: vec3(0.5, 70.0, -0.25),
+                rotation: vec3(-45.0, 90.0, 0.0),
+            },
+            Message::PlayerLeft { player_id: 3 },
+            Message::InventorySync {
+                selected_hotbar_slot: 4,
+                slots: vec![
+                    Some(ItemStack::new(BlockID::DIRT, 64)),
+                    None,
+                    Some(ItemStack::new(BlockID::OAK_LOG, 3)),
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        for message in sample_messages() {
+            assert_eq!(Message::decode(&message.encode()).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn framed_messages_round_trip() {
+        let mut stream = Vec::new();
+
+        for message in sample_messages() {
+            write_message(&mut stream, &message).unwrap();
+        }
+
+        let mut reader = stream.as_slice();
+
+        for message in sample_messages() {
+            assert_eq!(read_message(&mut reader).unwrap(), message);
+        }
+
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected() {
+        let data = Message::KeepAlive { id: 1 }.encode();
+
+        assert!(Message::decode(&data[..data.len() - 1]).is_err());
+        assert!(Message::decode(&[data.as_slice(), &[0]].concat()).is_err());
+        assert!(Message::decode(&[255]).is_err());
+        assert!(Message::decode(&[]).is_err());
+
+        // Unknown block id
+        let mut data = Message::BlockChange {
+            x: 0,
+            y: 0,
+            z: 0,
+            block: BlockID::STONE,
+            level: 0,
+        }
+        .encode();
+        let block_index = data.len() - 2;
+        data[block_index] = 255;
+        assert!(Message::decode(&data).is_err());
+
+        // Fluid level out of range
+        let mut data = Message::BlockChange {
+            x: 0,
+            y: 0,
+            z: 0,
+            block: BlockID::WATER,
+            level: MAX_FLUID_LEVEL,
+        }
+        .encode();
+        assert!(Message::decode(&data).is_ok());
+        *data.last_mut().unwrap() = MAX_FLUID_LEVEL + 1;
+        assert!(Message::decode(&data).is_err());
+
+        // Length prefix above the limit
+        let frame = ((MAX_MESSAGE_SIZE + 1) as u32).to_be_bytes();
+        assert!(read_message(&mut frame.as_slice()).is_err());
+    }
+}
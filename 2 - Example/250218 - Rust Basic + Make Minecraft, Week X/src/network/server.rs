@@ -0,0 +1,552 @@
+use crate::chunk_manager::{BlockChange, ChunkManager};
+use crate::constants::{
+    HANDSHAKE_TIMEOUT, KEEP_ALIVE_INTERVAL, KEEP_ALIVE_TIMEOUT, PLAYER_EYES_HEIGHT, REACH_DISTANCE,
+};
+use crate::inventory::{Inventory, HOTBAR_SIZE, INVENTORY_SIZE};
+use crate::network::protocol::{is_column_in_range, player_column, Message, PROTOCOL_VERSION};
+use crate::network::transport::{Connection, TcpConnection};
+use crate::storage::encode_column;
+use crossbeam_channel::Receiver;
+use nalgebra_glm::{vec3, Vec3};
+use std::collections::HashSet;
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Instant;
+
+// What happened to the players during `Server::update`, so that the caller can mirror it
+// in its own world (e.g. to keep the chunks around every player loaded)
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerEvent {
+    PlayerJoined { player_id: u32 },
+    PlayerMoved { player_id: u32, position: Vec3 },
+    PlayerLeft { player_id: u32 },
+}
+
+pub struct RemotePlayer {
+    pub id: u32,
+    pub name: String,
+    pub position: Vec3,
+    pub rotation: Vec3,
+    pub inventory: Inventory,
+    connection: Box<dyn Connection>,
+    requested_columns: HashSet<(i32, i32)>,
+    last_message_time: Instant,
+    is_connected: bool,
+}
+
+// Why a message from a player is not applied
+enum InvalidMessage {
+    // A lagging client can send it, the message is dropped
+    Outdated(String),
+    // No honest client sends it, the player is disconnected
+    Malformed(String),
+}
+
+impl RemotePlayer {
+    // Same reach as the raycast of the player, to the closest point of the block
+    fn can_reach(&self, x: i32, y: i32, z: i32) -> bool {
+        let eyes = self.position + vec3(0.0, PLAYER_EYES_HEIGHT, 0.0);
+        let closest_point = vec3(
+            eyes.x.clamp(x as f32, x as f32 + 1.0),
+            eyes.y.clamp(y as f32, y as f32 + 1.0),
+            eyes.z.clamp(z as f32, z as f32 + 1.0),
+        );
+
+        nalgebra_glm::distance(&eyes, &closest_point) <= REACH_DISTANCE
+    }
+
+    fn check_message(&self, message: &Message) -> Result<(), InvalidMessage> {
+        match message {
+            Message::RequestChunkColumn { x, z }
+                if !is_column_in_range(player_column(&self.position), *x, *z) =>
+            {
+                return Err(InvalidMessage::Outdated(format!(
+                    "Column ({x}, {z}) is out of the view distance"
+                )));
+            }
+            Message::BlockChange {
+                x,
+                y,
+                z,
+                block,
+                level,
+            } => {
+                if *level != 0 && !block.is_fluid() {
+                    return Err(InvalidMessage::Malformed(format!(
+                        "Fluid level {level} given to {block:?}"
+                    )));
+                }
+
+                if !self.can_reach(*x, *y, *z) {
+                    return Err(InvalidMessage::Outdated(format!(
+                        "Block at ({x}, {y}, {z}) is out of reach"
+                    )));
+                }
+
+                // The placed blocks are taken from the hand, the broken ones are replaced by air
+                if !block.is_air() && self.inventory.get_selected_item() != Some(*block) {
+                    return Err(InvalidMessage::Outdated(format!(
+                        "{block:?} placed at ({x}, {y}, {z}) is not in the hand"
+                    )));
+                }
+            }
+            Message::PlayerMove {
+                position, rotation, ..
+            } if position
+                .iter()
+                .chain(rotation.iter())
+                .any(|c| !c.is_finite()) =>
+            {
+                return Err(InvalidMessage::Malformed(
+                    "Non-finite position or rotation".to_string(),
+                ));
+            }
+            Message::InventorySync {
+                selected_hotbar_slot,
+                slots,
+            } => {
+                if slots.len() != INVENTORY_SIZE {
+                    return Err(InvalidMessage::Malformed(format!(
+                        "Inventory of {} slots",
+                        slots.len()
+                    )));
+                }
+
+                if *selected_hotbar_slot as usize >= HOTBAR_SIZE {
+                    return Err(InvalidMessage::Malformed(format!(
+                        "Invalid hotbar slot {selected_hotbar_slot}"
+                    )));
+                }
+
+                if let Some(item_stack) = slots.iter().flatten().find(|item_stack| {
+                    item_stack.amount == 0 || item_stack.amount > item_stack.max_stack_size()
+                }) {
+                    return Err(InvalidMessage::Malformed(format!(
+                        "Stack of {} {:?}",
+                        item_stack.amount, item_stack.item
+                    )));
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, message: &Message) {
+        if !self.is_connected {
+            return;
+        }
+
+        if let Err(err) = self.connection.send(message) {
+            warn!(
+                "Lost connection to player {} ({}): {err}",
+                self.id, self.name
+            );
+            self.is_connected = false;
+        }
+    }
+}
+
+struct PendingConnection {
+    connection: Box<dyn Connection>,
+    connection_time: Instant,
+}
+
+// Shares one `ChunkManager` between every connected client
+pub struct Server {
+    chunk_manager: Arc<ChunkManager>,
+    block_changes_rx: Receiver<BlockChange>,
+    spawn_position: Vec3,
+    listener: Option<TcpListener>,
+    pending_connections: Vec<PendingConnection>,
+    players: Vec<RemotePlayer>,
+    next_player_id: u32,
+    last_keep_alive_time: Instant,
+    keep_alive_id: u64,
+}
+
+impl Server {
+    pub fn new(chunk_manager: Arc<ChunkManager>, spawn_position: Vec3) -> Self {
+        Self {
+            block_changes_rx: chunk_manager.subscribe_to_block_changes(),
+            chunk_manager,
+            spawn_position,
+            listener: None,
+            pending_connections: Vec::new(),
+            players: Vec::new(),
+            next_player_id: 1,
+            last_keep_alive_time: Instant::now(),
+            keep_alive_id: 0,
+        }
+    }
+
+    // Starts accepting TCP connections, returns the address actually bound
+    pub fn listen(&mut self, address: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        let address = listener.local_addr()?;
+        self.listener = Some(listener);
+
+        Ok(address)
+    }
+
+    // Adds a connection of any transport, it becomes a player once its handshake is accepted
+    pub fn add_connection(&mut self, connection: Box<dyn Connection>) {
+        self.pending_connections.push(PendingConnection {
+            connection,
+            connection_time: Instant::now(),
+        });
+    }
+
+    pub fn players(&self) -> &[RemotePlayer] {
+        &self.players
+    }
+
+    // Handles everything received since the last call, never blocks
+    pub fn update(&mut self) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+
+        self.accept_tcp_connections();
+        self.handle_handshakes(&mut events);
+        self.handle_player_messages(&mut events);
+        self.send_requested_columns();
+
+        for block_change in self.block_changes_rx.try_iter() {
+            let message = Message::BlockChange {
+                x: block_change.x,
+                y: block_change.y,
+                z: block_change.z,
+                block: block_change.block,
+                level: block_change.level,
+            };
+
+            for player in self.players.iter_mut() {
+                player.send(&message);
+            }
+        }
+
+        if self.last_keep_alive_time.elapsed() >= *KEEP_ALIVE_INTERVAL {
+            self.last_keep_alive_time = Instant::now();
+            self.keep_alive_id += 1;
+
+            let message = Message::KeepAlive {
+                id: self.keep_alive_id,
+            };
+
+            for player in self.players.iter_mut() {
+                player.send(&message);
+
+                if player.last_message_time.elapsed() >= *KEEP_ALIVE_TIMEOUT {
+                    warn!("Player {} ({}) timed out", player.id, player.name);
+                    player.is_connected = false;
+                }
+            }
+        }
+
+        self.remove_disconnected_players(&mut events);
+
+        events
+    }
+
+    // Disconnects everybody, e.g. when the server stops
+    pub fn shutdown(&mut self, reason: &str) {
+        let message = Message::Disconnect {
+            reason: reason.to_string(),
+        };
+
+        for player in self.players.iter_mut() {
+            player.send(&message);
+        }
+
+        self.players.clear();
+        self.pending_connections.clear();
+    }
+
+    fn accept_tcp_connections(&mut self) {
+        let listener = match &self.listener {
+            Some(listener) => listener,
+            None => return,
+        };
+
+        loop {
+            match listener.accept() {
+                Ok((stream, address)) => {
+                    let connection = stream
+                        .set_nonblocking(false)
+                        .and_then(|_| TcpConnection::from_stream(stream));
+
+                    match connection {
+                        Ok(connection) => {
+                            info!("New connection from {address}");
+                            self.pending_connections.push(PendingConnection {
+                                connection: Box::new(connection),
+                                connection_time: Instant::now(),
+                            });
+                        }
+                        Err(err) => error!("{err}"),
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    error!("{err}");
+                    break;
+                }
+            }
+        }
+    }
+
+    fn handle_handshakes(&mut self, events: &mut Vec<ServerEvent>) {
+        for mut pending in std::mem::take(&mut self.pending_connections) {
+            let player_name = match pending.connection.try_receive() {
+                Ok(Some(Message::Handshake {
+                    protocol_version,
+                    player_name,
+                })) => {
+                    if protocol_version != PROTOCOL_VERSION {
+                        let _ = pending.connection.send(&Message::Disconnect {
+                            reason: format!(
+                                "Unsupported protocol version {protocol_version}, the server uses {PROTOCOL_VERSION}"
+                            ),
+                        });
+                        continue;
+                    }
+
+                    player_name
+                }
+                Ok(Some(message)) => {
+                    warn!("Expected a handshake, received {message:?}");
+                    continue;
+                }
+                Ok(None) => {
+                    if pending.connection_time.elapsed() < *HANDSHAKE_TIMEOUT {
+                        self.pending_connections.push(pending);
+                    }
+                    continue;
+                }
+                Err(err) => {
+                    warn!("Handshake failed: {err}");
+                    continue;
+                }
+            };
+
+            let mut player = RemotePlayer {
+                id: self.next_player_id,
+                name: player_name,
+                position: self.spawn_position,
+                rotation: vec3(0.0, 0.0, 0.0),
+                inventory: Inventory::new(),
+                connection: pending.connection,
+                requested_columns: HashSet::new(),
+                last_message_time: Instant::now(),
+                is_connected: true,
+            };
+            self.next_player_id += 1;
+
+            info!("Player {} ({}) joined", player.id, player.name);
+
+            player.send(&Message::HandshakeAccepted {
+                player_id: player.id,
+                spawn_position: self.spawn_position,
+            });
+
+            // Introduce the new player and the ones already there to each other
+            let new_player_move = Message::PlayerMove {
+                player_id: player.id,
+                position: player.position,
+                rotation: player.rotation,
+            };
+
+            for other_player in self.players.iter_mut() {
+                player.send(&Message::PlayerMove {
+                    player_id: other_player.id,
+                    position: other_player.position,
+                    rotation: other_player.rotation,
+                });
+                other_player.send(&new_player_move);
+            }
+
+            events.push(ServerEvent::PlayerJoined {
+                player_id: player.id,
+            });
+            self.players.push(player);
+        }
+    }
+
+    fn handle_player_messages(&mut self, events: &mut Vec<ServerEvent>) {
+        let mut moves = Vec::new();
+
+        for player in self.players.iter_mut() {
+            while player.is_connected {
+                let message = match player.connection.try_receive() {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(err) => {
+                        warn!(
+                            "Lost connection to player {} ({}): {err}",
+                            player.id, player.name
+                        );
+                        player.is_connected = false;
+                        break;
+                    }
+                };
+
+                player.last_message_time = Instant::now();
+
+                match player.check_message(&message) {
+                    Ok(()) => {}
+                    Err(InvalidMessage::Outdated(reason)) => {
+                        warn!("Dropped a message from player {}: {reason}", player.id);
+
+                        // The player already changed the block on their side, undo it
+                        if let Message::BlockChange { x, y, z, .. } = message {
+                            if let (Some(block), Some(level)) = (
+                                self.chunk_manager.get_block(x, y, z),
+                                self.chunk_manager.get_fluid_level(x, y, z),
+                            ) {
+                                player.send(&Message::BlockChange {
+                                    x,
+                                    y,
+                                    z,
+                                    block,
+                                    level,
+                                });
+                            }
+                        }
+
+                        continue;
+                    }
+                    Err(InvalidMessage::Malformed(reason)) => {
+                        warn!(
+                            "Disconnecting player {} ({}): {reason}",
+                            player.id, player.name
+                        );
+                        player.send(&Message::Disconnect {
+                            reason: format!("Invalid message: {reason}"),
+                        });
+                        player.is_connected = false;
+                        break;
+                    }
+                }
+
+                match message {
+                    Message::RequestChunkColumn { x, z } => {
+                        player.requested_columns.insert((x, z));
+                    }
+                    Message::BlockChange {
+                        x,
+                        y,
+                        z,
+                        block,
+                        level,
+                    } => {
+                        // Broadcast to everybody (the sender included) through the subscription
+                        if !self
+                            .chunk_manager
+                            .put_block_with_level(x, y, z, block, level)
+                        {
+                            warn!(
+                                "Player {} changed a block in an unloaded chunk at ({x}, {y}, {z})",
+                                player.id
+                            );
+                        } else if !block.is_air() {
+                            player.inventory.take_from_selected_slot(1);
+                        }
+                    }
+                    Message::PlayerMove {
+                        position, rotation, ..
+                    } => {
+                        player.position = position;
+                        player.rotation = rotation;
+
+                        // Forget the requests of the columns left behind
+                        let column = player_column(&player.position);
+                        player
+                            .requested_columns
+                            .retain(|&(x, z)| is_column_in_range(column, x, z));
+
+                        moves.push((player.id, position, rotation));
+                        events.push(ServerEvent::PlayerMoved {
+                            player_id: player.id,
+                            position,
+                        });
+                    }
+                    Message::InventorySync {
+                        selected_hotbar_slot,
+                        slots,
+                    } => {
+                        player.inventory.slots.copy_from_slice(&slots);
+                        player.inventory.selected_hotbar_slot = selected_hotbar_slot as usize;
+                    }
+                    Message::KeepAlive { .. } => {}
+                    Message::Disconnect { reason } => {
+                        info!("Player {} ({}) left: {reason}", player.id, player.name);
+                        player.is_connected = false;
+                    }
+                    message => warn!("Unexpected message from player {}: {message:?}", player.id),
+                }
+            }
+        }
+
+        for (player_id, position, rotation) in moves {
+            let message = Message::PlayerMove {
+                player_id,
+                position,
+                rotation,
+            };
+
+            for player in self.players.iter_mut() {
+                if player.id != player_id {
+                    player.send(&message);
+                }
+            }
+        }
+    }
+
+    // Columns are only sent once they are fully generated
+    fn send_requested_columns(&mut self) {
+        for player in self.players.iter_mut() {
+            let columns: Vec<_> = player
+                .requested_columns
+                .iter()
+                .filter_map(|&(x, z)| {
+                    self.chunk_manager
+                        .get_column(x, z)
+                        .filter(|column| *column.has_foliage.read())
+                        .map(|column| (x, z, column))
+                })
+                .collect();
+
+            for (x, z, column) in columns {
+                player.requested_columns.remove(&(x, z));
+                player.send(&Message::ChunkColumnData {
+                    x,
+                    z,
+                    data: encode_column(&column),
+                });
+            }
+        }
+    }
+
+    fn remove_disconnected_players(&mut self, events: &mut Vec<ServerEvent>) {
+        let mut left_players = Vec::new();
+
+        self.players.retain(|player| {
+            if !player.is_connected {
+                left_players.push(player.id);
+            }
+
+            player.is_connected
+        });
+
+        for player_id in left_players {
+            for player in self.players.iter_mut() {
+                player.send(&Message::PlayerLeft { player_id });
+            }
+
+            events.push(ServerEvent::PlayerLeft { player_id });
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::chunk_manager::ChunkManager;
+use crate::day_night::WorldTime;
+use crate::ecs::components::{ChunkLoadAnchor, MainHandItemChanged};
+use crate::ecs::systems::chunk_loading::ChunkLoading;
+use crate::ecs::systems::{
+    AdvanceGlobalTime, UpdateBlockTicks, UpdateDroppedItems, UpdatePhysics, UpdateVitals,
+};
+use crate::game_mode::GameMode;
+use crate::inventory::dropped_item::DroppedItem;
+use crate::inventory::Inventory;
+use crate::network::server::ServerEvent;
+use crate::physics::{Collider, Gravity, Interpolator, PhysicsBody, Transform};
+use crate::player::PlayerState;
+use crate::timer::Timer;
+use crate::vitals::{Health, Hunger, SpawnPoint};
+use crate::world_generation::WorldGenerator;
+use nalgebra_glm::Vec3;
+use specs::{Builder, Dispatcher, DispatcherBuilder, Entity, World, WorldExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Simulation of the headless server, without any window: world generation, block ticks and the
+// physics, dropped items and vitals of the entities. The connected players are simulated by their
+// clients, they only keep the world loaded around them like the spawn area.
+pub struct ServerWorld {
+    pub world: World,
+    dispatcher: Dispatcher<'static, 'static>,
+    // Entity of every connected player, by player id
+    remote_players: HashMap<u32, Entity>,
+    spawn_position: Vec3,
+}
+
+impl ServerWorld {
+    pub fn new(
+        chunk_manager: Arc<ChunkManager>,
+        world_generator: Arc<dyn WorldGenerator>,
+        seed: u32,
+        spawn_position: Vec3,
+    ) -> Self {
+        let mut world = World::new();
+        world.register::<PlayerState>();
+        world.register::<GameMode>();
+        world.register::<Transform>();
+        world.register::<Interpolator<PhysicsBody>>();
+        world.register::<Gravity>();
+        world.register::<Collider>();
+        world.register::<Inventory>();
+        world.register::<MainHandItemChanged>();
+        world.register::<DroppedItem>();
+        world.register::<Health>();
+        world.register::<Hunger>();
+        world.register::<ChunkLoadAnchor>();
+
+        world.insert(Timer::default());
+        world.insert(WorldTime::default());
+        world.insert(SpawnPoint(spawn_position));
+        world.insert(chunk_manager);
+
+        // The spawn area stays loaded when nobody is connected
+        world
+            .create_entity()
+            .with(Transform::new(spawn_position))
+            .with(ChunkLoadAnchor)
+            .build();
+
+        // Chunks are generated but never meshed since nothing is rendered
+        let dispatcher = DispatcherBuilder::new()
+            .with_thread_local(UpdatePhysics)
+            .with_thread_local(UpdateVitals::new())
+            .with_thread_local(UpdateDroppedItems)
+            .with_thread_local(UpdateBlockTicks::new(seed))
+            .with_thread_local(ChunkLoading::new(None, world_generator, seed))
+            .with_thread_local(AdvanceGlobalTime)
+            .build();
+
+        Self {
+            world,
+            dispatcher,
+            remote_players: HashMap::new(),
+            spawn_position,
+        }
+    }
+
+    // Mirrors the players of `Server::update` in the world
+    pub fn handle_event(&mut self, event: ServerEvent) {
+        match event {
+            ServerEvent::PlayerJoined { player_id } => {
+                let entity = self
+                    .world
+                    .create_entity()
+                    .with(PlayerState::new())
+                    .with(Transform::new(self.spawn_position))
+                    .build();
+                self.remote_players.insert(player_id, entity);
+            }
+            ServerEvent::PlayerMoved {
+                player_id,
+                position,
+            } => {
+                let mut transform = self.world.write_storage::<Transform>();
+
+                if let Some(transform) = self
+                    .remote_players
+                    .get(&player_id)
+                    .and_then(|&entity| transform.get_mut(entity))
+                {
+                    transform.position = position;
+                }
+            }
+            ServerEvent::PlayerLeft { player_id } => {
+                if let Some(entity) = self.remote_players.remove(&player_id) {
+                    if let Err(err) = self.world.delete_entity(entity) {
+                        error!("{err}");
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.dispatcher.dispatch(&self.world);
+        self.world.maintain();
+    }
+}
@@ -0,0 +1,157 @@
+use crate::constants::{KEEP_ALIVE_TIMEOUT, OUTGOING_MESSAGE_QUEUE_SIZE};
+use crate::network::protocol::{read_message, write_message, Message};
+use crossbeam_channel::{
+    bounded, unbounded, Receiver, RecvTimeoutError, Sender, TryRecvError, TrySendError,
+};
+use std::io::{self, BufReader, BufWriter};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+// One end of a connection between a client and the server
+pub trait Connection: Send {
+    fn send(&mut self, message: &Message) -> io::Result<()>;
+
+    // Returns the next received message without blocking
+    fn try_receive(&mut self) -> io::Result<Option<Message>>;
+
+    // Waits at most `timeout` for the next message
+    fn receive_timeout(&mut self, timeout: Duration) -> io::Result<Option<Message>>;
+}
+
+fn connection_closed() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "Connection closed")
+}
+
+// In-process transport, used to run the server and the client in the same process.
+// Messages are still encoded so that both transports behave the same way.
+pub struct ChannelConnection {
+    outgoing_tx: Sender<Vec<u8>>,
+    incoming_rx: Receiver<Vec<u8>>,
+}
+
+impl ChannelConnection {
+    // Returns both ends of a new connection
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, a_rx) = unbounded();
+        let (b_tx, b_rx) = unbounded();
+
+        (
+            Self {
+                outgoing_tx: a_tx,
+                incoming_rx: b_rx,
+            },
+            Self {
+                outgoing_tx: b_tx,
+                incoming_rx: a_rx,
+            },
+        )
+    }
+}
+
+impl Connection for ChannelConnection {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.outgoing_tx
+            .send(message.encode())
+            .map_err(|_| connection_closed())
+    }
+
+    fn try_receive(&mut self) -> io::Result<Option<Message>> {
+        match self.incoming_rx.try_recv() {
+            Ok(data) => Message::decode(&data).map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(connection_closed()),
+        }
+    }
+
+    fn receive_timeout(&mut self, timeout: Duration) -> io::Result<Option<Message>> {
+        match self.incoming_rx.recv_timeout(timeout) {
+            Ok(data) => Message::decode(&data).map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(connection_closed()),
+        }
+    }
+}
+
+// The messages are read and written by two background threads so that neither receiving nor
+// sending blocks the game loop, even when the other end is slow
+pub struct TcpConnection {
+    outgoing_tx: Sender<Message>,
+    incoming_rx: Receiver<io::Result<Message>>,
+}
+
+impl TcpConnection {
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(address)?)
+    }
+
+    pub fn from_stream(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+
+        // A stalled peer makes the writer thread give up instead of blocking forever
+        stream.set_write_timeout(Some(*KEEP_ALIVE_TIMEOUT))?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let (incoming_tx, incoming_rx) = unbounded();
+
+        thread::spawn(move || loop {
+            let message = read_message(&mut reader);
+            let is_error = message.is_err();
+
+            // Stop on the first error, the stream can't be resynchronized anyway
+            if incoming_tx.send(message).is_err() || is_error {
+                break;
+            }
+        });
+
+        let mut writer = BufWriter::new(stream);
+        let (outgoing_tx, outgoing_rx) = bounded(OUTGOING_MESSAGE_QUEUE_SIZE);
+
+        // Sends what is left in the queue once the connection is dropped, then closes the stream,
+        // which also stops the reader thread
+        thread::spawn(move || {
+            for message in outgoing_rx {
+                if write_message(&mut writer, &message).is_err() {
+                    break;
+                }
+            }
+
+            let _ = writer.get_ref().shutdown(Shutdown::Both);
+        });
+
+        Ok(Self {
+            outgoing_tx,
+            incoming_rx,
+        })
+    }
+}
+
+impl Connection for TcpConnection {
+    // Fails if the other end doesn't keep up with the messages sent to it
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        match self.outgoing_tx.try_send(message.clone()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "Too many messages waiting to be sent",
+            )),
+            Err(TrySendError::Disconnected(_)) => Err(connection_closed()),
+        }
+    }
+
+    fn try_receive(&mut self) -> io::Result<Option<Message>> {
+        match self.incoming_rx.try_recv() {
+            Ok(message) => message.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(connection_closed()),
+        }
+    }
+
+    fn receive_timeout(&mut self, timeout: Duration) -> io::Result<Option<Message>> {
+        match self.incoming_rx.recv_timeout(timeout) {
+            Ok(message) => message.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(connection_closed()),
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use crate::aabb::{get_block_aabb, AABB};
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{GRAVITY, IN_AIR_FRICTION, MAX_VERTICAL_VELOCITY, ON_GROUND_FRICTION};
+use nalgebra_glm::{vec3, Vec3};
+use num_traits::Zero;
 use std::time::Instant;
 
 pub trait Interpolatable {
@@ -93,3 +98,205 @@ impl Interpolator<f32> {
         });
     }
 }
+
+// Where an entity is: the centre of the bottom of its bounding box. The physics updates it from
+// the interpolated `PhysicsBody` of the entity, the entities without a body are moved directly.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Transform {
+    pub position: Vec3,
+}
+
+impl Transform {
+    pub fn new(position: Vec3) -> Self {
+        Self { position }
+    }
+}
+
+// Moving box of an entity, it is integrated at a fixed time step in an `Interpolator`
+#[derive(Clone, Debug)]
+pub struct PhysicsBody {
+    pub aabb: AABB,
+    pub velocity: Vec3,
+    // Reset after every step, the forces of the step are added to it
+    pub acceleration: Vec3,
+    pub is_on_ground: bool,
+}
+
+// Entities with this component fall
+#[derive(Copy, Clone, Debug)]
+pub struct Gravity {
+    pub acceleration: f32,
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Self {
+            acceleration: GRAVITY,
+        }
+    }
+}
+
+// Entities with this component can't go through the solid blocks
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Collider;
+
+impl PhysicsBody {
+    // Box of `2 * half_width` x `height` x `2 * half_width` standing at `position`
+    pub fn new(position: Vec3, half_width: f32, height: f32) -> Self {
+        Self {
+            aabb: AABB::new(
+                vec3(position.x - half_width, position.y, position.z - half_width),
+                vec3(
+                    position.x + half_width,
+                    position.y + height,
+                    position.z + half_width,
+                ),
+            ),
+            velocity: vec3(0.0, 0.0, 0.0),
+            acceleration: vec3(0.0, 0.0, 0.0),
+            is_on_ground: false,
+        }
+    }
+
+    // Centre of the bottom of the box
+    pub fn position(&self) -> Vec3 {
+        vec3(
+            (self.aabb.mins.x + self.aabb.maxs.x) / 2.0,
+            self.aabb.mins.y,
+            (self.aabb.mins.z + self.aabb.maxs.z) / 2.0,
+        )
+    }
+
+    pub fn get_colliding_block_coords(&self, chunk_manager: &ChunkManager) -> Option<Vec3> {
+        let mins = &self.aabb.mins;
+        let maxs = &self.aabb.maxs;
+
+        let block_min = vec3(
+            mins.x.floor() as i32,
+            mins.y.floor() as i32,
+            mins.z.floor() as i32,
+        );
+        let block_max = vec3(
+            maxs.x.floor() as i32,
+            maxs.y.floor() as i32,
+            maxs.z.floor() as i32,
+        );
+
+        // Find the block that the body is colliding with
+        for y in block_min.y..=block_max.y {
+            for z in block_min.z..=block_max.z {
+                for x in block_min.x..=block_max.x {
+                    if let Some(block) = chunk_manager.get_block(x, y, z) {
+                        if !block.is_solid() {
+                            continue;
+                        }
+
+                        let block_aabb = get_block_aabb(&vec3(x as f32, y as f32, z as f32));
+
+                        if self.aabb.intersects(&block_aabb) {
+                            return Some(vec3(x as f32, y as f32, z as f32));
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    // Moves the box out of the block along the direction of `v`, returns whether it landed on it
+    pub fn separate_from_block(&mut self, v: &Vec3, block_coords: &Vec3) -> bool {
+        let mut is_on_ground = false;
+        let block_aabb = get_block_aabb(block_coords);
+        let size = self.aabb.maxs - self.aabb.mins;
+
+        if !v.x.is_zero() {
+            let mins_x = if v.x < 0.0 {
+                block_aabb.maxs.x
+            } else {
+                block_aabb.mins.x - size.x
+            };
+
+            self.aabb.mins.x = mins_x;
+            self.aabb.maxs.x = mins_x + size.x;
+            self.velocity.x = 0.0;
+        }
+
+        if !v.y.is_zero() {
+            let mins_y = if v.y < 0.0 {
+                is_on_ground = true;
+                block_aabb.maxs.y
+            } else {
+                block_aabb.mins.y - size.y
+            };
+
+            self.aabb.mins.y = mins_y;
+            self.aabb.maxs.y = mins_y + size.y;
+            self.velocity.y = 0.0;
+        }
+
+        if !v.z.is_zero() {
+            let mins_z = if v.z < 0.0 {
+                block_aabb.maxs.z
+            } else {
+                block_aabb.mins.z - size.z
+            };
+
+            self.aabb.mins.z = mins_z;
+            self.aabb.maxs.z = mins_z + size.z;
+            self.velocity.z = 0.0;
+        }
+
+        is_on_ground
+    }
+
+    // Slows the horizontal movement down, and the vertical one for the bodies that don't fall
+    pub fn apply_friction(&mut self, dt: f32, has_vertical_friction: bool) {
+        let friction = if self.is_on_ground {
+            ON_GROUND_FRICTION
+        } else {
+            IN_AIR_FRICTION
+        };
+
+        if self.acceleration.x.is_zero() || self.acceleration.x.signum() != self.velocity.x.signum()
+        {
+            self.velocity.x -= friction * self.velocity.x * dt;
+        }
+
+        if self.acceleration.z.is_zero() || self.acceleration.z.signum() != self.velocity.z.signum()
+        {
+            self.velocity.z -= friction * self.velocity.z * dt;
+        }
+
+        if has_vertical_friction
+            && (self.acceleration.y.is_zero()
+                || self.acceleration.y.signum() != self.velocity.y.signum())
+        {
+            self.velocity.y -= ON_GROUND_FRICTION * self.velocity.y * dt;
+        }
+    }
+
+    // Limit the free-falling speed (vertically)
+    // NOTE: https://www.planetminecraft.com/blog/the-acceleration-of-gravity-in-minecraft-and-terminal-velocity/
+    pub fn limit_fall_speed(&mut self) {
+        if self.velocity.y < -MAX_VERTICAL_VELOCITY {
+            self.velocity.y = -MAX_VERTICAL_VELOCITY;
+        }
+    }
+}
+
+impl Interpolatable for PhysicsBody {
+    fn interpolate(&self, other: &Self, alpha: f32) -> Self {
+        let interpolate_vec3 = |from: &Vec3, to: &Vec3| alpha * from + (1.0 - alpha) * to;
+
+        Self {
+            aabb: AABB {
+                mins: interpolate_vec3(&self.aabb.mins, &other.aabb.mins),
+                maxs: interpolate_vec3(&self.aabb.maxs, &other.aabb.maxs),
+            },
+            velocity: interpolate_vec3(&self.velocity, &other.velocity),
+            acceleration: interpolate_vec3(&self.acceleration, &other.acceleration),
+            is_on_ground: self.is_on_ground,
+        }
+    }
+}
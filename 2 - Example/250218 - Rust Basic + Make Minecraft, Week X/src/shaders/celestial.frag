@@ -0,0 +1,23 @@
+#version 460 core
+
+out vec4 Color;
+
+uniform vec3 color;
+// Opacity of the halo around the body
+uniform float glow;
+
+in VertexAttributes {
+    vec2 texture_coords;
+} attrs;
+
+void main() {
+    // A square body in the middle of the quad, the halo fades towards the edges
+    vec2 from_center = abs(attrs.texture_coords - 0.5) * 2.0;
+    float distance = max(from_center.x, from_center.y);
+
+    if (distance < 0.4) {
+        Color = vec4(color, 1.0);
+    } else {
+        Color = vec4(color, glow * (1.0 - distance) / 0.6);
+    }
+}
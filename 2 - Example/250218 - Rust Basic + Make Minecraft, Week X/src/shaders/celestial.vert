@@ -0,0 +1,18 @@
+#version 460 core
+
+uniform mat4 model;
+// Without the translation, the sun and the moon are infinitely far
+uniform mat4 view;
+uniform mat4 projection;
+
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec2 texture_coords;
+
+out VertexAttributes {
+    vec2 texture_coords;
+} attrs;
+
+void main() {
+    gl_Position = projection * view * model * vec4(pos, 1.0);
+    attrs.texture_coords = texture_coords;
+}
@@ -0,0 +1,25 @@
+#version 460 core
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+// The stages of the crack texture are side by side
+uniform int stage;
+uniform int stages;
+
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec2 texture_coords;
+
+out VertexAttributes {
+    vec2 texture_coords;
+} attrs;
+
+float z_offset = -0.001;
+
+void main() {
+    vec4 position = projection * view * model * vec4(pos, 1.0);
+    position.z += z_offset;
+    gl_Position = position;
+
+    attrs.texture_coords = vec2((texture_coords.x + stage) / stages, texture_coords.y);
+}
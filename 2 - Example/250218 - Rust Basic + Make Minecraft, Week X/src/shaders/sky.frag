@@ -0,0 +1,19 @@
+#version 460 core
+
+out vec4 Color;
+
+// Of the rotation of the camera and the projection, to find where each pixel looks at
+uniform mat4 inverse_view_projection;
+uniform vec3 sky_color;
+uniform vec3 fog_color;
+
+in vec2 ndc;
+
+void main() {
+    vec4 far = inverse_view_projection * vec4(ndc, 1.0, 1.0);
+    vec3 direction = normalize(far.xyz / far.w);
+
+    // The horizon has the colour of the fog, so that the far blocks fade into it
+    float height = clamp(direction.y, 0.0, 1.0);
+    Color = vec4(mix(fog_color, sky_color, sqrt(height)), 1.0);
+}
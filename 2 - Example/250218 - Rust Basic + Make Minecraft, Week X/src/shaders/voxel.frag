@@ -1,15 +1,20 @@
 #version 460 core
 
+const float min_brightness = 0.05;
+
 out vec4 Color;
 
 uniform sampler2DArray array_texture;
 uniform bool enable_fog;
-uniform vec3 sky_color;
+uniform vec3 fog_color;
+// Brightness of the skylight at the current time of day
+uniform float skylight_multiplier;
 
 in VertexAttributes {
     vec3 texture_coords;
     vec3 normal;
     float ao;
+    vec2 light;
     float visibility;
 } attrs;
 
@@ -32,7 +37,11 @@ void main() {
 
     Color.rgb *= (1.0 - attrs.ao * 0.15);
 
+    // Each light level is 20% darker than the one above, but it's never pitch black
+    float light_level = max(attrs.light.x * skylight_multiplier, attrs.light.y);
+    Color.rgb *= max(pow(0.8, 15.0 * (1.0 - light_level)), min_brightness);
+
     if (enable_fog) {
-        Color.rgb = mix(sky_color, Color.rgb, attrs.visibility);
+        Color.rgb = mix(fog_color, Color.rgb, attrs.visibility);
     }
 }
@@ -2,7 +2,6 @@
 
 const float fog_gradient = 10.0;
 
-uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
 uniform float render_distance;
@@ -11,11 +10,18 @@ layout (location = 0) in vec3 pos;
 layout (location = 1) in vec3 texture_coords;
 layout (location = 2) in vec3 normal;
 layout (location = 3) in float ao;
+layout (location = 4) in vec2 light;
+
+// Position of every chunk drawn this frame, each draw command gives its own as base instance
+layout (std430, binding = 0) readonly buffer ChunkPositions {
+    vec4 chunk_positions[];
+};
 
 out VertexAttributes {
     vec3 texture_coords;
     vec3 normal;
     float ao;
+    vec2 light;
     float visibility;
 } attrs;
 
@@ -23,9 +29,10 @@ void main() {
     attrs.texture_coords = texture_coords;
     attrs.normal = normal;
     attrs.ao = ao;
+    attrs.light = light;
     attrs.visibility = 1.0;
 
-    vec4 frag_pos = view * model * vec4(pos, 1.0f);
+    vec4 frag_pos = view * vec4(pos + chunk_positions[gl_BaseInstance].xyz, 1.0f);
     gl_Position = projection * frag_pos;
 
     // Fog
@@ -31,10 +31,14 @@ pub unsafe fn write_unit_cube_to_ptr(
     (front_layer, back_layer, top_layer, bottom_layer, left_layer, right_layer): UVFaces,
     [right, left, top, bottom, front, back]: [bool; 6],
     ao: [[u8; 4]; 6],
+    light: [[u8; 4]; 6],
 ) -> u32 {
     let (x, y, z) = position;
 
-    let vertex_size = 10;
+    // Skylight and block light between 0 and 1
+    let light = light.map(|face| face.map(|vertex| ((vertex >> 4) as f32 / 15.0, (vertex & 0xF) as f32 / 15.0)));
+
+    let vertex_size = 12;
     let vertices_per_face = 6;
     let face_size = vertex_size * vertices_per_face;
 
@@ -45,12 +49,12 @@ pub unsafe fn write_unit_cube_to_ptr(
 
     if front {
         ptr.offset(idx).copy_from_nonoverlapping([
-            0.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][0] as f32,
-            1.0 + x, 0.0 + y, 1.0 + z, uv.2, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][1] as f32,
-            1.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][2] as f32,
-            1.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][2] as f32,
-            0.0 + x, 1.0 + y, 1.0 + z, uv.0, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][3] as f32,
-            0.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][0] as f32,
+            0.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][0] as f32, light[4][0].0, light[4][0].1,
+            1.0 + x, 0.0 + y, 1.0 + z, uv.2, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][1] as f32, light[4][1].0, light[4][1].1,
+            1.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][2] as f32, light[4][2].0, light[4][2].1,
+            1.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][2] as f32, light[4][2].0, light[4][2].1,
+            0.0 + x, 1.0 + y, 1.0 + z, uv.0, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][3] as f32, light[4][3].0, light[4][3].1,
+            0.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][0] as f32, light[4][0].0, light[4][0].1,
         ].as_ptr(), face_size);
 
         idx += face_size as isize;
@@ -59,12 +63,12 @@ pub unsafe fn write_unit_cube_to_ptr(
 
     if back {
         ptr.offset(idx).copy_from_nonoverlapping([
-            1.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][0] as f32,
-            0.0 + x, 0.0 + y, 0.0 + z, uv.2, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][1] as f32,
-            0.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][2] as f32,
-            0.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][2] as f32,
-            1.0 + x, 1.0 + y, 0.0 + z, uv.0, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][3] as f32,
-            1.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][0] as f32,
+            1.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][0] as f32, light[5][0].0, light[5][0].1,
+            0.0 + x, 0.0 + y, 0.0 + z, uv.2, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][1] as f32, light[5][1].0, light[5][1].1,
+            0.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][2] as f32, light[5][2].0, light[5][2].1,
+            0.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][2] as f32, light[5][2].0, light[5][2].1,
+            1.0 + x, 1.0 + y, 0.0 + z, uv.0, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][3] as f32, light[5][3].0, light[5][3].1,
+            1.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][0] as f32, light[5][0].0, light[5][0].1,
         ].as_ptr(), face_size);
 
         idx += face_size as isize;
@@ -73,12 +77,12 @@ pub unsafe fn write_unit_cube_to_ptr(
 
     if left {
         ptr.offset(idx).copy_from_nonoverlapping([
-            0.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][0] as f32,
-            0.0 + x, 0.0 + y, 1.0 + z, uv.2, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][1] as f32,
-            0.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][2] as f32,
-            0.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][2] as f32,
-            0.0 + x, 1.0 + y, 0.0 + z, uv.0, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][3] as f32,
-            0.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][0] as f32,
+            0.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][0] as f32, light[1][0].0, light[1][0].1,
+            0.0 + x, 0.0 + y, 1.0 + z, uv.2, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][1] as f32, light[1][1].0, light[1][1].1,
+            0.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][2] as f32, light[1][2].0, light[1][2].1,
+            0.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][2] as f32, light[1][2].0, light[1][2].1,
+            0.0 + x, 1.0 + y, 0.0 + z, uv.0, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][3] as f32, light[1][3].0, light[1][3].1,
+            0.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][0] as f32, light[1][0].0, light[1][0].1,
         ].as_ptr(), face_size);
 
         idx += face_size as isize;
@@ -87,12 +91,12 @@ pub unsafe fn write_unit_cube_to_ptr(
 
     if right {
         ptr.offset(idx).copy_from_nonoverlapping([
-            1.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][0] as f32,
-            1.0 + x, 0.0 + y, 0.0 + z, uv.2, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][1] as f32,
-            1.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][2] as f32,
-            1.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][2] as f32,
-            1.0 + x, 1.0 + y, 1.0 + z, uv.0, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][3] as f32,
-            1.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][0] as f32,
+            1.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][0] as f32, light[0][0].0, light[0][0].1,
+            1.0 + x, 0.0 + y, 0.0 + z, uv.2, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][1] as f32, light[0][1].0, light[0][1].1,
+            1.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][2] as f32, light[0][2].0, light[0][2].1,
+            1.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][2] as f32, light[0][2].0, light[0][2].1,
+            1.0 + x, 1.0 + y, 1.0 + z, uv.0, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][3] as f32, light[0][3].0, light[0][3].1,
+            1.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][0] as f32, light[0][0].0, light[0][0].1,
         ].as_ptr(), face_size);
 
         idx += face_size as isize;
@@ -101,12 +105,12 @@ pub unsafe fn write_unit_cube_to_ptr(
 
     if top {
         ptr.offset(idx).copy_from_nonoverlapping([
-            0.0 + x, 1.0 + y, 1.0 + z, uv.0, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][0] as f32,
-            1.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][1] as f32,
-            1.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][2] as f32,
-            1.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][2] as f32,
-            0.0 + x, 1.0 + y, 0.0 + z, uv.0, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][3] as f32,
-            0.0 + x, 1.0 + y, 1.0 + z, uv.0, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][0] as f32,
+            0.0 + x, 1.0 + y, 1.0 + z, uv.0, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][0] as f32, light[2][0].0, light[2][0].1,
+            1.0 + x, 1.0 + y, 1.0 + z, uv.2, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][1] as f32, light[2][1].0, light[2][1].1,
+            1.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][2] as f32, light[2][2].0, light[2][2].1,
+            1.0 + x, 1.0 + y, 0.0 + z, uv.2, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][2] as f32, light[2][2].0, light[2][2].1,
+            0.0 + x, 1.0 + y, 0.0 + z, uv.0, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][3] as f32, light[2][3].0, light[2][3].1,
+            0.0 + x, 1.0 + y, 1.0 + z, uv.0, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][0] as f32, light[2][0].0, light[2][0].1,
         ].as_ptr(), face_size);
 
         idx += face_size as isize;
@@ -115,12 +119,12 @@ pub unsafe fn write_unit_cube_to_ptr(
 
     if bottom {
         ptr.offset(idx).copy_from_nonoverlapping([
-            0.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][0] as f32,
-            1.0 + x, 0.0 + y, 0.0 + z, uv.2, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][1] as f32,
-            1.0 + x, 0.0 + y, 1.0 + z, uv.2, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][2] as f32,
-            1.0 + x, 0.0 + y, 1.0 + z, uv.2, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][2] as f32,
-            0.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][3] as f32,
-            0.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][0] as f32,
+            0.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][0] as f32, light[3][0].0, light[3][0].1,
+            1.0 + x, 0.0 + y, 0.0 + z, uv.2, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][1] as f32, light[3][1].0, light[3][1].1,
+            1.0 + x, 0.0 + y, 1.0 + z, uv.2, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][2] as f32, light[3][2].0, light[3][2].1,
+            1.0 + x, 0.0 + y, 1.0 + z, uv.2, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][2] as f32, light[3][2].0, light[3][2].1,
+            0.0 + x, 0.0 + y, 1.0 + z, uv.0, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][3] as f32, light[3][3].0, light[3][3].1,
+            0.0 + x, 0.0 + y, 0.0 + z, uv.0, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][0] as f32, light[3][0].0, light[3][0].1,
         ].as_ptr(), face_size);
 
         // idx += face_size as isize;
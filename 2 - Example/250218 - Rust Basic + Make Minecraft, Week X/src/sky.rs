@@ -0,0 +1,143 @@
+use crate::constants::{CELESTIAL_DISTANCE, MOON_SIZE, SUN_SIZE};
+use crate::day_night::WorldTime;
+use crate::shader::ShaderProgram;
+use crate::shapes::quad;
+use nalgebra::Matrix4;
+use nalgebra_glm::{mat3_to_mat4, mat4_to_mat3, vec3, Mat4};
+use std::f32::consts::PI;
+use std::os::raw::c_void;
+
+// The sky is a single triangle generated by the vertex shader, it still needs a VAO
+pub fn create_sky_vao() -> u32 {
+    let mut vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+    vao
+}
+
+pub fn create_celestial_vao() -> u32 {
+    // Setup VAO
+    let mut vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+    // Position
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        0,
+        3,
+        gl::FLOAT,
+        gl::FALSE,
+        0
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+    // Texture coords
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        1,
+        2,
+        gl::FLOAT,
+        gl::FALSE,
+        3 * std::mem::size_of::<f32>() as u32
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+    // Setup VBO
+    let mut vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut vbo));
+
+    gl_call!(gl::VertexArrayVertexBuffer(
+        vao,
+        0,
+        vbo,
+        0,
+        (5 * std::mem::size_of::<f32>()) as i32
+    ));
+    gl_call!(gl::NamedBufferData(
+        vbo,
+        (30 * std::mem::size_of::<f32>()) as isize,
+        quad((0.0, 0.0, 1.0, 1.0)).as_ptr() as *const c_void,
+        gl::STATIC_DRAW
+    ));
+
+    vao
+}
+
+// The camera only turns around the sky, it never moves in it
+fn sky_view_matrix(view_matrix: &Mat4) -> Mat4 {
+    mat3_to_mat4(&mat4_to_mat3(view_matrix))
+}
+
+// Fills the screen with the gradient from the fog colour at the horizon to the sky colour above
+pub fn draw_sky(
+    vao: u32,
+    world_time: &WorldTime,
+    view_matrix: &Mat4,
+    projection_matrix: &Mat4,
+    shader: &mut ShaderProgram,
+) {
+    let inverse_view_projection = (projection_matrix * sky_view_matrix(view_matrix))
+        .try_inverse()
+        .unwrap_or_else(Mat4::identity);
+
+    shader.use_program();
+    unsafe {
+        shader.set_uniform_matrix4fv("inverse_view_projection", inverse_view_projection.as_ptr());
+    }
+    shader.set_uniform3f("sky_color", world_time.sky_color().as_slice());
+    shader.set_uniform3f("fog_color", world_time.fog_color().as_slice());
+
+    gl_call!(gl::BindVertexArray(vao));
+    gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+}
+
+// Draws the sun and, on the other side of the world, the moon as squares facing the camera
+pub fn draw_sun_and_moon(
+    vao: u32,
+    world_time: &WorldTime,
+    view_matrix: &Mat4,
+    projection_matrix: &Mat4,
+    shader: &mut ShaderProgram,
+) {
+    let view_matrix = sky_view_matrix(view_matrix);
+
+    shader.use_program();
+    unsafe {
+        shader.set_uniform_matrix4fv("view", view_matrix.as_ptr());
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+    }
+
+    gl_call!(gl::BindVertexArray(vao));
+
+    let bodies = [
+        (world_time.sun_angle(), SUN_SIZE, [1.0, 0.95, 0.6], 0.5),
+        (
+            world_time.sun_angle() + PI,
+            MOON_SIZE,
+            [0.85, 0.87, 0.95],
+            0.2,
+        ),
+    ];
+
+    for (angle, size, color, glow) in bodies {
+        // Turned towards the camera, moved to the east of the sky then turned with the time
+        let model_matrix = {
+            let rotate_matrix = nalgebra_glm::rotation(angle, &vec3(0.0, 0.0, 1.0));
+            let translate_matrix = Matrix4::new_translation(&vec3(CELESTIAL_DISTANCE, 0.0, 0.0));
+            let face_matrix = nalgebra_glm::rotation(-PI / 2.0, &vec3(0.0, 1.0, 0.0));
+            let scale_matrix = Matrix4::new_scaling(size);
+
+            rotate_matrix * translate_matrix * face_matrix * scale_matrix
+        };
+
+        unsafe {
+            shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+        }
+        shader.set_uniform3f("color", &color);
+        shader.set_uniform1f("glow", glow);
+
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+    }
+}
@@ -0,0 +1,569 @@
+pub mod region;
+pub mod world_settings;
+
+use crate::biome::Biome;
+use crate::chunk::{BlockID, ChunkColumn};
+use crate::chunk_manager::{ChunkManager, CHUNK_VOLUME};
+use crate::fluid::MAX_FLUID_LEVEL;
+use crate::storage::region::{RegionFile, REGION_SIZE};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Bumped every time the layout written by `encode_column` changes
+pub const COLUMN_FORMAT_VERSION: u8 = 5;
+
+const COLUMN_HEADER_SIZE: usize = 2 + 2 * 16 * 16;
+
+// Columns saved with a u16 number of scheduled ticks, which cannot count a tick on every block
+const COLUMN_FORMAT_VERSION_WITH_U16_SCHEDULED_TICK_COUNT: u8 = 4;
+
+// Columns saved before the scheduled ticks existed end after their chunks
+const COLUMN_FORMAT_VERSION_WITHOUT_SCHEDULED_TICKS: u8 = 3;
+
+// Columns saved before the fluids existed only use the presence flags 0 and 1
+const COLUMN_FORMAT_VERSION_WITHOUT_FLUIDS: u8 = 2;
+
+// Columns saved before the biomes existed, they are loaded as plains
+const COLUMN_FORMAT_VERSION_WITHOUT_BIOMES: u8 = 1;
+
+// Presence flag of a chunk
+const EMPTY_CHUNK: u8 = 0;
+const CHUNK_WITH_BLOCKS: u8 = 1;
+const CHUNK_WITH_BLOCKS_AND_FLUID_LEVELS: u8 = 2;
+const COLUMN_HEADER_SIZE_WITHOUT_BIOMES: usize = 2 + 16 * 16;
+
+// Chunk, block index and delay of a scheduled tick
+const SCHEDULED_TICK_SIZE: usize = 1 + 2 + 4;
+
+// Serializes a chunk column into a byte buffer.
+// Layout: format version, foliage flag, highest block and biome of each (x, z), then for each
+// of the 16 chunks a presence flag followed by its block ids when the chunk is not empty, and
+// by its fluid levels when some of them are not 0. The column ends with the number of scheduled
+// ticks (u32) and, for each of them, the chunk (u8), the block index (u16) and the ticks left
+// (u32), in little endian.
+pub fn encode_column(column: &ChunkColumn) -> Vec<u8> {
+    let mut data = Vec::with_capacity(COLUMN_HEADER_SIZE + 16 + 4);
+
+    data.push(COLUMN_FORMAT_VERSION);
+    data.push(*column.has_foliage.read() as u8);
+    data.extend_from_slice(&column.highest_blocks.read()[..]);
+    data.extend(column.biomes.read().iter().map(|biome| biome.id()));
+
+    for chunk in column.chunks.iter() {
+        if chunk.is_empty() {
+            data.push(EMPTY_CHUNK);
+            continue;
+        }
+
+        let fluid_levels = chunk.fluid_levels.read();
+        let has_fluid_levels = fluid_levels.iter().any(|&level| level != 0);
+
+        data.push(if has_fluid_levels {
+            CHUNK_WITH_BLOCKS_AND_FLUID_LEVELS
+        } else {
+            CHUNK_WITH_BLOCKS
+        });
+        data.extend(chunk.blocks.read().iter().map(|block| block.id()));
+
+        if has_fluid_levels {
+            data.extend_from_slice(&fluid_levels[..]);
+        }
+    }
+
+    let scheduled_ticks: Vec<_> = column
+        .chunks
+        .iter()
+        .enumerate()
+        .flat_map(|(y, chunk)| {
+            let scheduled_ticks = chunk.scheduled_ticks.read();
+
+            scheduled_ticks
+                .iter()
+                .map(|(&index, &delay)| (y as u8, index, delay))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    data.extend_from_slice(&(scheduled_ticks.len() as u32).to_le_bytes());
+
+    for (y, index, delay) in scheduled_ticks {
+        data.push(y);
+        data.extend_from_slice(&index.to_le_bytes());
+        data.extend_from_slice(&delay.to_le_bytes());
+    }
+
+    data
+}
+
+// Fills a (reset) chunk column with the data produced by `encode_column`
+pub fn decode_column(data: &[u8], column: &ChunkColumn) -> io::Result<()> {
+    fn invalid_data(message: String) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+
+    let header_size = match data.first() {
+        Some(&COLUMN_FORMAT_VERSION)
+        | Some(&COLUMN_FORMAT_VERSION_WITH_U16_SCHEDULED_TICK_COUNT)
+        | Some(&COLUMN_FORMAT_VERSION_WITHOUT_SCHEDULED_TICKS)
+        | Some(&COLUMN_FORMAT_VERSION_WITHOUT_FLUIDS) => COLUMN_HEADER_SIZE,
+        Some(&COLUMN_FORMAT_VERSION_WITHOUT_BIOMES) => COLUMN_HEADER_SIZE_WITHOUT_BIOMES,
+        Some(version) => {
+            return Err(invalid_data(format!(
+                "Unsupported column format version {version}"
+            )))
+        }
+        None => return Err(invalid_data("Column data is empty".to_string())),
+    };
+
+    if data.len() < header_size {
+        return Err(invalid_data(format!(
+            "Column data is too short ({} bytes)",
+            data.len()
+        )));
+    }
+
+    *column.has_foliage.write() = data[1] != 0;
+    column
+        .highest_blocks
+        .write()
+        .copy_from_slice(&data[2..2 + 16 * 16]);
+
+    for (biome, &id) in column
+        .biomes
+        .write()
+        .iter_mut()
+        .zip(data[2 + 16 * 16..header_size].iter())
+    {
+        *biome =
+            Biome::from_id(id).ok_or_else(|| invalid_data(format!("Unknown biome id {id}")))?;
+    }
+
+    let mut cursor = header_size;
+
+    for (y, chunk) in column.chunks.iter().enumerate() {
+        let presence = *data
+            .get(cursor)
+            .ok_or_else(|| invalid_data(format!("Missing chunk {y}")))?;
+        cursor += 1;
+
+        match presence {
+            EMPTY_CHUNK => continue,
+            CHUNK_WITH_BLOCKS | CHUNK_WITH_BLOCKS_AND_FLUID_LEVELS => {}
+            _ => {
+                return Err(invalid_data(format!(
+                    "Invalid presence flag {presence} of chunk {y}"
+                )))
+            }
+        }
+
+        let ids = data
+            .get(cursor..cursor + CHUNK_VOLUME as usize)
+            .ok_or_else(|| invalid_data(format!("Truncated chunk {y}")))?;
+        cursor += CHUNK_VOLUME as usize;
+
+        for (index, &id) in ids.iter().enumerate() {
+            let block = BlockID::from_id(id)
+                .ok_or_else(|| invalid_data(format!("Unknown block id {id} in chunk {y}")))?;
+
+            if block.is_air() {
+                continue;
+            }
+
+            let index = index as u32;
+            chunk.set_block(index % 16, index / 256, (index / 16) % 16, block);
+        }
+
+        if presence == CHUNK_WITH_BLOCKS_AND_FLUID_LEVELS {
+            let fluid_levels = data
+                .get(cursor..cursor + CHUNK_VOLUME as usize)
+                .ok_or_else(|| invalid_data(format!("Truncated fluid levels of chunk {y}")))?;
+            cursor += CHUNK_VOLUME as usize;
+
+            if let Some(&level) = fluid_levels.iter().find(|&&level| level > MAX_FLUID_LEVEL) {
+                return Err(invalid_data(format!(
+                    "Invalid fluid level {level} in chunk {y}"
+                )));
+            }
+
+            chunk.fluid_levels.write().copy_from_slice(fluid_levels);
+        }
+    }
+
+    let count_size = match data[0] {
+        COLUMN_FORMAT_VERSION => 4,
+        COLUMN_FORMAT_VERSION_WITH_U16_SCHEDULED_TICK_COUNT => 2,
+        _ => return Ok(()),
+    };
+
+    let count = data
+        .get(cursor..cursor + count_size)
+        .ok_or_else(|| invalid_data("Missing the number of scheduled ticks".to_string()))?;
+    let count = match *count {
+        [a, b, c, d] => u32::from_le_bytes([a, b, c, d]) as usize,
+        [a, b] => u16::from_le_bytes([a, b]) as usize,
+        _ => unreachable!(),
+    };
+    cursor += count_size;
+
+    let scheduled_ticks = data
+        .get(cursor..cursor + count * SCHEDULED_TICK_SIZE)
+        .ok_or_else(|| invalid_data("Truncated scheduled ticks".to_string()))?;
+
+    for scheduled_tick in scheduled_ticks.chunks_exact(SCHEDULED_TICK_SIZE) {
+        let y = scheduled_tick[0] as usize;
+        let index = u16::from_le_bytes([scheduled_tick[1], scheduled_tick[2]]);
+        let delay = u32::from_le_bytes([
+            scheduled_tick[3],
+            scheduled_tick[4],
+            scheduled_tick[5],
+            scheduled_tick[6],
+        ]);
+
+        if y >= column.chunks.len() || index as u32 >= CHUNK_VOLUME {
+            return Err(invalid_data(format!(
+                "Invalid scheduled tick of block {index} in chunk {y}"
+            )));
+        }
+
+        column.chunks[y]
+            .scheduled_ticks
+            .write()
+            .insert(index, delay);
+    }
+
+    Ok(())
+}
+
+type SharedRegionFile = Arc<Mutex<RegionFile>>;
+
+// Saves and loads chunk columns to the region files of a world directory
+pub struct WorldStorage {
+    region_directory: PathBuf,
+    region_files: Mutex<HashMap<(i32, i32), SharedRegionFile>>,
+}
+
+impl WorldStorage {
+    pub fn open(world_directory: impl AsRef<Path>) -> io::Result<Self> {
+        let region_directory = world_directory.as_ref().join("region");
+        std::fs::create_dir_all(&region_directory)?;
+
+        Ok(Self {
+            region_directory,
+            region_files: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn region_file(&self, x: i32, z: i32) -> io::Result<SharedRegionFile> {
+        let region_xz = (x.div_euclid(REGION_SIZE), z.div_euclid(REGION_SIZE));
+        let mut region_files = self.region_files.lock();
+
+        if let Some(region_file) = region_files.get(&region_xz) {
+            return Ok(Arc::clone(region_file));
+        }
+
+        let path = self
+            .region_directory
+            .join(format!("r.{}.{}.mcr", region_xz.0, region_xz.1));
+        let region_file = Arc::new(Mutex::new(RegionFile::open(&path)?));
+        region_files.insert(region_xz, Arc::clone(&region_file));
+
+        Ok(region_file)
+    }
+
+    pub fn save_column(&self, x: i32, z: i32, column: &ChunkColumn) -> io::Result<()> {
+        let data = encode_column(column);
+        self.region_file(x, z)?.lock().write_column(x, z, &data)
+    }
+
+    // Loads a saved column into `column`, returns false if the column was never saved
+    pub fn load_column(&self, x: i32, z: i32, column: &ChunkColumn) -> io::Result<bool> {
+        let data = match self.region_file(x, z)?.lock().read_column(x, z)? {
+            Some(data) => data,
+            None => return Ok(false),
+        };
+
+        if let Err(err) = decode_column(&data, column) {
+            // Don't leave a half decoded column behind, the caller will generate it again
+            column.reset();
+
+            return Err(err);
+        }
+
+        Ok(true)
+    }
+
+    pub fn save_loaded_columns(&self, chunk_manager: &ChunkManager) -> io::Result<()> {
+        let columns: Vec<_> = chunk_manager
+            .loaded_chunk_columns
+            .read()
+            .iter()
+            .map(|(&xz, column)| (xz, Arc::clone(column)))
+            .collect();
+
+        for ((x, z), column) in columns {
+            self.save_column(x, z, &column)?;
+        }
+
+        self.flush()
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        for region_file in self.region_files.lock().values() {
+            region_file.lock().flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::BLOCK_REGISTRY;
+
+    fn temporary_world_directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "minecraft-storage-test-{}-{name}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+
+        path
+    }
+
+    fn sample_column() -> ChunkColumn {
+        let column = ChunkColumn::new();
+
+        for x in 0..16 {
+            for z in 0..16 {
+                column.set_block(x, 0, z, BlockID::BEDROCK);
+                column.set_block(x, 1 + (x + z) % 5, z, BlockID::STONE);
+            }
+        }
+
+        column.set_block(3, 70, 9, BlockID::GLASS);
+        column.set_block(4, 71, 9, BlockID::OAK_LEAVES);
+        column.set_block(15, 255, 15, BlockID::OAK_PLANKS);
+        column.set_block(5, 72, 9, BlockID::LAVA);
+        column.set_block(6, 72, 9, BlockID::WATER);
+        column.get_chunk(4).set_fluid_level(6, 8, 9, 3);
+        column.get_chunk(4).schedule_tick(6, 8, 9, 5);
+        column.get_chunk(0).schedule_tick(1, 2, 3, 70_000);
+        *column.has_foliage.write() = true;
+        column.biomes.write()[16 * 9 + 3] = Biome::Desert;
+        column.biomes.write()[255] = Biome::Taiga;
+
+        column
+    }
+
+    fn assert_columns_equal(a: &ChunkColumn, b: &ChunkColumn) {
+        assert_eq!(*a.has_foliage.read(), *b.has_foliage.read());
+        assert_eq!(a.highest_blocks.read()[..], b.highest_blocks.read()[..]);
+        assert_eq!(a.biomes.read()[..], b.biomes.read()[..]);
+
+        for (chunk_a, chunk_b) in a.chunks.iter().zip(b.chunks.iter()) {
+            assert_eq!(chunk_a.blocks.read()[..], chunk_b.blocks.read()[..]);
+            assert_eq!(
+                chunk_a.fluid_levels.read()[..],
+                chunk_b.fluid_levels.read()[..]
+            );
+            assert_eq!(
+                *chunk_a.scheduled_ticks.read(),
+                *chunk_b.scheduled_ticks.read()
+            );
+            assert_eq!(
+                *chunk_a.number_of_opaque_blocks.read(),
+                *chunk_b.number_of_opaque_blocks.read()
+            );
+            assert_eq!(
+                *chunk_a.number_of_transparent_blocks.read(),
+                *chunk_b.number_of_transparent_blocks.read()
+            );
+        }
+    }
+
+    #[test]
+    fn block_ids_round_trip() {
+        for id in 0..=u8::MAX {
+            if let Some(block) = BlockID::from_id(id) {
+                assert_eq!(block.id(), id);
+            }
+        }
+
+        let last_block = BLOCK_REGISTRY.iter().last().unwrap().id;
+        assert_eq!(BlockID::from_id(last_block.id() + 1), None);
+    }
+
+    #[test]
+    fn encoded_column_round_trips() {
+        let column = sample_column();
+        let decoded = ChunkColumn::new();
+
+        decode_column(&encode_column(&column), &decoded).unwrap();
+        assert_columns_equal(&column, &decoded);
+    }
+
+    #[test]
+    fn empty_chunks_are_not_stored() {
+        let data = encode_column(&ChunkColumn::new());
+        assert_eq!(data.len(), COLUMN_HEADER_SIZE + 16 + 4);
+    }
+
+    #[test]
+    fn fluid_levels_are_only_stored_when_needed() {
+        let column = ChunkColumn::new();
+        column.set_block(0, 0, 0, BlockID::WATER);
+        assert_eq!(
+            encode_column(&column).len(),
+            COLUMN_HEADER_SIZE + 16 + CHUNK_VOLUME as usize + 4
+        );
+
+        column.get_chunk(0).set_fluid_level(0, 0, 0, 7);
+        assert_eq!(
+            encode_column(&column).len(),
+            COLUMN_HEADER_SIZE + 16 + 2 * CHUNK_VOLUME as usize + 4
+        );
+    }
+
+    #[test]
+    fn columns_without_scheduled_ticks_are_loaded() {
+        let column = sample_column();
+
+        for chunk in column.chunks.iter() {
+            chunk.scheduled_ticks.write().clear();
+        }
+
+        let mut data = encode_column(&column);
+        data[0] = COLUMN_FORMAT_VERSION_WITHOUT_SCHEDULED_TICKS;
+        data.truncate(data.len() - 4);
+
+        let decoded = ChunkColumn::new();
+        decode_column(&data, &decoded).unwrap();
+        assert_columns_equal(&column, &decoded);
+    }
+
+    #[test]
+    fn a_tick_can_be_scheduled_on_every_block() {
+        let column = ChunkColumn::new();
+
+        for chunk in column.chunks.iter() {
+            chunk
+                .scheduled_ticks
+                .write()
+                .extend((0..CHUNK_VOLUME as u16).map(|index| (index, 1)));
+        }
+
+        let decoded = ChunkColumn::new();
+        decode_column(&encode_column(&column), &decoded).unwrap();
+        assert_columns_equal(&column, &decoded);
+    }
+
+    #[test]
+    fn columns_with_a_u16_scheduled_tick_count_are_loaded() {
+        let column = sample_column();
+
+        let mut data = encode_column(&column);
+        data[0] = COLUMN_FORMAT_VERSION_WITH_U16_SCHEDULED_TICK_COUNT;
+        let count = data.len() - 4 - 2 * SCHEDULED_TICK_SIZE;
+        data.drain(count + 2..count + 4);
+
+        let decoded = ChunkColumn::new();
+        decode_column(&data, &decoded).unwrap();
+        assert_columns_equal(&column, &decoded);
+    }
+
+    #[test]
+    fn columns_without_biomes_are_loaded_as_plains() {
+        let column = sample_column();
+
+        for chunk in column.chunks.iter() {
+            chunk.scheduled_ticks.write().clear();
+        }
+
+        let mut data = encode_column(&column);
+        data[0] = COLUMN_FORMAT_VERSION_WITHOUT_BIOMES;
+        data.drain(COLUMN_HEADER_SIZE_WITHOUT_BIOMES..COLUMN_HEADER_SIZE);
+        data.truncate(data.len() - 4);
+
+        let decoded = ChunkColumn::new();
+        decode_column(&data, &decoded).unwrap();
+
+        column.biomes.write().fill(Biome::Plains);
+        assert_columns_equal(&column, &decoded);
+    }
+
+    #[test]
+    fn corrupted_column_is_rejected() {
+        let mut data = encode_column(&sample_column());
+        let column = ChunkColumn::new();
+
+        data[0] = COLUMN_FORMAT_VERSION + 1;
+        assert!(decode_column(&data, &column).is_err());
+
+        data[0] = COLUMN_FORMAT_VERSION;
+        data[COLUMN_HEADER_SIZE - 1] = u8::MAX;
+        assert!(decode_column(&data, &column).is_err());
+
+        data[COLUMN_HEADER_SIZE - 1] = Biome::Plains.id();
+        data.truncate(data.len() - 1);
+        assert!(decode_column(&data, &column).is_err());
+
+        // A fluid level out of range
+        let fluid_column = ChunkColumn::new();
+        fluid_column.set_block(0, 0, 0, BlockID::WATER);
+        fluid_column
+            .get_chunk(0)
+            .set_fluid_level(0, 0, 0, MAX_FLUID_LEVEL);
+        let mut data = encode_column(&fluid_column);
+        assert!(decode_column(&data, &ChunkColumn::new()).is_ok());
+
+        let level_index = COLUMN_HEADER_SIZE + 1 + CHUNK_VOLUME as usize;
+        assert_eq!(data[level_index], MAX_FLUID_LEVEL);
+        data[level_index] = u8::MAX;
+        assert!(decode_column(&data, &ChunkColumn::new()).is_err());
+    }
+
+    #[test]
+    fn saved_columns_are_loaded_back() {
+        let directory = temporary_world_directory("save-load");
+        let column = sample_column();
+
+        {
+            let storage = WorldStorage::open(&directory).unwrap();
+            storage.save_column(-33, 40, &column).unwrap();
+            storage.flush().unwrap();
+        }
+
+        let storage = WorldStorage::open(&directory).unwrap();
+        let loaded = ChunkColumn::new();
+        assert!(storage.load_column(-33, 40, &loaded).unwrap());
+        assert_columns_equal(&column, &loaded);
+
+        // Same region file, but never saved
+        assert!(!storage.load_column(-34, 40, &ChunkColumn::new()).unwrap());
+        assert!(directory.join("region").join("r.-2.1.mcr").exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn loaded_chunk_manager_columns_are_saved() {
+        let directory = temporary_world_directory("chunk-manager");
+        let storage = WorldStorage::open(&directory).unwrap();
+        let chunk_manager = ChunkManager::new();
+
+        chunk_manager.add_chunk_column((0, 0), Arc::new(sample_column()));
+        chunk_manager.add_chunk_column((5, -7), Arc::new(ChunkColumn::new()));
+        storage.save_loaded_columns(&chunk_manager).unwrap();
+
+        let loaded = ChunkColumn::new();
+        assert!(storage.load_column(0, 0, &loaded).unwrap());
+        assert_columns_equal(&sample_column(), &loaded);
+        assert!(storage.load_column(5, -7, &ChunkColumn::new()).unwrap());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
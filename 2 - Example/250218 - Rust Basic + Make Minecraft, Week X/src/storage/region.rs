@@ -0,0 +1,347 @@
+use bit_vec::BitVec;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Number of chunk columns along each horizontal axis of a region
+pub const REGION_SIZE: i32 = 32;
+pub const COLUMNS_PER_REGION: usize = (REGION_SIZE * REGION_SIZE) as usize;
+
+const SECTOR_SIZE: usize = 4096;
+const HEADER_SECTORS: usize = 2;
+const MAX_SECTORS_PER_COLUMN: usize = 255;
+const COMPRESSION_ZLIB: u8 = 2;
+
+// A region file holds 32x32 chunk columns in the same spirit as Minecraft's Anvil format.
+// The file is divided into 4 KiB sectors:
+// - Sector 0 holds one location entry per column (3 bytes of sector offset, 1 byte of sector count)
+// - Sector 1 holds the time at which each column was last saved
+// - Every column is then stored in its own run of sectors as a 4 bytes length,
+//   a 1 byte compression type and the compressed data
+pub struct RegionFile {
+    file: File,
+    locations: [u32; COLUMNS_PER_REGION],
+    timestamps: [u32; COLUMNS_PER_REGION],
+    used_sectors: BitVec,
+}
+
+impl RegionFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let file_length = file.metadata()?.len() as usize;
+
+        // A new (or truncated) file gets an empty header
+        if file_length < HEADER_SECTORS * SECTOR_SIZE {
+            file.set_len(0)?;
+            file.write_all(&[0; HEADER_SECTORS * SECTOR_SIZE])?;
+        }
+
+        let mut header = [0; HEADER_SECTORS * SECTOR_SIZE];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+
+        let mut locations = [0; COLUMNS_PER_REGION];
+        let mut timestamps = [0; COLUMNS_PER_REGION];
+
+        for i in 0..COLUMNS_PER_REGION {
+            locations[i] = u32::from_be_bytes(header[4 * i..4 * i + 4].try_into().unwrap());
+            timestamps[i] = u32::from_be_bytes(
+                header[SECTOR_SIZE + 4 * i..SECTOR_SIZE + 4 * i + 4]
+                    .try_into()
+                    .unwrap(),
+            );
+        }
+
+        let total_sectors = (file.metadata()?.len() as usize).div_ceil(SECTOR_SIZE);
+        let mut used_sectors = BitVec::from_elem(total_sectors.max(HEADER_SECTORS), false);
+
+        for sector in 0..HEADER_SECTORS {
+            used_sectors.set(sector, true);
+        }
+
+        for location in locations.iter_mut() {
+            let (offset, count) = Self::unpack_location(*location);
+
+            if offset == 0 {
+                continue;
+            }
+
+            // Forget columns pointing outside of the file instead of failing the whole region
+            if offset < HEADER_SECTORS || offset + count > used_sectors.len() {
+                *location = 0;
+                continue;
+            }
+
+            for sector in offset..offset + count {
+                used_sectors.set(sector, true);
+            }
+        }
+
+        Ok(Self {
+            file,
+            locations,
+            timestamps,
+            used_sectors,
+        })
+    }
+
+    // Index of the column in the header, (x, z) are chunk column coordinates
+    #[inline]
+    pub fn column_index(x: i32, z: i32) -> usize {
+        (z.rem_euclid(REGION_SIZE) * REGION_SIZE + x.rem_euclid(REGION_SIZE)) as usize
+    }
+
+    #[inline]
+    fn unpack_location(location: u32) -> (usize, usize) {
+        ((location >> 8) as usize, (location & 0xFF) as usize)
+    }
+
+    pub fn has_column(&self, x: i32, z: i32) -> bool {
+        self.locations[Self::column_index(x, z)] != 0
+    }
+
+    // Returns the time (in seconds since the UNIX epoch) at which the column was last saved
+    pub fn timestamp(&self, x: i32, z: i32) -> u32 {
+        self.timestamps[Self::column_index(x, z)]
+    }
+
+    // Reads and decompresses the data of a column, returns `None` if the column was never saved
+    pub fn read_column(&mut self, x: i32, z: i32) -> io::Result<Option<Vec<u8>>> {
+        let (offset, count) = Self::unpack_location(self.locations[Self::column_index(x, z)]);
+
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let mut header = [0; 5];
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        self.file.read_exact(&mut header)?;
+
+        let length = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        let compression = header[4];
+
+        if length == 0 || length + 4 > count * SECTOR_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid length {length} for column ({x}, {z})"),
+            ));
+        }
+
+        if compression != COMPRESSION_ZLIB {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown compression type {compression} for column ({x}, {z})"),
+            ));
+        }
+
+        let mut compressed = vec![0; length - 1];
+        self.file.read_exact(&mut compressed)?;
+
+        let mut data = Vec::new();
+        ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut data)?;
+
+        Ok(Some(data))
+    }
+
+    // Compresses and writes the data of a column, reusing its previous sectors when they are large enough
+    pub fn write_column(&mut self, x: i32, z: i32, data: &[u8]) -> io::Result<()> {
+        let index = Self::column_index(x, z);
+
+        let mut payload = vec![0, 0, 0, 0, COMPRESSION_ZLIB];
+        {
+            let mut encoder = ZlibEncoder::new(&mut payload, Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()?;
+        }
+
+        let length = (payload.len() - 4) as u32;
+        payload[0..4].copy_from_slice(&length.to_be_bytes());
+
+        let sectors_needed = payload.len().div_ceil(SECTOR_SIZE);
+
+        if sectors_needed > MAX_SECTORS_PER_COLUMN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Column ({x}, {z}) is too large to be saved"),
+            ));
+        }
+
+        let (old_offset, old_count) = Self::unpack_location(self.locations[index]);
+
+        let offset = if old_offset != 0 && old_count >= sectors_needed {
+            // Give back the sectors we don't need anymore
+            for sector in old_offset + sectors_needed..old_offset + old_count {
+                self.used_sectors.set(sector, false);
+            }
+
+            old_offset
+        } else {
+            for sector in old_offset..old_offset + old_count {
+                self.used_sectors.set(sector, false);
+            }
+
+            self.allocate_sectors(sectors_needed)
+        };
+
+        // Pad the data to a whole number of sectors
+        payload.resize(sectors_needed * SECTOR_SIZE, 0);
+
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        self.file.write_all(&payload)?;
+
+        let location = ((offset as u32) << 8) | sectors_needed as u32;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as u32)
+            .unwrap_or(0);
+
+        self.locations[index] = location;
+        self.timestamps[index] = timestamp;
+
+        self.file.seek(SeekFrom::Start((4 * index) as u64))?;
+        self.file.write_all(&location.to_be_bytes())?;
+        self.file
+            .seek(SeekFrom::Start((SECTOR_SIZE + 4 * index) as u64))?;
+        self.file.write_all(&timestamp.to_be_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
+    // Finds the first run of `count` free sectors, growing the file if there is none
+    fn allocate_sectors(&mut self, count: usize) -> usize {
+        let mut run_start = HEADER_SECTORS;
+        let mut run_length = 0;
+
+        for sector in HEADER_SECTORS..self.used_sectors.len() {
+            if self.used_sectors[sector] {
+                run_start = sector + 1;
+                run_length = 0;
+            } else {
+                run_length += 1;
+
+                if run_length == count {
+                    break;
+                }
+            }
+        }
+
+        // A free run at the end of the file can be extended past it
+        let run_end = run_start + count;
+
+        if run_end > self.used_sectors.len() {
+            self.used_sectors
+                .grow(run_end - self.used_sectors.len(), false);
+        }
+
+        for sector in run_start..run_end {
+            self.used_sectors.set(sector, true);
+        }
+
+        run_start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temporary_region_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "minecraft-region-test-{}-{name}.mcr",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        path
+    }
+
+    #[test]
+    fn column_index_wraps_negative_coordinates() {
+        assert_eq!(RegionFile::column_index(0, 0), 0);
+        assert_eq!(RegionFile::column_index(31, 0), 31);
+        assert_eq!(RegionFile::column_index(0, 1), 32);
+        assert_eq!(RegionFile::column_index(-1, -1), COLUMNS_PER_REGION - 1);
+        assert_eq!(RegionFile::column_index(32, 32), 0);
+    }
+
+    #[test]
+    fn columns_survive_reopening_the_file() {
+        let path = temporary_region_path("reopen");
+
+        {
+            let mut region = RegionFile::open(&path).unwrap();
+            assert!(!region.has_column(3, 4));
+            assert_eq!(region.read_column(3, 4).unwrap(), None);
+
+            region.write_column(3, 4, b"first column").unwrap();
+            region.write_column(-1, 7, b"second column").unwrap();
+        }
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert!(region.has_column(3, 4));
+        assert!(region.timestamp(3, 4) > 0);
+        assert_eq!(
+            region.read_column(3, 4).unwrap().as_deref(),
+            Some(&b"first column"[..])
+        );
+        assert_eq!(
+            region.read_column(-1, 7).unwrap().as_deref(),
+            Some(&b"second column"[..])
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn growing_a_column_moves_it_without_corrupting_its_neighbours() {
+        let path = temporary_region_path("grow");
+        let mut region = RegionFile::open(&path).unwrap();
+
+        // Pseudo-random bytes don't compress, so this needs several sectors
+        let mut state = 1u64;
+        let large: Vec<u8> = (0..3 * SECTOR_SIZE)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect();
+
+        region.write_column(0, 0, b"small").unwrap();
+        region.write_column(1, 0, b"neighbour").unwrap();
+        region.write_column(0, 0, &large).unwrap();
+
+        assert_eq!(region.read_column(0, 0).unwrap(), Some(large));
+        assert_eq!(
+            region.read_column(1, 0).unwrap().as_deref(),
+            Some(&b"neighbour"[..])
+        );
+
+        // The sector freed by the first version of the column is reused
+        region
+            .write_column(2, 0, b"reuses the freed sector")
+            .unwrap();
+        let (offset, _) = RegionFile::unpack_location(region.locations[2]);
+        assert_eq!(offset, HEADER_SECTORS);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
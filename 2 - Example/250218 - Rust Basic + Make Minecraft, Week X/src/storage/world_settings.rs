@@ -0,0 +1,278 @@
+use crate::world_generation::DEFAULT_WORLD_GENERATOR;
+use rand::random;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+// Name of the settings file in the world directory
+pub const WORLD_SETTINGS_FILE: &str = "world.toml";
+
+// The seed can be written as a number or as any text, which is hashed to a number
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum SeedValue {
+    Number(i64),
+    Text(String),
+}
+
+impl SeedValue {
+    fn to_seed(&self) -> u32 {
+        match self {
+            SeedValue::Number(number) => *number as u32,
+            SeedValue::Text(text) => parse_seed(text),
+        }
+    }
+}
+
+// Content of `world.toml`, both fields can be left out before the world is created
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorldSettingsFile {
+    seed: Option<SeedValue>,
+    generator: Option<String>,
+}
+
+// Turns what the user typed into a seed: integers are used as they are (negative ones wrap
+// around), anything else is hashed like Java's `String.hashCode`
+pub fn parse_seed(seed: &str) -> u32 {
+    let seed = seed.trim();
+
+    if let Ok(number) = seed.parse::<i64>() {
+        return number as u32;
+    }
+
+    seed.encode_utf16().fold(0_u32, |hash, c| {
+        hash.wrapping_mul(31).wrapping_add(c as u32)
+    })
+}
+
+// What a world is generated from. It is saved with the world since the columns that are not
+// saved yet must be generated the same way as the others.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldSettings {
+    pub seed: u32,
+    pub generator: String,
+}
+
+impl WorldSettings {
+    // Reads the settings of the world, the values of `world.toml` win over `seed` and
+    // `generator` (usually given on the command line). The missing values are taken from them,
+    // or chosen (random seed, default generator), then written to `world.toml`.
+    pub fn open(
+        world_directory: impl AsRef<Path>,
+        seed: Option<u32>,
+        generator: Option<&str>,
+    ) -> io::Result<Self> {
+        let path = world_directory.as_ref().join(WORLD_SETTINGS_FILE);
+
+        let mut file = match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str::<WorldSettingsFile>(&text).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid {}: {err}", path.display()),
+                )
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => WorldSettingsFile::default(),
+            Err(err) => return Err(err),
+        };
+
+        let is_complete = file.seed.is_some() && file.generator.is_some();
+
+        let settings = WorldSettings {
+            seed: match &file.seed {
+                Some(saved_seed) => {
+                    let saved_seed = saved_seed.to_seed();
+
+                    if seed.is_some_and(|seed| seed != saved_seed) {
+                        warn!("The world already has the seed {saved_seed}, the given seed is ignored");
+                    }
+
+                    saved_seed
+                }
+                None => seed.unwrap_or_else(random),
+            },
+            generator: match &file.generator {
+                Some(saved_generator) => {
+                    if generator.is_some_and(|generator| generator != saved_generator) {
+                        warn!(
+                            "The world already uses the generator \"{saved_generator}\", the given generator is ignored"
+                        );
+                    }
+
+                    saved_generator.clone()
+                }
+                None => generator.unwrap_or(DEFAULT_WORLD_GENERATOR).to_string(),
+            },
+        };
+
+        // A seed written as text is kept as it is
+        if !is_complete {
+            file.seed
+                .get_or_insert(SeedValue::Number(settings.seed as i64));
+            file.generator
+                .get_or_insert_with(|| settings.generator.clone());
+            save_settings_file(&path, &file)?;
+        }
+
+        Ok(settings)
+    }
+}
+
+fn save_settings_file(path: &Path, file: &WorldSettingsFile) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+
+    let text = toml::to_string(file).map_err(|err| io::Error::other(err.to_string()))?;
+    std::fs::write(path, text)
+}
+
+// Options shared by the binaries: `--seed <seed>`, `--generator <name>` and (for the client)
+// `--connect <address>`, the other arguments are returned in order
+#[derive(Debug, Default, PartialEq)]
+pub struct WorldArguments {
+    pub seed: Option<u32>,
+    pub generator: Option<String>,
+    // Address of the server to play on instead of the local world
+    pub connect: Option<String>,
+    pub positional: Vec<String>,
+}
+
+impl WorldArguments {
+    // `args` doesn't include the name of the program
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut arguments = WorldArguments::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let seed = args.next().ok_or("--seed needs a value")?;
+                    arguments.seed = Some(parse_seed(&seed));
+                }
+                "--generator" => {
+                    arguments.generator = Some(args.next().ok_or("--generator needs a value")?);
+                }
+                "--connect" => {
+                    arguments.connect = Some(args.next().ok_or("--connect needs an address")?);
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+                _ => arguments.positional.push(arg),
+            }
+        }
+
+        Ok(arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temporary_world_directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "minecraft-world-settings-test-{}-{name}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+
+        path
+    }
+
+    #[test]
+    fn seeds_are_numbers_or_hashed_text() {
+        assert_eq!(parse_seed("42"), 42);
+        assert_eq!(parse_seed(" 42 "), 42);
+        assert_eq!(parse_seed("-1"), u32::MAX);
+        assert_eq!(parse_seed("4294967296"), 0);
+
+        // Same values as Java's `String.hashCode`
+        assert_eq!(parse_seed(""), 0);
+        assert_eq!(parse_seed("a"), 97);
+        assert_eq!(parse_seed("Minecraft"), 2_699_041_165);
+        assert_ne!(parse_seed("glacier"), parse_seed("Glacier"));
+    }
+
+    #[test]
+    fn settings_are_stored_with_the_world() {
+        let directory = temporary_world_directory("stored");
+
+        let settings = WorldSettings::open(&directory, Some(7), Some("superflat")).unwrap();
+        assert_eq!(
+            settings,
+            WorldSettings {
+                seed: 7,
+                generator: "superflat".to_string()
+            }
+        );
+
+        // The saved values win over the given ones
+        assert_eq!(
+            WorldSettings::open(&directory, Some(8), Some("void")).unwrap(),
+            settings
+        );
+        assert_eq!(
+            WorldSettings::open(&directory, None, None).unwrap(),
+            settings
+        );
+
+        // A random seed is chosen and kept when none is given
+        let directory = temporary_world_directory("random");
+        let settings = WorldSettings::open(&directory, None, None).unwrap();
+        assert_eq!(settings.generator, DEFAULT_WORLD_GENERATOR);
+        assert_eq!(
+            WorldSettings::open(&directory, None, None).unwrap(),
+            settings
+        );
+    }
+
+    #[test]
+    fn settings_file_can_be_written_by_hand() {
+        let directory = temporary_world_directory("by-hand");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(WORLD_SETTINGS_FILE);
+
+        std::fs::write(&path, "seed = \"Minecraft\"\n").unwrap();
+        let settings = WorldSettings::open(&directory, Some(1), Some("void")).unwrap();
+        assert_eq!(settings.seed, parse_seed("Minecraft"));
+        assert_eq!(settings.generator, "void");
+
+        // The missing generator was saved, the text of the seed was kept
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("Minecraft"));
+        assert!(text.contains("void"));
+
+        std::fs::write(&path, "seed = -1\ngenerator = \"default\"\n").unwrap();
+        assert_eq!(
+            WorldSettings::open(&directory, None, None).unwrap().seed,
+            u32::MAX
+        );
+
+        std::fs::write(&path, "seed = 1\nsize = 2\n").unwrap();
+        assert!(WorldSettings::open(&directory, None, None).is_err());
+    }
+
+    #[test]
+    fn world_arguments_are_parsed() {
+        let args = |args: &[&str]| WorldArguments::parse(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(args(&[]).unwrap(), WorldArguments::default());
+        assert_eq!(
+            args(&["100", "--seed", "hello", "--generator", "superflat:stone"]).unwrap(),
+            WorldArguments {
+                seed: Some(parse_seed("hello")),
+                generator: Some("superflat:stone".to_string()),
+                connect: None,
+                positional: vec!["100".to_string()],
+            }
+        );
+        assert_eq!(
+            args(&["--connect", "localhost:25565"]).unwrap().connect,
+            Some("localhost:25565".to_string())
+        );
+        assert!(args(&["--seed"]).is_err());
+        assert!(args(&["--connect"]).is_err());
+        assert!(args(&["--size", "2"]).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+// Worlds shared by the unit tests of several modules
+
+use crate::chunk::{BlockID, ChunkColumn};
+use crate::chunk_manager::ChunkManager;
+use crate::game_mode::GameMode;
+use crate::inventory::item::ItemStack;
+use crate::inventory::{Inventory, INVENTORY_SIZE};
+use crate::light::light_column;
+use crate::physics::{Collider, Gravity, Interpolator, PhysicsBody, Transform};
+use crate::player::PlayerState;
+use crate::timer::Timer;
+use crate::vitals::{Health, Hunger};
+use specs::{World, WorldExt};
+use std::sync::Arc;
+
+// A specs world with the components of `UpdatePhysics` registered and a single chunk column at
+// (0, 0), filled with stone up to `floor_height`
+pub fn create_world_with_floor(floor_height: u32) -> World {
+    let mut world = World::new();
+    world.register::<Transform>();
+    world.register::<Interpolator<PhysicsBody>>();
+    world.register::<Gravity>();
+    world.register::<Collider>();
+    world.register::<PlayerState>();
+    world.register::<GameMode>();
+    world.register::<Health>();
+    world.register::<Hunger>();
+    world.insert(Timer::default());
+
+    let chunk_manager = ChunkManager::new();
+    chunk_manager.add_chunk_column(
+        (0, 0),
+        Arc::new(layered_column(&vec![BlockID::STONE; floor_height as usize])),
+    );
+    world.insert(Arc::new(chunk_manager));
+
+    world
+}
+
+// The 3x3 chunk columns around (0, 0), filled with `layers` from y = 0 upwards. They aren't lit
+// so that the tests can change the blocks first, see `light_flat_world`.
+pub fn flat_world(layers: &[BlockID]) -> ChunkManager {
+    let chunk_manager = ChunkManager::new();
+
+    for column_x in -1..=1 {
+        for column_z in -1..=1 {
+            chunk_manager.add_chunk_column((column_x, column_z), Arc::new(layered_column(layers)));
+        }
+    }
+
+    chunk_manager
+}
+
+pub fn light_flat_world(chunk_manager: &ChunkManager) {
+    for column_x in -1..=1 {
+        for column_z in -1..=1 {
+            light_column(chunk_manager, column_x, column_z);
+        }
+    }
+}
+
+fn layered_column(layers: &[BlockID]) -> ChunkColumn {
+    let column = ChunkColumn::new();
+
+    for x in 0..16 {
+        for z in 0..16 {
+            for (y, &block) in layers.iter().enumerate() {
+                column.set_block(x, y as u32, z, block);
+            }
+        }
+    }
+
+    column
+}
+
+// An inventory holding only the given stacks, by slot index
+pub fn inventory_with(slots: &[(usize, BlockID, u32)]) -> Inventory {
+    let mut inventory = Inventory {
+        slots: [None; INVENTORY_SIZE],
+        selected_hotbar_slot: 0,
+    };
+
+    for &(index, item, amount) in slots {
+        inventory.slots[index] = Some(ItemStack::new(item, amount));
+    }
+
+    inventory
+}
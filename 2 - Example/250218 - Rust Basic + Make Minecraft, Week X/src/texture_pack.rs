@@ -1,3 +1,4 @@
+use crate::block_registry::BLOCK_REGISTRY;
 use crate::block_texture_faces::BlockFaces;
 use crate::chunk::BlockID;
 use crate::constants::ITEM_ARRAY_TEXTURE_LAYERS;
@@ -14,55 +15,17 @@ pub fn generate_array_texture() -> (u32, TexturePack) {
     (array_texture, face_uvs)
 }
 
+// Paths of the textures of every block of the registry
 fn create_face_images_map() -> HashMap<BlockID, BlockFaces<&'static str>> {
-    let mut face_images: HashMap<BlockID, BlockFaces<&str>> = HashMap::new();
-
-    face_images.insert(BlockID::Dirt, BlockFaces::All("textures/blocks/dirt.png"));
-    face_images.insert(
-        BlockID::GrassBlock,
-        BlockFaces::Sides {
-            sides: "textures/blocks/grass_block_side.png",
-            top: "textures/blocks/grass_block_top.png",
-            bottom: "textures/blocks/dirt.png",
-        },
-    );
-    face_images.insert(BlockID::Stone, BlockFaces::All("textures/blocks/stone.png"));
-    face_images.insert(
-        BlockID::Cobblestone,
-        BlockFaces::All("textures/blocks/cobblestone.png"),
-    );
-    face_images.insert(
-        BlockID::Bedrock,
-        BlockFaces::All("textures/blocks/bedrock.png"),
-    );
-    face_images.insert(
-        BlockID::Obsidian,
-        BlockFaces::All("textures/blocks/obsidian.png"),
-    );
-    face_images.insert(
-        BlockID::OakLog,
-        BlockFaces::Sides {
-            sides: "textures/blocks/oak_log.png",
-            top: "textures/blocks/oak_log_top.png",
-            bottom: "textures/blocks/oak_log_top.png",
-        },
-    );
-    face_images.insert(
-        BlockID::OakLeaves,
-        BlockFaces::All("textures/blocks/oak_leaves.png"),
-    );
-    face_images.insert(
-        BlockID::OakPlanks,
-        BlockFaces::All("textures/blocks/oak_planks.png"),
-    );
-    face_images.insert(BlockID::Glass, BlockFaces::All("textures/blocks/glass.png"));
-    face_images.insert(BlockID::Debug, BlockFaces::All("textures/blocks/debug.png"));
-    face_images.insert(
-        BlockID::Debug2,
-        BlockFaces::All("textures/blocks/debug2.png"),
-    );
-
-    face_images
+    BLOCK_REGISTRY
+        .iter()
+        .filter_map(|block| {
+            block
+                .textures
+                .as_ref()
+                .map(|textures| (block.id, textures.map(|path| path.as_str())))
+        })
+        .collect()
 }
 
 fn create_array_texture(layers: i32) -> u32 {
@@ -1,6 +1,8 @@
 use crate::block_texture_faces::BlockFaces;
 use crate::chunk::BlockID;
+#[cfg(feature = "client")]
 use crate::particle_system::ParticleSystem;
+#[cfg(feature = "client")]
 use crate::shader::ShaderProgram;
 use std::collections::HashMap;
 
@@ -14,5 +16,7 @@ pub type UVFaces = (
     TextureLayer,
 );
 pub type TexturePack = HashMap<BlockID, BlockFaces<TextureLayer>>;
+#[cfg(feature = "client")]
 pub type ParticleSystems = HashMap<&'static str, ParticleSystem>;
+#[cfg(feature = "client")]
 pub type Shaders = HashMap<&'static str, ShaderProgram>;
@@ -0,0 +1,230 @@
+use crate::chunk::Chunk;
+use crate::chunk_manager::{CHUNK_SIZE, CHUNK_VOLUME};
+
+// Faces of a chunk, the opposite of a face is `face ^ 1`
+pub const NEGATIVE_X: usize = 0;
+pub const POSITIVE_X: usize = 1;
+pub const NEGATIVE_Y: usize = 2;
+pub const POSITIVE_Y: usize = 3;
+pub const NEGATIVE_Z: usize = 4;
+pub const POSITIVE_Z: usize = 5;
+
+// Offset to the chunk behind every face
+pub const FACE_OFFSETS: [(i32, i32, i32); 6] = [
+    (-1, 0, 0),
+    (1, 0, 0),
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+];
+
+#[inline]
+pub fn opposite_face(face: usize) -> usize {
+    face ^ 1
+}
+
+// Which faces of a chunk see each other through its non-opaque blocks. Bit `b` of
+// `connections[a]` is set if a line of sight can enter by the face `a` and leave by the face `b`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VisibilityGraph {
+    connections: [u8; 6],
+}
+
+impl VisibilityGraph {
+    pub fn all() -> Self {
+        Self {
+            connections: [0b111111; 6],
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            connections: [0; 6],
+        }
+    }
+
+    // Flood fills every region of non-opaque blocks and connects together the faces it touches
+    pub fn compute(is_opaque: impl Fn(u32, u32, u32) -> bool) -> Self {
+        let mut visited = vec![false; CHUNK_VOLUME as usize];
+        let mut graph = Self::none();
+        let mut stack = vec![];
+
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let i = Chunk::chunk_coords_to_array_index(x, y, z);
+
+                    if visited[i] || is_opaque(x, y, z) {
+                        continue;
+                    }
+
+                    visited[i] = true;
+                    stack.push((x, y, z));
+                    let mut faces = 0u8;
+
+                    while let Some((x, y, z)) = stack.pop() {
+                        faces |= Self::faces_touched(x, y, z);
+
+                        for (dx, dy, dz) in FACE_OFFSETS {
+                            let (nx, ny, nz) = (x as i32 + dx, y as i32 + dy, z as i32 + dz);
+
+                            if [nx, ny, nz]
+                                .iter()
+                                .any(|&c| c < 0 || c >= CHUNK_SIZE as i32)
+                            {
+                                continue;
+                            }
+
+                            let (nx, ny, nz) = (nx as u32, ny as u32, nz as u32);
+                            let i = Chunk::chunk_coords_to_array_index(nx, ny, nz);
+
+                            if !visited[i] && !is_opaque(nx, ny, nz) {
+                                visited[i] = true;
+                                stack.push((nx, ny, nz));
+                            }
+                        }
+                    }
+
+                    for face in 0..6 {
+                        if faces & (1 << face) != 0 {
+                            graph.connections[face] |= faces;
+                        }
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+
+    fn faces_touched(x: u32, y: u32, z: u32) -> u8 {
+        let last = CHUNK_SIZE - 1;
+        let mut faces = 0;
+
+        for (face, is_on_face) in [x == 0, x == last, y == 0, y == last, z == 0, z == last]
+            .into_iter()
+            .enumerate()
+        {
+            if is_on_face {
+                faces |= 1 << face;
+            }
+        }
+
+        faces
+    }
+
+    #[inline]
+    pub fn connects(&self, from: usize, to: usize) -> bool {
+        self.connections[from] & (1 << to) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::{BlockID, BlockIterator, ChunkColumn};
+    use crate::chunk_manager::ChunkManager;
+    use crate::frustum::Frustum;
+    use nalgebra::Vector3;
+    use nalgebra_glm::vec3;
+    use std::sync::Arc;
+
+    #[test]
+    fn empty_and_opaque_chunks() {
+        assert_eq!(
+            VisibilityGraph::compute(|_, _, _| false),
+            VisibilityGraph::all()
+        );
+        assert_eq!(
+            VisibilityGraph::compute(|_, _, _| true),
+            VisibilityGraph::none()
+        );
+    }
+
+    #[test]
+    fn a_wall_splits_the_chunk_in_two() {
+        let graph = VisibilityGraph::compute(|x, _, _| x == 8);
+
+        assert!(!graph.connects(NEGATIVE_X, POSITIVE_X));
+        assert!(!graph.connects(POSITIVE_X, NEGATIVE_X));
+
+        // Both halves still touch the other faces
+        assert!(graph.connects(NEGATIVE_X, POSITIVE_Y));
+        assert!(graph.connects(POSITIVE_X, NEGATIVE_Z));
+        assert!(graph.connects(NEGATIVE_Y, POSITIVE_Y));
+
+        // A hole in the wall joins them again
+        let graph = VisibilityGraph::compute(|x, y, z| x == 8 && !(y == 3 && z == 12));
+        assert!(graph.connects(NEGATIVE_X, POSITIVE_X));
+    }
+
+    #[test]
+    fn a_tunnel_only_connects_its_ends() {
+        let graph = VisibilityGraph::compute(|x, y, _| !(x == 5 && y == 7));
+
+        assert!(graph.connects(NEGATIVE_Z, POSITIVE_Z));
+        assert!(graph.connects(POSITIVE_Z, NEGATIVE_Z));
+
+        for face in [NEGATIVE_X, POSITIVE_X, NEGATIVE_Y, POSITIVE_Y] {
+            assert!(!graph.connects(NEGATIVE_Z, face));
+            assert!(!graph.connects(face, POSITIVE_Z));
+        }
+    }
+
+    #[test]
+    fn chunks_behind_a_wall_are_hidden() {
+        // Four columns along +X, the third one is made of stone
+        let chunk_manager = ChunkManager::new();
+
+        for x in 0..4 {
+            let mut column = ChunkColumn::new();
+
+            if x == 2 {
+                column.chunks = Box::new(std::array::from_fn(|_| {
+                    Chunk::full_of_block(BlockID::STONE)
+                }));
+            }
+
+            chunk_manager.add_chunk_column((x, 0), Arc::new(column));
+        }
+
+        // The camera is in the middle of the chunk (0, 8, 0) and looks towards +X
+        let position = vec3(8.0, 136.0, 8.0);
+        let view = nalgebra_glm::look_at(&position, &vec3(9.0, 136.0, 8.0), &Vector3::y());
+        let projection = nalgebra_glm::perspective(1.0, 90.0f32.to_radians(), 0.1, 1000.0);
+        let frustum = Frustum::from_matrix(&(projection * view));
+
+        let visible = chunk_manager
+            .find_visible_chunks((0, 8, 0), &frustum)
+            .unwrap();
+        assert!(visible.contains(&(0, 8, 0)));
+        assert!(visible.contains(&(1, 8, 0)));
+        assert!(visible.contains(&(2, 8, 0)));
+        assert!(!visible.contains(&(3, 8, 0)));
+        assert!(chunk_manager
+            .find_visible_chunks((5, 8, 0), &frustum)
+            .is_none());
+
+        // A tunnel through the wall, the graph is outdated until the chunk is updated
+        for x in 32..48 {
+            chunk_manager.set_block(x, 136, 8, BlockID::AIR);
+        }
+
+        let chunk = chunk_manager.get_chunk(2, 8, 0).unwrap();
+        assert!(*chunk.is_visibility_outdated.read());
+
+        chunk_manager.update_blocks(2, 8, 0, BlockIterator::new());
+        assert!(!*chunk.is_visibility_outdated.read());
+        assert!(chunk.visibility().connects(NEGATIVE_X, POSITIVE_X));
+        assert!(!chunk.visibility().connects(NEGATIVE_X, POSITIVE_Y));
+
+        let visible = chunk_manager
+            .find_visible_chunks((0, 8, 0), &frustum)
+            .unwrap();
+        assert!(visible.contains(&(3, 8, 0)));
+
+        // Nothing is visible behind the camera
+        assert!(!visible.contains(&(0, 8, -1)));
+    }
+}
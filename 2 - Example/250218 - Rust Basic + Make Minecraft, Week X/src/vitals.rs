@@ -0,0 +1,208 @@
+use crate::constants::{
+    EXHAUSTION_PER_FOOD, FOOD_TICK_SECONDS, GRAVITY, MAX_FOOD, MAX_HEALTH, REGENERATION_EXHAUSTION,
+    REGENERATION_MIN_FOOD, SAFE_FALL_DISTANCE, VOID_DAMAGE, VOID_DAMAGE_SECONDS,
+};
+use nalgebra_glm::Vec3;
+
+// Health of an entity in half hearts, it dies at 0
+#[derive(Copy, Clone, Debug)]
+pub struct Health {
+    pub points: u32,
+    // Set when the entity (re)spawns, it isn't hurt by the fall to the ground below the spawn point
+    pub is_spawning: bool,
+    pub void_timer: f32,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self {
+            points: MAX_HEALTH,
+            is_spawning: true,
+            void_timer: 0.0,
+        }
+    }
+
+    pub fn damage(&mut self, amount: u32) {
+        self.points = self.points.saturating_sub(amount);
+    }
+
+    pub fn heal(&mut self, amount: u32) {
+        self.points = (self.points + amount).min(MAX_HEALTH);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.points == 0
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Food of a player in half drumsticks, drained by the exhaustion of sprinting, jumping and healing
+#[derive(Copy, Clone, Debug)]
+pub struct Hunger {
+    pub food: u32,
+    pub exhaustion: f32,
+    pub food_timer: f32,
+}
+
+impl Hunger {
+    pub fn new() -> Self {
+        Self {
+            food: MAX_FOOD,
+            exhaustion: 0.0,
+            food_timer: 0.0,
+        }
+    }
+
+    pub fn exhaust(&mut self, amount: f32) {
+        self.exhaustion += amount;
+
+        while self.exhaustion >= EXHAUSTION_PER_FOOD {
+            self.exhaustion -= EXHAUSTION_PER_FOOD;
+            self.food = self.food.saturating_sub(1);
+        }
+    }
+}
+
+impl Default for Hunger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Where the players appear and reappear after dying
+#[derive(Copy, Clone, Debug)]
+pub struct SpawnPoint(pub Vec3);
+
+// Damage of landing with `vertical_velocity`, from the height it was reached with the gravity
+pub fn fall_damage(vertical_velocity: f32) -> u32 {
+    let fall_distance = vertical_velocity * vertical_velocity / (2.0 * -GRAVITY);
+
+    (fall_distance - SAFE_FALL_DISTANCE).ceil().max(0.0) as u32
+}
+
+// Advances the regeneration and the starvation by `dt` seconds. A well fed entity heals by
+// exhausting itself, a starving one is hurt down to half a heart.
+pub fn update_hunger(health: &mut Health, hunger: &mut Hunger, dt: f32) {
+    let is_regenerating = hunger.food >= REGENERATION_MIN_FOOD && health.points < MAX_HEALTH;
+    let is_starving = hunger.food == 0 && health.points > 1;
+
+    if !is_regenerating && !is_starving {
+        hunger.food_timer = 0.0;
+        return;
+    }
+
+    hunger.food_timer += dt;
+
+    if hunger.food_timer >= FOOD_TICK_SECONDS {
+        hunger.food_timer -= FOOD_TICK_SECONDS;
+
+        if is_regenerating {
+            health.heal(1);
+            hunger.exhaust(REGENERATION_EXHAUSTION);
+        } else {
+            health.damage(1);
+        }
+    }
+}
+
+// Hurts the entities that fell below the world every `VOID_DAMAGE_SECONDS`
+pub fn update_void_damage(health: &mut Health, is_in_void: bool, dt: f32) {
+    if !is_in_void {
+        health.void_timer = 0.0;
+        return;
+    }
+
+    health.void_timer += dt;
+
+    while health.void_timer >= VOID_DAMAGE_SECONDS {
+        health.void_timer -= VOID_DAMAGE_SECONDS;
+        health.damage(VOID_DAMAGE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn velocity_after_falling(distance: f32) -> f32 {
+        -(2.0 * -GRAVITY * distance).sqrt()
+    }
+
+    #[test]
+    fn falls_hurt_above_the_safe_distance() {
+        assert_eq!(fall_damage(0.0), 0);
+        assert_eq!(fall_damage(velocity_after_falling(2.9)), 0);
+        assert_eq!(fall_damage(velocity_after_falling(3.5)), 1);
+        assert_eq!(fall_damage(velocity_after_falling(12.5)), 10);
+        assert_eq!(fall_damage(velocity_after_falling(29.5)), 27);
+    }
+
+    #[test]
+    fn exhaustion_drains_the_food() {
+        let mut hunger = Hunger::new();
+
+        hunger.exhaust(3.0);
+        assert_eq!(hunger.food, MAX_FOOD);
+
+        hunger.exhaust(6.0);
+        assert_eq!(hunger.food, MAX_FOOD - 2);
+        assert!((hunger.exhaustion - 1.0).abs() < 1e-4);
+
+        hunger.food = 0;
+        hunger.exhaust(EXHAUSTION_PER_FOOD);
+        assert_eq!(hunger.food, 0);
+    }
+
+    #[test]
+    fn full_hunger_regenerates_the_health() {
+        let mut health = Health::new();
+        let mut hunger = Hunger::new();
+        health.damage(5);
+
+        update_hunger(&mut health, &mut hunger, 3.0);
+        assert_eq!(health.points, MAX_HEALTH - 5);
+
+        update_hunger(&mut health, &mut hunger, 1.0);
+        assert_eq!(health.points, MAX_HEALTH - 4);
+        assert_eq!(hunger.food, MAX_FOOD - 1);
+
+        // Not enough food anymore
+        hunger.food = REGENERATION_MIN_FOOD - 1;
+        update_hunger(&mut health, &mut hunger, 10.0);
+        assert_eq!(health.points, MAX_HEALTH - 4);
+    }
+
+    #[test]
+    fn starvation_stops_at_half_a_heart() {
+        let mut health = Health::new();
+        let mut hunger = Hunger::new();
+        hunger.food = 0;
+
+        for _ in 0..100 {
+            update_hunger(&mut health, &mut hunger, FOOD_TICK_SECONDS);
+        }
+
+        assert_eq!(health.points, 1);
+        assert!(!health.is_dead());
+    }
+
+    #[test]
+    fn the_void_kills() {
+        let mut health = Health::new();
+
+        update_void_damage(&mut health, true, 0.4);
+        assert_eq!(health.points, MAX_HEALTH);
+
+        update_void_damage(&mut health, false, 0.4);
+        update_void_damage(&mut health, true, 0.4);
+        assert_eq!(health.points, MAX_HEALTH);
+
+        update_void_damage(&mut health, true, 3.0);
+        assert!(health.is_dead());
+    }
+}
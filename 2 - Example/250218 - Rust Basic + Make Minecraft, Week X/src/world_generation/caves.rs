@@ -0,0 +1,279 @@
+use crate::chunk::{BlockID, ChunkColumn};
+use crate::world_generation::column_rng;
+use noise::{NoiseFn, SuperSimplex};
+use rand::Rng;
+use std::f64::consts::PI;
+
+// Given to `column_rng` so that the caves don't use the same random numbers as the other passes
+const CAVE_PASS: u32 = 1;
+
+// Tunnels start in a column with this chance and are at most `WORM_MAX_LENGTH` blocks long,
+// so the tunnels of the columns up to `WORM_RANGE` columns away can reach a column
+const WORM_CHANCE: f64 = 0.4;
+const WORM_MIN_LENGTH: usize = 40;
+const WORM_MAX_LENGTH: usize = 80;
+const WORM_RANGE: i32 = (WORM_MAX_LENGTH as i32 + 15) / 16;
+const WORM_MIN_RADIUS: f64 = 1.2;
+const WORM_MAX_RADIUS: f64 = 2.6;
+
+// Caverns are carved where the noise is above the threshold
+const CAVERN_THRESHOLD: f64 = 0.6;
+const CAVERN_HORIZONTAL_SCALE: f64 = 48.0;
+const CAVERN_VERTICAL_SCALE: f64 = 24.0;
+
+// Nothing is carved in the bedrock rows, nor right below the surface (which also keeps the
+// highest blocks of the column right)
+const LOWEST_CARVED_Y: i32 = 3;
+const SURFACE_THICKNESS: i32 = 5;
+
+// The bottom of the caves is filled with lava up to this height
+const LAVA_LEVEL: i32 = 10;
+
+// Centre and radius of each step of a tunnel
+type Worm = Vec<([f64; 3], f64)>;
+
+// Tunnel starting in the column (x, z), it can go through the columns around it
+fn worm_starting_in(direction_noise: &SuperSimplex, seed: u32, x: i32, z: i32) -> Option<Worm> {
+    let mut rng = column_rng(seed, CAVE_PASS, x, z);
+
+    if rng.gen::<f64>() >= WORM_CHANCE {
+        return None;
+    }
+
+    let mut position = [
+        (16 * x) as f64 + rng.gen_range(0.0..16.0),
+        rng.gen_range(16.0..96.0),
+        (16 * z) as f64 + rng.gen_range(0.0..16.0),
+    ];
+    let length = rng.gen_range(WORM_MIN_LENGTH..=WORM_MAX_LENGTH);
+    let noise_offset = rng.gen_range(0.0..1000.0);
+    let mut yaw = rng.gen_range(0.0..2.0 * PI);
+
+    let mut worm = Vec::with_capacity(length);
+
+    for step in 0..length {
+        // Thin at both ends
+        let progress = step as f64 / length as f64;
+        let radius = WORM_MIN_RADIUS + (WORM_MAX_RADIUS - WORM_MIN_RADIUS) * (PI * progress).sin();
+        worm.push((position, radius));
+
+        // The direction slowly wanders, following the noise
+        let t = step as f64 / 12.0;
+        yaw += direction_noise.get([noise_offset, t]) * 0.5;
+        let pitch = direction_noise.get([noise_offset + 500.0, t]) * 0.6;
+
+        position[0] += yaw.cos() * pitch.cos();
+        position[1] += pitch.sin();
+        position[2] += yaw.sin() * pitch.cos();
+    }
+
+    Some(worm)
+}
+
+// Carves tunnels and caverns in the stone of a generated column, the lowest ones are filled with
+// lava. The result only depends on the seed and the position of the column.
+pub fn carve_caves(column: &ChunkColumn, x: i32, z: i32, seed: u32) {
+    let highest_blocks = **column.highest_blocks.read();
+    let highest_carved_y = |block_x: i32, block_z: i32| {
+        highest_blocks[(16 * block_z + block_x) as usize] as i32 - SURFACE_THICKNESS
+    };
+
+    let carve = |block_x: i32, y: i32, block_z: i32| {
+        if y < LOWEST_CARVED_Y || y > highest_carved_y(block_x, block_z) {
+            return;
+        }
+
+        let chunk = column.get_chunk(y / 16);
+        let (block_x, block_y, block_z) = (block_x as u32, (y % 16) as u32, block_z as u32);
+        let carved_block = if y <= LAVA_LEVEL {
+            BlockID::LAVA
+        } else {
+            BlockID::AIR
+        };
+
+        if chunk.get_block(block_x, block_y, block_z) != carved_block {
+            chunk.set_block(block_x, block_y, block_z, carved_block);
+        }
+    };
+
+    // Caverns
+    let cavern_noise = SuperSimplex::new(seed.wrapping_add(3));
+
+    for block_x in 0..16 {
+        for block_z in 0..16 {
+            for y in LOWEST_CARVED_Y..=highest_carved_y(block_x, block_z) {
+                let noise = cavern_noise.get([
+                    (16 * x + block_x) as f64 / CAVERN_HORIZONTAL_SCALE,
+                    y as f64 / CAVERN_VERTICAL_SCALE,
+                    (16 * z + block_z) as f64 / CAVERN_HORIZONTAL_SCALE,
+                ]);
+
+                if noise > CAVERN_THRESHOLD {
+                    carve(block_x, y, block_z);
+                }
+            }
+        }
+    }
+
+    // Tunnels, only the part inside this column is carved
+    let direction_noise = SuperSimplex::new(seed.wrapping_add(4));
+    let (min_x, min_z) = ((16 * x) as f64, (16 * z) as f64);
+
+    for origin_x in x - WORM_RANGE..=x + WORM_RANGE {
+        for origin_z in z - WORM_RANGE..=z + WORM_RANGE {
+            if let Some(worm) = worm_starting_in(&direction_noise, seed, origin_x, origin_z) {
+                for ([center_x, center_y, center_z], radius) in worm {
+                    if center_x + radius < min_x
+                        || center_x - radius >= min_x + 16.0
+                        || center_z + radius < min_z
+                        || center_z - radius >= min_z + 16.0
+                    {
+                        continue;
+                    }
+
+                    let block_x_range = ((center_x - radius - min_x).floor().max(0.0) as i32)
+                        ..=((center_x + radius - min_x).floor().min(15.0) as i32);
+                    let block_z_range = ((center_z - radius - min_z).floor().max(0.0) as i32)
+                        ..=((center_z + radius - min_z).floor().min(15.0) as i32);
+                    let y_range =
+                        ((center_y - radius).floor() as i32)..=((center_y + radius).floor() as i32);
+
+                    for block_x in block_x_range {
+                        for block_z in block_z_range.clone() {
+                            for y in y_range.clone() {
+                                let dx = min_x + block_x as f64 + 0.5 - center_x;
+                                let dy = y as f64 + 0.5 - center_y;
+                                let dz = min_z + block_z as f64 + 0.5 - center_z;
+
+                                if dx * dx + dy * dy + dz * dz <= radius * radius {
+                                    carve(block_x, y, block_z);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generation::superflat::SuperflatGenerator;
+    use crate::world_generation::WorldGenerator;
+
+    // Stone up to y = 199
+    fn stone_column() -> ChunkColumn {
+        let column = ChunkColumn::new();
+        SuperflatGenerator {
+            layers: vec![(BlockID::STONE, 200)],
+        }
+        .generate_column(&column, 0, 0, 0);
+
+        column
+    }
+
+    fn count_blocks(column: &ChunkColumn, block: BlockID) -> usize {
+        column
+            .chunks
+            .iter()
+            .flat_map(|chunk| chunk.blocks.read().to_vec())
+            .filter(|&other_block| other_block == block)
+            .count()
+    }
+
+    #[test]
+    fn caves_stay_between_the_bedrock_and_the_surface() {
+        let (mut carved_blocks, mut lava_blocks) = (0, 0);
+
+        for x in 0..8 {
+            let column = stone_column();
+            carve_caves(&column, x, -x, 42);
+
+            for block_x in 0..16 {
+                for block_z in 0..16 {
+                    for y in (0..LOWEST_CARVED_Y).chain(200 - SURFACE_THICKNESS..200) {
+                        assert_eq!(
+                            column
+                                .get_chunk(y / 16)
+                                .get_block(block_x, (y % 16) as u32, block_z),
+                            BlockID::STONE
+                        );
+                    }
+                }
+            }
+
+            assert!(column.highest_blocks.read().iter().all(|&y| y == 199));
+            carved_blocks += count_blocks(&column, BlockID::AIR) - 16 * 16 * 56;
+            lava_blocks += count_blocks(&column, BlockID::LAVA);
+
+            // Lava only fills the bottom of the caves
+            for y in LAVA_LEVEL + 1..200 {
+                for block_x in 0..16 {
+                    for block_z in 0..16 {
+                        assert_ne!(
+                            column
+                                .get_chunk(y / 16)
+                                .get_block(block_x, (y % 16) as u32, block_z),
+                            BlockID::LAVA
+                        );
+                    }
+                }
+            }
+        }
+
+        assert!(carved_blocks > 0);
+        assert!(lava_blocks > 0);
+    }
+
+    #[test]
+    fn tunnels_go_through_column_borders() {
+        // A seed with a tunnel starting in (0, 0)
+        let seed = (0..)
+            .find(|&seed: &u32| {
+                worm_starting_in(&SuperSimplex::new(seed + 4), seed, 0, 0).is_some()
+            })
+            .unwrap();
+        let direction_noise = SuperSimplex::new(seed + 4);
+
+        let columns: Vec<_> = (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |z| (x, z)))
+            .map(|(x, z)| {
+                let column = stone_column();
+                carve_caves(&column, x, z, seed);
+                ((x, z), column)
+            })
+            .collect();
+
+        let mut checked_columns = Vec::new();
+
+        for (center, _) in worm_starting_in(&direction_noise, seed, 0, 0).unwrap() {
+            let [x, y, z] = center.map(|coordinate| coordinate.floor() as i32);
+
+            if !(LOWEST_CARVED_Y..200 - SURFACE_THICKNESS).contains(&y) {
+                continue;
+            }
+
+            let column_xz = (x.div_euclid(16), z.div_euclid(16));
+
+            if let Some((_, column)) = columns.iter().find(|(xz, _)| *xz == column_xz) {
+                let block = column.get_chunk(y / 16).get_block(
+                    x.rem_euclid(16) as u32,
+                    (y % 16) as u32,
+                    z.rem_euclid(16) as u32,
+                );
+                assert!(
+                    block.is_air() || block == BlockID::LAVA,
+                    "({x}, {y}, {z}) was not carved"
+                );
+
+                if !checked_columns.contains(&column_xz) {
+                    checked_columns.push(column_xz);
+                }
+            }
+        }
+
+        assert!(checked_columns.len() > 1);
+    }
+}
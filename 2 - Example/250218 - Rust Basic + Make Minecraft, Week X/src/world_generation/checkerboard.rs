@@ -0,0 +1,77 @@
+use crate::chunk::{BlockID, ChunkColumn};
+use crate::chunk_manager::CHUNK_SIZE;
+use crate::world_generation::WorldGenerator;
+
+// Debug world of chunks full of one block, alternating with their neighbours in every direction
+// like `ChunkColumn::alternating`
+pub struct CheckerboardGenerator {
+    pub blocks: (BlockID, BlockID),
+    // Number of chunks filled in every column
+    pub height: u32,
+}
+
+impl Default for CheckerboardGenerator {
+    fn default() -> Self {
+        Self {
+            blocks: (BlockID::DIRT, BlockID::COBBLESTONE),
+            height: 4,
+        }
+    }
+}
+
+impl WorldGenerator for CheckerboardGenerator {
+    fn generate_column(&self, column: &ChunkColumn, x: i32, z: i32, _seed: u32) {
+        for chunk_y in 0..self.height.min(16) {
+            let block = if (x + chunk_y as i32 + z).rem_euclid(2) == 0 {
+                self.blocks.0
+            } else {
+                self.blocks.1
+            };
+
+            for block_y in 0..CHUNK_SIZE {
+                for block_x in 0..CHUNK_SIZE {
+                    for block_z in 0..CHUNK_SIZE {
+                        column.set_block(block_x, CHUNK_SIZE * chunk_y + block_y, block_z, block);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generation::column_hash;
+
+    #[test]
+    fn checkerboard_matches_the_golden_hashes() {
+        for (x, z, golden_hash) in [
+            (0, 0, 0x1073956e89e9b425),
+            (-1, 0, 0x7a11815ba1e9b425),
+            (4, -7, 0x7a11815ba1e9b425),
+        ] {
+            let column = ChunkColumn::new();
+            CheckerboardGenerator::default().generate_column(&column, x, z, 42);
+
+            assert_eq!(column_hash(&column), golden_hash, "({x}, {z})");
+        }
+    }
+
+    #[test]
+    fn neighbouring_chunks_alternate() {
+        let generator = CheckerboardGenerator::default();
+        let (column, neighbour) = (ChunkColumn::new(), ChunkColumn::new());
+        generator.generate_column(&column, 0, 0, 0);
+        generator.generate_column(&neighbour, -1, 0, 0);
+
+        assert_eq!(column.get_chunk(0).get_block(0, 0, 0), BlockID::DIRT);
+        assert_eq!(column.get_chunk(1).get_block(0, 0, 0), BlockID::COBBLESTONE);
+        assert_eq!(
+            neighbour.get_chunk(0).get_block(15, 0, 0),
+            BlockID::COBBLESTONE
+        );
+        assert!(column.get_chunk(4).is_empty());
+        assert!(column.highest_blocks.read().iter().all(|&y| y == 63));
+    }
+}
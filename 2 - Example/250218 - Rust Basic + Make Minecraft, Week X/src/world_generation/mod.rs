@@ -0,0 +1,126 @@
+pub mod caves;
+pub mod checkerboard;
+pub mod noise_terrain;
+pub mod ores;
+pub mod superflat;
+pub mod void;
+
+use crate::chunk::ChunkColumn;
+use crate::chunk_manager::ChunkManager;
+use crate::world_generation::checkerboard::CheckerboardGenerator;
+use crate::world_generation::noise_terrain::NoiseTerrainGenerator;
+use crate::world_generation::superflat::SuperflatGenerator;
+use crate::world_generation::void::VoidGenerator;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::Arc;
+
+// Decides what a chunk column is made of. The same generator must be used for the whole world
+// since the saved columns are never generated again.
+pub trait WorldGenerator: Send + Sync {
+    // Fills a (reset) column with its terrain, columns are generated in parallel
+    fn generate_column(&self, column: &ChunkColumn, x: i32, z: i32, seed: u32);
+
+    // Places what can cross the border of the column (e.g. trees). It is called once per column
+    // after its neighbours were generated, one column at a time.
+    fn decorate_column(&self, _chunk_manager: &ChunkManager, _x: i32, _z: i32, _seed: u32) {}
+}
+
+// Generator used when no other one is asked for
+pub const DEFAULT_WORLD_GENERATOR: &str = "default";
+
+// Creates a generator from its name, the superflat layers can follow a colon
+// (e.g. "superflat:bedrock,2*dirt,grass_block")
+pub fn world_generator_from_name(name: &str) -> Result<Arc<dyn WorldGenerator>, String> {
+    let (name, options) = match name.split_once(':') {
+        Some((name, options)) => (name, Some(options)),
+        None => (name, None),
+    };
+
+    match (name, options) {
+        ("default", None) => Ok(Arc::new(NoiseTerrainGenerator::default())),
+        ("superflat", None) => Ok(Arc::new(SuperflatGenerator::default())),
+        ("superflat", Some(layers)) => Ok(Arc::new(SuperflatGenerator::from_preset(layers)?)),
+        ("void", None) => Ok(Arc::new(VoidGenerator)),
+        ("checkerboard", None) => Ok(Arc::new(CheckerboardGenerator::default())),
+        (_, Some(_)) => Err(format!("The world generator \"{name}\" has no options")),
+        _ => Err(format!("Unknown world generator \"{name}\"")),
+    }
+}
+
+pub(crate) fn position_hash(seed: u64, x: i32, z: i32) -> u64 {
+    let position = ((x as u32 as u64) << 32) | z as u32 as u64;
+    let mut hash = seed ^ position.wrapping_mul(0x9e3779b97f4a7c15);
+
+    // SplitMix64 finalizer
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58476d1ce4e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d049bb133111eb);
+    hash ^= hash >> 31;
+
+    hash
+}
+
+// Pseudo random number between 0 and 1 that only depends on the seed and the position
+pub(crate) fn random_at(seed: u32, x: i32, z: i32) -> f64 {
+    (position_hash(seed as u64, x, z) >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+// Random number generator of a generation pass that only depends on the seed and the column,
+// so that a column is the same whatever the order in which the columns are generated
+pub(crate) fn column_rng(seed: u32, pass: u32, x: i32, z: i32) -> StdRng {
+    StdRng::seed_from_u64(position_hash(((pass as u64) << 32) | seed as u64, x, z))
+}
+
+// Hash of the blocks, the height map and the biomes of a column, compared with the golden
+// values of the tests
+#[cfg(test)]
+pub(crate) fn column_hash(column: &ChunkColumn) -> u64 {
+    // FNV-1a
+    let mut hash = 0xcbf29ce484222325_u64;
+    let mut write = |byte: u8| {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    };
+
+    for chunk in column.chunks.iter() {
+        for block in chunk.blocks.read().iter() {
+            write(block.id());
+        }
+    }
+
+    for &height in column.highest_blocks.read().iter() {
+        write(height);
+    }
+
+    for biome in column.biomes.read().iter() {
+        write(biome.id());
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::BlockID;
+
+    #[test]
+    fn generators_are_found_by_name() {
+        let column = ChunkColumn::new();
+
+        world_generator_from_name("superflat:stone,grass_block")
+            .unwrap()
+            .generate_column(&column, 0, 0, 0);
+        assert_eq!(column.get_chunk(0).get_block(0, 1, 0), BlockID::GRASS_BLOCK);
+
+        for name in ["default", "superflat", "void", "checkerboard"] {
+            assert!(world_generator_from_name(name).is_ok());
+        }
+
+        assert!(world_generator_from_name("amplified").is_err());
+        assert!(world_generator_from_name("void:stone").is_err());
+        assert!(world_generator_from_name("superflat:unknown_block").is_err());
+    }
+}
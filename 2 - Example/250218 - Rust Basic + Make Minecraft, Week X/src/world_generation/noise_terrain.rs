@@ -0,0 +1,550 @@
+use crate::biome::{Biome, BiomeParameters, TreeKind};
+use crate::chunk::{BlockID, ChunkColumn};
+use crate::chunk_manager::ChunkManager;
+use crate::world_generation::caves::carve_caves;
+use crate::world_generation::ores::{place_ores, OreConfig, DEFAULT_ORES};
+use crate::world_generation::{random_at, WorldGenerator};
+use noise::{NoiseFn, SuperSimplex};
+
+// Hills of stone shaped by a 3D noise whose height and amplitude depend on the biomes, covered
+// with the surface blocks and the trees of each biome, with caves and ores underground. The
+// terrain below `SEA_LEVEL` is under water.
+pub struct NoiseTerrainGenerator {
+    pub ores: Vec<OreConfig>,
+}
+
+impl Default for NoiseTerrainGenerator {
+    fn default() -> Self {
+        Self {
+            ores: DEFAULT_ORES.to_vec(),
+        }
+    }
+}
+
+// Highest water block of the seas and lakes
+pub const SEA_LEVEL: i32 = 90;
+
+// Size of the areas of similar temperature and humidity
+const CLIMATE_SCALE: f64 = 480.0;
+
+// The shape of the terrain at a position is the average of the biomes at this distance around
+const BLEND_RADIUS: i32 = 8;
+const BLEND_WIDTH: usize = 16 + 2 * BLEND_RADIUS as usize;
+
+struct Climate {
+    temperature: SuperSimplex,
+    humidity: SuperSimplex,
+}
+
+impl Climate {
+    fn new(seed: u32) -> Self {
+        Self {
+            temperature: SuperSimplex::new(seed.wrapping_add(1)),
+            humidity: SuperSimplex::new(seed.wrapping_add(2)),
+        }
+    }
+
+    fn biome_at(&self, x: i32, z: i32) -> Biome {
+        let point = [x as f64 / CLIMATE_SCALE, z as f64 / CLIMATE_SCALE];
+
+        Biome::from_climate(self.temperature.get(point), self.humidity.get(point))
+    }
+}
+
+// Biomes of a column and of the blocks around it, up to `BLEND_RADIUS`
+struct BiomeMap {
+    biomes: Vec<Biome>,
+}
+
+impl BiomeMap {
+    fn new(climate: &Climate, chunk_x: i32, chunk_z: i32) -> Self {
+        let mut biomes = Vec::with_capacity(BLEND_WIDTH * BLEND_WIDTH);
+
+        for i in 0..BLEND_WIDTH as i32 {
+            for j in 0..BLEND_WIDTH as i32 {
+                biomes.push(climate.biome_at(
+                    16 * chunk_x + i - BLEND_RADIUS,
+                    16 * chunk_z + j - BLEND_RADIUS,
+                ));
+            }
+        }
+
+        Self { biomes }
+    }
+
+    // (x, z) is relative to the column, between -`BLEND_RADIUS` and 15 + `BLEND_RADIUS`
+    #[inline]
+    fn get(&self, x: i32, z: i32) -> Biome {
+        self.biomes[(x + BLEND_RADIUS) as usize * BLEND_WIDTH + (z + BLEND_RADIUS) as usize]
+    }
+
+    // Base height and height amplitude of a block of the column, averaged over the biomes around
+    fn blended_shape(&self, x: i32, z: i32) -> (f64, f64) {
+        let (mut base_height, mut height_amplitude) = (0.0, 0.0);
+
+        for i in x - BLEND_RADIUS..=x + BLEND_RADIUS {
+            for j in z - BLEND_RADIUS..=z + BLEND_RADIUS {
+                let parameters = self.get(i, j).parameters();
+                base_height += parameters.base_height;
+                height_amplitude += parameters.height_amplitude;
+            }
+        }
+
+        let samples = ((2 * BLEND_RADIUS + 1) * (2 * BLEND_RADIUS + 1)) as f64;
+
+        (base_height / samples, height_amplitude / samples)
+    }
+}
+
+// Grass and snow don't stay under water, the filler block is used instead
+fn surface_block_at(parameters: &BiomeParameters, y: i32) -> BlockID {
+    match parameters.surface_block {
+        BlockID::GRASS_BLOCK | BlockID::SNOW_BLOCK if y < SEA_LEVEL => parameters.filler_block,
+        surface_block => surface_block,
+    }
+}
+
+fn compute_tree_placement_in_chunk(noise: &SuperSimplex, x: f64, z: f64) -> Vec<(u32, u32)> {
+    let mut maximums = Vec::new();
+
+    #[inline]
+    fn index(i: i32, j: i32) -> usize {
+        (18 * i + j) as usize
+    }
+
+    let mut samples: [f64; 18 * 18] = [0.0; 18 * 18];
+
+    for i in -1..=16 {
+        for j in -1..=16 {
+            let x = x + j as f64 * 0.075;
+            let z = z + i as f64 * 0.075;
+            samples[index(i + 1, j + 1)] = noise.get([x, z]);
+        }
+    }
+
+    for i in 1..17 {
+        for j in 1..17 {
+            let center = samples[index(i, j)];
+            let is_max = (|| {
+                for i_new in i - 1..=i + 1 {
+                    for j_new in j - 1..=j + 1 {
+                        if i_new == i && j_new == j {
+                            continue;
+                        }
+
+                        if samples[index(i_new, j_new)] >= center {
+                            return false;
+                        }
+                    }
+                }
+
+                true
+            })();
+
+            if is_max {
+                maximums.push(((j - 1) as u32, (i - 1) as u32));
+            }
+        }
+    }
+
+    maximums
+}
+
+impl WorldGenerator for NoiseTerrainGenerator {
+    fn generate_column(&self, column: &ChunkColumn, x: i32, z: i32, seed: u32) {
+        let noise_fn = SuperSimplex::new(seed);
+        let biome_map = BiomeMap::new(&Climate::new(seed), x, z);
+
+        for block_x in 0..16 {
+            for block_z in 0..16 {
+                let biome = biome_map.get(block_x, block_z);
+                let (base_height, height_amplitude) = biome_map.blended_shape(block_x, block_z);
+                column.biomes.write()[16 * block_z as usize + block_x as usize] = biome;
+
+                // Stone, the noise can only change the blocks within the amplitude
+                let lowest_y = (base_height - height_amplitude).floor().max(0.0) as i32;
+                let highest_y = (base_height + height_amplitude).ceil().min(255.0) as i32;
+
+                for y in 0..=highest_y {
+                    let is_stone = y < lowest_y || {
+                        let scale = 90.0;
+
+                        // Scale the input for the noise function
+                        let (xf, yf, zf) = (
+                            (16 * x + block_x) as f64 / scale,
+                            y as f64 / scale,
+                            (16 * z + block_z) as f64 / scale,
+                        );
+
+                        (y as f64) < base_height + noise_fn.get([xf, yf, zf]) * height_amplitude
+                    };
+
+                    if is_stone {
+                        column.set_block(block_x as u32, y as u32, block_z as u32, BlockID::STONE);
+                    }
+                }
+
+                // Surface and filler blocks
+                let parameters = biome.parameters();
+                let y =
+                    column.highest_blocks.read()[16 * block_z as usize + block_x as usize] as i32;
+
+                column.get_chunk(y / 16).set_block(
+                    block_x as u32,
+                    (y % 16) as u32,
+                    block_z as u32,
+                    surface_block_at(parameters, y),
+                );
+
+                for y in (y - parameters.filler_depth).max(0)..y {
+                    let chunk = column.get_chunk(y / 16);
+                    let block_y = (y % 16) as u32;
+
+                    if chunk
+                        .get_block(block_x as u32, block_y, block_z as u32)
+                        .is_air()
+                    {
+                        continue;
+                    }
+
+                    chunk.set_block(
+                        block_x as u32,
+                        block_y,
+                        block_z as u32,
+                        parameters.filler_block,
+                    );
+                }
+
+                // Water sources up to the sea level, they are not part of the height map
+                for y in y + 1..=SEA_LEVEL {
+                    column.get_chunk(y / 16).set_block(
+                        block_x as u32,
+                        (y % 16) as u32,
+                        block_z as u32,
+                        BlockID::WATER,
+                    );
+                }
+            }
+        }
+
+        carve_caves(column, x, z, seed);
+        place_ores(column, x, z, seed, &self.ores);
+
+        // Bedrock
+        let chunk = column.get_chunk(0);
+
+        for block_x in 0..16 {
+            for block_z in 0..16 {
+                for block_y in 0..3 {
+                    chunk.set_block(block_x, block_y, block_z, BlockID::BEDROCK);
+                }
+            }
+        }
+    }
+
+    fn decorate_column(&self, chunk_manager: &ChunkManager, chunk_x: i32, chunk_z: i32, seed: u32) {
+        let column = match chunk_manager.get_column(chunk_x, chunk_z) {
+            Some(column) => column,
+            None => return,
+        };
+        let noise_fn = SuperSimplex::new(seed);
+
+        // Trees
+        for (x, z) in
+            compute_tree_placement_in_chunk(&noise_fn, (chunk_x * 16) as f64, (chunk_z * 16) as f64)
+        {
+            let (x, z) = (x as usize, z as usize);
+            let y = column.highest_blocks.read()[16 * z + x] as i32;
+            let parameters = column.biomes.read()[16 * z + x].parameters();
+
+            let x = chunk_x * 16 + x as i32;
+            let z = chunk_z * 16 + z as i32;
+
+            // No trees under water
+            if y < SEA_LEVEL || random_at(seed, x, z) >= parameters.tree_density {
+                continue;
+            }
+
+            match parameters.tree {
+                Some(TreeKind::Oak) => place_oak_tree(chunk_manager, x, y, z),
+                Some(TreeKind::Spruce) => place_spruce_tree(chunk_manager, x, y, z),
+                None => {}
+            }
+        }
+    }
+}
+
+// Trees of neighbouring columns can overlap, so a tree block only replaces the blocks that are
+// less important than it. This makes the result independent of the order of the columns.
+fn place_tree_block(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32, block: BlockID) {
+    // Logs over leaves over air, the id breaks ties. The terrain is never replaced.
+    let importance = |block: BlockID| match block {
+        BlockID::AIR => Some((0, block.id())),
+        BlockID::OAK_LEAVES | BlockID::SPRUCE_LEAVES => Some((1, block.id())),
+        BlockID::OAK_LOG | BlockID::SPRUCE_LOG => Some((2, block.id())),
+        _ => None,
+    };
+
+    if let Some(old_block) = chunk_manager.get_block(x, y, z) {
+        if importance(old_block)
+            .is_some_and(|old_importance| importance(block) > Some(old_importance))
+        {
+            chunk_manager.set_block(x, y, z, block);
+        }
+    }
+}
+
+// (x, y, z) is the block on which the tree grows
+fn place_oak_tree(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) {
+    let h = 5;
+
+    for i in y + 1..y + 1 + h {
+        place_tree_block(chunk_manager, x, i, z, BlockID::OAK_LOG);
+    }
+
+    for yy in y + h - 2..=y + h - 1 {
+        for xx in x - 2..=x + 2 {
+            for zz in z - 2..=z + 2 {
+                if xx != x || zz != z {
+                    place_tree_block(chunk_manager, xx, yy, zz, BlockID::OAK_LEAVES);
+                }
+            }
+        }
+    }
+
+    for xx in x - 1..=x + 1 {
+        for zz in z - 1..=z + 1 {
+            if xx != x || zz != z {
+                place_tree_block(chunk_manager, xx, y + h, zz, BlockID::OAK_LEAVES);
+            }
+        }
+    }
+
+    place_tree_block(chunk_manager, x, y + h + 1, z, BlockID::OAK_LEAVES);
+    place_tree_block(chunk_manager, x + 1, y + h + 1, z, BlockID::OAK_LEAVES);
+    place_tree_block(chunk_manager, x - 1, y + h + 1, z, BlockID::OAK_LEAVES);
+    place_tree_block(chunk_manager, x, y + h + 1, z + 1, BlockID::OAK_LEAVES);
+    place_tree_block(chunk_manager, x, y + h + 1, z - 1, BlockID::OAK_LEAVES);
+}
+
+fn place_spruce_tree(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) {
+    let h = 7;
+
+    for i in y + 1..y + 1 + h {
+        place_tree_block(chunk_manager, x, i, z, BlockID::SPRUCE_LOG);
+    }
+
+    // Layers of leaves from the top, their radius grows and shrinks to make a cone
+    for (dy, radius) in [
+        (h + 1, 0),
+        (h, 1),
+        (h - 1, 1),
+        (h - 2, 2),
+        (h - 3, 1),
+        (h - 4, 2),
+    ] {
+        for xx in x - radius..=x + radius {
+            for zz in z - radius..=z + radius {
+                let is_corner = radius > 0 && (xx - x).abs() == radius && (zz - z).abs() == radius;
+
+                if (xx != x || zz != z || dy > h) && !is_corner {
+                    place_tree_block(chunk_manager, xx, y + dy, zz, BlockID::SPRUCE_LEAVES);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generation::column_hash;
+    use std::sync::Arc;
+
+    #[test]
+    fn terrain_matches_the_golden_hashes() {
+        for (x, z, seed, golden_hash) in [
+            (0, 0, 42, 0xaf8083a810ab8147),
+            (-3, 7, 42, 0x4aeb7842c87431a3),
+            (0, 0, 1234, 0xa04710cd8ff6e600),
+        ] {
+            let column = ChunkColumn::new();
+            NoiseTerrainGenerator::default().generate_column(&column, x, z, seed);
+
+            assert_eq!(column_hash(&column), golden_hash, "({x}, {z}) seed {seed}");
+        }
+    }
+
+    #[test]
+    fn surface_blocks_come_from_the_biomes() {
+        for (x, z) in [(5, -2), (40, 17), (-60, 90)] {
+            let column = ChunkColumn::new();
+            NoiseTerrainGenerator::default().generate_column(&column, x, z, 42);
+
+            for x in 0..16 {
+                for z in 0..16 {
+                    let y = column.highest_blocks.read()[16 * z + x] as u32;
+                    let biome = column.biomes.read()[16 * z + x];
+                    let (x, z) = (x as u32, z as u32);
+
+                    assert_eq!(
+                        column.get_chunk((y / 16) as i32).get_block(x, y % 16, z),
+                        surface_block_at(biome.parameters(), y as i32)
+                    );
+                    assert_eq!(column.get_chunk(0).get_block(x, 0, z), BlockID::BEDROCK);
+                    assert_eq!(column.get_chunk(0).get_block(x, 2, z), BlockID::BEDROCK);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seas_are_filled_with_water_up_to_the_sea_level() {
+        // A column in the middle of an ocean
+        let climate = Climate::new(42);
+        let (x, z) = (-40..40)
+            .flat_map(|x| (-40..40).map(move |z| (8 * x, 8 * z)))
+            .find(|&(x, z)| climate.biome_at(16 * x + 8, 16 * z + 8) == Biome::Ocean)
+            .unwrap();
+
+        let column = ChunkColumn::new();
+        NoiseTerrainGenerator::default().generate_column(&column, x, z, 42);
+        let block_at =
+            |x: u32, y: i32, z: u32| column.get_chunk(y / 16).get_block(x, (y % 16) as u32, z);
+
+        let mut water_blocks = 0;
+
+        for x in 0..16 {
+            for z in 0..16 {
+                let y = column.highest_blocks.read()[16 * z as usize + x as usize] as i32;
+
+                for y in y + 1..=SEA_LEVEL {
+                    assert_eq!(block_at(x, y, z), BlockID::WATER);
+                    water_blocks += 1;
+                }
+
+                if y < SEA_LEVEL {
+                    assert!(block_at(x, SEA_LEVEL + 1, z).is_air());
+                    assert_ne!(block_at(x, y, z), BlockID::GRASS_BLOCK);
+                }
+            }
+        }
+
+        assert!(water_blocks > 0);
+    }
+
+    #[test]
+    fn every_biome_is_generated() {
+        let climate = Climate::new(42);
+        let mut biomes = Vec::new();
+
+        for x in -40..40 {
+            for z in -40..40 {
+                let biome = climate.biome_at(64 * x, 64 * z);
+
+                if !biomes.contains(&biome) {
+                    biomes.push(biome);
+                }
+            }
+        }
+
+        assert_eq!(biomes.len(), Biome::ALL.len());
+    }
+
+    #[test]
+    fn biome_borders_are_blended() {
+        let heights = Biome::ALL.map(|biome| biome.parameters().base_height);
+        let highest = heights.iter().copied().fold(f64::MIN, f64::max);
+        let lowest = heights.iter().copied().fold(f64::MAX, f64::min);
+
+        // Moving by one block changes one row of the averaged biomes
+        let samples = (2 * BLEND_RADIUS + 1) as f64;
+        let max_step = (highest - lowest) / samples + 1e-9;
+
+        let climate = Climate::new(42);
+        let mut is_border_crossed = false;
+
+        for chunk_x in 0..16 {
+            let biome_map = BiomeMap::new(&climate, chunk_x, 3);
+
+            for x in 0..15 {
+                let (base_height, _) = biome_map.blended_shape(x, 7);
+                let (next_base_height, _) = biome_map.blended_shape(x + 1, 7);
+
+                assert!((next_base_height - base_height).abs() <= max_step);
+                is_border_crossed |= biome_map.get(x, 7) != biome_map.get(x + 1, 7);
+            }
+        }
+
+        assert!(is_border_crossed);
+    }
+
+    #[test]
+    fn trees_match_the_golden_hash() {
+        let chunk_manager = ChunkManager::new();
+
+        // In a forest
+        for x in -9..=-7 {
+            for z in 0..=2 {
+                let column = ChunkColumn::new();
+                NoiseTerrainGenerator::default().generate_column(&column, x, z, 42);
+                chunk_manager.add_chunk_column((x, z), Arc::new(column));
+            }
+        }
+
+        NoiseTerrainGenerator::default().decorate_column(&chunk_manager, -8, 1, 42);
+
+        let column = chunk_manager.get_column(-8, 1).unwrap();
+        let logs = column
+            .chunks
+            .iter()
+            .flat_map(|chunk| chunk.blocks.read().to_vec())
+            .filter(|&block| block == BlockID::OAK_LOG)
+            .count();
+
+        assert!(logs > 0);
+        assert_eq!(logs % 5, 0);
+        assert_eq!(column_hash(&column), 0x1368d9de9b7562ae);
+    }
+
+    #[test]
+    fn trees_do_not_depend_on_the_decoration_order() {
+        let decorate = |columns: &[(i32, i32)]| {
+            let chunk_manager = ChunkManager::new();
+
+            for x in -10..=-6 {
+                for z in -1..=3 {
+                    let column = ChunkColumn::new();
+                    NoiseTerrainGenerator::default().generate_column(&column, x, z, 42);
+                    chunk_manager.add_chunk_column((x, z), Arc::new(column));
+                }
+            }
+
+            for &(x, z) in columns {
+                NoiseTerrainGenerator::default().decorate_column(&chunk_manager, x, z, 42);
+            }
+
+            let mut hashes: Vec<_> = chunk_manager
+                .loaded_chunk_columns
+                .read()
+                .iter()
+                .map(|(&xz, column)| (xz, column_hash(column)))
+                .collect();
+            hashes.sort();
+
+            hashes
+        };
+
+        // The trees of these forest columns overlap
+        let mut columns: Vec<_> = (-9..=-7)
+            .flat_map(|x| (0..=2).map(move |z| (x, z)))
+            .collect();
+        let hashes = decorate(&columns);
+
+        columns.reverse();
+        assert_eq!(decorate(&columns), hashes);
+
+        columns.rotate_left(4);
+        assert_eq!(decorate(&columns), hashes);
+    }
+}
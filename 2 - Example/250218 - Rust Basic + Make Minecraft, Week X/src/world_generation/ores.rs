@@ -0,0 +1,148 @@
+use crate::chunk::{BlockID, ChunkColumn};
+use crate::world_generation::column_rng;
+use rand::Rng;
+
+// Given to `column_rng` so that the ores don't use the same random numbers as the other passes
+const ORE_PASS: u32 = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OreConfig {
+    pub block: BlockID,
+    // The veins only replace the stone between these heights (inclusive)
+    pub min_height: i32,
+    pub max_height: i32,
+    pub veins_per_column: u32,
+    // Number of steps of the random walk drawing a vein, the vein can be smaller
+    pub vein_size: u32,
+}
+
+pub const DEFAULT_ORES: [OreConfig; 4] = [
+    OreConfig {
+        block: BlockID::COAL_ORE,
+        min_height: 5,
+        max_height: 128,
+        veins_per_column: 20,
+        vein_size: 14,
+    },
+    OreConfig {
+        block: BlockID::IRON_ORE,
+        min_height: 5,
+        max_height: 64,
+        veins_per_column: 12,
+        vein_size: 8,
+    },
+    OreConfig {
+        block: BlockID::GOLD_ORE,
+        min_height: 5,
+        max_height: 32,
+        veins_per_column: 3,
+        vein_size: 8,
+    },
+    OreConfig {
+        block: BlockID::DIAMOND_ORE,
+        min_height: 5,
+        max_height: 16,
+        veins_per_column: 1,
+        vein_size: 6,
+    },
+];
+
+// Replaces some stone of the column by veins of ore. Veins stay inside the column so that the
+// result only depends on the seed and the position of the column.
+pub fn place_ores(column: &ChunkColumn, x: i32, z: i32, seed: u32, ores: &[OreConfig]) {
+    let mut rng = column_rng(seed, ORE_PASS, x, z);
+
+    for ore in ores {
+        for _ in 0..ore.veins_per_column {
+            let mut position = [
+                rng.gen_range(0..16),
+                rng.gen_range(ore.min_height..=ore.max_height),
+                rng.gen_range(0..16),
+            ];
+
+            for _ in 0..ore.vein_size {
+                let [block_x, y, block_z] = position;
+
+                if (0..16).contains(&block_x)
+                    && (0..16).contains(&block_z)
+                    && (ore.min_height..=ore.max_height).contains(&y)
+                {
+                    let chunk = column.get_chunk(y / 16);
+                    let (block_x, block_y, block_z) =
+                        (block_x as u32, (y % 16) as u32, block_z as u32);
+
+                    if chunk.get_block(block_x, block_y, block_z) == BlockID::STONE {
+                        chunk.set_block(block_x, block_y, block_z, ore.block);
+                    }
+                }
+
+                // The vein grows from its last block
+                let axis = rng.gen_range(0..3);
+                position[axis] += if rng.gen() { 1 } else { -1 };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generation::superflat::SuperflatGenerator;
+    use crate::world_generation::{column_hash, WorldGenerator};
+
+    #[test]
+    fn ores_replace_stone_within_their_heights() {
+        let column = ChunkColumn::new();
+        SuperflatGenerator {
+            layers: vec![
+                (BlockID::BEDROCK, 1),
+                (BlockID::STONE, 99),
+                (BlockID::DIRT, 1),
+            ],
+        }
+        .generate_column(&column, 0, 0, 0);
+
+        place_ores(&column, 7, -3, 42, &DEFAULT_ORES);
+
+        let mut found_ores = Vec::new();
+
+        for y in 0..256 {
+            for x in 0..16 {
+                for z in 0..16 {
+                    let block = column.get_chunk(y / 16).get_block(x, (y % 16) as u32, z);
+
+                    if let Some(ore) = DEFAULT_ORES.iter().find(|ore| ore.block == block) {
+                        assert!((ore.min_height..=ore.max_height).contains(&y));
+                        assert!(y < 100);
+
+                        if !found_ores.contains(&block) {
+                            found_ores.push(block);
+                        }
+                    }
+                }
+            }
+        }
+
+        assert_eq!(found_ores.len(), DEFAULT_ORES.len());
+        assert_eq!(column.get_chunk(0).get_block(3, 0, 3), BlockID::BEDROCK);
+        assert_eq!(column.get_chunk(6).get_block(3, 4, 3), BlockID::DIRT);
+    }
+
+    #[test]
+    fn ores_only_depend_on_the_seed_and_the_column() {
+        let hash = |x, z, seed| {
+            let column = ChunkColumn::new();
+            SuperflatGenerator {
+                layers: vec![(BlockID::STONE, 64)],
+            }
+            .generate_column(&column, 0, 0, 0);
+            place_ores(&column, x, z, seed, &DEFAULT_ORES);
+
+            column_hash(&column)
+        };
+
+        assert_eq!(hash(1, 2, 42), hash(1, 2, 42));
+        assert_ne!(hash(1, 2, 42), hash(2, 1, 42));
+        assert_ne!(hash(1, 2, 42), hash(1, 2, 43));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::block_registry::BLOCK_REGISTRY;
+use crate::chunk::{BlockID, ChunkColumn};
+use crate::chunk_manager::CHUNK_SIZE;
+use crate::world_generation::WorldGenerator;
+
+// Flat world made of horizontal layers, the same everywhere
+pub struct SuperflatGenerator {
+    // From the bottom to the top, with the thickness of each layer
+    pub layers: Vec<(BlockID, u32)>,
+}
+
+impl Default for SuperflatGenerator {
+    fn default() -> Self {
+        Self {
+            layers: vec![
+                (BlockID::BEDROCK, 1),
+                (BlockID::DIRT, 2),
+                (BlockID::GRASS_BLOCK, 1),
+            ],
+        }
+    }
+}
+
+impl SuperflatGenerator {
+    // Reads layers written from the bottom to the top and separated by commas, the thickness can
+    // be given before the block name (e.g. "bedrock,2*dirt,grass_block")
+    pub fn from_preset(preset: &str) -> Result<Self, String> {
+        let mut layers = Vec::new();
+
+        for layer in preset.split(',') {
+            let (thickness, name) = match layer.trim().split_once('*') {
+                Some((thickness, name)) => (
+                    thickness
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|err| format!("Invalid thickness in \"{layer}\": {err}"))?,
+                    name.trim(),
+                ),
+                None => (1, layer.trim()),
+            };
+
+            let block = BLOCK_REGISTRY
+                .get_by_name(name)
+                .ok_or_else(|| format!("Unknown block \"{name}\""))?;
+
+            layers.push((block, thickness));
+        }
+
+        // The thicknesses may overflow before the height is checked
+        let height = layers.iter().try_fold(0u32, |height, &(_, thickness)| {
+            height.checked_add(thickness)
+        });
+
+        if height.is_none_or(|height| height > 16 * CHUNK_SIZE) {
+            return Err(format!(
+                "The layers are more than {} blocks high",
+                16 * CHUNK_SIZE
+            ));
+        }
+
+        Ok(Self { layers })
+    }
+}
+
+impl WorldGenerator for SuperflatGenerator {
+    fn generate_column(&self, column: &ChunkColumn, _x: i32, _z: i32, _seed: u32) {
+        let mut y = 0;
+
+        for &(block, thickness) in self.layers.iter() {
+            for _ in 0..thickness {
+                if !block.is_air() {
+                    for x in 0..16 {
+                        for z in 0..16 {
+                            column.set_block(x, y, z, block);
+                        }
+                    }
+                }
+
+                y += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generation::column_hash;
+
+    #[test]
+    fn default_layers_match_the_golden_hash() {
+        let column = ChunkColumn::new();
+        SuperflatGenerator::default().generate_column(&column, 3, -8, 42);
+
+        assert_eq!(column_hash(&column), 0x6612000909d96325);
+
+        let chunk = column.get_chunk(0);
+        assert_eq!(chunk.get_block(7, 0, 7), BlockID::BEDROCK);
+        assert_eq!(chunk.get_block(7, 2, 7), BlockID::DIRT);
+        assert_eq!(chunk.get_block(7, 3, 7), BlockID::GRASS_BLOCK);
+        assert_eq!(chunk.get_block(7, 4, 7), BlockID::AIR);
+        assert!(column.highest_blocks.read().iter().all(|&y| y == 3));
+    }
+
+    #[test]
+    fn layers_are_read_from_a_preset() {
+        let generator = SuperflatGenerator::from_preset("bedrock, 20*stone,air,glass").unwrap();
+
+        assert_eq!(
+            generator.layers,
+            vec![
+                (BlockID::BEDROCK, 1),
+                (BlockID::STONE, 20),
+                (BlockID::AIR, 1),
+                (BlockID::GLASS, 1),
+            ]
+        );
+
+        let column = ChunkColumn::new();
+        generator.generate_column(&column, 0, 0, 0);
+        assert_eq!(column.get_chunk(1).get_block(0, 4, 0), BlockID::STONE);
+        assert_eq!(column.get_chunk(1).get_block(0, 5, 0), BlockID::AIR);
+        assert_eq!(column.get_chunk(1).get_block(0, 6, 0), BlockID::GLASS);
+
+        assert!(SuperflatGenerator::from_preset("x*stone").is_err());
+        assert!(SuperflatGenerator::from_preset("300*stone").is_err());
+        assert!(SuperflatGenerator::from_preset("4294967295*stone,stone").is_err());
+    }
+}
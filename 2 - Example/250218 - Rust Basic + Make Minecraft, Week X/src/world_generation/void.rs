@@ -0,0 +1,24 @@
+use crate::chunk::ChunkColumn;
+use crate::world_generation::WorldGenerator;
+
+// Nothing but air, useful to build or test things without terrain in the way
+pub struct VoidGenerator;
+
+impl WorldGenerator for VoidGenerator {
+    fn generate_column(&self, _column: &ChunkColumn, _x: i32, _z: i32, _seed: u32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generation::column_hash;
+
+    #[test]
+    fn void_matches_the_golden_hash() {
+        let column = ChunkColumn::new();
+        VoidGenerator.generate_column(&column, 1, 2, 42);
+
+        assert_eq!(column_hash(&column), 0xd67247383e994b25);
+        assert!(column.chunks.iter().all(|chunk| chunk.is_empty()));
+    }
+}
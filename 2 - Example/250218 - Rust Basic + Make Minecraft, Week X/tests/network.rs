@@ -0,0 +1,593 @@
+use minecraft::chunk::{BlockID, ChunkColumn};
+use minecraft::chunk_manager::ChunkManager;
+use minecraft::constants::PHYSICS_TICKRATE;
+use minecraft::ecs::systems::{spawn_dropped_item, SyncWithServer};
+use minecraft::inventory::dropped_item::DroppedItem;
+use minecraft::inventory::item::ItemStack;
+use minecraft::inventory::{Inventory, HOTBAR_SIZE, INVENTORY_SIZE};
+use minecraft::network::client::Client;
+use minecraft::network::protocol::{Message, PROTOCOL_VERSION};
+use minecraft::network::server::Server;
+use minecraft::network::server_world::ServerWorld;
+use minecraft::network::transport::{ChannelConnection, Connection, TcpConnection};
+use minecraft::physics::Transform;
+use minecraft::player::PlayerState;
+use minecraft::timer::Timer;
+use minecraft::world_generation::superflat::SuperflatGenerator;
+use nalgebra_glm::vec3;
+use specs::{Builder, Join, LazyUpdate, RunNow, World, WorldExt};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct RunningServer {
+    is_running: Arc<AtomicBool>,
+    thread: JoinHandle<Server>,
+}
+
+impl RunningServer {
+    fn start(mut server: Server) -> Self {
+        let is_running = Arc::new(AtomicBool::new(true));
+
+        let thread = {
+            let is_running = Arc::clone(&is_running);
+
+            thread::spawn(move || {
+                while is_running.load(Ordering::SeqCst) {
+                    server.update();
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                server
+            })
+        };
+
+        Self { is_running, thread }
+    }
+
+    fn stop(self) -> Server {
+        self.is_running.store(false, Ordering::SeqCst);
+        self.thread.join().unwrap()
+    }
+}
+
+// A fully generated column with a stone floor
+fn server_chunk_manager() -> Arc<ChunkManager> {
+    let chunk_manager = Arc::new(ChunkManager::new());
+    let column = ChunkColumn::new();
+
+    for x in 0..16 {
+        for z in 0..16 {
+            column.set_block(x, 0, z, BlockID::STONE);
+        }
+    }
+
+    *column.has_foliage.write() = true;
+    chunk_manager.add_chunk_column((0, 0), Arc::new(column));
+
+    chunk_manager
+}
+
+fn wait_until(client: &mut Client, condition: impl Fn(&Client) -> bool) {
+    let start = Instant::now();
+
+    while !condition(client) {
+        client.update().unwrap();
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "Timed out waiting for the server"
+        );
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+// Both clients must be connected to a server using `server_chunk_manager`
+fn play_together(mut client_a: Client, mut client_b: Client) -> (Client, Client) {
+    assert_ne!(client_a.player_id, client_b.player_id);
+    assert_eq!(client_a.spawn_position, client_b.spawn_position);
+
+    // Chunk column data
+    for client in [&mut client_a, &mut client_b] {
+        client.request_chunk_column(0, 0).unwrap();
+        wait_until(client, |client| {
+            client.chunk_manager.get_block(3, 0, 3) == Some(BlockID::STONE)
+        });
+    }
+
+    // Inventory sync, sent first so that the glass placed below is in the hand on the server
+    let mut inventory = Inventory::new();
+    inventory.slots[4] = Some(ItemStack::new(BlockID::GLASS, 12));
+    inventory.select_item(4);
+    client_a.sync_inventory(&inventory).unwrap();
+
+    // Block changes are applied for everybody, the sender included
+    client_a.put_block(3, 1, 3, BlockID::GLASS).unwrap();
+
+    for client in [&mut client_a, &mut client_b] {
+        wait_until(client, |client| {
+            client.chunk_manager.get_block(3, 1, 3) == Some(BlockID::GLASS)
+        });
+    }
+
+    client_b.put_block(3, 0, 3, BlockID::AIR).unwrap();
+    wait_until(&mut client_a, |client| {
+        client.chunk_manager.get_block(3, 0, 3) == Some(BlockID::AIR)
+    });
+
+    // Player moves are sent to the other players only
+    let position = vec3(3.5, 2.0, 3.5);
+    let player_a = client_a.player_id;
+    client_a
+        .move_player(position, vec3(10.0, 20.0, 0.0))
+        .unwrap();
+
+    wait_until(&mut client_b, |client| {
+        client
+            .other_players
+            .get(&player_a)
+            .is_some_and(|&(player_position, _)| player_position == position)
+    });
+    assert!(!client_a.other_players.contains_key(&player_a));
+
+    (client_a, client_b)
+}
+
+#[test]
+fn two_clients_play_together_over_tcp() {
+    let chunk_manager = server_chunk_manager();
+    let mut server = Server::new(Arc::clone(&chunk_manager), vec3(8.0, 2.0, 8.0));
+    let address = server.listen("127.0.0.1:0").unwrap();
+    let server = RunningServer::start(server);
+
+    let connect = |name: &str| {
+        Client::connect(
+            Box::new(TcpConnection::connect(address).unwrap()),
+            name,
+            Arc::new(ChunkManager::new()),
+        )
+        .unwrap()
+    };
+
+    let (client_a, mut client_b) = play_together(connect("Alex"), connect("Steve"));
+
+    // The server world was changed as well
+    assert_eq!(chunk_manager.get_block(3, 1, 3), Some(BlockID::GLASS));
+    assert_eq!(chunk_manager.get_block(3, 0, 3), Some(BlockID::AIR));
+
+    let player_a = client_a.player_id;
+    client_a.disconnect("Bye").unwrap();
+    wait_until(&mut client_b, |client| {
+        !client.other_players.contains_key(&player_a)
+    });
+
+    let server = server.stop();
+    assert_eq!(server.players()[0].id, client_b.player_id);
+}
+
+#[test]
+fn two_clients_play_together_over_channels() {
+    let chunk_manager = server_chunk_manager();
+    let mut server = Server::new(Arc::clone(&chunk_manager), vec3(8.0, 2.0, 8.0));
+
+    let (client_end_a, server_end_a) = ChannelConnection::pair();
+    let (client_end_b, server_end_b) = ChannelConnection::pair();
+    server.add_connection(Box::new(server_end_a));
+    server.add_connection(Box::new(server_end_b));
+
+    let server = RunningServer::start(server);
+
+    let client_a = Client::connect(
+        Box::new(client_end_a),
+        "Alex",
+        Arc::new(ChunkManager::new()),
+    )
+    .unwrap();
+    let client_b = Client::connect(
+        Box::new(client_end_b),
+        "Steve",
+        Arc::new(ChunkManager::new()),
+    )
+    .unwrap();
+
+    let (mut client_a, client_b) = play_together(client_a, client_b);
+    assert_eq!(chunk_manager.get_block(3, 1, 3), Some(BlockID::GLASS));
+
+    let player_b = client_b.player_id;
+    client_b.disconnect("Bye").unwrap();
+    wait_until(&mut client_a, |client| {
+        !client.other_players.contains_key(&player_b)
+    });
+
+    // The server kept the position and the inventory sent by the remaining player, without the
+    // glass placed
+    let server = server.stop();
+    assert_eq!(server.players().len(), 1);
+
+    let player = &server.players()[0];
+    assert_eq!(player.id, client_a.player_id);
+    assert_eq!(player.position, vec3(3.5, 2.0, 3.5));
+    assert_eq!(player.inventory.selected_hotbar_slot, 4);
+    assert_eq!(
+        player.inventory.slots[4],
+        Some(ItemStack::new(BlockID::GLASS, 11))
+    );
+}
+
+#[test]
+fn handshake_with_another_protocol_version_is_refused() {
+    let mut server = Server::new(server_chunk_manager(), vec3(8.0, 2.0, 8.0));
+    let (mut client_end, server_end) = ChannelConnection::pair();
+    server.add_connection(Box::new(server_end));
+
+    client_end
+        .send(&Message::Handshake {
+            protocol_version: PROTOCOL_VERSION + 1,
+            player_name: "Notch".to_string(),
+        })
+        .unwrap();
+    server.update();
+
+    assert!(matches!(
+        client_end.receive_timeout(TIMEOUT),
+        Ok(Some(Message::Disconnect { .. }))
+    ));
+    assert!(server.players().is_empty());
+}
+
+// Connects a raw connection to the server, updated by hand
+fn join(server: &mut Server) -> ChannelConnection {
+    let (mut client_end, server_end) = ChannelConnection::pair();
+    server.add_connection(Box::new(server_end));
+
+    client_end
+        .send(&Message::Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            player_name: "Herobrine".to_string(),
+        })
+        .unwrap();
+    server.update();
+
+    assert!(matches!(
+        client_end.receive_timeout(TIMEOUT),
+        Ok(Some(Message::HandshakeAccepted { .. }))
+    ));
+
+    client_end
+}
+
+#[test]
+fn out_of_reach_block_changes_are_dropped() {
+    let chunk_manager = server_chunk_manager();
+    let mut server = Server::new(Arc::clone(&chunk_manager), vec3(8.0, 2.0, 8.0));
+    let mut client_end = join(&mut server);
+
+    for (x, y) in [(15, 20), (3, 1)] {
+        client_end
+            .send(&Message::BlockChange {
+                x,
+                y,
+                z: 3,
+                block: BlockID::DIRT,
+                level: 0,
+            })
+            .unwrap();
+    }
+
+    server.update();
+
+    // Only the block within reach was changed, the player is still there
+    assert!(chunk_manager.get_block(15, 20, 3).unwrap().is_air());
+    assert_eq!(chunk_manager.get_block(3, 1, 3), Some(BlockID::DIRT));
+    assert_eq!(server.players().len(), 1);
+}
+
+#[test]
+fn placed_blocks_are_taken_from_the_inventory() {
+    let chunk_manager = server_chunk_manager();
+    let mut server = Server::new(Arc::clone(&chunk_manager), vec3(8.0, 2.0, 8.0));
+    let mut client_end = join(&mut server);
+
+    // A single dirt block in the hand
+    let mut inventory = Inventory::new();
+    inventory.slots[0] = Some(ItemStack::new(BlockID::DIRT, 1));
+    client_end
+        .send(&Message::InventorySync {
+            selected_hotbar_slot: 0,
+            slots: inventory.slots.to_vec(),
+        })
+        .unwrap();
+
+    for (x, block) in [(3, BlockID::GLASS), (4, BlockID::DIRT), (5, BlockID::DIRT)] {
+        client_end
+            .send(&Message::BlockChange {
+                x,
+                y: 1,
+                z: 3,
+                block,
+                level: 0,
+            })
+            .unwrap();
+    }
+
+    server.update();
+
+    // Only the first dirt block was placed, the others weren't in the hand anymore
+    assert!(chunk_manager.get_block(3, 1, 3).unwrap().is_air());
+    assert_eq!(chunk_manager.get_block(4, 1, 3), Some(BlockID::DIRT));
+    assert!(chunk_manager.get_block(5, 1, 3).unwrap().is_air());
+    assert_eq!(server.players()[0].inventory.slots[0], None);
+    assert_eq!(server.players().len(), 1);
+
+    // The rejected blocks are set back to what they are on the server for the player
+    let mut block_changes = Vec::new();
+
+    while let Ok(Some(message)) = client_end.receive_timeout(Duration::from_millis(100)) {
+        if let Message::BlockChange { x, block, .. } = message {
+            block_changes.push((x, block));
+        }
+    }
+
+    assert_eq!(
+        block_changes,
+        [(3, BlockID::AIR), (5, BlockID::AIR), (4, BlockID::DIRT)]
+    );
+}
+
+#[test]
+fn malformed_messages_disconnect_the_player() {
+    let mut empty_inventory = Inventory::new();
+    empty_inventory.slots = [None; INVENTORY_SIZE];
+
+    let mut overfull_slots = empty_inventory.slots.to_vec();
+    overfull_slots[0] = Some(ItemStack::new(
+        BlockID::DIRT,
+        BlockID::DIRT.max_stack_size() + 1,
+    ));
+
+    let messages = [
+        Message::BlockChange {
+            x: 3,
+            y: 1,
+            z: 3,
+            block: BlockID::GLASS,
+            level: 2,
+        },
+        Message::InventorySync {
+            selected_hotbar_slot: HOTBAR_SIZE as u8,
+            slots: empty_inventory.slots.to_vec(),
+        },
+        Message::InventorySync {
+            selected_hotbar_slot: 0,
+            slots: overfull_slots,
+        },
+        Message::InventorySync {
+            selected_hotbar_slot: 0,
+            slots: vec![None; 3],
+        },
+    ];
+
+    for message in messages {
+        let chunk_manager = server_chunk_manager();
+        let mut server = Server::new(Arc::clone(&chunk_manager), vec3(8.0, 2.0, 8.0));
+        let mut client_end = join(&mut server);
+
+        client_end.send(&message).unwrap();
+        server.update();
+
+        assert!(server.players().is_empty(), "{message:?} was accepted");
+        assert!(matches!(
+            client_end.receive_timeout(TIMEOUT),
+            Ok(Some(Message::Disconnect { .. }))
+        ));
+        assert!(chunk_manager.get_block(3, 1, 3).unwrap().is_air());
+    }
+}
+
+#[test]
+fn only_the_columns_around_the_player_are_sent() {
+    let chunk_manager = server_chunk_manager();
+    let far_column = ChunkColumn::new();
+    *far_column.has_foliage.write() = true;
+    chunk_manager.add_chunk_column((30, 0), Arc::new(far_column));
+
+    let mut server = Server::new(Arc::clone(&chunk_manager), vec3(8.0, 2.0, 8.0));
+    let mut client_end = join(&mut server);
+
+    client_end
+        .send(&Message::RequestChunkColumn { x: 30, z: 0 })
+        .unwrap();
+    client_end
+        .send(&Message::RequestChunkColumn { x: 0, z: 0 })
+        .unwrap();
+    server.update();
+
+    let mut sent_columns = Vec::new();
+
+    while let Ok(Some(message)) = client_end.try_receive() {
+        if let Message::ChunkColumnData { x, z, .. } = message {
+            sent_columns.push((x, z));
+        }
+    }
+
+    assert_eq!(sent_columns, vec![(0, 0)]);
+    assert_eq!(server.players().len(), 1);
+}
+
+#[test]
+fn sending_to_a_stalled_peer_never_blocks() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut connection = TcpConnection::connect(listener.local_addr().unwrap()).unwrap();
+
+    // Accepted but never read
+    let (_stalled_stream, _) = listener.accept().unwrap();
+
+    let message = Message::ChunkColumnData {
+        x: 0,
+        z: 0,
+        data: vec![0; 16 * 1024],
+    };
+    let start = Instant::now();
+
+    // The queue fills up once the socket buffers are full, then the connection gives up
+    let sent = (0..100_000)
+        .take_while(|_| connection.send(&message).is_ok())
+        .count();
+
+    assert!(sent < 100_000);
+    assert!(start.elapsed() < TIMEOUT);
+}
+
+#[test]
+fn the_world_of_a_client_is_synced_with_the_server() {
+    let server_chunk_manager = server_chunk_manager();
+    let mut server = Server::new(Arc::clone(&server_chunk_manager), vec3(8.0, 2.0, 8.0));
+    let (client_end, server_end) = ChannelConnection::pair();
+    server.add_connection(Box::new(server_end));
+
+    // The server runs on its own thread until the handshake is accepted
+    let running_server = RunningServer::start(server);
+    let client_chunk_manager = Arc::new(ChunkManager::new());
+    let client = Client::connect(
+        Box::new(client_end),
+        "Alex",
+        Arc::clone(&client_chunk_manager),
+    )
+    .unwrap();
+    let mut server = running_server.stop();
+    let mut sync_with_server = SyncWithServer::new(client);
+
+    let mut world = World::new();
+    world.register::<Transform>();
+    world.register::<PlayerState>();
+    world.register::<Inventory>();
+
+    // Glass is in the hand, the player is in creative and keeps all of it
+    let mut inventory = Inventory::new();
+    inventory.select_item(6);
+    world
+        .create_entity()
+        .with(Transform::new(vec3(8.0, 2.0, 8.0)))
+        .with(PlayerState::new())
+        .with(inventory)
+        .build();
+
+    let mut update_until = |condition: &dyn Fn(&Server) -> bool, server: &mut Server| {
+        let start = Instant::now();
+
+        while !condition(server) {
+            sync_with_server.run_now(&world);
+            server.update();
+            assert!(
+                start.elapsed() < TIMEOUT,
+                "Timed out waiting for the server"
+            );
+        }
+    };
+
+    // The column around the player is requested and received
+    update_until(
+        &|_| client_chunk_manager.get_block(3, 0, 3) == Some(BlockID::STONE),
+        &mut server,
+    );
+    assert_eq!(server.players()[0].inventory.selected_hotbar_slot, 6);
+
+    // The blocks changed by the player are sent to the server
+    client_chunk_manager.put_block(3, 1, 3, BlockID::GLASS);
+    update_until(
+        &|_| server_chunk_manager.get_block(3, 1, 3) == Some(BlockID::GLASS),
+        &mut server,
+    );
+
+    // And the ones changed on the server are received, without being sent back
+    server_chunk_manager.put_block(4, 1, 4, BlockID::DIRT);
+    server_chunk_manager.put_block(4, 1, 4, BlockID::AIR);
+    update_until(
+        &|_| client_chunk_manager.get_block(4, 1, 4) == Some(BlockID::AIR),
+        &mut server,
+    );
+
+    for _ in 0..10 {
+        sync_with_server.run_now(&world);
+        server.update();
+    }
+
+    assert!(server_chunk_manager.get_block(4, 1, 4).unwrap().is_air());
+    assert_eq!(server.players().len(), 1);
+
+    // The inventory taken by the server for the glass was sent again
+    let inventory = world.read_storage::<Inventory>();
+    let inventory = (&inventory).join().next().unwrap();
+    assert_eq!(server.players()[0].inventory.slots, inventory.slots);
+}
+
+#[test]
+fn the_server_simulates_the_world_without_players() {
+    let chunk_manager = Arc::new(ChunkManager::new());
+    let mut server_world = ServerWorld::new(
+        Arc::clone(&chunk_manager),
+        Arc::new(SuperflatGenerator::default()),
+        0,
+        vec3(8.0, 10.0, 8.0),
+    );
+
+    let tick_until = |condition: &dyn Fn(&World) -> bool, server_world: &mut ServerWorld| {
+        let start = Instant::now();
+
+        while !condition(&server_world.world) {
+            server_world.tick();
+            assert!(
+                start.elapsed() < Duration::from_secs(60),
+                "Timed out simulating the world"
+            );
+            thread::sleep(Duration::from_secs_f32(1.0 / PHYSICS_TICKRATE));
+        }
+    };
+
+    // The spawn area is generated and lit with nobody connected
+    tick_until(
+        &|_| {
+            chunk_manager
+                .get_column(0, 0)
+                .is_some_and(|column| *column.is_lit.read())
+        },
+        &mut server_world,
+    );
+    assert_eq!(chunk_manager.get_block(8, 3, 8), Some(BlockID::GRASS_BLOCK));
+
+    // An item dropped above the ground falls until it lands on the grass
+    let item = {
+        let world = &server_world.world;
+        let time = world.read_resource::<Timer>().time();
+
+        spawn_dropped_item(
+            &world.entities(),
+            &world.read_resource::<LazyUpdate>(),
+            vec3(8.5, 10.0, 8.5),
+            vec3(0.0, 0.0, 0.0),
+            DroppedItem::new(ItemStack::new(BlockID::DIRT, 1), time, Duration::ZERO),
+        )
+    };
+    server_world.tick();
+
+    let position = |world: &World| {
+        world
+            .read_storage::<Transform>()
+            .get(item)
+            .unwrap()
+            .position
+    };
+    tick_until(&|world| position(world).y < 5.0, &mut server_world);
+
+    for _ in 0..PHYSICS_TICKRATE as usize {
+        server_world.tick();
+        thread::sleep(Duration::from_secs_f32(1.0 / PHYSICS_TICKRATE));
+    }
+
+    let position = position(&server_world.world);
+    assert!((position.y - 4.0).abs() < 0.01, "{position:?}");
+    assert!((position.x - 8.5).abs() < 0.01 && (position.z - 8.5).abs() < 0.01);
+}